@@ -1,11 +1,11 @@
 use std::{net::TcpStream, time::Duration};
 
+use bitcoin::{Amount, OutPoint};
 use clap::Parser;
 use coinswap::{
     maker::{MakerError, RpcMsgReq, RpcMsgResp},
     utill::{read_message, send_message, setup_maker_logger},
 };
-use bitcoin::Amount;
 
 /// A simple command line app to operate the makerd server.
 ///
@@ -53,17 +53,15 @@ enum Commands {
         /// Recipient's address.
         #[clap(long, short = 't')]
         address: String,
-<<<<<<< HEAD
-        amount: Amount,
-        fee:Amount,
-=======
         /// Amount to send in sats
         #[clap(long, short = 'a')]
         amount: u64,
-        /// Total fee to be paid in sats
+        /// Feerate in sats/vB. Estimated by the server if not provided.
         #[clap(long, short = 'f')]
-        fee: u64,
->>>>>>> 58a3446c6c5617d2f5761117ad2278068e215443
+        feerate: Option<f64>,
+        /// Utxo to spend, as txid:vout. Can be repeated. Coins are selected by the server if not provided.
+        #[clap(long = "utxo", short = 'u')]
+        utxos: Vec<OutPoint>,
     },
     /// Show the server tor address
     ShowTorAddress,
@@ -80,6 +78,36 @@ enum Commands {
     ShowFidelity,
     /// Sync the maker wallet with current blockchain state.
     SyncWallet,
+    /// Show the configured fee policy, and the fee policy currently offered to takers.
+    GetFeePolicy,
+    /// Update the fee policy. Only the given values are changed. The new policy is saved in the config file.
+    SetFeePolicy {
+        /// Fixed fee in sats, charged for every swap.
+        #[clap(long)]
+        base_fee: Option<u64>,
+        /// Percentage fee on the swap amount.
+        #[clap(long)]
+        amount_relative_fee_pct: Option<f64>,
+        /// Percentage fee on the swap amount, per block of refund locktime.
+        #[clap(long)]
+        time_relative_fee_pct: Option<f64>,
+        /// Raise the relative fees with liquidity utilization (true/false).
+        #[clap(long)]
+        auto_adjust: Option<bool>,
+    },
+    /// Lists all the swaps funded by the maker, with their amounts, fees and outcome.
+    ListSwaps,
+    /// Show the details of a swap, including its transactions.
+    ShowSwap {
+        /// The swap id.
+        id: String,
+    },
+    /// Sum up the fees earned by the completed swaps.
+    Earnings {
+        /// Only count the swaps completed since this time, in seconds since the unix epoch.
+        #[clap(long)]
+        since: Option<u64>,
+    },
 }
 
 fn main() -> Result<(), MakerError> {
@@ -122,14 +150,16 @@ fn main() -> Result<(), MakerError> {
         Commands::SendToAddress {
             address,
             amount,
-            fee,
+            feerate,
+            utxos,
         } => {
             send_rpc_req(
                 stream,
                 RpcMsgReq::SendToAddress {
                     address,
-                    amount,
-                    fee,
+                    amount: Amount::from_sat(amount),
+                    feerate,
+                    utxos,
                 },
             )?;
         }
@@ -151,6 +181,34 @@ fn main() -> Result<(), MakerError> {
         Commands::SyncWallet => {
             send_rpc_req(stream, RpcMsgReq::SyncWallet)?;
         }
+        Commands::GetFeePolicy => {
+            send_rpc_req(stream, RpcMsgReq::GetFeePolicy)?;
+        }
+        Commands::SetFeePolicy {
+            base_fee,
+            amount_relative_fee_pct,
+            time_relative_fee_pct,
+            auto_adjust,
+        } => {
+            send_rpc_req(
+                stream,
+                RpcMsgReq::SetFeePolicy {
+                    base_fee,
+                    amount_relative_fee_pct,
+                    time_relative_fee_pct,
+                    auto_adjust,
+                },
+            )?;
+        }
+        Commands::ListSwaps => {
+            send_rpc_req(stream, RpcMsgReq::ListSwaps)?;
+        }
+        Commands::ShowSwap { id } => {
+            send_rpc_req(stream, RpcMsgReq::ShowSwap(id))?;
+        }
+        Commands::Earnings { since } => {
+            send_rpc_req(stream, RpcMsgReq::Earnings { since })?;
+        }
     }
 
     Ok(())
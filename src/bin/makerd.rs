@@ -2,7 +2,7 @@ use bitcoind::bitcoincore_rpc::Auth;
 use clap::Parser;
 use coinswap::{
     maker::{start_maker_server, Maker, MakerBehavior, MakerError},
-    utill::{parse_proxy_auth, setup_maker_logger, ConnectionType},
+    utill::{parse_proxy_auth, read_passphrase, setup_maker_logger, ConnectionType},
     wallet::RPCConfig,
 };
 use std::{path::PathBuf, sync::Arc};
@@ -46,6 +46,27 @@ struct Cli {
     /// Optional wallet name. If the wallet exists, load the wallet, else create a new wallet with given name. Default: maker-wallet
     #[clap(name = "WALLET", long, short = 'w')]
     pub(crate) wallet_name: Option<String>,
+    /// Prompt for a passphrase to encrypt the wallet file at rest. Required to load an encrypted wallet.
+    /// If given for an existing unencrypted wallet, the wallet file will be encrypted with it.
+    /// The passphrase can be set in the COINSWAP_WALLET_PASSPHRASE environment variable instead.
+    #[clap(long)]
+    pub(crate) passphrase: bool,
+    /// Optional command to run before starting the server.
+    #[clap(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Parser, Debug)]
+enum Commands {
+    /// Restore the wallet from its 12-word mnemonic, then start the server. Fails if the wallet file already exists.
+    Restore {
+        /// The wallet's BIP39 mnemonic words, as a single quoted string.
+        #[clap(long, short = 'm')]
+        mnemonic: String,
+        /// Block height to rescan the blockchain from. Use a height at or before the wallet's creation.
+        #[clap(long, short = 'b', default_value = "0")]
+        birthday: u64,
+    },
 }
 
 fn main() -> Result<(), MakerError> {
@@ -69,10 +90,24 @@ fn main() -> Result<(), MakerError> {
     #[cfg(not(feature = "tor"))]
     let connection_type = ConnectionType::CLEARNET;
 
+    let passphrase = read_passphrase(args.passphrase)?;
+
+    if let Some(Commands::Restore { mnemonic, birthday }) = args.command {
+        Maker::restore_wallet(
+            args.data_directory.clone(),
+            args.wallet_name.clone(),
+            Some(rpc_config.clone()),
+            passphrase.clone(),
+            &mnemonic,
+            birthday,
+        )?;
+    }
+
     let maker = Arc::new(Maker::init(
         args.data_directory,
         args.wallet_name,
         Some(rpc_config),
+        passphrase,
         None,
         None,
         None,
@@ -1,9 +1,11 @@
-use bitcoin::{Address, Amount};
+use bitcoin::{Address, Amount, OutPoint};
 use bitcoind::bitcoincore_rpc::{json::ListUnspentResultEntry, Auth};
 use clap::Parser;
 use coinswap::{
     taker::{error::TakerError, SwapParams, Taker, TakerBehavior},
-    utill::{parse_proxy_auth, setup_taker_logger, ConnectionType, REQUIRED_CONFIRMS},
+    utill::{
+        parse_proxy_auth, read_passphrase, setup_taker_logger, ConnectionType, REQUIRED_CONFIRMS,
+    },
     wallet::{Destination, RPCConfig, SendAmount},
 };
 use log::LevelFilter;
@@ -43,6 +45,12 @@ struct Cli {
     #[clap(name = "WALLET", long, short = 'w')]
     pub wallet_name: Option<String>,
 
+    /// Prompt for a passphrase to encrypt the wallet file at rest. Required to load an encrypted wallet.
+    /// If given for an existing unencrypted wallet, the wallet file will be encrypted with it.
+    /// The passphrase can be set in the COINSWAP_WALLET_PASSPHRASE environment variable instead.
+    #[clap(long)]
+    pub passphrase: bool,
+
     /// Sets the verbosity level of debug.log file
     #[clap(long, short = 'v', possible_values = &["off", "error", "warn", "info", "debug", "trace"], default_value = "info")]
     pub verbosity: String,
@@ -74,20 +82,15 @@ enum Commands {
         /// Recipient's address.
         #[clap(long, short = 't')]
         address: String,
-<<<<<<< HEAD
-        /// Amount to be sent (in sats)
-        #[clap(name = "amount")]
-        amount: Amount,
-        /// Fee of a Tx(in sats)
-        #[clap(name = "fee")]
-=======
         /// Amount to send in sats
         #[clap(long, short = 'a')]
         amount: u64,
-        /// Mining fee to be paid in sats
+        /// Feerate in sats/vB. Estimated from Bitcoin Core if not provided.
         #[clap(long, short = 'f')]
->>>>>>> 58a3446c6c5617d2f5761117ad2278068e215443
-        fee: u64,
+        feerate: Option<f64>,
+        /// Utxo to spend, as txid:vout. Can be repeated. Coins are selected by the wallet if not provided.
+        #[clap(long = "utxo", short = 'u')]
+        utxos: Vec<OutPoint>,
     },
     /// Update the offerbook with current market offers and display them
     FetchOffers,
@@ -102,13 +105,42 @@ enum Commands {
         /// Sets the swap amount in sats.
         #[clap(long, short = 'a', default_value = "20000")]
         amount: u64,
-        // /// Sets how many new swap utxos to get. The swap amount will be randomly distrubted across the new utxos.
-        // /// Increasing this number also increases total swap fee.
-        // #[clap(long, short = 'u', default_value = "1")]
-        // utxos: u32,
+        /// Sets how many new swap utxos to get. The swap amount will be randomly distrubted across the new utxos.
+        /// Increasing this number also increases total swap fee.
+        #[clap(long, short = 'u', default_value = "1")]
+        utxos: u32,
+    },
+    /// Quote the total cost of a coinswap, and the worst case cost of recovering from it, without swapping
+    Quote {
+        /// Sets the maker count to swap with.
+        #[clap(long, short = 'm', default_value = "2")]
+        makers: usize,
+        /// Sets the swap amount in sats.
+        #[clap(long, short = 'a', default_value = "20000")]
+        amount: u64,
+        /// Sets how many new swap utxos to get.
+        #[clap(long, short = 'u', default_value = "1")]
+        utxos: u32,
     },
     /// Recover from all failed swaps
     Recover,
+    /// Resume the swaps left unfinished when the taker was last shut down
+    Resume {
+        /// Id of the swap to resume. All unfinished swaps are resumed if not given.
+        #[clap(long, short = 'i')]
+        id: Option<String>,
+    },
+    /// Show the finished swaps, with a privacy summary of each
+    History,
+    /// Restore the wallet from its 12-word mnemonic. Fails if the wallet file already exists.
+    Restore {
+        /// The wallet's BIP39 mnemonic words, as a single quoted string.
+        #[clap(long, short = 'm')]
+        mnemonic: String,
+        /// Block height to rescan the blockchain from. Use a height at or before the wallet's creation.
+        #[clap(long, short = 'b', default_value = "0")]
+        birthday: u64,
+    },
 }
 
 fn main() -> Result<(), TakerError> {
@@ -131,10 +163,26 @@ fn main() -> Result<(), TakerError> {
     #[cfg(not(feature = "tor"))]
     let connection_type = ConnectionType::CLEARNET;
 
+    let passphrase = read_passphrase(args.passphrase)?;
+
+    if let Commands::Restore { mnemonic, birthday } = &args.command {
+        Taker::restore_wallet(
+            args.data_directory,
+            args.wallet_name,
+            Some(rpc_config),
+            passphrase,
+            mnemonic,
+            *birthday,
+        )?;
+        println!("Wallet restored successfully");
+        return Ok(());
+    }
+
     let mut taker = Taker::init(
         args.data_directory.clone(),
         args.wallet_name.clone(),
         Some(rpc_config.clone()),
+        passphrase,
         TakerBehavior::Normal,
         Some(connection_type),
     )?;
@@ -186,30 +234,29 @@ fn main() -> Result<(), TakerError> {
         Commands::SendToAddress {
             address,
             amount,
-            fee,
+            feerate,
+            utxos,
         } => {
-            // NOTE:
-            //
-            // Currently, we take `fee` instead of `fee_rate` because we cannot calculate the fee for a
-            // transaction that hasn't been created yet when only a `fee_rate` is provided.
-            //
-            // As a result, the user must supply the fee as a parameter, and the function will return the
-            // transaction hex and the calculated `fee_rate`.
-            // This allows the user to infer what fee is needed for a successful transaction.
-            //
-            // This approach will be improved in the future BDK integration.
-
-            let fee = Amount::from_sat(fee);
+            let feerate = match feerate {
+                Some(feerate) => feerate,
+                None => taker
+                    .get_wallet()
+                    .estimate_feerate(&taker.config.fee_estimator)?,
+            };
 
             let amount = Amount::from_sat(amount);
 
-            let coins_to_spend = taker.get_wallet().coin_select(amount + fee)?;
+            let coins_to_spend = if utxos.is_empty() {
+                taker.get_wallet().coin_select_with_fee(amount, feerate)?
+            } else {
+                taker.get_wallet().select_utxos(&utxos)?
+            };
 
             let destination =
                 Destination::Address(Address::from_str(&address).unwrap().assume_checked());
 
             let tx = taker.get_wallet_mut().spend_from_wallet(
-                fee,
+                feerate,
                 SendAmount::Amount(amount),
                 destination,
                 &coins_to_spend,
@@ -225,11 +272,15 @@ fn main() -> Result<(), TakerError> {
             let offerbook = taker.fetch_offers()?;
             println!("{:#?}", offerbook)
         }
-        Commands::Coinswap { makers, amount } => {
+        Commands::Coinswap {
+            makers,
+            amount,
+            utxos,
+        } => {
             let swap_params = SwapParams {
                 send_amount: Amount::from_sat(amount),
                 maker_count: makers,
-                tx_count: 1,
+                tx_count: utxos,
                 required_confirms: REQUIRED_CONFIRMS,
             };
 
@@ -238,11 +289,58 @@ fn main() -> Result<(), TakerError> {
             println!("succesfully completed coinswap!! Check `list-utxo` to see the new coins");
         }
 
+        Commands::Quote {
+            makers,
+            amount,
+            utxos,
+        } => {
+            let swap_params = SwapParams {
+                send_amount: Amount::from_sat(amount),
+                maker_count: makers,
+                tx_count: utxos,
+                required_confirms: REQUIRED_CONFIRMS,
+            };
+
+            println!("Fetching offerdata from the market. use `tail -f <data-dir>/debug.log` to see progress.");
+            let quote = taker.quote_swap(swap_params)?;
+            println!("{:#?}", quote);
+        }
+
         Commands::Recover => {
             println!("Starting recovery. use `tail -f <data-dir>/debug.log` to see progress.");
             taker.recover_from_swap()?;
             println!("Recovery completed succesfully.");
         }
+        Commands::Resume { id } => {
+            let ids = match id {
+                Some(id) => vec![id],
+                None => taker.list_swap_journals()?,
+            };
+            if ids.is_empty() {
+                println!("No unfinished swaps to resume.");
+            }
+            for id in ids {
+                println!(
+                    "Resuming swap {}. use `tail -f <data-dir>/debug.log` to see progress.",
+                    id
+                );
+                // A failed swap doesn't keep the others from resuming.
+                match taker.resume_swap(&id) {
+                    Ok(()) => println!("Swap {} completed.", id),
+                    Err(e) => println!("Swap {} failed: {:?}", id, e),
+                }
+            }
+        }
+        Commands::History => {
+            let history = taker.swap_history()?;
+            if history.is_empty() {
+                println!("No finished swaps.");
+            }
+            for report in history {
+                println!("{}", report);
+            }
+        }
+        Commands::Restore { .. } => unreachable!("handled before taker initialization"),
     }
 
     Ok(())
@@ -26,6 +26,23 @@ pub enum NetError {
 
     /// Error indicating an invalid CLI application network.
     InvalidAppNetwork,
+
+    /// Error in the encrypted transport, either in the handshake or in decrypting a message.
+    Encryption(&'static str),
+
+    /// Error indicating the peer announced a message larger than allowed.
+    MessageTooLarge {
+        /// Announced message size, in bytes.
+        size: u32,
+        /// Largest allowed message size, in bytes.
+        max: u32,
+    },
+
+    /// Error indicating a message didn't arrive completely within the read deadline.
+    ReadDeadlineExceeded,
+
+    /// Error indicating the peer didn't start a message within the idle timeout.
+    IdleTimeout,
 }
 
 impl std::fmt::Display for NetError {
@@ -8,24 +8,26 @@
 
 use crate::{
     protocol::{
-        contract::check_hashvalues_are_equal,
-        messages::{FidelityProof, ReqContractSigsForSender},
+        contract::{calculate_coinswap_fee, check_hashvalues_are_equal, verify_utxo_proof},
+        error::ProtocolError,
+        messages::{FidelityProof, NegotiatedProtocol, ReqContractSigsForSender},
         Hash160,
     },
     utill::{
-        get_maker_dir, redeemscript_to_scriptpubkey, ConnectionType, HEART_BEAT_INTERVAL,
-        REQUIRED_CONFIRMS,
+        get_maker_dir, redeemscript_to_scriptpubkey, unix_time, ConnectionType,
+        HEART_BEAT_INTERVAL, REQUIRED_CONFIRMS,
     },
     wallet::{RPCConfig, SwapCoin, WalletSwapCoin},
 };
 use bitcoin::{
     ecdsa::Signature,
     secp256k1::{self, Secp256k1},
-    OutPoint, PublicKey, ScriptBuf, Transaction, Amount
+    OutPoint, PublicKey, Script, ScriptBuf, Transaction, Txid, Amount
 };
 use bitcoind::bitcoincore_rpc::RpcApi;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    net::IpAddr,
     path::PathBuf,
     sync::{
         atomic::{AtomicBool, Ordering::Relaxed},
@@ -43,10 +45,15 @@ use crate::{
         },
         messages::ProofOfFunding,
     },
-    wallet::{IncomingSwapCoin, OutgoingSwapCoin, Wallet, WalletError},
+    wallet::{IncomingSwapCoin, OutgoingSwapCoin, Wallet, WalletError, MIN_RELAY_FEERATE},
 };
 
-use super::{config::MakerConfig, error::MakerError};
+use super::{
+    config::{FeePolicy, MakerConfig},
+    error::MakerError,
+    rate_limit::PeerTracker,
+    swap_ledger::{SwapLedger, SwapOutcome, SwapRecord},
+};
 
 /// Interval for health checks on a stable RPC connection with bitcoind.
 pub const RPC_PING_INTERVAL: Duration = Duration::from_secs(10);
@@ -59,6 +66,15 @@ pub const RPC_PING_INTERVAL: Duration = Duration::from_secs(10);
 /// Maker triggers the recovery mechanism, if Taker is idle for more than 1 hour.
 pub const IDLE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(60 * 60);
 
+/// Interval at which the offered fee policy is re-adjusted to the liquidity utilization.
+pub const FEE_POLICY_REFRESH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// How long after it was served the fee policy of an offer is honored for new swaps.
+pub const OFFER_VALIDITY: Duration = Duration::from_secs(30 * 60);
+
+/// How long the fee policies pinned for a swap are kept, while waiting for its proof of funding.
+pub const FEE_POLICY_PIN_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
 /// The minimum difference in locktime (in blocks) between the incoming and outgoing swaps.
 ///
 /// This value specifies the reaction time, in blocks, available to a Maker
@@ -71,10 +87,10 @@ pub const MIN_CONTRACT_REACTION_TIME: u16 = 20;
 
 /// # Fee Parameters for Coinswap
 ///
-/// These parameters define the fees charged by Makers in a coinswap transaction.
+/// These parameters define the default fees charged by Makers in a coinswap transaction.
+/// Makers can override them in their config file, or at runtime via `maker-cli set-fee-policy`.
+/// See [`FeePolicy`].
 ///
-/// TODO: These parameters are currently hardcoded. Consider making them configurable for Makers in the future.
-///p
 /// - `BASE_FEE`: A fixed base fee charged by the Maker for providing its services
 /// - `AMOUNT_RELATIVE_FEE_PCT`: A percentage fee based on the swap amount.
 /// - `TIME_RELATIVE_FEE_PCT`: A percentage fee based on the refund locktime (duration the Maker must wait for a refund).
@@ -95,7 +111,7 @@ pub const MIN_CONTRACT_REACTION_TIME: u16 = 20;
 ///
 /// Fee rates are designed to asymptotically approach 5% of the swap amount as the swap amount increases..
 #[cfg(feature = "integration-test")]
-pub const BASE_FEE: Amount = 1000;
+pub const BASE_FEE: u64 = 1000;
 #[cfg(feature = "integration-test")]
 pub const AMOUNT_RELATIVE_FEE_PCT: f64 = 2.50;
 #[cfg(feature = "integration-test")]
@@ -157,9 +173,20 @@ pub(crate) enum ExpectedMessage {
 #[derive(Debug, Default, Clone)]
 pub(crate) struct ConnectionState {
     pub(crate) allowed_message: ExpectedMessage,
+    /// The taker's address, if it can be told apart from other takers. See [PeerTracker].
+    pub(crate) peer: Option<IpAddr>,
+    /// Hash of the connection's Noise handshake, which unidentified takers do proof-of-work on.
+    pub(crate) handshake_hash: [u8; 32],
+    /// Contract signing requests made on this connection.
+    pub(crate) sig_requests: u32,
+    /// Protocol parameters agreed with the taker in the handshake.
+    pub(crate) protocol: NegotiatedProtocol,
     pub(crate) incoming_swapcoins: Vec<IncomingSwapCoin>,
     pub(crate) outgoing_swapcoins: Vec<OutgoingSwapCoin>,
     pub(crate) pending_funding_txes: Vec<Transaction>,
+    /// Refund txs signed for our outgoing swapcoins in a cooperative abort, as the funding outpoints
+    /// they spend and their txids.
+    pub(crate) refund_txids: Vec<(OutPoint, Txid)>,
 }
 
 pub(crate) struct ThreadPool {
@@ -177,6 +204,8 @@ impl ThreadPool {
 
     pub(crate) fn add_thread(&self, handle: JoinHandle<()>) {
         let mut threads = self.threads.lock().unwrap();
+        // Forget the finished client handler threads, so the pool doesn't grow with each connection.
+        threads.retain(|thread| !thread.is_finished());
         threads.push(handle);
     }
     #[inline]
@@ -220,6 +249,8 @@ pub struct Maker {
     pub(crate) behavior: MakerBehavior,
     /// Maker configurations
     pub(crate) config: MakerConfig,
+    /// The configured fee policy. Can be updated at runtime, and is persisted in the config file.
+    pub(crate) fee_policy: RwLock<FeePolicy>,
     /// Maker's underlying wallet
     pub wallet: RwLock<Wallet>,
     /// A flag to trigger shutdown event
@@ -228,12 +259,23 @@ pub struct Maker {
     pub(crate) connection_state: Mutex<HashMap<String, (ConnectionState, Instant)>>,
     /// Highest Value Fidelity Proof
     pub(crate) highest_fidelity_proof: RwLock<Option<FidelityProof>>,
+    /// Fee policy currently served in offers. Derived from `fee_policy`.
+    pub(crate) offered_fee_policy: RwLock<FeePolicy>,
+    /// Fee policies served in offers within the [`OFFER_VALIDITY`], with the time each was last served.
+    pub(crate) served_fee_policies: Mutex<Vec<(FeePolicy, Instant)>>,
+    /// Fee policies a taker may have accepted for a swap, pinned when the swap starts.
+    /// Keyed by the swap's hashvalue.
+    pub(crate) swap_fee_policies: Mutex<HashMap<Hash160, (Vec<FeePolicy>, Instant)>>,
     /// Is setup complete
     pub is_setup_complete: AtomicBool,
     /// Path for the data directory.
     pub(crate) data_dir: PathBuf,
     /// Thread pool for managing all spawned threads
     pub(crate) thread_pool: Arc<ThreadPool>,
+    /// Connections and requests of all Takers, against the configured peer limits.
+    pub(crate) peers: Mutex<PeerTracker>,
+    /// Record of all the swaps funded by the Maker.
+    pub(crate) swap_ledger: Mutex<SwapLedger>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -251,10 +293,14 @@ impl Maker {
     ///   - `Some(value)`: Attempt to load a wallet file named `value`. If it does not exist, a new wallet with the given name will be created.
     ///   - `None`: Create a new wallet file with the default name `maker-wallet`.
     /// - If `rpc_config` = `None`: Use the default [`RPCConfig`]
+    /// - `passphrase`:
+    ///   - `Some(value)`: Encrypt the wallet file with `value`. An existing unencrypted wallet file will be encrypted in place.
+    ///   - `None`: Keep the wallet file unencrypted. Loading an encrypted wallet file will fail.
     pub fn init(
         data_dir: Option<PathBuf>,
         wallet_file_name: Option<String>,
         rpc_config: Option<RPCConfig>,
+        passphrase: Option<String>,
         network_port: Option<u16>,
         rpc_port: Option<u16>,
         socks_port: Option<u16>,
@@ -275,12 +321,12 @@ impl Maker {
 
         let mut wallet = if wallet_path.exists() {
             // wallet already exists , load the wallet
-            let wallet = Wallet::load(&wallet_path, &rpc_config)?;
+            let wallet = Wallet::load(&wallet_path, &rpc_config, passphrase)?;
             log::info!("Wallet file at {:?} successfully loaded.", wallet_path);
             wallet
         } else {
             // wallet doesn't exists at the given path , create a new one
-            let wallet = Wallet::init(&wallet_path, &rpc_config)?;
+            let wallet = Wallet::init(&wallet_path, &rpc_config, passphrase)?;
             log::info!("New Wallet created at : {:?}", wallet_path);
             wallet
         };
@@ -305,24 +351,80 @@ impl Maker {
         }
 
         let port = config.network_port;
+        let peer_limits = config.peer_limits;
 
         config.write_to_file(&data_dir.join("config.toml"))?;
 
+        wallet.cross_pool_policy = config.cross_pool_spend;
+        wallet.coin_selection = config.coin_selection;
+
+        // Load the swap ledger. A corrupted ledger is not recreated, so no accounting record is lost.
+        let swap_ledger_path = data_dir.join("swaps.dat");
+        let swap_ledger = if swap_ledger_path.exists() {
+            SwapLedger::read_from_disk(&swap_ledger_path)?
+        } else {
+            SwapLedger::default()
+        };
+
         log::info!("Initializing wallet sync");
         wallet.sync()?;
         log::info!("Completed wallet sync");
 
-        Ok(Self {
+        let fee_policy = config.fee_policy;
+
+        let maker = Self {
             behavior,
             config,
+            fee_policy: RwLock::new(fee_policy),
             wallet: RwLock::new(wallet),
             shutdown: AtomicBool::new(false),
             connection_state: Mutex::new(HashMap::new()),
             highest_fidelity_proof: RwLock::new(None),
+            offered_fee_policy: RwLock::new(fee_policy),
+            served_fee_policies: Mutex::new(Vec::new()),
+            swap_fee_policies: Mutex::new(HashMap::new()),
             is_setup_complete: AtomicBool::new(false),
             data_dir,
             thread_pool: Arc::new(ThreadPool::new(port)),
-        })
+            peers: Mutex::new(PeerTracker::new(peer_limits)),
+            swap_ledger: Mutex::new(swap_ledger),
+        };
+
+        maker.refresh_fee_policy()?;
+
+        Ok(maker)
+    }
+
+    /// Restores a maker wallet from its BIP39 mnemonic.
+    ///
+    /// The wallet file is recreated in the maker's data directory, the blockchain is rescanned from
+    /// `birthday_height` and any fidelity bonds of the seed are recovered. The data directory, wallet
+    /// name and passphrase are interpreted the same way as in [`Maker::init`].
+    /// Errors if a wallet file with the same name already exists.
+    pub fn restore_wallet(
+        data_dir: Option<PathBuf>,
+        wallet_file_name: Option<String>,
+        rpc_config: Option<RPCConfig>,
+        passphrase: Option<String>,
+        mnemonic: &str,
+        birthday_height: u64,
+    ) -> Result<(), MakerError> {
+        let data_dir = data_dir.unwrap_or(get_maker_dir());
+        let wallet_file_name = wallet_file_name.unwrap_or_else(|| "maker-wallet".to_string());
+        let wallet_path = data_dir.join("wallets").join(&wallet_file_name);
+
+        let mut rpc_config = rpc_config.unwrap_or_default();
+        rpc_config.wallet_name = wallet_file_name;
+
+        Wallet::restore(
+            &wallet_path,
+            &rpc_config,
+            mnemonic,
+            passphrase,
+            birthday_height,
+        )?;
+        log::info!("Wallet restored at : {:?}", wallet_path);
+        Ok(())
     }
 
     pub(crate) fn get_data_dir(&self) -> &PathBuf {
@@ -334,6 +436,153 @@ impl Maker {
         &self.wallet
     }
 
+    /// Returns the configured fee policy and the policy currently served in offers.
+    pub fn get_fee_policy(&self) -> Result<(FeePolicy, FeePolicy), MakerError> {
+        Ok((*self.fee_policy.read()?, *self.offered_fee_policy.read()?))
+    }
+
+    /// Replaces the configured fee policy, and persists it in the config file.
+    ///
+    /// The new policy is served to takers from the next offer onwards.
+    pub fn set_fee_policy(&self, fee_policy: FeePolicy) -> Result<FeePolicy, MakerError> {
+        if fee_policy.amount_relative_fee_pct < 0.0 || fee_policy.time_relative_fee_pct < 0.0 {
+            return Err(MakerError::General("Fee percentages can't be negative"));
+        }
+        *self.fee_policy.write()? = fee_policy;
+
+        let mut config = self.config.clone();
+        config.fee_policy = fee_policy;
+        config.write_to_file(&self.data_dir.join("config.toml"))?;
+
+        self.refresh_fee_policy()
+    }
+
+    /// Recalculates the offered fee policy from the configured one and returns it.
+    ///
+    /// With auto adjustment enabled, the fees are scaled by the liquidity utilization,
+    /// i.e. the fraction of the spendable balance locked in live swap contracts, or reserved
+    /// for the funding txs of in-flight swaps.
+    pub(crate) fn refresh_fee_policy(&self) -> Result<FeePolicy, MakerError> {
+        let fee_policy = *self.fee_policy.read()?;
+
+        let offered = if fee_policy.auto_adjust {
+            let (locked, total) = {
+                let wallet = self.wallet.read()?;
+                (
+                    wallet.balance_live_contract(None)? + wallet.balance_reserved(None)?,
+                    wallet.spendable_balance()?,
+                )
+            };
+            let utilization = if total == Amount::ZERO {
+                0.0
+            } else {
+                locked.to_sat() as f64 / total.to_sat() as f64
+            };
+            fee_policy.adjusted(utilization)
+        } else {
+            fee_policy
+        };
+
+        let mut offered_fee_policy = self.offered_fee_policy.write()?;
+        if *offered_fee_policy != offered {
+            log::info!(
+                "[{}] Offered fee policy updated: {:?}",
+                self.config.network_port,
+                offered
+            );
+            *offered_fee_policy = offered;
+        }
+
+        Ok(offered)
+    }
+
+    /// Returns the fee policy to serve in an offer, and remembers it for the [`OFFER_VALIDITY`].
+    pub(crate) fn serve_fee_policy(&self) -> Result<FeePolicy, MakerError> {
+        let offered = *self.offered_fee_policy.read()?;
+        let now = Instant::now();
+        let mut served = self.served_fee_policies.lock()?;
+        served.retain(|(policy, served_at)| {
+            *policy != offered && now.duration_since(*served_at) < OFFER_VALIDITY
+        });
+        served.push((offered, now));
+        Ok(offered)
+    }
+
+    /// Pins the fee policies of all the offers the taker may have accepted for a swap.
+    ///
+    /// The offered policy can change while the swap's funding confirms, so the coinswap fee is
+    /// later charged at the pinned policies instead. Pins of a restarted swap are kept.
+    pub(crate) fn pin_fee_policies(&self, hashvalue: Hash160) -> Result<(), MakerError> {
+        let now = Instant::now();
+        let mut policies = self
+            .served_fee_policies
+            .lock()?
+            .iter()
+            .filter(|(_, served_at)| now.duration_since(*served_at) < OFFER_VALIDITY)
+            .map(|(policy, _)| *policy)
+            .collect::<Vec<_>>();
+        policies.push(*self.offered_fee_policy.read()?);
+
+        let mut pinned = self.swap_fee_policies.lock()?;
+        pinned.retain(|_, (_, pinned_at)| now.duration_since(*pinned_at) < FEE_POLICY_PIN_LIFETIME);
+        pinned.entry(hashvalue).or_insert((policies, now));
+        Ok(())
+    }
+
+    /// Calculates the coinswap fee of a swap, at the cheapest of the fee policies pinned for it.
+    ///
+    /// Falls back to the currently offered policy if none are pinned, like after a restart.
+    pub(crate) fn coinswap_fee(
+        &self,
+        hashvalue: &Hash160,
+        swap_amount: Amount,
+        refund_locktime: u16,
+    ) -> Result<Amount, MakerError> {
+        let policies = match self.swap_fee_policies.lock()?.get(hashvalue) {
+            Some((policies, _)) => policies.clone(),
+            None => vec![*self.offered_fee_policy.read()?],
+        };
+        Ok(policies
+            .iter()
+            .map(|policy| {
+                calculate_coinswap_fee(
+                    swap_amount.to_sat(),
+                    refund_locktime,
+                    policy.base_fee,
+                    policy.amount_relative_fee_pct,
+                    policy.time_relative_fee_pct,
+                )
+            })
+            .min()
+            .expect("at least one fee policy"))
+    }
+
+    /// Records a swap funded by the Maker in the swap ledger, and saves it to disk.
+    ///
+    /// Errors if a swap with the same id already ended, so a taker can't overwrite its record.
+    pub(crate) fn record_swap_started(&self, record: SwapRecord) -> Result<(), MakerError> {
+        let mut swap_ledger = self.swap_ledger.lock()?;
+        if !swap_ledger.record_started(record) {
+            return Err(MakerError::General(
+                "Swap id already used by an ended swap. Failing the swap.",
+            ));
+        }
+        swap_ledger.write_to_disk(&self.data_dir.join("swaps.dat"))
+    }
+
+    /// Records how a swap ended in the swap ledger, and saves it to disk.
+    pub(crate) fn record_swap_outcome(
+        &self,
+        id: &str,
+        outcome: SwapOutcome,
+    ) -> Result<(), MakerError> {
+        let mut swap_ledger = self.swap_ledger.lock()?;
+        if swap_ledger.record_outcome(id, outcome, unix_time()) {
+            swap_ledger.write_to_disk(&self.data_dir.join("swaps.dat"))?;
+        }
+        Ok(())
+    }
+
     /// Checks consistency of the [ProofOfFunding] message and return the Hashvalue
     /// used in hashlock transaction.
     pub(crate) fn verify_proof_of_funding(
@@ -344,6 +593,17 @@ impl Maker {
             return Err(MakerError::General("No funding txs provided by Taker"));
         }
 
+        // Contract txs below the relay feerate can't be broadcasted for recovery. Above our max
+        // feerate, the fees could eat up the funding amounts.
+        if !message.contract_feerate.is_finite()
+            || message.contract_feerate < MIN_RELAY_FEERATE
+            || message.contract_feerate > self.config.fee_estimator.max_feerate
+        {
+            return Err(MakerError::General(
+                "Invalid contract feerate provided by Taker",
+            ));
+        }
+
         for funding_info in &message.confirmed_funding_txes {
             // check that the new locktime is sufficently short enough compared to the
             // locktime in the provided funding tx
@@ -413,11 +673,66 @@ impl Maker {
         Ok(check_hashvalues_are_equal(message)?)
     }
 
+    /// Checks that the [UtxoOwnershipProof](crate::protocol::messages::UtxoOwnershipProof)s of a [ReqContractSigsForSender] prove unspent coins
+    /// worth at least its funding amount, on the connection of `handshake_hash`.
+    pub(crate) fn verify_utxo_proofs(
+        &self,
+        message: &ReqContractSigsForSender,
+        handshake_hash: &[u8; 32],
+    ) -> Result<(), MakerError> {
+        let funding_amount = message
+            .txs_info
+            .iter()
+            .map(|txinfo| txinfo.funding_input_value)
+            .sum::<Amount>();
+
+        let wallet = self.wallet.read()?;
+        let (_, tweakable_point) = wallet.get_tweakable_keypair()?;
+
+        let mut proven_outpoints = HashSet::new();
+        let mut proven_amount = Amount::ZERO;
+        for proof in &message.utxo_proofs {
+            if !proven_outpoints.insert(proof.outpoint) {
+                return Err(ProtocolError::General("utxo proven twice").into());
+            }
+            let txout = wallet
+                .rpc
+                .get_tx_out(&proof.outpoint.txid, proof.outpoint.vout, Some(true))
+                .map_err(WalletError::Rpc)?
+                .ok_or(ProtocolError::General(
+                    "proven utxo is spent or doesn't exist",
+                ))?;
+            verify_utxo_proof(
+                proof,
+                &tweakable_point,
+                &message.hashvalue,
+                handshake_hash,
+                Script::from_bytes(&txout.script_pub_key.hex),
+            )?;
+            proven_amount += txout.value;
+        }
+
+        if proven_amount < funding_amount {
+            return Err(ProtocolError::IncorrectFundingAmount {
+                expected: funding_amount,
+                found: proven_amount,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
     /// Verify the contract transaction for Sender and return the signatures.
     pub(crate) fn verify_and_sign_contract_tx(
         &self,
         message: &ReqContractSigsForSender,
+        handshake_hash: &[u8; 32],
     ) -> Result<Vec<Signature>, MakerError> {
+        // Don't reveal anything to takers who can't show the funds.
+        if self.config.require_utxo_proof {
+            self.verify_utxo_proofs(message, handshake_hash)?;
+        }
+
         let mut sigs = Vec::<Signature>::new();
         for txinfo in &message.txs_info {
             if txinfo.senders_contract_tx.input.len() != 1
@@ -529,6 +844,10 @@ pub(crate) fn check_for_broadcasted_contracts(maker: Arc<Maker>) -> Result<(), M
                         );
                         // Extract Incoming and Outgoing contracts, and timelock spends of the contract transactions.
                         // fully signed.
+                        let feerate = maker
+                            .wallet
+                            .read()?
+                            .estimate_feerate(&maker.config.fee_estimator)?;
                         for (og_sc, ic_sc) in connection_state
                             .outgoing_swapcoins
                             .iter()
@@ -538,7 +857,7 @@ pub(crate) fn check_for_broadcasted_contracts(maker: Arc<Maker>) -> Result<(), M
                             let next_internal_address =
                                 &maker.wallet.read()?.get_next_internal_addresses(1)?[0];
                             let time_lock_spend =
-                                og_sc.create_timelock_spend(next_internal_address)?;
+                                og_sc.create_timelock_spend(next_internal_address, feerate)?;
 
                             // Sometimes we might not have other's contact signatures.
                             // This means the protocol have been stopped abruptly.
@@ -589,9 +908,11 @@ pub(crate) fn check_for_broadcasted_contracts(maker: Arc<Maker>) -> Result<(), M
                 }
             }
 
-            // Clear the state entry here
+            // Clear the state entry here, and release its reserved liquidity.
             for ip in failed_swap_ip.iter() {
                 lock_onstate.remove(ip);
+                maker.wallet.write()?.release_utxos(ip)?;
+                maker.record_swap_outcome(ip, SwapOutcome::Recovered)?;
             }
         } // All locks are cleared here.
 
@@ -610,10 +931,14 @@ pub(crate) fn restore_broadcasted_contracts_on_reboot(maker: Arc<Maker>) -> Resu
     let mut incomings = Vec::new();
     // Extract Incoming and Outgoing contracts, and timelock spends of the contract transactions.
     // fully signed.
+    let feerate = maker
+        .wallet
+        .read()?
+        .estimate_feerate(&maker.config.fee_estimator)?;
     for og_sc in out.iter() {
         let contract_timelock = og_sc.get_timelock()?;
         let next_internal_address = &maker.wallet.read()?.get_next_internal_addresses(1)?[0];
-        let time_lock_spend = og_sc.create_timelock_spend(next_internal_address)?;
+        let time_lock_spend = og_sc.create_timelock_spend(next_internal_address, feerate)?;
 
         let tx = og_sc.get_fully_signed_contract_tx()?;
         outgoings.push((
@@ -679,6 +1004,10 @@ pub(crate) fn check_for_idle_states(maker: Arc<Maker>) -> Result<(), MakerError>
 
                     // Extract Incoming and Outgoing contracts, and timelock spends of the contract transactions.
                     // fully signed.
+                    let feerate = maker
+                        .wallet
+                        .read()?
+                        .estimate_feerate(&maker.config.fee_estimator)?;
                     for (og_sc, ic_sc) in state
                         .outgoing_swapcoins
                         .iter()
@@ -688,7 +1017,8 @@ pub(crate) fn check_for_idle_states(maker: Arc<Maker>) -> Result<(), MakerError>
                         let contract = og_sc.get_fully_signed_contract_tx()?;
                         let next_internal_address =
                             &maker.wallet.read()?.get_next_internal_addresses(1)?[0];
-                        let time_lock_spend = og_sc.create_timelock_spend(next_internal_address)?;
+                        let time_lock_spend =
+                            og_sc.create_timelock_spend(next_internal_address, feerate)?;
                         outgoings.push((
                             (og_sc.get_multisig_redeemscript(), contract),
                             (contract_timelock, time_lock_spend),
@@ -717,9 +1047,11 @@ pub(crate) fn check_for_idle_states(maker: Arc<Maker>) -> Result<(), MakerError>
                 }
             }
 
-            // Clear the state entry here
+            // Clear the state entry here, and release its reserved liquidity.
             for ip in bad_ip.iter() {
                 lock_on_state.remove(ip);
+                maker.wallet.write()?.release_utxos(ip)?;
+                maker.record_swap_outcome(ip, SwapOutcome::Recovered)?;
             }
         } // All locks are cleared here
 
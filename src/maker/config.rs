@@ -1,13 +1,72 @@
 //! Maker Configuration. Controlling various behaviors.
 
 use crate::utill::parse_toml;
-use std::{io, path::Path};
+use std::{io, path::Path, time::Duration};
 use bitcoin::Amount;
+use serde::{Deserialize, Serialize};
 use std::io::Write;
 
-use crate::utill::{get_maker_dir, parse_field, ConnectionType};
+use crate::{
+    utill::{get_maker_dir, parse_field, ConnectionType, MessageLimits},
+    wallet::{CoinSelectionAlgorithm, CrossPoolPolicy, FeeEstimator},
+};
 
-use super::api::MIN_SWAP_AMOUNT;
+use super::{
+    api::{AMOUNT_RELATIVE_FEE_PCT, BASE_FEE, MIN_SWAP_AMOUNT, TIME_RELATIVE_FEE_PCT},
+    rate_limit::PeerLimits,
+};
+
+/// The highest factor by which the relative fees are raised, when auto adjustment is enabled
+/// and all the swap liquidity is locked in live contracts.
+pub(crate) const MAX_FEE_ADJUSTMENT_FACTOR: f64 = 2.0;
+
+/// The fee policy of a Maker, advertised to Takers in its [`Offer`](crate::protocol::messages::Offer).
+///
+/// Refer to the fee parameters in [`maker::api`](super::api) for how the total swap fee is calculated.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FeePolicy {
+    /// Fixed fee in sats, charged for every swap.
+    pub base_fee: u64,
+    /// Percentage fee on the swap amount.
+    pub amount_relative_fee_pct: f64,
+    /// Percentage fee on the swap amount, per block of refund locktime.
+    pub time_relative_fee_pct: f64,
+    /// Raise the relative fees with the liquidity utilization of the Maker.
+    pub auto_adjust: bool,
+}
+
+impl Default for FeePolicy {
+    fn default() -> Self {
+        Self {
+            base_fee: BASE_FEE,
+            amount_relative_fee_pct: AMOUNT_RELATIVE_FEE_PCT,
+            time_relative_fee_pct: TIME_RELATIVE_FEE_PCT,
+            auto_adjust: false,
+        }
+    }
+}
+
+impl FeePolicy {
+    /// Returns the policy to be offered at the given liquidity utilization.
+    ///
+    /// `utilization` is the fraction of the Maker's liquidity locked in live swap contracts.
+    /// With auto adjustment, the relative fees scale linearly from 1x at no utilization up to
+    /// [`MAX_FEE_ADJUSTMENT_FACTOR`] at full utilization. The utilization is rounded down to 10% steps,
+    /// so the offered fees don't change between an offer and the swap that follows it.
+    /// The base fee is never adjusted.
+    pub(crate) fn adjusted(&self, utilization: f64) -> FeePolicy {
+        if !self.auto_adjust {
+            return *self;
+        }
+        let utilization = (utilization.clamp(0.0, 1.0) * 10.0).floor() / 10.0;
+        let factor = 1.0 + (MAX_FEE_ADJUSTMENT_FACTOR - 1.0) * utilization;
+        FeePolicy {
+            amount_relative_fee_pct: self.amount_relative_fee_pct * factor,
+            time_relative_fee_pct: self.time_relative_fee_pct * factor,
+            ..*self
+        }
+    }
+}
 
 /// Maker Configuration, controlling various maker behavior.
 #[derive(Debug, Clone, PartialEq)]
@@ -28,6 +87,22 @@ pub struct MakerConfig {
     pub fidelity_timelock: u32,
     /// Connection type
     pub connection_type: ConnectionType,
+    /// Fee policy, can be changed at runtime via `maker-cli set-fee-policy`.
+    pub fee_policy: FeePolicy,
+    /// Feerate estimation for the Maker's own transactions.
+    pub fee_estimator: FeeEstimator,
+    /// Limits on messages read from Takers.
+    pub p2p_limits: MessageLimits,
+    /// Limits on messages read from `maker-cli`.
+    pub rpc_limits: MessageLimits,
+    /// Limits on connections and requests from Takers.
+    pub peer_limits: PeerLimits,
+    /// Whether Takers must prove they own the swap amount before contracts are signed for them.
+    pub require_utxo_proof: bool,
+    /// How spends merging seed coins with swap coins are handled.
+    pub cross_pool_spend: CrossPoolPolicy,
+    /// The coin selection algorithm of the wallet.
+    pub coin_selection: CoinSelectionAlgorithm,
 }
 
 impl Default for MakerConfig {
@@ -57,6 +132,20 @@ impl Default for MakerConfig {
                     ConnectionType::CLEARNET
                 }
             },
+            fee_policy: FeePolicy::default(),
+            fee_estimator: FeeEstimator::default(),
+            p2p_limits: MessageLimits {
+                max_size: 4 * 1024 * 1024,
+                read_timeout: Duration::from_secs(60),
+            },
+            rpc_limits: MessageLimits {
+                max_size: 1024 * 1024,
+                read_timeout: Duration::from_secs(20),
+            },
+            peer_limits: PeerLimits::default(),
+            require_utxo_proof: false,
+            cross_pool_spend: CrossPoolPolicy::default(),
+            coin_selection: CoinSelectionAlgorithm::default(),
         }
     }
 }
@@ -119,6 +208,100 @@ impl MakerConfig {
                 config_map.get("connection_type"),
                 default_config.connection_type,
             ),
+            fee_policy: FeePolicy {
+                base_fee: parse_field(
+                    config_map.get("base_fee"),
+                    default_config.fee_policy.base_fee,
+                ),
+                amount_relative_fee_pct: parse_field(
+                    config_map.get("amount_relative_fee_pct"),
+                    default_config.fee_policy.amount_relative_fee_pct,
+                ),
+                time_relative_fee_pct: parse_field(
+                    config_map.get("time_relative_fee_pct"),
+                    default_config.fee_policy.time_relative_fee_pct,
+                ),
+                auto_adjust: parse_field(
+                    config_map.get("auto_adjust_fees"),
+                    default_config.fee_policy.auto_adjust,
+                ),
+            },
+            fee_estimator: FeeEstimator {
+                conf_target: parse_field(
+                    config_map.get("fee_conf_target"),
+                    default_config.fee_estimator.conf_target,
+                ),
+                min_feerate: parse_field(
+                    config_map.get("min_feerate"),
+                    default_config.fee_estimator.min_feerate,
+                ),
+                max_feerate: parse_field(
+                    config_map.get("max_feerate"),
+                    default_config.fee_estimator.max_feerate,
+                ),
+            },
+            p2p_limits: MessageLimits {
+                max_size: parse_field(
+                    config_map.get("p2p_max_message_size"),
+                    default_config.p2p_limits.max_size,
+                ),
+                read_timeout: Duration::from_secs(parse_field(
+                    config_map.get("p2p_message_timeout"),
+                    default_config.p2p_limits.read_timeout.as_secs(),
+                )),
+            },
+            rpc_limits: MessageLimits {
+                max_size: parse_field(
+                    config_map.get("rpc_max_message_size"),
+                    default_config.rpc_limits.max_size,
+                ),
+                read_timeout: Duration::from_secs(parse_field(
+                    config_map.get("rpc_message_timeout"),
+                    default_config.rpc_limits.read_timeout.as_secs(),
+                )),
+            },
+            peer_limits: PeerLimits {
+                max_connections: parse_field(
+                    config_map.get("max_connections"),
+                    default_config.peer_limits.max_connections,
+                ),
+                max_connections_per_peer: parse_field(
+                    config_map.get("max_connections_per_peer"),
+                    default_config.peer_limits.max_connections_per_peer,
+                ),
+                max_handshakes_per_minute: parse_field(
+                    config_map.get("max_handshakes_per_minute"),
+                    default_config.peer_limits.max_handshakes_per_minute,
+                ),
+                max_unfunded_sig_requests: parse_field(
+                    config_map.get("max_unfunded_sig_requests"),
+                    default_config.peer_limits.max_unfunded_sig_requests,
+                ),
+                ban_threshold: parse_field(
+                    config_map.get("ban_threshold"),
+                    default_config.peer_limits.ban_threshold,
+                ),
+                ban_duration: Duration::from_secs(parse_field(
+                    config_map.get("ban_duration"),
+                    default_config.peer_limits.ban_duration.as_secs(),
+                )),
+                idle_timeout: Duration::from_secs(parse_field(
+                    config_map.get("peer_idle_timeout"),
+                    default_config.peer_limits.idle_timeout.as_secs(),
+                )),
+            },
+            require_utxo_proof: parse_field(
+                config_map.get("require_utxo_proof"),
+                default_config.require_utxo_proof,
+            ),
+            cross_pool_spend: parse_field(
+                config_map.get("cross_pool_spend"),
+                default_config.cross_pool_spend,
+            ),
+            coin_selection: parse_field(
+                config_map.get("coin_selection"),
+                default_config.coin_selection,
+            ),
         })
     }
 
@@ -132,7 +315,28 @@ socks_port = {}
 directory_server_address = {}
 fidelity_amount = {}
 fidelity_timelock = {}
-connection_type = {:?}",
+connection_type = {:?}
+base_fee = {}
+amount_relative_fee_pct = {}
+time_relative_fee_pct = {}
+auto_adjust_fees = {}
+fee_conf_target = {}
+min_feerate = {}
+max_feerate = {}
+p2p_max_message_size = {}
+p2p_message_timeout = {}
+rpc_max_message_size = {}
+rpc_message_timeout = {}
+max_connections = {}
+max_connections_per_peer = {}
+max_handshakes_per_minute = {}
+max_unfunded_sig_requests = {}
+ban_threshold = {}
+ban_duration = {}
+peer_idle_timeout = {}
+require_utxo_proof = {}
+cross_pool_spend = {}
+coin_selection = {}",
             self.network_port,
             self.rpc_port,
             self.min_swap_amount,
@@ -141,6 +345,27 @@ connection_type = {:?}",
             self.fidelity_amount,
             self.fidelity_timelock,
             self.connection_type,
+            self.fee_policy.base_fee,
+            self.fee_policy.amount_relative_fee_pct,
+            self.fee_policy.time_relative_fee_pct,
+            self.fee_policy.auto_adjust,
+            self.fee_estimator.conf_target,
+            self.fee_estimator.min_feerate,
+            self.fee_estimator.max_feerate,
+            self.p2p_limits.max_size,
+            self.p2p_limits.read_timeout.as_secs(),
+            self.rpc_limits.max_size,
+            self.rpc_limits.read_timeout.as_secs(),
+            self.peer_limits.max_connections,
+            self.peer_limits.max_connections_per_peer,
+            self.peer_limits.max_handshakes_per_minute,
+            self.peer_limits.max_unfunded_sig_requests,
+            self.peer_limits.ban_threshold,
+            self.peer_limits.ban_duration.as_secs(),
+            self.peer_limits.idle_timeout.as_secs(),
+            self.require_utxo_proof,
+            self.cross_pool_spend,
+            self.coin_selection,
         );
 
         std::fs::create_dir_all(path.parent().expect("Path should NOT be root!"))?;
@@ -180,13 +405,33 @@ mod tests {
             required_confirms = 1
             min_swap_amount = 10000
             socks_port = 19050
+            p2p_max_message_size = 100000
+            p2p_message_timeout = 30
+            rpc_message_timeout = 5
         "#;
         let config_path = create_temp_config(contents, "valid_maker_config.toml");
         let config = MakerConfig::new(Some(&config_path)).unwrap();
-        remove_temp_config(&config_path);
 
         let default_config = MakerConfig::default();
-        assert_eq!(config, default_config);
+        assert_eq!(
+            config,
+            MakerConfig {
+                p2p_limits: MessageLimits {
+                    max_size: 100000,
+                    read_timeout: Duration::from_secs(30),
+                },
+                rpc_limits: MessageLimits {
+                    read_timeout: Duration::from_secs(5),
+                    ..default_config.rpc_limits
+                },
+                ..default_config
+            }
+        );
+
+        config.write_to_file(&config_path).unwrap();
+        let reloaded = MakerConfig::new(Some(&config_path)).unwrap();
+        remove_temp_config(&config_path);
+        assert_eq!(reloaded, config);
     }
 
     #[test]
@@ -222,6 +467,134 @@ mod tests {
         assert_eq!(config, MakerConfig::default());
     }
 
+    #[test]
+    fn test_fee_policy_config() {
+        let contents = r#"
+            base_fee = 500
+            amount_relative_fee_pct = 0.25
+            time_relative_fee_pct = 0.01
+            auto_adjust_fees = true
+        "#;
+        let config_path = create_temp_config(contents, "fee_policy_maker_config.toml");
+        let config = MakerConfig::new(Some(&config_path)).unwrap();
+
+        let expected = FeePolicy {
+            base_fee: 500,
+            amount_relative_fee_pct: 0.25,
+            time_relative_fee_pct: 0.01,
+            auto_adjust: true,
+        };
+        assert_eq!(config.fee_policy, expected);
+
+        // The policy survives a write and reload.
+        config.write_to_file(&config_path).unwrap();
+        let reloaded = MakerConfig::new(Some(&config_path)).unwrap();
+        remove_temp_config(&config_path);
+        assert_eq!(reloaded, config);
+    }
+
+    #[test]
+    fn test_fee_estimator_config() {
+        let contents = r#"
+            fee_conf_target = 6
+            min_feerate = 1.5
+            max_feerate = 50.0
+        "#;
+        let config_path = create_temp_config(contents, "fee_estimator_maker_config.toml");
+        let config = MakerConfig::new(Some(&config_path)).unwrap();
+
+        let expected = FeeEstimator {
+            conf_target: 6,
+            min_feerate: 1.5,
+            max_feerate: 50.0,
+        };
+        assert_eq!(config.fee_estimator, expected);
+
+        config.write_to_file(&config_path).unwrap();
+        let reloaded = MakerConfig::new(Some(&config_path)).unwrap();
+        remove_temp_config(&config_path);
+        assert_eq!(reloaded, config);
+    }
+
+    #[test]
+    fn test_cross_pool_spend_config() {
+        let contents = r#"
+            cross_pool_spend = allow
+        "#;
+        let config_path = create_temp_config(contents, "cross_pool_maker_config.toml");
+        let config = MakerConfig::new(Some(&config_path)).unwrap();
+        assert_eq!(config.cross_pool_spend, CrossPoolPolicy::Allow);
+
+        config.write_to_file(&config_path).unwrap();
+        let reloaded = MakerConfig::new(Some(&config_path)).unwrap();
+        remove_temp_config(&config_path);
+        assert_eq!(reloaded, config);
+
+        // Unknown policies fall back to refusing.
+        let contents = r#"
+            cross_pool_spend = merge
+        "#;
+        let config_path = create_temp_config(contents, "bad_cross_pool_maker_config.toml");
+        let config = MakerConfig::new(Some(&config_path)).unwrap();
+        remove_temp_config(&config_path);
+        assert_eq!(config.cross_pool_spend, CrossPoolPolicy::Refuse);
+    }
+
+    #[test]
+    fn test_peer_limits_config() {
+        let contents = r#"
+            max_connections = 10
+            max_connections_per_peer = 2
+            ban_duration = 600
+            peer_idle_timeout = 120
+            require_utxo_proof = true
+        "#;
+        let config_path = create_temp_config(contents, "peer_limits_maker_config.toml");
+        let config = MakerConfig::new(Some(&config_path)).unwrap();
+
+        let expected = PeerLimits {
+            max_connections: 10,
+            max_connections_per_peer: 2,
+            ban_duration: Duration::from_secs(600),
+            idle_timeout: Duration::from_secs(120),
+            ..PeerLimits::default()
+        };
+        assert_eq!(config.peer_limits, expected);
+        assert!(config.require_utxo_proof);
+
+        config.write_to_file(&config_path).unwrap();
+        let reloaded = MakerConfig::new(Some(&config_path)).unwrap();
+        remove_temp_config(&config_path);
+        assert_eq!(reloaded, config);
+    }
+
+    #[test]
+    fn test_fee_policy_adjustment() {
+        let policy = FeePolicy {
+            base_fee: 100,
+            amount_relative_fee_pct: 0.1,
+            time_relative_fee_pct: 0.005,
+            auto_adjust: false,
+        };
+        assert_eq!(policy.adjusted(0.8), policy);
+
+        let policy = FeePolicy {
+            auto_adjust: true,
+            ..policy
+        };
+        assert_eq!(policy.adjusted(0.0), policy);
+        // Utilization is rounded down to 10% steps.
+        assert_eq!(policy.adjusted(0.05), policy);
+        assert_eq!(policy.adjusted(0.55), policy.adjusted(0.5));
+
+        let full = policy.adjusted(1.0);
+        assert_eq!(full.base_fee, 100);
+        assert!((full.amount_relative_fee_pct - 0.2).abs() < 1e-9);
+        assert!((full.time_relative_fee_pct - 0.01).abs() < 1e-9);
+        // Out of range utilization is clamped.
+        assert_eq!(policy.adjusted(3.0), full);
+    }
+
     #[test]
     fn test_missing_file() {
         let config_path = get_maker_dir().join("maker.toml");
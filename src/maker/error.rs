@@ -6,7 +6,7 @@ use bitcoin::secp256k1;
 
 use crate::{error::NetError, protocol::error::ProtocolError, wallet::WalletError};
 
-use super::MakerBehavior;
+use super::{MakerBehavior, PeerRejection};
 
 /// Enum to handle Maker-related errors.
 ///
@@ -38,6 +38,8 @@ pub enum MakerError {
     SpecialBehaviour(MakerBehavior),
     /// Represents a protocol-related error.
     Protocol(ProtocolError),
+    /// Represents a connection or request refused by the peer limits.
+    PeerRejected(PeerRejection),
 }
 
 impl From<std::io::Error> for MakerError {
@@ -99,3 +101,9 @@ impl From<NetError> for MakerError {
         Self::Net(value)
     }
 }
+
+impl From<PeerRejection> for MakerError {
+    fn from(value: PeerRejection) -> Self {
+        Self::PeerRejected(value)
+    }
+}
@@ -7,39 +7,51 @@
 //! The file includes functions to validate and sign contract transactions, verify proof of funding, and handle unexpected recovery scenarios.
 //! Implements the core functionality for a Maker in a Bitcoin coinswap protocol.
 
-use std::{collections::HashMap, sync::Arc, time::Instant};
+use std::{sync::Arc, time::Instant};
 
 use bitcoin::{
     hashes::Hash,
     secp256k1::{self, Secp256k1},
     Amount, OutPoint, PublicKey, Transaction, Txid,
 };
+use bitcoind::bitcoincore_rpc::RpcApi;
 
 use super::{
     api::{
         recover_from_swap, ConnectionState, ExpectedMessage, Maker, MakerBehavior,
-        AMOUNT_RELATIVE_FEE_PCT, BASE_FEE, MIN_CONTRACT_REACTION_TIME, TIME_RELATIVE_FEE_PCT,
+        MIN_CONTRACT_REACTION_TIME,
     },
     error::MakerError,
+    rate_limit::PeerRejection,
+    swap_ledger::{SwapOutcome, SwapRecord},
 };
 
 use crate::{
     protocol::{
         contract::{
-            calculate_coinswap_fee, create_receivers_contract_tx, find_funding_output_index,
-            read_hashvalue_from_contract, read_pubkeys_from_multisig_redeemscript,
+            create_receivers_contract_tx, create_refund_tx, find_funding_output_index,
+            negotiate_contract_type, read_hashvalue_from_contract,
+            read_pubkeys_from_multisig_redeemscript, sign_contract_tx, validate_refund_tx,
+            SUPPORTED_CONTRACT_TYPES,
         },
         error::ProtocolError,
         messages::{
-            ContractSigsAsRecvrAndSender, ContractSigsForRecvr, ContractSigsForRecvrAndSender,
-            ContractSigsForSender, HashPreimage, MakerHello, MakerToTakerMessage, MultisigPrivkey,
-            Offer, PrivKeyHandover, ProofOfFunding, ReqContractSigsForRecvr,
-            ReqContractSigsForSender, SenderContractTxInfo, TakerToMakerMessage,
+            negotiate_version, ContractSigsAsRecvrAndSender, ContractSigsForRecvr,
+            ContractSigsForRecvrAndSender, ContractSigsForSender, ContractType, Features,
+            HashPreimage, MakerHello, MakerToTakerMessage, MultisigPrivkey, NegotiatedProtocol,
+            Offer, PrivKeyHandover, ProofOfFunding, RefundSigs, RefundTxInfo, RefundTxs,
+            ReqContractSigsForRecvr, ReqContractSigsForSender, ReqRefundSigs, ReqRefundTxs,
+            SenderContractTxInfo, TakerToMakerMessage, CONNECTION_POW_BITS, PROTOCOL_VERSION_MAX,
+            PROTOCOL_VERSION_MIN, SUPPORTED_FEATURES,
         },
+        noise::check_pow,
         Hash160,
     },
-    utill::REQUIRED_CONFIRMS,
-    wallet::{IncomingSwapCoin, SwapCoin, WalletError, WalletSwapCoin},
+    utill::{unix_time, REQUIRED_CONFIRMS},
+    wallet::{
+        calculate_fee, contract_tx_vsize, funding_tx_vsize, refund_tx_vsize, IncomingSwapCoin,
+        SwapCoin, WalletError, WalletSwapCoin,
+    },
 };
 
 /// The Global Handle Message function. Takes in a [`Arc<Maker>`] and handle messages
@@ -49,25 +61,82 @@ pub(crate) fn handle_message(
     connection_state: &mut ConnectionState,
     message: TakerToMakerMessage,
 ) -> Result<Option<MakerToTakerMessage>, MakerError> {
+    // Charge the peer for the messages that cost us work, before doing any of it.
+    // Takers we can't tell apart pay for each connection with a proof-of-work instead, and get one
+    // signing request per connection, so every request costs a fresh proof.
+    match message {
+        TakerToMakerMessage::TakerHello(ref m) => {
+            if connection_state.peer.is_none()
+                && !check_pow(
+                    &connection_state.handshake_hash,
+                    m.pow_nonce,
+                    CONNECTION_POW_BITS,
+                )
+            {
+                return Err(PeerRejection::MissingProofOfWork.into());
+            }
+            maker
+                .peers
+                .lock()?
+                .handshake(connection_state.peer, Instant::now())?
+        }
+        TakerToMakerMessage::ReqContractSigsForSender(_) => {
+            if connection_state.peer.is_none() && connection_state.sig_requests > 0 {
+                return Err(PeerRejection::TooManyUnfundedRequests.into());
+            }
+            connection_state.sig_requests += 1;
+            maker
+                .peers
+                .lock()?
+                .sig_request(connection_state.peer, Instant::now())?
+        }
+        _ => {}
+    }
+    let is_proof_of_funding = matches!(message, TakerToMakerMessage::RespProofOfFunding(_));
+
     let outgoing_message = match connection_state.allowed_message {
         ExpectedMessage::TakerHello => {
             if let TakerToMakerMessage::TakerHello(m) = message {
-                if m.protocol_version_min != 1 && m.protocol_version_max != 1 {
-                    return Err(ProtocolError::WrongMessage {
-                        expected: "Only protocol version 1 is allowed".to_string(),
-                        received: format!(
-                            "min/max version  = {}/{}",
-                            m.protocol_version_min, m.protocol_version_max
-                        ),
-                    }
-                    .into());
-                }
+                let mut reply = MakerHello {
+                    protocol_version_min: PROTOCOL_VERSION_MIN,
+                    protocol_version_max: PROTOCOL_VERSION_MAX,
+                    contract_type: ContractType::default(),
+                    features: SUPPORTED_FEATURES,
+                };
+                // Without a common version, still reply with our version range so the taker can
+                // tell why it's rejected. The connection stays waiting for a TakerHello.
+                let version =
+                    match negotiate_version(m.protocol_version_min, m.protocol_version_max) {
+                        Ok(version) => version,
+                        Err(e) => {
+                            log::warn!(
+                                "[{}] Rejecting taker hello: {:?}",
+                                maker.config.network_port,
+                                e
+                            );
+                            return Ok(Some(MakerToTakerMessage::MakerHello(reply)));
+                        }
+                    };
+                reply.contract_type = negotiate_contract_type(
+                    SUPPORTED_CONTRACT_TYPES,
+                    &m.contract_types,
+                )
+                .ok_or(ProtocolError::WrongMessage {
+                    expected: format!("One of contract types {:?}", SUPPORTED_CONTRACT_TYPES),
+                    received: format!("{:?}", m.contract_types),
+                })?;
+                connection_state.protocol = NegotiatedProtocol {
+                    version,
+                    features: SUPPORTED_FEATURES.intersection(m.features),
+                    contract_type: reply.contract_type,
+                };
+                log::info!(
+                    "[{}] Negotiated protocol : {:?}",
+                    maker.config.network_port,
+                    connection_state.protocol
+                );
                 connection_state.allowed_message = ExpectedMessage::NewlyConnectedTaker;
-                let reply = MakerToTakerMessage::MakerHello(MakerHello {
-                    protocol_version_min: 1,
-                    protocol_version_max: 1,
-                });
-                Some(reply)
+                Some(MakerToTakerMessage::MakerHello(reply))
             } else {
                 return Err(MakerError::UnexpectedMessage {
                     expected: "TakerHello".to_string(),
@@ -86,21 +155,26 @@ pub(crate) fn handle_message(
                 connection_state.allowed_message = ExpectedMessage::ReqContractSigsForSender;
                 let fidelity = maker.highest_fidelity_proof.read()?;
                 let fidelity = fidelity.as_ref().expect("proof expected");
+                let fee_policy = maker.serve_fee_policy()?;
                 Some(MakerToTakerMessage::RespOffer(Box::new(Offer {
-                    base_fee: BASE_FEE,
-                    amount_relative_fee_pct: AMOUNT_RELATIVE_FEE_PCT,
-                    time_relative_fee_pct: TIME_RELATIVE_FEE_PCT,
+                    base_fee: fee_policy.base_fee,
+                    amount_relative_fee_pct: fee_policy.amount_relative_fee_pct,
+                    time_relative_fee_pct: fee_policy.time_relative_fee_pct,
                     required_confirms: REQUIRED_CONFIRMS,
                     minimum_locktime: MIN_CONTRACT_REACTION_TIME,
                     max_size,
                     min_size: maker.config.min_swap_amount,
                     tweakable_point,
                     fidelity: fidelity.clone(),
+                    requires_utxo_proof: maker.config.require_utxo_proof,
                 })))
             }
             TakerToMakerMessage::ReqContractSigsForSender(message) => {
                 connection_state.allowed_message = ExpectedMessage::ProofOfFunding;
-                Some(maker.handle_req_contract_sigs_for_sender(message)?)
+                Some(maker.handle_req_contract_sigs_for_sender(
+                    message,
+                    &connection_state.handshake_hash,
+                )?)
             }
             TakerToMakerMessage::RespProofOfFunding(proof) => {
                 connection_state.allowed_message =
@@ -115,6 +189,22 @@ pub(crate) fn handle_message(
                 connection_state.allowed_message = ExpectedMessage::PrivateKeyHandover;
                 Some(maker.handle_hash_preimage(message)?)
             }
+            TakerToMakerMessage::ReqRefundTxs(message)
+                if connection_state
+                    .protocol
+                    .features
+                    .contains(Features::COOPERATIVE_ABORT) =>
+            {
+                Some(maker.handle_req_refund_txs(message)?)
+            }
+            TakerToMakerMessage::ReqRefundSigs(message)
+                if connection_state
+                    .protocol
+                    .features
+                    .contains(Features::COOPERATIVE_ABORT) =>
+            {
+                Some(maker.handle_req_refund_sigs(message)?)
+            }
             _ => {
                 log::info!("Newlyconnected taker stage message: {:?} ", message);
                 return Err(MakerError::General(
@@ -125,7 +215,10 @@ pub(crate) fn handle_message(
         ExpectedMessage::ReqContractSigsForSender => {
             if let TakerToMakerMessage::ReqContractSigsForSender(message) = message {
                 connection_state.allowed_message = ExpectedMessage::ProofOfFunding;
-                Some(maker.handle_req_contract_sigs_for_sender(message)?)
+                Some(maker.handle_req_contract_sigs_for_sender(
+                    message,
+                    &connection_state.handshake_hash,
+                )?)
             } else {
                 return Err(MakerError::UnexpectedMessage {
                     expected: "ReqContractSigsForSender".to_string(),
@@ -199,6 +292,10 @@ pub(crate) fn handle_message(
         }
     };
 
+    if is_proof_of_funding {
+        maker.peers.lock()?.funded(connection_state.peer);
+    }
+
     Ok(outgoing_message)
 }
 
@@ -206,17 +303,18 @@ impl Maker {
     /// This is the first message handler for the Maker. It receives a [ReqContractSigsForSender] message,
     /// checks the validity of contract transactions, and provide's the signature for the sender side.
     /// This will fail if the maker doesn't have enough utxos to fund the next coinswap hop, or the contract
-    /// transaction isn't valid.
+    /// transaction isn't valid. `handshake_hash` is the one of the taker's connection.
     pub(crate) fn handle_req_contract_sigs_for_sender(
         &self,
         message: ReqContractSigsForSender,
+        handshake_hash: &[u8; 32],
     ) -> Result<MakerToTakerMessage, MakerError> {
         if let MakerBehavior::CloseAtReqContractSigsForSender = self.behavior {
             return Err(self.behavior.into());
         }
 
         // Verify and sign the contract transaction, check function definition for all the checks.
-        let sigs = self.verify_and_sign_contract_tx(&message)?;
+        let sigs = self.verify_and_sign_contract_tx(&message, handshake_hash)?;
 
         let funding_txids = message
             .txs_info
@@ -237,6 +335,7 @@ impl Maker {
 
         let max_size = self.wallet.read()?.store.offer_maxsize;
         if total_funding_amount >= self.config.min_swap_amount && total_funding_amount <= max_size {
+            self.pin_fee_policies(message.hashvalue)?;
             Ok(MakerToTakerMessage::RespContractSigsForSender(
                 ContractSigsForSender { sigs },
             ))
@@ -291,7 +390,7 @@ impl Maker {
                 },
                 funding_output.value,
                 &funding_info.contract_redeemscript,
-                Amount::from_sat(message.contract_feerate),
+                calculate_fee(message.contract_feerate, contract_tx_vsize()),
             )?;
 
             let (tweakable_privkey, _) = self.wallet.read()?.get_tweakable_keypair()?;
@@ -344,20 +443,14 @@ impl Maker {
                 Ok::<_, MakerError>(acc + txout.value)
             })?;
 
-        let calc_coinswap_fees = Amount::from_sat(calculate_coinswap_fee(
-            incoming_amount,
-            message.refund_locktime,
-            BASE_FEE,
-            AMOUNT_RELATIVE_FEE_PCT,
-            TIME_RELATIVE_FEE_PCT,
-        ));
-
-        // NOTE: The `contract_feerate` currently represents the hardcoded `MINER_FEE` of a transaction, not the fee rate.
-        // This will remain unchanged to avoid modifying the structure of the [ProofOfFunding] message.
-        // Once issue https://github.com/citadel-tech/coinswap/issues/309 is resolved,
-        //`contract_feerate` will represent the actual fee rate instead of the `MINER_FEE`.
-        let calc_funding_tx_fees =
-            Amount::from_sat(message.contract_feerate * (message.next_coinswap_info.len() as u64));
+        // Charge the fees of the offer the taker accepted, which it has used to calculate the amounts.
+        let calc_coinswap_fees =
+            self.coinswap_fee(&hashvalue, incoming_amount, message.refund_locktime)?;
+
+        // The taker pays the fees of our funding txs at its feerate. Extra fees for
+        // spending more inputs are paid by us.
+        let calc_funding_tx_fees = calculate_fee(message.contract_feerate, funding_tx_vsize())
+            * message.next_coinswap_info.len() as u64;
 
         // Check for overflow. If happens hard error.
         // This can happen if the fee_rate for funding tx is very high and incoming_amount is very low.
@@ -372,9 +465,11 @@ impl Maker {
             ));
         };
 
-        // Create outgoing coinswap of the next hop
-        let (my_funding_txes, outgoing_swapcoins, act_funding_txs_fees) = {
-            self.wallet.write()?.initalize_coinswap(
+        // Create outgoing coinswap of the next hop, and reserve the coins it spends under the same
+        // wallet lock, so no concurrent swap can select them.
+        let (my_funding_txes, outgoing_swapcoins, act_funding_txs_fees, act_coinswap_fees) = {
+            let mut wallet_writer = self.wallet.write()?;
+            let (funding_txs, swapcoins, funding_txs_fees) = wallet_writer.initalize_coinswap(
                 Amount::from_sat(outgoing_amount),
                 &message
                     .next_coinswap_info
@@ -388,84 +483,117 @@ impl Maker {
                     .collect::<Vec<PublicKey>>(),
                 hashvalue,
                 message.refund_locktime,
-                Amount::from_sat(message.contract_feerate),
-            )?
+                message.contract_feerate,
+            )?;
+            // Our actual funding fees can exceed what the taker paid for, eating into the coinswap
+            // fee. Refuse the swap if they exceed it, before any coins are reserved.
+            let act_coinswap_fees = incoming_amount
+                .checked_sub(Amount::from_sat(outgoing_amount) + funding_txs_fees)
+                .ok_or(MakerError::General(
+                    "Funding tx fees are more than the coinswap fee. Failing the swap.",
+                ))?;
+            wallet_writer.reserve_utxos(&message.id, &funding_txs)?;
+            (funding_txs, swapcoins, funding_txs_fees, act_coinswap_fees)
         };
 
-        let act_coinswap_fees = incoming_amount
-            .checked_sub(outgoing_amount + act_funding_txs_fees)
-            .expect("This should not overflow as we just above.");
+        // Everything past this point can still fail the swap. Release the reservation if it does,
+        // so the coins are not locked away from other swaps until restart.
+        let result = (|| -> Result<MakerToTakerMessage, MakerError> {
+            log::info!(
+                "[{}] Outgoing Funding Txids: {:?}.",
+                self.config.network_port,
+                my_funding_txes
+                    .iter()
+                    .map(|tx| tx.compute_txid())
+                    .collect::<Vec<_>>()
+            );
 
-        log::info!(
-            "[{}] Outgoing Funding Txids: {:?}.",
-            self.config.network_port,
-            my_funding_txes
-                .iter()
-                .map(|tx| tx.compute_txid())
-                .collect::<Vec<_>>()
-        );
+            log::info!(
+                "[{}] Incoming Swap Amount = {} | Outgoing Swap Amount = {} | Coinswap Fee = {} |   Refund Tx locktime (blocks) = {} | Total Funding Tx Mining Fees = {} |",
+                self.config.network_port,
+                Amount::from_sat(incoming_amount),
+                Amount::from_sat(outgoing_amount),
+                Amount::from_sat(act_coinswap_fees),
+                message.refund_locktime,
+                act_funding_txs_fees
+            );
 
-        log::info!(
-            "[{}] Incoming Swap Amount = {} | Outgoing Swap Amount = {} | Coinswap Fee = {} |   Refund Tx locktime (blocks) = {} | Total Funding Tx Mining Fees = {} |",
-            self.config.network_port,
-            Amount::from_sat(incoming_amount),
-            Amount::from_sat(outgoing_amount),
-            Amount::from_sat(act_coinswap_fees),
-            message.refund_locktime,
-            act_funding_txs_fees
-        );
+            self.record_swap_started(SwapRecord {
+                id: message.id.clone(),
+                started_at: unix_time(),
+                finished_at: None,
+                incoming_amount,
+                outgoing_amount: outgoing_swapcoins
+                    .iter()
+                    .map(|swapcoin| swapcoin.funding_amount)
+                    .sum(),
+                incoming_txids: message
+                    .confirmed_funding_txes
+                    .iter()
+                    .map(|funding_info| funding_info.funding_tx.compute_txid())
+                    .collect(),
+                outgoing_txids: my_funding_txes.iter().map(|tx| tx.compute_txid()).collect(),
+                mining_fee: act_funding_txs_fees,
+                mining_fee_reimbursed: calc_funding_tx_fees,
+                outcome: SwapOutcome::InProgress,
+            })?;
 
-        connection_state.pending_funding_txes = my_funding_txes;
-        connection_state.outgoing_swapcoins = outgoing_swapcoins;
+            connection_state.pending_funding_txes = my_funding_txes;
+            connection_state.outgoing_swapcoins = outgoing_swapcoins;
 
-        // Save things to disk after Proof of Funding is confirmed.
-        {
-            let mut wallet_writer = self.wallet.write()?;
-            for (incoming_sc, outgoing_sc) in connection_state
-                .incoming_swapcoins
-                .iter()
-                .zip(connection_state.outgoing_swapcoins.iter())
+            // Save things to disk after Proof of Funding is confirmed.
             {
-                wallet_writer.add_incoming_swapcoin(incoming_sc);
-                wallet_writer.add_outgoing_swapcoin(outgoing_sc);
+                let mut wallet_writer = self.wallet.write()?;
+                for (incoming_sc, outgoing_sc) in connection_state
+                    .incoming_swapcoins
+                    .iter()
+                    .zip(connection_state.outgoing_swapcoins.iter())
+                {
+                    wallet_writer.add_incoming_swapcoin(incoming_sc);
+                    wallet_writer.add_outgoing_swapcoin(outgoing_sc);
+                }
+                wallet_writer.save_to_disk()?;
             }
-            wallet_writer.save_to_disk()?;
-        }
 
-        // Craft ReqContractSigsAsRecvrAndSender message to send to the Taker.
-        let receivers_contract_txs = connection_state
-            .incoming_swapcoins
-            .iter()
-            .map(|isc| isc.contract_tx.clone())
-            .collect::<Vec<Transaction>>();
+            // Craft ReqContractSigsAsRecvrAndSender message to send to the Taker.
+            let receivers_contract_txs = connection_state
+                .incoming_swapcoins
+                .iter()
+                .map(|isc| isc.contract_tx.clone())
+                .collect::<Vec<Transaction>>();
 
-        let senders_contract_txs_info = connection_state
-            .outgoing_swapcoins
-            .iter()
-            .map(|outgoing_swapcoin| {
-                Ok(SenderContractTxInfo {
-                    contract_tx: outgoing_swapcoin.contract_tx.clone(),
-                    timelock_pubkey: outgoing_swapcoin.get_timelock_pubkey()?,
-                    multisig_redeemscript: outgoing_swapcoin.get_multisig_redeemscript(),
-                    funding_amount: outgoing_swapcoin.funding_amount,
+            let senders_contract_txs_info = connection_state
+                .outgoing_swapcoins
+                .iter()
+                .map(|outgoing_swapcoin| {
+                    Ok(SenderContractTxInfo {
+                        contract_tx: outgoing_swapcoin.contract_tx.clone(),
+                        timelock_pubkey: outgoing_swapcoin.get_timelock_pubkey()?,
+                        multisig_redeemscript: outgoing_swapcoin.get_multisig_redeemscript(),
+                        funding_amount: outgoing_swapcoin.funding_amount,
+                    })
                 })
-            })
-            .collect::<Result<Vec<SenderContractTxInfo>, WalletError>>()?;
+                .collect::<Result<Vec<SenderContractTxInfo>, WalletError>>()?;
 
-        // Update the connection state.
-        self.connection_state.lock()?.insert(
-            message.id.clone(),
-            (connection_state.clone(), Instant::now()),
-        );
+            // Update the connection state.
+            self.connection_state.lock()?.insert(
+                message.id.clone(),
+                (connection_state.clone(), Instant::now()),
+            );
 
-        log::info!("Connection state initiatilzed for swap id: {}", message.id);
+            log::info!("Connection state initiatilzed for swap id: {}", message.id);
 
-        Ok(MakerToTakerMessage::ReqContractSigsAsRecvrAndSender(
-            ContractSigsAsRecvrAndSender {
-                receivers_contract_txs,
-                senders_contract_txs_info,
-            },
-        ))
+            Ok(MakerToTakerMessage::ReqContractSigsAsRecvrAndSender(
+                ContractSigsAsRecvrAndSender {
+                    receivers_contract_txs,
+                    senders_contract_txs_info,
+                },
+            ))
+        })();
+        if result.is_err() {
+            self.wallet.write()?.release_utxos(&message.id)?;
+        }
+        result
     }
 
     /// Handles [ContractSigsForRecvrAndSender] message and updates the wallet state
@@ -621,6 +749,180 @@ impl Maker {
         }))
     }
 
+    /// Handles a [ReqRefundTxs] message. Creates refund txs of our outgoing swapcoins in the swap back into
+    /// our wallet, and signs them. Getting our own coins back is always fine for us, it's up to the
+    /// receiver of the hop to sign them too.
+    pub(crate) fn handle_req_refund_txs(
+        &self,
+        message: ReqRefundTxs,
+    ) -> Result<MakerToTakerMessage, MakerError> {
+        let state = self
+            .connection_state
+            .lock()?
+            .get(&message.id)
+            .map(|(state, _)| state.clone())
+            .ok_or(MakerError::General("no ongoing swap with this id"))?;
+
+        let (fee, destination) = {
+            let wallet_read = self.wallet.read()?;
+            let feerate = wallet_read.estimate_feerate(&self.config.fee_estimator)?;
+            let destination = wallet_read.get_next_internal_addresses(1)?[0].script_pubkey();
+            (calculate_fee(feerate, refund_tx_vsize()), destination)
+        };
+
+        let mut refunds = Vec::new();
+        let mut sigs = Vec::new();
+        for outgoing_swapcoin in &state.outgoing_swapcoins {
+            let refund_tx = create_refund_tx(
+                outgoing_swapcoin.contract_tx.input[0].previous_output,
+                outgoing_swapcoin.funding_amount,
+                destination.clone(),
+                fee,
+            )?;
+            sigs.push(outgoing_swapcoin.sign_contract_tx_with_my_privkey(&refund_tx)?);
+            refunds.push(RefundTxInfo {
+                multisig_redeemscript: outgoing_swapcoin.get_multisig_redeemscript(),
+                refund_tx,
+            });
+        }
+
+        // Remember the refunds, to check that they are the ones confirmed before signing away the
+        // incoming swapcoins.
+        if let Some((state, last_seen)) = self.connection_state.lock()?.get_mut(&message.id) {
+            state.refund_txids.extend(refunds.iter().map(|refund| {
+                (
+                    refund.refund_tx.input[0].previous_output,
+                    refund.refund_tx.compute_txid(),
+                )
+            }));
+            *last_seen = Instant::now();
+        }
+
+        log::info!(
+            "[{}] Signed {} refund txs of outgoing swapcoins for swap id: {}",
+            self.config.network_port,
+            refunds.len(),
+            message.id
+        );
+
+        Ok(MakerToTakerMessage::RespRefundTxs(RefundTxs {
+            refunds,
+            sigs,
+        }))
+    }
+
+    /// Handles a [ReqRefundSigs] message, and signs the refund txs of our incoming swapcoins in the swap.
+    ///
+    /// Giving up the incoming swapcoins is only safe while the hash preimage is unknown, and once the
+    /// next peer can't claim any of our outgoing swapcoins. So each of their funding outputs must be
+    /// spent by one of our refund txs, with [REQUIRED_CONFIRMS] confirmations. A refund in the mempool
+    /// could still be replaced by the contract tx, and the next peer claim it with the preimage.
+    /// Until then the swap is kept, and it's forgotten along with all its swapcoins after.
+    pub(crate) fn handle_req_refund_sigs(
+        &self,
+        message: ReqRefundSigs,
+    ) -> Result<MakerToTakerMessage, MakerError> {
+        let state = self
+            .connection_state
+            .lock()?
+            .get(&message.id)
+            .map(|(state, _)| state.clone())
+            .ok_or(MakerError::General("no ongoing swap with this id"))?;
+
+        // Every incoming swapcoin needs exactly one refund.
+        if message.refunds.len() != state.incoming_swapcoins.len()
+            || !state.incoming_swapcoins.iter().all(|incoming_swapcoin| {
+                message.refunds.iter().any(|refund| {
+                    refund.multisig_redeemscript == incoming_swapcoin.get_multisig_redeemscript()
+                })
+            })
+        {
+            return Err(MakerError::General(
+                "refund txs don't match the incoming swapcoins",
+            ));
+        }
+
+        {
+            let wallet_read = self.wallet.read()?;
+            for outgoing_swapcoin in &state.outgoing_swapcoins {
+                let funding_outpoint = outgoing_swapcoin.contract_tx.input[0].previous_output;
+                let refunded = state
+                    .refund_txids
+                    .iter()
+                    .filter(|(outpoint, _)| *outpoint == funding_outpoint)
+                    .any(|(_, txid)| {
+                        wallet_read
+                            .rpc
+                            .get_raw_transaction_info(txid, None)
+                            .is_ok_and(|info| info.confirmations >= Some(REQUIRED_CONFIRMS))
+                    });
+                if !refunded {
+                    return Err(MakerError::General(
+                        "outgoing swapcoins are not refunded by confirmed refund txs",
+                    ));
+                }
+            }
+        }
+
+        let mut sigs = Vec::new();
+        for refund in &message.refunds {
+            let incoming_swapcoin = state
+                .incoming_swapcoins
+                .iter()
+                .find(|swapcoin| {
+                    swapcoin.get_multisig_redeemscript() == refund.multisig_redeemscript
+                })
+                .expect("incoming swapcoin expected");
+            // The preimage and privkey are recorded on the wallet's copy of the swapcoin.
+            if self
+                .wallet
+                .read()?
+                .find_incoming_swapcoin(&refund.multisig_redeemscript)
+                .is_some_and(|swapcoin| {
+                    swapcoin.is_hash_preimage_known() || swapcoin.other_privkey.is_some()
+                })
+            {
+                return Err(MakerError::General("swap is already settled"));
+            }
+            validate_refund_tx(
+                &refund.refund_tx,
+                &incoming_swapcoin.contract_tx.input[0].previous_output,
+            )?;
+            sigs.push(sign_contract_tx(
+                &refund.refund_tx,
+                &refund.multisig_redeemscript,
+                incoming_swapcoin.funding_amount,
+                &incoming_swapcoin.my_privkey,
+            )?);
+        }
+
+        // Nothing is left to recover from this swap.
+        {
+            let mut wallet_write = self.wallet.write()?;
+            for incoming_swapcoin in &state.incoming_swapcoins {
+                wallet_write
+                    .remove_incoming_swapcoin(&incoming_swapcoin.get_multisig_redeemscript())?;
+            }
+            for outgoing_swapcoin in &state.outgoing_swapcoins {
+                wallet_write
+                    .remove_outgoing_swapcoin(&outgoing_swapcoin.get_multisig_redeemscript())?;
+            }
+            wallet_write.sync()?;
+            wallet_write.save_to_disk()?;
+        }
+        self.connection_state.lock()?.remove(&message.id);
+        self.wallet.write()?.release_utxos(&message.id)?;
+        self.record_swap_outcome(&message.id, SwapOutcome::Aborted)?;
+
+        log::info!(
+            "[{}] Signed refund txs of incoming swapcoins. Aborted swap id: {}",
+            self.config.network_port,
+            message.id
+        );
+
+        Ok(MakerToTakerMessage::RespRefundSigs(RefundSigs { sigs }))
+    }
+
     /// Handles [PrivKeyHandover] message and updates all the coinswap wallet states and stores it to disk.
     /// This is the last step of completing a coinswap round.
     pub(crate) fn handle_private_key_handover(
@@ -635,9 +937,25 @@ impl Maker {
                 .apply_privkey(swapcoin_private_key.key)?;
         }
 
-        // Reset the connection state so watchtowers are not triggered.
+        // Drop the connection state of this swap so watchtowers are not triggered, and release its
+        // reserved liquidity. The states of other swaps, with this or other takers, are kept.
         let mut conn_state = self.connection_state.lock()?;
-        *conn_state = HashMap::default();
+        let completed_ids = conn_state
+            .iter()
+            .filter(|(_, (state, _))| {
+                state.incoming_swapcoins.iter().any(|swapcoin| {
+                    message.multisig_privkeys.iter().any(|privkey| {
+                        privkey.multisig_redeemscript == swapcoin.get_multisig_redeemscript()
+                    })
+                })
+            })
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        for id in &completed_ids {
+            conn_state.remove(id);
+            self.wallet.write()?.release_utxos(id)?;
+            self.record_swap_outcome(id, SwapOutcome::Completed)?;
+        }
 
         log::info!("initializing Wallet Sync.");
         {
@@ -658,6 +976,10 @@ fn unexpected_recovery(maker: Arc<Maker>) -> Result<(), MakerError> {
         let mut incomings = Vec::new();
         // Extract Incoming and Outgoing contracts, and timelock spends of the contract transactions.
         // fully signed.
+        let feerate = maker
+            .wallet
+            .read()?
+            .estimate_feerate(&maker.config.fee_estimator)?;
         for (og_sc, ic_sc) in state
             .outgoing_swapcoins
             .iter()
@@ -666,7 +988,7 @@ fn unexpected_recovery(maker: Arc<Maker>) -> Result<(), MakerError> {
             let contract_timelock = og_sc.get_timelock()?;
             let contract = og_sc.get_fully_signed_contract_tx()?;
             let next_internal_address = &maker.wallet.read()?.get_next_internal_addresses(1)?[0];
-            let time_lock_spend = og_sc.create_timelock_spend(next_internal_address)?;
+            let time_lock_spend = og_sc.create_timelock_spend(next_internal_address, feerate)?;
             outgoings.push((
                 (og_sc.get_multisig_redeemscript(), contract),
                 (contract_timelock, time_lock_spend),
@@ -14,10 +14,15 @@ mod api;
 mod config;
 mod error;
 mod handlers;
+mod rate_limit;
 mod rpc;
 mod server;
+mod swap_ledger;
 
 pub use api::{Maker, MakerBehavior};
+pub use config::FeePolicy;
 pub use error::MakerError;
+pub use rate_limit::{PeerLimits, PeerRejection};
 pub use rpc::{RpcMsgReq, RpcMsgResp};
 pub use server::start_maker_server;
+pub use swap_ledger::{Earnings, SwapOutcome, SwapRecord};
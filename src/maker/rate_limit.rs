@@ -0,0 +1,330 @@
+//! Per-peer rate limiting and bans for the Maker's P2P port.
+//!
+//! Every connection is admitted through the [`PeerTracker`], which caps concurrent connections in total
+//! and per peer, the rate of handshakes per peer, and the contract signing requests a peer can make
+//! without ever funding a swap. Peers that repeatedly violate the protocol are banned for a while.
+//!
+//! Peers are identified by their IP address, which is only meaningful on clearnet. Over Tor every
+//! connection comes from the local Tor daemon, so instead of per-peer limits each connection has to
+//! carry a proof-of-work, and gets a single contract signing request.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+/// Window over which handshakes are rate limited.
+const HANDSHAKE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Window over which unfunded contract signing requests are counted.
+const SIG_REQUEST_WINDOW: Duration = Duration::from_secs(60 * 60);
+
+/// Limits on what Takers can make the Maker do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerLimits {
+    /// Concurrent connections, from all peers.
+    pub max_connections: u32,
+    /// Concurrent connections from one peer.
+    pub max_connections_per_peer: u32,
+    /// Handshakes accepted from one peer per minute.
+    pub max_handshakes_per_minute: u32,
+    /// Contract signing requests accepted from one peer per hour, until it funds a swap.
+    pub max_unfunded_sig_requests: u32,
+    /// Protocol violations by one peer before it's banned.
+    pub ban_threshold: u32,
+    /// How long a banned peer is refused.
+    pub ban_duration: Duration,
+    /// How long a connection may wait for the Taker's next message, once the handshake is done.
+    pub idle_timeout: Duration,
+}
+
+impl Default for PeerLimits {
+    fn default() -> Self {
+        Self {
+            max_connections: 32,
+            max_connections_per_peer: 8,
+            max_handshakes_per_minute: 30,
+            max_unfunded_sig_requests: 10,
+            ban_threshold: 3,
+            ban_duration: Duration::from_secs(60 * 60),
+            idle_timeout: Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// Reason a peer's connection or request is refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerRejection {
+    /// The peer is banned for protocol violations.
+    Banned,
+    /// Too many concurrent connections, in total or from the peer.
+    TooManyConnections,
+    /// Too many handshakes from the peer.
+    TooManyHandshakes,
+    /// Too many contract signing requests from the peer, without funding a swap.
+    TooManyUnfundedRequests,
+    /// An unidentified peer's handshake lacks the proof-of-work.
+    MissingProofOfWork,
+}
+
+#[derive(Debug, Default)]
+struct PeerRecord {
+    connections: u32,
+    handshakes: VecDeque<Instant>,
+    sig_requests: VecDeque<Instant>,
+    violations: u32,
+    banned_until: Option<Instant>,
+}
+
+impl PeerRecord {
+    fn is_banned(&self, now: Instant) -> bool {
+        self.banned_until.is_some_and(|until| now < until)
+    }
+
+    /// Whether the record holds nothing worth remembering.
+    fn is_idle(&self, now: Instant) -> bool {
+        let expired = |events: &VecDeque<Instant>, window: Duration| {
+            events
+                .back()
+                .is_none_or(|event| now.duration_since(*event) >= window)
+        };
+        self.connections == 0
+            && self.violations == 0
+            && !self.is_banned(now)
+            && expired(&self.handshakes, HANDSHAKE_WINDOW)
+            && expired(&self.sig_requests, SIG_REQUEST_WINDOW)
+    }
+}
+
+/// Drop the events older than `window`, and count the new one if it's within `max`.
+fn admit_event(events: &mut VecDeque<Instant>, now: Instant, window: Duration, max: u32) -> bool {
+    while events
+        .front()
+        .is_some_and(|event| now.duration_since(*event) >= window)
+    {
+        events.pop_front();
+    }
+    if events.len() >= max as usize {
+        return false;
+    }
+    events.push_back(now);
+    true
+}
+
+/// Tracks the connections and requests of all peers, against the [`PeerLimits`].
+///
+/// A peer of `None` is an unidentified one, to which only the global connection cap applies.
+#[derive(Debug)]
+pub(crate) struct PeerTracker {
+    limits: PeerLimits,
+    connections: u32,
+    peers: HashMap<IpAddr, PeerRecord>,
+}
+
+impl PeerTracker {
+    pub(crate) fn new(limits: PeerLimits) -> Self {
+        Self {
+            limits,
+            connections: 0,
+            peers: HashMap::new(),
+        }
+    }
+
+    /// Admit a new connection. Must be followed by [`PeerTracker::disconnect`] once it ends.
+    pub(crate) fn connect(
+        &mut self,
+        peer: Option<IpAddr>,
+        now: Instant,
+    ) -> Result<(), PeerRejection> {
+        if let Some(record) = peer.and_then(|peer| self.peers.get(&peer)) {
+            if record.is_banned(now) {
+                return Err(PeerRejection::Banned);
+            }
+            if record.connections >= self.limits.max_connections_per_peer {
+                return Err(PeerRejection::TooManyConnections);
+            }
+        }
+        if self.connections >= self.limits.max_connections {
+            return Err(PeerRejection::TooManyConnections);
+        }
+        self.connections += 1;
+        if let Some(peer) = peer {
+            self.peers.entry(peer).or_default().connections += 1;
+        }
+        Ok(())
+    }
+
+    /// Release a connection admitted by [`PeerTracker::connect`].
+    pub(crate) fn disconnect(&mut self, peer: Option<IpAddr>, now: Instant) {
+        self.connections = self.connections.saturating_sub(1);
+        if let Some(peer) = peer {
+            if let Some(record) = self.peers.get_mut(&peer) {
+                record.connections = record.connections.saturating_sub(1);
+            }
+        }
+        self.peers.retain(|_, record| !record.is_idle(now));
+    }
+
+    /// Count a handshake from the peer.
+    pub(crate) fn handshake(
+        &mut self,
+        peer: Option<IpAddr>,
+        now: Instant,
+    ) -> Result<(), PeerRejection> {
+        let Some(peer) = peer else {
+            return Ok(());
+        };
+        let record = self.peers.entry(peer).or_default();
+        if admit_event(
+            &mut record.handshakes,
+            now,
+            HANDSHAKE_WINDOW,
+            self.limits.max_handshakes_per_minute,
+        ) {
+            Ok(())
+        } else {
+            Err(PeerRejection::TooManyHandshakes)
+        }
+    }
+
+    /// Count a contract signing request from the peer.
+    pub(crate) fn sig_request(
+        &mut self,
+        peer: Option<IpAddr>,
+        now: Instant,
+    ) -> Result<(), PeerRejection> {
+        let Some(peer) = peer else {
+            return Ok(());
+        };
+        let record = self.peers.entry(peer).or_default();
+        if admit_event(
+            &mut record.sig_requests,
+            now,
+            SIG_REQUEST_WINDOW,
+            self.limits.max_unfunded_sig_requests,
+        ) {
+            Ok(())
+        } else {
+            Err(PeerRejection::TooManyUnfundedRequests)
+        }
+    }
+
+    /// The peer has funded a swap, which pays for its earlier signing requests.
+    pub(crate) fn funded(&mut self, peer: Option<IpAddr>) {
+        if let Some(record) = peer.and_then(|peer| self.peers.get_mut(&peer)) {
+            record.sig_requests.clear();
+        }
+    }
+
+    /// Count a protocol violation by the peer. Returns whether the peer got banned.
+    pub(crate) fn violation(&mut self, peer: Option<IpAddr>, now: Instant) -> bool {
+        let Some(peer) = peer else {
+            return false;
+        };
+        let record = self.peers.entry(peer).or_default();
+        record.violations += 1;
+        if record.violations < self.limits.ban_threshold {
+            return false;
+        }
+        record.violations = 0;
+        record.banned_until = Some(now + self.limits.ban_duration);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    const PEER_A: Option<IpAddr> = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+    const PEER_B: Option<IpAddr> = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+
+    fn limits() -> PeerLimits {
+        PeerLimits {
+            max_connections: 3,
+            max_connections_per_peer: 2,
+            max_handshakes_per_minute: 2,
+            max_unfunded_sig_requests: 2,
+            ban_threshold: 2,
+            ban_duration: Duration::from_secs(100),
+            idle_timeout: Duration::from_secs(100),
+        }
+    }
+
+    #[test]
+    fn test_connection_limits() {
+        let mut tracker = PeerTracker::new(limits());
+        let now = Instant::now();
+
+        assert_eq!(tracker.connect(PEER_A, now), Ok(()));
+        assert_eq!(tracker.connect(PEER_A, now), Ok(()));
+        assert_eq!(
+            tracker.connect(PEER_A, now),
+            Err(PeerRejection::TooManyConnections)
+        );
+        assert_eq!(tracker.connect(PEER_B, now), Ok(()));
+        // The global cap applies to unidentified peers too.
+        assert_eq!(
+            tracker.connect(None, now),
+            Err(PeerRejection::TooManyConnections)
+        );
+
+        tracker.disconnect(PEER_A, now);
+        assert_eq!(tracker.connect(None, now), Ok(()));
+        tracker.disconnect(None, now);
+        assert_eq!(tracker.connect(PEER_A, now), Ok(()));
+
+        for peer in [PEER_A, PEER_A, PEER_B] {
+            tracker.disconnect(peer, now);
+        }
+        assert_eq!(tracker.connections, 0);
+        assert!(tracker.peers.is_empty());
+    }
+
+    #[test]
+    fn test_request_rate_limits() {
+        let mut tracker = PeerTracker::new(limits());
+        let now = Instant::now();
+
+        assert_eq!(tracker.handshake(PEER_A, now), Ok(()));
+        assert_eq!(tracker.handshake(PEER_A, now), Ok(()));
+        assert_eq!(
+            tracker.handshake(PEER_A, now),
+            Err(PeerRejection::TooManyHandshakes)
+        );
+        assert_eq!(tracker.handshake(PEER_B, now), Ok(()));
+        assert_eq!(tracker.handshake(None, now), Ok(()));
+        // Handshakes are allowed again after the window.
+        assert_eq!(tracker.handshake(PEER_A, now + HANDSHAKE_WINDOW), Ok(()));
+
+        assert_eq!(tracker.sig_request(PEER_A, now), Ok(()));
+        assert_eq!(tracker.sig_request(PEER_A, now), Ok(()));
+        assert_eq!(
+            tracker.sig_request(PEER_A, now),
+            Err(PeerRejection::TooManyUnfundedRequests)
+        );
+        // Funding a swap pays for the earlier requests.
+        tracker.funded(PEER_A);
+        assert_eq!(tracker.sig_request(PEER_A, now), Ok(()));
+    }
+
+    #[test]
+    fn test_ban() {
+        let mut tracker = PeerTracker::new(limits());
+        let now = Instant::now();
+
+        assert!(!tracker.violation(PEER_A, now));
+        assert!(tracker.violation(PEER_A, now));
+        assert!(!tracker.violation(None, now));
+        assert!(!tracker.violation(None, now));
+
+        assert_eq!(tracker.connect(PEER_A, now), Err(PeerRejection::Banned));
+        assert_eq!(tracker.connect(PEER_B, now), Ok(()));
+        assert_eq!(
+            tracker.connect(PEER_A, now + Duration::from_secs(100)),
+            Ok(())
+        );
+    }
+}
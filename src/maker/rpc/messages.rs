@@ -1,11 +1,14 @@
 use std::{collections::HashMap, fmt::Display};
 use bitcoin::Amount; 
-use bitcoin::Txid;
+use bitcoin::{OutPoint, Txid};
 use bitcoind::bitcoincore_rpc::json::ListUnspentResultEntry;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-use crate::wallet::FidelityBond;
+use crate::{
+    maker::{Earnings, FeePolicy, SwapRecord},
+    wallet::FidelityBond,
+};
 
 /// Enum representing RPC message requests.
 ///
@@ -39,8 +42,10 @@ pub enum RpcMsgReq {
         address: String,
         /// The amount to send.
         amount: Amount,
-        /// The transaction fee to include.
-        fee: u64,
+        /// Feerate in sats/vB. Estimated by the Maker if not provided.
+        feerate: Option<f64>,
+        /// Utxos to spend. Selected by the Maker if empty.
+        utxos: Vec<OutPoint>,
     },
     /// Request to retrieve the Tor address of the Maker.
     GetTorAddress,
@@ -54,6 +59,28 @@ pub enum RpcMsgReq {
     ListFidelity,
     /// Request to sync the internal wallet with blockchain.
     SyncWallet,
+    /// Request to retrieve the configured and the currently offered fee policy.
+    GetFeePolicy,
+    /// Request to update the fee policy. Fields set to `None` are left unchanged.
+    SetFeePolicy {
+        /// Fixed fee in sats.
+        base_fee: Option<u64>,
+        /// Percentage fee on the swap amount.
+        amount_relative_fee_pct: Option<f64>,
+        /// Percentage fee on the swap amount, per block of refund locktime.
+        time_relative_fee_pct: Option<f64>,
+        /// Adjust the fees to the liquidity utilization.
+        auto_adjust: Option<bool>,
+    },
+    /// Request to list all the swaps funded by the Maker.
+    ListSwaps,
+    /// Request to show a swap, by its id.
+    ShowSwap(String),
+    /// Request to sum up the earnings of the swaps completed since a time.
+    Earnings {
+        /// Seconds since the unix epoch. All completed swaps are counted if not provided.
+        since: Option<u64>,
+    },
 }
 
 /// Enum representing RPC message responses.
@@ -108,6 +135,19 @@ pub enum RpcMsgResp {
     ServerError(String),
     /// Response listing all current and past fidelity bonds.
     ListBonds(HashMap<u32, (FidelityBond, bool)>),
+    /// Response containing the fee policy.
+    FeePolicyResp {
+        /// The fee policy set by the maker.
+        configured: FeePolicy,
+        /// The fee policy currently served in offers, after any auto adjustment.
+        offered: FeePolicy,
+    },
+    /// Response listing all the swaps funded by the Maker.
+    SwapsResp(Vec<SwapRecord>),
+    /// Response containing a swap.
+    SwapResp(SwapRecord),
+    /// Response containing the earnings of the completed swaps.
+    EarningsResp(Earnings),
 }
 
 impl Display for RpcMsgResp {
@@ -130,6 +170,22 @@ impl Display for RpcMsgResp {
             Self::FidelitySpend(txid) => write!(f, "{}", txid),
             Self::ServerError(e) => write!(f, "{}", e),
             Self::ListBonds(v) => write!(f, "{:#?}", v),
+            Self::FeePolicyResp {
+                configured,
+                offered,
+            } => write!(f, "Configured: {:#?}\nOffered: {:#?}", configured, offered),
+            Self::SwapsResp(swaps) => {
+                if swaps.is_empty() {
+                    return write!(f, "No swaps");
+                }
+                let lines = swaps
+                    .iter()
+                    .map(|swap| swap.to_string())
+                    .collect::<Vec<_>>();
+                write!(f, "{}", lines.join("\n"))
+            }
+            Self::SwapResp(swap) => write!(f, "{:#?}", swap),
+            Self::EarningsResp(earnings) => write!(f, "{}", earnings),
         }
     }
 }
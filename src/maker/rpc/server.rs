@@ -7,18 +7,18 @@ use std::{
     time::Duration,
 };
 
-use bitcoin::{Address, Amount};
+use bitcoin::Address;
 
 use super::messages::RpcMsgReq;
 use crate::{
-    maker::{error::MakerError, rpc::messages::RpcMsgResp, Maker},
-    utill::{read_message, send_message, ConnectionType, HEART_BEAT_INTERVAL},
+    maker::{error::MakerError, rpc::messages::RpcMsgResp, FeePolicy, Maker},
+    utill::{read_message_with_limits, send_message, ConnectionType, HEART_BEAT_INTERVAL},
     wallet::{Destination, SendAmount},
 };
 use std::str::FromStr;
 
 fn handle_request(maker: &Arc<Maker>, socket: &mut TcpStream) -> Result<(), MakerError> {
-    let msg_bytes = read_message(socket)?;
+    let msg_bytes = read_message_with_limits(socket, &maker.config.rpc_limits)?;
     let rpc_request: RpcMsgReq = serde_cbor::from_slice(&msg_bytes)?;
     log::info!("RPC request received: {:?}", rpc_request);
 
@@ -87,24 +87,36 @@ fn handle_request(maker: &Arc<Maker>, socket: &mut TcpStream) -> Result<(), Make
         RpcMsgReq::SendToAddress {
             address,
             amount,
-            fee,
+            feerate,
+            utxos,
         } => {
-            let amount = Amount::from_sat(amount);
-            let fee = Amount::from_sat(fee);
+            let feerate = match feerate {
+                Some(feerate) => feerate,
+                None => maker
+                    .get_wallet()
+                    .read()?
+                    .estimate_feerate(&maker.config.fee_estimator)?,
+            };
             let destination =
                 Destination::Address(Address::from_str(&address).unwrap().assume_checked());
 
-            let coins_to_send = maker.get_wallet().read()?.coin_select(amount + fee)?;
+            let coins_to_send = if utxos.is_empty() {
+                maker
+                    .get_wallet()
+                    .read()?
+                    .coin_select_with_fee(amount, feerate)?
+            } else {
+                maker.get_wallet().read()?.select_utxos(&utxos)?
+            };
 
             let tx = maker.get_wallet().write()?.spend_from_wallet(
-                fee,
+                feerate,
                 SendAmount::Amount(amount),
                 destination,
                 &coins_to_send,
             )?;
 
-            let calculated_fee_rate = fee / (tx.weight());
-            log::info!("Calculated FeeRate : {:#}", calculated_fee_rate);
+            log::info!("Sending with feerate : {} sats/vB", feerate);
 
             let txid = maker.get_wallet().read()?.send_tx(&tx)?;
 
@@ -151,6 +163,42 @@ fn handle_request(maker: &Arc<Maker>, socket: &mut TcpStream) -> Result<(), Make
 
             RpcMsgResp::ListBonds(list)
         }
+        RpcMsgReq::GetFeePolicy => {
+            let (configured, offered) = maker.get_fee_policy()?;
+            RpcMsgResp::FeePolicyResp {
+                configured,
+                offered,
+            }
+        }
+        RpcMsgReq::SetFeePolicy {
+            base_fee,
+            amount_relative_fee_pct,
+            time_relative_fee_pct,
+            auto_adjust,
+        } => {
+            let current = maker.get_fee_policy()?.0;
+            let configured = FeePolicy {
+                base_fee: base_fee.unwrap_or(current.base_fee),
+                amount_relative_fee_pct: amount_relative_fee_pct
+                    .unwrap_or(current.amount_relative_fee_pct),
+                time_relative_fee_pct: time_relative_fee_pct
+                    .unwrap_or(current.time_relative_fee_pct),
+                auto_adjust: auto_adjust.unwrap_or(current.auto_adjust),
+            };
+            let offered = maker.set_fee_policy(configured)?;
+            RpcMsgResp::FeePolicyResp {
+                configured,
+                offered,
+            }
+        }
+        RpcMsgReq::ListSwaps => RpcMsgResp::SwapsResp(maker.swap_ledger.lock()?.list().to_vec()),
+        RpcMsgReq::ShowSwap(id) => match maker.swap_ledger.lock()?.get(&id) {
+            Some(swap) => RpcMsgResp::SwapResp(swap.clone()),
+            None => RpcMsgResp::ServerError(format!("No swap with id {}", id)),
+        },
+        RpcMsgReq::Earnings { since } => {
+            RpcMsgResp::EarningsResp(maker.swap_ledger.lock()?.earnings(since.unwrap_or(0)))
+        }
         RpcMsgReq::SyncWallet => {
             log::info!("Initializing wallet sync");
             if let Err(e) = maker.get_wallet().write()?.sync() {
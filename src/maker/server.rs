@@ -6,7 +6,7 @@
 
 use std::{
     io::ErrorKind,
-    net::{Ipv4Addr, TcpListener, TcpStream},
+    net::{IpAddr, Ipv4Addr, TcpListener, TcpStream},
     path::PathBuf,
     process::Child,
     sync::{
@@ -14,7 +14,7 @@ use std::{
         Arc,
     },
     thread::{self, sleep},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use bitcoin::{absolute::LockTime, Amount};
@@ -23,7 +23,10 @@ use bitcoind::bitcoincore_rpc::RpcApi;
 #[cfg(feature = "tor")]
 use socks::Socks5Stream;
 
-pub(crate) use super::{api::RPC_PING_INTERVAL, Maker};
+pub(crate) use super::{
+    api::{FEE_POLICY_REFRESH_INTERVAL, RPC_PING_INTERVAL},
+    Maker,
+};
 
 use crate::{
     error::NetError,
@@ -35,8 +38,11 @@ use crate::{
         handlers::handle_message,
         rpc::start_rpc_server,
     },
-    protocol::messages::{DnsMetadata, DnsRequest, TakerToMakerMessage},
-    utill::{get_tor_addrs, read_message, send_message, ConnectionType, HEART_BEAT_INTERVAL},
+    protocol::{
+        messages::{DnsMetadata, DnsRequest, TakerToMakerMessage},
+        noise::NoiseStream,
+    },
+    utill::{get_tor_addrs, send_message, ConnectionType, HEART_BEAT_INTERVAL},
     wallet::WalletError,
 };
 
@@ -272,7 +278,6 @@ fn setup_fidelity_bond(maker: &Arc<Maker>, maker_address: &str) -> Result<(), Ma
         let mut sleep_multiplier = 0;
         log::info!("No active Fidelity Bonds found. Creating one.");
         log::info!("Fidelity value chosen = {:?} sats", amount.to_sat());
-        log::info!("Fidelity Tx fee = 300 sats");
         log::info!(
             "Fidelity timelock {} blocks",
             maker.config.fidelity_timelock
@@ -283,10 +288,12 @@ fn setup_fidelity_bond(maker: &Arc<Maker>, maker_address: &str) -> Result<(), Ma
             // sync the wallet
             maker.get_wallet().write()?.sync()?;
 
-            let fidelity_result = maker
-                .get_wallet()
-                .write()?
-                .create_fidelity(amount, locktime);
+            let fidelity_result = {
+                let mut wallet = maker.get_wallet().write()?;
+                let feerate = wallet.estimate_feerate(&maker.config.fee_estimator)?;
+                log::info!("Fidelity Tx feerate = {} sats/vb", feerate);
+                wallet.create_fidelity(amount, locktime, feerate)
+            };
 
             match fidelity_result {
                 // Wait for sufficient fund to create fidelity bond.
@@ -382,29 +389,81 @@ fn check_connection_with_core(
     Ok(())
 }
 
+/// Keep the offered fee policy in line with the liquidity utilization of the wallet.
+///
+/// Only does work if fee auto adjustment is enabled. Refresh failures are logged and retried at the next interval.
+fn refresh_fee_policy(maker: Arc<Maker>) -> Result<(), MakerError> {
+    let trigger_count = FEE_POLICY_REFRESH_INTERVAL.as_secs() / HEART_BEAT_INTERVAL.as_secs();
+    let mut i = 0;
+    while !maker.shutdown.load(Relaxed) {
+        if i >= trigger_count {
+            if let Err(e) = maker.refresh_fee_policy() {
+                log::error!(
+                    "[{}] Failed refreshing fee policy: {:?}",
+                    maker.config.network_port,
+                    e
+                );
+            }
+            i = 0;
+        }
+        i += 1;
+        thread::sleep(HEART_BEAT_INTERVAL);
+    }
+
+    Ok(())
+}
+
 /// Handle a single client connection.
-fn handle_client(maker: Arc<Maker>, stream: &mut TcpStream) -> Result<(), MakerError> {
+///
+/// The connection is encrypted, and authenticated with the key of our fidelity bond.
+/// `peer` identifies the taker to the [PeerTracker](super::rate_limit::PeerTracker), if possible.
+fn handle_client(
+    maker: Arc<Maker>,
+    stream: TcpStream,
+    peer: Option<IpAddr>,
+) -> Result<(), MakerError> {
     stream.set_nonblocking(false)?; // Block this thread until message is read.
 
-    let mut connection_state = ConnectionState::default();
+    let transport_key = {
+        let proof = maker.highest_fidelity_proof.read()?;
+        let outpoint = proof
+            .as_ref()
+            .ok_or(MakerError::General("No fidelity bond to authenticate with"))?
+            .bond
+            .outpoint;
+        maker
+            .get_wallet()
+            .read()?
+            .get_fidelity_keypair_by_outpoint(&outpoint)?
+    };
+    let mut stream = NoiseStream::accept(stream, &transport_key, maker.config.p2p_limits)?;
+
+    let mut connection_state = ConnectionState {
+        peer,
+        handshake_hash: stream.handshake_hash(),
+        ..Default::default()
+    };
 
     while !maker.shutdown.load(Relaxed) {
-        let mut taker_msg_bytes = Vec::new();
-        match read_message(stream) {
-            Ok(b) => taker_msg_bytes = b,
+        let taker_msg_bytes = match stream.read_message() {
+            Ok(b) => b,
+            Err(NetError::ReachedEOF) => {
+                log::info!("[{}] Connection ended.", maker.config.network_port);
+                break;
+            }
+            Err(NetError::IdleTimeout) => {
+                log::info!(
+                    "[{}] Connection idle, closing it.",
+                    maker.config.network_port
+                );
+                break;
+            }
             Err(e) => {
-                if let NetError::IO(e) = e {
-                    if e.kind() == ErrorKind::UnexpectedEof {
-                        log::info!("[{}] Connection ended.", maker.config.network_port);
-                        break;
-                    } else {
-                        // For any other errors, report them
-                        log::error!("[{}] Net Error: {}", maker.config.network_port, e);
-                        continue;
-                    }
-                }
+                // Oversized, slow or undecryptable messages end the connection.
+                log::error!("[{}] Net Error: {}", maker.config.network_port, e);
+                return Err(e.into());
             }
-        }
+        };
 
         let taker_msg: TakerToMakerMessage = serde_cbor::from_slice(&taker_msg_bytes)?;
         log::info!("[{}]  <=== {}", maker.config.network_port, taker_msg);
@@ -413,9 +472,12 @@ fn handle_client(maker: Arc<Maker>, stream: &mut TcpStream) -> Result<(), MakerE
 
         match reply {
             Ok(reply) => {
+                // The first message has to arrive within the message timeout. After it, the taker may
+                // be busy with other makers in between messages, up to the idle timeout.
+                stream.set_read_timeout(Some(maker.config.peer_limits.idle_timeout))?;
                 if let Some(message) = reply {
                     log::info!("[{}] ===> {} ", maker.config.network_port, message);
-                    if let Err(e) = send_message(stream, &message) {
+                    if let Err(e) = stream.send_message(&message) {
                         log::error!("Closing due to IO error in sending message: {:?}", e);
                         continue;
                     }
@@ -442,6 +504,17 @@ fn handle_client(maker: Arc<Maker>, stream: &mut TcpStream) -> Result<(), MakerE
                         );
                     }
                 }
+                if matches!(
+                    err,
+                    MakerError::Protocol(_) | MakerError::UnexpectedMessage { .. }
+                ) && maker.peers.lock()?.violation(peer, Instant::now())
+                {
+                    log::warn!(
+                        "[{}] Banning peer {:?} for protocol violations",
+                        maker.config.network_port,
+                        peer
+                    );
+                }
                 return Err(err);
             }
         }
@@ -458,6 +531,7 @@ fn handle_client(maker: Arc<Maker>, stream: &mut TcpStream) -> Result<(), MakerE
 /// - Monitoring idle client connections.
 /// - Watching for broadcasted contract transactions.
 /// - Running an RPC server for interacting with `maker-cli`.
+/// - Adjusting the offered fees to the liquidity utilization.
 ///
 /// It also handles incoming peer-to-peer (P2P) client connections in a loop, where
 /// each connection spawns a dedicated handler thread.
@@ -553,6 +627,20 @@ pub fn start_maker_server(maker: Arc<Maker>) -> Result<(), MakerError> {
 
         maker.thread_pool.add_thread(rpc_thread);
 
+        // 5: Fee policy refresh thread.
+        // Re-adjusts the offered fees to the current liquidity utilization.
+        let maker_clone = maker.clone();
+        let fee_policy_thread = thread::Builder::new()
+            .name("Fee Policy Refresh Thread".to_string())
+            .spawn(move || {
+                log::info!("[{}] Spawning fee policy refresh thread", port);
+                if let Err(e) = refresh_fee_policy(maker_clone.clone()) {
+                    log::error!("Failed refreshing fee policy {:?}", e);
+                    maker_clone.shutdown.store(true, Relaxed);
+                }
+            })?;
+        maker.thread_pool.add_thread(fee_policy_thread);
+
         sleep(HEART_BEAT_INTERVAL); // wait for 1 beat, to complete spawns of all the threads.
         maker.is_setup_complete.store(true, Relaxed);
         log::info!("[{}] Server Setup completed!! Use maker-cli to operate the server and the internal wallet.", maker.config.network_port);
@@ -583,14 +671,45 @@ pub fn start_maker_server(maker: Arc<Maker>) -> Result<(), MakerError> {
         }
 
         match listener.accept() {
-            Ok((mut stream, _)) => {
+            Ok((stream, addr)) => {
                 log::info!(
                     "[{}] Received incoming connection",
                     maker.config.network_port
                 );
 
-                if let Err(e) = handle_client(maker, &mut stream) {
-                    log::error!("[{}] Error Handling client request {:?}", port, e);
+                // Over Tor, every taker connects from the local Tor daemon.
+                let peer = if maker.config.connection_type == ConnectionType::CLEARNET {
+                    Some(addr.ip())
+                } else {
+                    None
+                };
+                if let Err(e) = maker.peers.lock()?.connect(peer, Instant::now()) {
+                    log::warn!(
+                        "[{}] Refusing connection from {}: {:?}",
+                        maker.config.network_port,
+                        addr,
+                        e
+                    );
+                    continue;
+                }
+
+                let maker_clone = maker.clone();
+                let client_thread = thread::Builder::new()
+                    .name(format!("Client Handler Thread {}", addr))
+                    .spawn(move || {
+                        if let Err(e) = handle_client(maker_clone.clone(), stream, peer) {
+                            log::error!("[{}] Error Handling client request {:?}", port, e);
+                        }
+                        if let Ok(mut peers) = maker_clone.peers.lock() {
+                            peers.disconnect(peer, Instant::now());
+                        }
+                    });
+                match client_thread {
+                    Ok(handle) => maker.thread_pool.add_thread(handle),
+                    Err(e) => {
+                        log::error!("[{}] Failed spawning client handler: {:?}", port, e);
+                        maker.peers.lock()?.disconnect(peer, Instant::now());
+                    }
                 }
             }
 
@@ -0,0 +1,313 @@
+//! The Maker's record of its swaps.
+//!
+//! The wallet forgets about a swap once its swapcoins are spent. The [`SwapLedger`] keeps a [`SwapRecord`]
+//! of every swap the Maker funded, with its amounts, transactions, fees and outcome, for the operator's
+//! accounting. It's stored in the `swaps.dat` file of the data directory, and read via `maker-cli`.
+
+use std::{fmt::Display, path::Path};
+
+use bitcoin::{Amount, Txid};
+use serde::{Deserialize, Serialize};
+
+use crate::utill::{read_with_backups, write_atomic};
+
+use super::error::MakerError;
+
+/// How a swap ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwapOutcome {
+    /// The swap is still ongoing, or the Maker was stopped before it ended.
+    InProgress,
+    /// The swap completed, and the private keys of the incoming swapcoins were received.
+    Completed,
+    /// The swap was refunded cooperatively with the taker, before any contract was broadcasted.
+    Aborted,
+    /// The swap failed, and the Maker recovered its outgoing coins via the contract transactions.
+    Recovered,
+}
+
+impl Display for SwapOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InProgress => write!(f, "in progress"),
+            Self::Completed => write!(f, "completed"),
+            Self::Aborted => write!(f, "aborted"),
+            Self::Recovered => write!(f, "recovered"),
+        }
+    }
+}
+
+/// A swap funded by the Maker.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwapRecord {
+    /// The swap id chosen by the taker.
+    pub id: String,
+    /// When the Maker accepted the proof of funding, in seconds since the unix epoch.
+    pub started_at: u64,
+    /// When the swap ended, in seconds since the unix epoch.
+    pub finished_at: Option<u64>,
+    /// Total amount received in the incoming swapcoins.
+    pub incoming_amount: Amount,
+    /// Total amount sent in the outgoing swapcoins.
+    pub outgoing_amount: Amount,
+    /// The funding txs of the incoming swapcoins.
+    pub incoming_txids: Vec<Txid>,
+    /// The Maker's funding txs of the outgoing swapcoins.
+    pub outgoing_txids: Vec<Txid>,
+    /// Miner fees paid by the Maker for its funding txs.
+    pub mining_fee: Amount,
+    /// Funding tx fees paid back by the taker, at its contract feerate, out of the incoming amount.
+    #[serde(default)]
+    pub mining_fee_reimbursed: Amount,
+    /// How the swap ended.
+    pub outcome: SwapOutcome,
+}
+
+impl SwapRecord {
+    /// The coinswap fee charged to the taker, i.e. the incoming amount not sent onwards, minus the
+    /// funding tx fees the taker paid back.
+    pub fn coinswap_fee(&self) -> Amount {
+        self.incoming_amount
+            .checked_sub(self.outgoing_amount + self.mining_fee_reimbursed)
+            .unwrap_or(Amount::ZERO)
+    }
+}
+
+impl Display for SwapRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} | started {} | {} | in {} sats | out {} sats | fee {} sats | mining fee {} sats (reimbursed {} sats)",
+            self.id,
+            self.started_at,
+            self.outcome,
+            self.incoming_amount.to_sat(),
+            self.outgoing_amount.to_sat(),
+            self.coinswap_fee().to_sat(),
+            self.mining_fee.to_sat(),
+            self.mining_fee_reimbursed.to_sat()
+        )
+    }
+}
+
+/// Earnings of the completed swaps over a period.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Earnings {
+    /// Number of completed swaps.
+    pub swap_count: u64,
+    /// Total volume received in the incoming swapcoins.
+    pub volume: Amount,
+    /// Total coinswap fees charged.
+    pub fees_earned: Amount,
+    /// Total miner fees paid for the funding txs.
+    pub mining_fees: Amount,
+    /// Total funding tx fees paid back by the takers.
+    #[serde(default)]
+    pub mining_fees_reimbursed: Amount,
+}
+
+impl Earnings {
+    /// Coinswap fees plus the reimbursed miner fees, minus the miner fees paid. Zero if the miner
+    /// fees exceed the rest.
+    pub fn net_earnings(&self) -> Amount {
+        (self.fees_earned + self.mining_fees_reimbursed)
+            .checked_sub(self.mining_fees)
+            .unwrap_or(Amount::ZERO)
+    }
+}
+
+impl Display for Earnings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Completed swaps: {}\nVolume: {} sats\nFees earned: {} sats\nMining fees: {} sats\nMining fees reimbursed: {} sats\nNet earnings: {} sats",
+            self.swap_count,
+            self.volume.to_sat(),
+            self.fees_earned.to_sat(),
+            self.mining_fees.to_sat(),
+            self.mining_fees_reimbursed.to_sat(),
+            self.net_earnings().to_sat()
+        )
+    }
+}
+
+/// All the swaps funded by the Maker, in the order they started.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct SwapLedger {
+    swaps: Vec<SwapRecord>,
+}
+
+impl SwapLedger {
+    /// Records a started swap. A swap retried by the taker with a new proof of funding replaces
+    /// the earlier record, keeping its start time.
+    ///
+    /// Returns false if a swap with the same id already ended, whose record is kept.
+    pub(crate) fn record_started(&mut self, mut record: SwapRecord) -> bool {
+        match self.swaps.iter_mut().find(|swap| swap.id == record.id) {
+            Some(existing) if existing.outcome != SwapOutcome::InProgress => false,
+            Some(existing) => {
+                record.started_at = existing.started_at;
+                *existing = record;
+                true
+            }
+            None => {
+                self.swaps.push(record);
+                true
+            }
+        }
+    }
+
+    /// Records the outcome of a swap. Returns false if the swap isn't known, or already ended.
+    pub(crate) fn record_outcome(&mut self, id: &str, outcome: SwapOutcome, at: u64) -> bool {
+        match self
+            .swaps
+            .iter_mut()
+            .find(|swap| swap.id == id && swap.outcome == SwapOutcome::InProgress)
+        {
+            Some(swap) => {
+                swap.outcome = outcome;
+                swap.finished_at = Some(at);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn get(&self, id: &str) -> Option<&SwapRecord> {
+        self.swaps.iter().find(|swap| swap.id == id)
+    }
+
+    pub(crate) fn list(&self) -> &[SwapRecord] {
+        &self.swaps
+    }
+
+    /// Sums up the swaps completed at or after `since`, in seconds since the unix epoch.
+    pub(crate) fn earnings(&self, since: u64) -> Earnings {
+        self.swaps
+            .iter()
+            .filter(|swap| {
+                swap.outcome == SwapOutcome::Completed
+                    && swap.finished_at.is_some_and(|at| at >= since)
+            })
+            .fold(Earnings::default(), |mut earnings, swap| {
+                earnings.swap_count += 1;
+                earnings.volume += swap.incoming_amount;
+                earnings.fees_earned += swap.coinswap_fee();
+                earnings.mining_fees += swap.mining_fee;
+                earnings.mining_fees_reimbursed += swap.mining_fee_reimbursed;
+                earnings
+            })
+    }
+
+    /// Atomically writes the ledger to the path, keeping the previous file as a backup.
+    pub(crate) fn write_to_disk(&self, path: &Path) -> Result<(), MakerError> {
+        Ok(write_atomic(path, &serde_cbor::to_vec(&self)?)?)
+    }
+
+    /// Reads from a path (errors if path doesn't exist).
+    ///
+    /// Falls back to the newest valid backup if the file is corrupted.
+    pub(crate) fn read_from_disk(path: &Path) -> Result<Self, MakerError> {
+        read_with_backups(path, |bytes| Ok(serde_cbor::from_slice::<Self>(bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::hashes::Hash;
+
+    fn record(id: &str, started_at: u64) -> SwapRecord {
+        SwapRecord {
+            id: id.to_string(),
+            started_at,
+            finished_at: None,
+            incoming_amount: Amount::from_sat(100_000),
+            outgoing_amount: Amount::from_sat(97_000),
+            incoming_txids: vec![Txid::all_zeros()],
+            outgoing_txids: vec![Txid::all_zeros()],
+            mining_fee: Amount::from_sat(500),
+            mining_fee_reimbursed: Amount::from_sat(400),
+            outcome: SwapOutcome::InProgress,
+        }
+    }
+
+    #[test]
+    fn test_record_swaps() {
+        let mut ledger = SwapLedger::default();
+        assert!(ledger.record_started(record("swap-1", 10)));
+        assert!(ledger.record_started(record("swap-2", 20)));
+
+        // A retried swap keeps its start time.
+        let mut retried = record("swap-1", 30);
+        retried.mining_fee = Amount::from_sat(700);
+        assert!(ledger.record_started(retried));
+        assert_eq!(ledger.list().len(), 2);
+        let swap = ledger.get("swap-1").unwrap();
+        assert_eq!(swap.started_at, 10);
+        assert_eq!(swap.mining_fee, Amount::from_sat(700));
+        assert_eq!(swap.coinswap_fee(), Amount::from_sat(2_600));
+
+        assert!(ledger.record_outcome("swap-1", SwapOutcome::Completed, 40));
+        // Ended swaps keep their outcome.
+        assert!(!ledger.record_outcome("swap-1", SwapOutcome::Recovered, 50));
+        assert!(!ledger.record_outcome("unknown", SwapOutcome::Completed, 50));
+        assert_eq!(ledger.get("swap-1").unwrap().finished_at, Some(40));
+        assert_eq!(
+            ledger.get("swap-1").unwrap().outcome,
+            SwapOutcome::Completed
+        );
+        assert!(ledger.get("unknown").is_none());
+
+        // An ended swap can't be started again under the same id.
+        assert!(!ledger.record_started(record("swap-1", 60)));
+        assert_eq!(ledger.list().len(), 2);
+        assert_eq!(ledger.get("swap-1").unwrap().started_at, 10);
+        assert_eq!(
+            ledger.get("swap-1").unwrap().outcome,
+            SwapOutcome::Completed
+        );
+    }
+
+    #[test]
+    fn test_earnings() {
+        let mut ledger = SwapLedger::default();
+        for (id, outcome, at) in [
+            ("swap-1", SwapOutcome::Completed, 100),
+            ("swap-2", SwapOutcome::Completed, 200),
+            ("swap-3", SwapOutcome::Recovered, 200),
+            ("swap-4", SwapOutcome::InProgress, 0),
+        ] {
+            ledger.record_started(record(id, 0));
+            ledger.record_outcome(id, outcome, at);
+        }
+
+        let earnings = ledger.earnings(0);
+        assert_eq!(earnings.swap_count, 2);
+        assert_eq!(earnings.volume, Amount::from_sat(200_000));
+        assert_eq!(earnings.fees_earned, Amount::from_sat(5_200));
+        assert_eq!(earnings.mining_fees, Amount::from_sat(1_000));
+        assert_eq!(earnings.mining_fees_reimbursed, Amount::from_sat(800));
+        assert_eq!(earnings.net_earnings(), Amount::from_sat(5_000));
+
+        let earnings = ledger.earnings(150);
+        assert_eq!(earnings.swap_count, 1);
+        assert_eq!(earnings.net_earnings(), Amount::from_sat(2_500));
+
+        assert_eq!(ledger.earnings(300), Earnings::default());
+    }
+
+    #[test]
+    fn test_ledger_persistence() {
+        let temp_dir = bitcoind::tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("swaps.dat");
+
+        let mut ledger = SwapLedger::default();
+        ledger.record_started(record("swap-1", 10));
+        ledger.record_outcome("swap-1", SwapOutcome::Aborted, 20);
+        ledger.write_to_disk(&path).unwrap();
+
+        let read = SwapLedger::read_from_disk(&path).unwrap();
+        assert_eq!(read.list(), ledger.list());
+    }
+}
@@ -10,8 +10,8 @@ use crate::{
     market::rpc::start_rpc_server_thread,
     protocol::messages::DnsRequest,
     utill::{
-        get_dns_dir, parse_field, parse_toml, read_message, send_message, verify_fidelity_checks,
-        ConnectionType, HEART_BEAT_INTERVAL,
+        get_dns_dir, parse_field, parse_toml, read_message_with_limits, send_message,
+        verify_fidelity_checks, ConnectionType, MessageLimits, HEART_BEAT_INTERVAL,
     },
     wallet::{RPCConfig, WalletError},
 };
@@ -123,6 +123,8 @@ pub struct DirectoryServer {
     pub socks_port: u16,
     /// Connection type
     pub connection_type: ConnectionType,
+    /// Limits on messages read from makers, takers and RPC clients.
+    pub message_limits: MessageLimits,
     /// Directory server data directory
     pub data_dir: PathBuf,
     /// Shutdown flag to stop the directory server
@@ -147,6 +149,10 @@ impl Default for DirectoryServer {
                     ConnectionType::CLEARNET
                 }
             },
+            message_limits: MessageLimits {
+                max_size: 64 * 1024,
+                read_timeout: Duration::from_secs(20),
+            },
             data_dir: get_dns_dir(),
             shutdown: AtomicBool::new(false),
             addresses: Arc::new(RwLock::new(HashMap::new())),
@@ -223,6 +229,16 @@ impl DirectoryServer {
                 config_map.get("connection_type"),
                 default_dns.connection_type,
             ),
+            message_limits: MessageLimits {
+                max_size: parse_field(
+                    config_map.get("max_message_size"),
+                    default_dns.message_limits.max_size,
+                ),
+                read_timeout: Duration::from_secs(parse_field(
+                    config_map.get("message_timeout"),
+                    default_dns.message_limits.read_timeout.as_secs(),
+                )),
+            },
             addresses,
         })
     }
@@ -299,6 +315,8 @@ fn write_default_directory_config(config_path: &Path) -> Result<(), DirectorySer
             socks_port = 19060\n\
             connection_type = tor\n\
             rpc_port = 4321\n\
+            max_message_size = 65536\n\
+            message_timeout = 20\n\
             ",
     );
     std::fs::create_dir_all(config_path.parent().expect("Path should NOT be root!"))?;
@@ -463,7 +481,7 @@ fn handle_client(
     directory: &Arc<DirectoryServer>,
     rpc: &Client,
 ) -> Result<(), DirectoryServerError> {
-    let buf = read_message(&mut stream.try_clone()?)?;
+    let buf = read_message_with_limits(&mut stream.try_clone()?, &directory.message_limits)?;
     let dns_request: DnsRequest = serde_cbor::de::from_reader(&buf[..])?;
     match dns_request {
         DnsRequest::Post { metadata } => {
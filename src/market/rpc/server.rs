@@ -4,7 +4,7 @@ use super::{RpcMsgReq, RpcMsgResp};
 use crate::{
     error::NetError,
     market::directory::{DirectoryServer, DirectoryServerError},
-    utill::{read_message, send_message, HEART_BEAT_INTERVAL},
+    utill::{read_message_with_limits, send_message, MessageLimits, HEART_BEAT_INTERVAL},
 };
 use std::{
     collections::{BTreeSet, HashMap},
@@ -16,9 +16,10 @@ use std::{
 };
 fn handle_request(
     socket: &mut TcpStream,
+    limits: &MessageLimits,
     address: Arc<RwLock<HashMap<OutPoint, (String, Instant)>>>,
 ) -> Result<(), DirectoryServerError> {
-    let req_bytes = read_message(socket)?;
+    let req_bytes = read_message_with_limits(socket, limits)?;
     let rpc_request: RpcMsgReq = serde_cbor::from_slice(&req_bytes).map_err(NetError::Cbor)?;
 
     match rpc_request {
@@ -54,7 +55,11 @@ pub fn start_rpc_server_thread(
                 log::info!("Got RPC request from: {}", addr);
                 stream.set_read_timeout(Some(Duration::from_secs(20)))?;
                 stream.set_write_timeout(Some(Duration::from_secs(20)))?;
-                if let Err(e) = handle_request(&mut stream, directory.addresses.clone()) {
+                if let Err(e) = handle_request(
+                    &mut stream,
+                    &directory.message_limits,
+                    directory.addresses.clone(),
+                ) {
                     log::error!("Error handling RPC request: {:?}", e);
                 }
             }
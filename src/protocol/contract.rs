@@ -10,8 +10,9 @@ use bitcoin::{
         opcodes::{self, all},
         script::{Builder, Instruction, Script},
     },
+    consensus::encode::serialize,
     ecdsa::Signature,
-    hashes::Hash,
+    hashes::{sha256d, Hash, HashEngine},
     secp256k1::{
         rand::{rngs::OsRng, RngCore},
         Message, Secp256k1, SecretKey,
@@ -27,7 +28,7 @@ use crate::utill::redeemscript_to_scriptpubkey;
 
 use super::{
     error::ProtocolError,
-    messages::{FundingTxInfo, ProofOfFunding},
+    messages::{ContractType, FundingTxInfo, ProofOfFunding, UtxoOwnershipProof},
 };
 
 // relatively simple handling of miner fees for now, each funding transaction is considered
@@ -51,6 +52,25 @@ const PUBKEY_LENGTH: usize = 33;
 const PUBKEY1_OFFSET: usize = 2;
 const PUBKEY2_OFFSET: usize = PUBKEY1_OFFSET + PUBKEY_LENGTH + 1;
 
+/// Contract types the swap routines can perform, in order of preference.
+///
+/// The swap messages only carry ECDSA signatures of P2WSH contracts so far.
+pub(crate) const SUPPORTED_CONTRACT_TYPES: &[ContractType] = &[ContractType::P2wsh];
+
+/// Choose the contract type of a swap, the first of the Taker's `offered` types that is also `supported`.
+/// Takers not advertising any contract type only know [ContractType::P2wsh].
+pub(crate) fn negotiate_contract_type(
+    supported: &[ContractType],
+    offered: &[ContractType],
+) -> Option<ContractType> {
+    if offered.is_empty() {
+        return supported
+            .contains(&ContractType::P2wsh)
+            .then_some(ContractType::P2wsh);
+    }
+    offered.iter().find(|t| supported.contains(t)).copied()
+}
+
 /// Calculate the coin swap fee based on various parameters.
 /// swap_amount in sats, refund_locktime in blocks.
 pub(crate) fn calculate_coinswap_fee(
@@ -390,8 +410,11 @@ pub(crate) fn create_senders_contract_tx(
     input: OutPoint,
     input_value: Amount,
     contract_redeemscript: &ScriptBuf,
-    fee_rate: Amount,
+    fee: Amount,
 ) -> Result<Transaction, ProtocolError> {
+    let value = input_value.checked_sub(fee).ok_or(ProtocolError::General(
+        "contract fee exceeds the funding amount",
+    ))?;
     Ok(Transaction {
         input: vec![TxIn {
             previous_output: input,
@@ -401,7 +424,7 @@ pub(crate) fn create_senders_contract_tx(
         }],
         output: vec![TxOut {
             script_pubkey: redeemscript_to_scriptpubkey(contract_redeemscript)?,
-            value: input_value - fee_rate,
+            value,
         }],
         lock_time: LockTime::ZERO,
         version: Version::TWO,
@@ -413,11 +436,53 @@ pub(crate) fn create_receivers_contract_tx(
     input: OutPoint,
     input_value: Amount,
     contract_redeemscript: &ScriptBuf,
-    fee_rate: Amount,
+    fee: Amount,
 ) -> Result<Transaction, ProtocolError> {
     //exactly the same thing as senders contract for now, until collateral
     //inputs are implemented
-    create_senders_contract_tx(input, input_value, contract_redeemscript, fee_rate)
+    create_senders_contract_tx(input, input_value, contract_redeemscript, fee)
+}
+
+/// Create a refund transaction, spending the funding output of a hop straight back to its sender.
+/// Signed by both sides of the multisig, it aborts the hop without going through the contract.
+pub(crate) fn create_refund_tx(
+    funding_outpoint: OutPoint,
+    funding_amount: Amount,
+    destination: ScriptBuf,
+    fee: Amount,
+) -> Result<Transaction, ProtocolError> {
+    let value = funding_amount
+        .checked_sub(fee)
+        .ok_or(ProtocolError::General(
+            "refund fee exceeds the funding amount",
+        ))?;
+    Ok(Transaction {
+        input: vec![TxIn {
+            previous_output: funding_outpoint,
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+            script_sig: ScriptBuf::new(),
+        }],
+        output: vec![TxOut {
+            script_pubkey: destination,
+            value,
+        }],
+        lock_time: LockTime::ZERO,
+        version: Version::TWO,
+    })
+}
+
+/// Check that a refund transaction spends nothing but the given funding output.
+pub(crate) fn validate_refund_tx(
+    refund_tx: &Transaction,
+    funding_outpoint: &OutPoint,
+) -> Result<(), ProtocolError> {
+    if refund_tx.input.len() != 1 || refund_tx.input[0].previous_output != *funding_outpoint {
+        return Err(ProtocolError::General(
+            "refund tx does not spend the funding output",
+        ));
+    }
+    Ok(())
 }
 
 /// Check if a contract output is valid.
@@ -515,6 +580,85 @@ pub(crate) fn verify_contract_tx_sig(
     Ok(secp.verify_ecdsa(&sighash, sig, &pubkey.inner)?)
 }
 
+/// Tag of the message signed in a [UtxoOwnershipProof].
+const UTXO_PROOF_TAG: &[u8] = b"coinswap utxo ownership proof";
+
+/// The message signed in a [UtxoOwnershipProof].
+fn utxo_proof_message(
+    outpoint: &OutPoint,
+    tweakable_point: &PublicKey,
+    hashvalue: &Hash160,
+    handshake_hash: &[u8; 32],
+) -> Message {
+    let mut engine = sha256d::Hash::engine();
+    engine.input(UTXO_PROOF_TAG);
+    engine.input(&serialize(outpoint));
+    engine.input(&tweakable_point.to_bytes());
+    engine.input(hashvalue.as_byte_array());
+    engine.input(handshake_hash);
+    Message::from_digest(sha256d::Hash::from_engine(engine).to_byte_array())
+}
+
+/// Prove the ownership of an output to the Maker of `tweakable_point`, for the swap of `hashvalue`,
+/// on the connection of `handshake_hash`.
+pub(crate) fn sign_utxo_proof(
+    outpoint: OutPoint,
+    privkey: &SecretKey,
+    multisig_redeemscript: Option<ScriptBuf>,
+    tweakable_point: &PublicKey,
+    hashvalue: &Hash160,
+    handshake_hash: &[u8; 32],
+) -> UtxoOwnershipProof {
+    let secp = Secp256k1::new();
+    let message = utxo_proof_message(&outpoint, tweakable_point, hashvalue, handshake_hash);
+    UtxoOwnershipProof {
+        outpoint,
+        pubkey: PublicKey {
+            compressed: true,
+            inner: privkey.public_key(&secp),
+        },
+        multisig_redeemscript,
+        sig: secp.sign_ecdsa(&message, privkey),
+    }
+}
+
+/// Verify a [UtxoOwnershipProof] made to the Maker of `tweakable_point`, for the swap of `hashvalue`,
+/// on the connection of `handshake_hash`. `script_pubkey` is the one of the proven output, as found on chain.
+pub(crate) fn verify_utxo_proof(
+    proof: &UtxoOwnershipProof,
+    tweakable_point: &PublicKey,
+    hashvalue: &Hash160,
+    handshake_hash: &[u8; 32],
+    script_pubkey: &Script,
+) -> Result<(), ProtocolError> {
+    let expected_script_pubkey = match &proof.multisig_redeemscript {
+        Some(redeemscript) => {
+            check_reedemscript_is_multisig(redeemscript)?;
+            let (pubkey1, pubkey2) = read_pubkeys_from_multisig_redeemscript(redeemscript)?;
+            if proof.pubkey != pubkey1 && proof.pubkey != pubkey2 {
+                return Err(ProtocolError::General(
+                    "utxo proof pubkey not in multisig_redeemscript",
+                ));
+            }
+            redeemscript_to_scriptpubkey(redeemscript)?
+        }
+        None => ScriptBuf::new_p2wpkh(
+            &proof
+                .pubkey
+                .wpubkey_hash()
+                .map_err(|_| ProtocolError::General("uncompressed utxo proof pubkey"))?,
+        ),
+    };
+    if expected_script_pubkey.as_script() != script_pubkey {
+        return Err(ProtocolError::General(
+            "utxo proof doesn't match the output's script_pubkey",
+        ));
+    }
+    let message = utxo_proof_message(&proof.outpoint, tweakable_point, hashvalue, handshake_hash);
+    let secp = Secp256k1::verification_only();
+    Ok(secp.verify_ecdsa(&message, &proof.sig, &proof.pubkey.inner)?)
+}
+
 #[cfg(test)]
 mod test {
     use crate::protocol::messages::NextHopInfo;
@@ -1210,6 +1354,139 @@ mod test {
         assert_eq!(tx_input_1, tx_input_2);
     }
 
+    #[test]
+    fn test_contract_tx_fee_exceeds_funding() {
+        let pubkey = PublicKey::from_str(
+            "032e58afe51f9ed8ad3cc7897f634d881fdbe49a81564629ded8156bebd2ffd1af",
+        )
+        .unwrap();
+        let contract_redeemscript = create_multisig_redeemscript(&pubkey, &pubkey);
+        let funding_outpoint = OutPoint {
+            txid: bitcoin::Txid::from_byte_array([3; 32]),
+            vout: 0,
+        };
+
+        // A fee above the funding amount is an error, not an underflow.
+        assert!(create_receivers_contract_tx(
+            funding_outpoint,
+            Amount::from_sat(10_000),
+            &contract_redeemscript,
+            Amount::from_sat(10_001)
+        )
+        .is_err());
+
+        let contract_tx = create_receivers_contract_tx(
+            funding_outpoint,
+            Amount::from_sat(10_000),
+            &contract_redeemscript,
+            Amount::from_sat(1_000),
+        )
+        .unwrap();
+        assert_eq!(contract_tx.output[0].value, Amount::from_sat(9_000));
+    }
+
+    #[test]
+    fn test_refund_tx() {
+        let secp = Secp256k1::new();
+        let sender_privkey = SecretKey::from_slice(&[1; 32]).unwrap();
+        let receiver_privkey = SecretKey::from_slice(&[2; 32]).unwrap();
+        let sender_pubkey = PublicKey {
+            compressed: true,
+            inner: secp256k1::PublicKey::from_secret_key(&secp, &sender_privkey),
+        };
+        let receiver_pubkey = PublicKey {
+            compressed: true,
+            inner: secp256k1::PublicKey::from_secret_key(&secp, &receiver_privkey),
+        };
+        let multisig_redeemscript = create_multisig_redeemscript(&sender_pubkey, &receiver_pubkey);
+
+        let funding_outpoint = OutPoint {
+            txid: bitcoin::Txid::from_byte_array([3; 32]),
+            vout: 1,
+        };
+        let funding_amount = Amount::from_sat(100_000);
+        let destination = ScriptBuf::new_p2wpkh(&sender_pubkey.wpubkey_hash().unwrap());
+
+        // The fee can't eat the whole funding output.
+        assert!(create_refund_tx(
+            funding_outpoint,
+            funding_amount,
+            destination.clone(),
+            Amount::from_sat(100_001)
+        )
+        .is_err());
+
+        let mut refund_tx = create_refund_tx(
+            funding_outpoint,
+            funding_amount,
+            destination.clone(),
+            Amount::from_sat(1_000),
+        )
+        .unwrap();
+        assert_eq!(refund_tx.output[0].value, Amount::from_sat(99_000));
+        assert_eq!(refund_tx.output[0].script_pubkey, destination);
+
+        validate_refund_tx(&refund_tx, &funding_outpoint).unwrap();
+        let other_outpoint = OutPoint {
+            vout: 0,
+            ..funding_outpoint
+        };
+        assert!(validate_refund_tx(&refund_tx, &other_outpoint).is_err());
+        let mut extra_input_tx = refund_tx.clone();
+        extra_input_tx.input.push(extra_input_tx.input[0].clone());
+        assert!(validate_refund_tx(&extra_input_tx, &funding_outpoint).is_err());
+
+        // Both sides sign, and each signature verifies against its own key only.
+        let sender_sig = sign_contract_tx(
+            &refund_tx,
+            &multisig_redeemscript,
+            funding_amount,
+            &sender_privkey,
+        )
+        .unwrap();
+        let receiver_sig = sign_contract_tx(
+            &refund_tx,
+            &multisig_redeemscript,
+            funding_amount,
+            &receiver_privkey,
+        )
+        .unwrap();
+        verify_contract_tx_sig(
+            &refund_tx,
+            &multisig_redeemscript,
+            funding_amount,
+            &sender_pubkey,
+            &sender_sig.signature,
+        )
+        .unwrap();
+        assert!(verify_contract_tx_sig(
+            &refund_tx,
+            &multisig_redeemscript,
+            funding_amount,
+            &receiver_pubkey,
+            &sender_sig.signature,
+        )
+        .is_err());
+        verify_contract_tx_sig(
+            &refund_tx,
+            &multisig_redeemscript,
+            funding_amount,
+            &receiver_pubkey,
+            &receiver_sig.signature,
+        )
+        .unwrap();
+
+        apply_two_signatures_to_2of2_multisig_spend(
+            &sender_pubkey,
+            &receiver_pubkey,
+            &sender_sig,
+            &receiver_sig,
+            &mut refund_tx.input[0],
+            &multisig_redeemscript,
+        );
+        assert_eq!(refund_tx.input[0].witness.len(), 4);
+    }
+
     #[test]
     fn test_check_hashvalues_are_equal() {
         let secp = Secp256k1::new();
@@ -1294,7 +1571,7 @@ mod test {
                 next_multisig_pubkey: pub_2,
             }],
             refund_locktime: u16::default(),
-            contract_feerate: u64::default(),
+            contract_feerate: f64::default(),
             id: "random".to_string(),
         };
 
@@ -1324,7 +1601,7 @@ mod test {
                 next_multisig_pubkey: pub_2,
             }],
             refund_locktime: u16::default(),
-            contract_feerate: u64::default(),
+            contract_feerate: f64::default(),
             id: "random".to_string(),
         };
 
@@ -1339,4 +1616,115 @@ mod test {
             "contract reedemscript doesn't have equal hashvalues"
         );
     }
+
+    #[test]
+    fn test_negotiate_contract_type() {
+        let both = [ContractType::Taproot, ContractType::P2wsh];
+        assert_eq!(
+            negotiate_contract_type(&both, &both),
+            Some(ContractType::Taproot)
+        );
+        assert_eq!(
+            negotiate_contract_type(&[ContractType::P2wsh], &both),
+            Some(ContractType::P2wsh)
+        );
+        // Takers not advertising contract types only know P2WSH.
+        assert_eq!(
+            negotiate_contract_type(&both, &[]),
+            Some(ContractType::P2wsh)
+        );
+        assert_eq!(negotiate_contract_type(&[ContractType::Taproot], &[]), None);
+        assert_eq!(
+            negotiate_contract_type(&[ContractType::P2wsh], &[ContractType::Taproot]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_utxo_ownership_proof() {
+        let secp = Secp256k1::new();
+        let privkey = SecretKey::from_slice(&[3; 32]).unwrap();
+        let pubkey = PublicKey {
+            compressed: true,
+            inner: privkey.public_key(&secp),
+        };
+        let other_pubkey = PublicKey {
+            compressed: true,
+            inner: SecretKey::from_slice(&[4; 32]).unwrap().public_key(&secp),
+        };
+        let tweakable_point = PublicKey::from_str(
+            "03bf98c86c3d536136378cf43ac42861ece609de87f5a44e19b730e8e9bd791938",
+        )
+        .unwrap();
+        let hashvalue = Hash160::hash(&[1; 32]);
+        let handshake_hash = [6; 32];
+        let outpoint = OutPoint {
+            txid: bitcoin::Txid::from_byte_array([5; 32]),
+            vout: 1,
+        };
+
+        // Whether a proof verifies with the maker, swap and connection above.
+        let verifies = |proof: &UtxoOwnershipProof, script_pubkey: &ScriptBuf| {
+            verify_utxo_proof(
+                proof,
+                &tweakable_point,
+                &hashvalue,
+                &handshake_hash,
+                script_pubkey,
+            )
+            .is_ok()
+        };
+
+        // A P2WPKH output.
+        let p2wpkh = ScriptBuf::new_p2wpkh(&pubkey.wpubkey_hash().unwrap());
+        let sign = |hashvalue: &Hash160, handshake_hash: &[u8; 32]| {
+            sign_utxo_proof(
+                outpoint,
+                &privkey,
+                None,
+                &tweakable_point,
+                hashvalue,
+                handshake_hash,
+            )
+        };
+        let proof = sign(&hashvalue, &handshake_hash);
+        assert!(verifies(&proof, &p2wpkh));
+        // Not valid for another maker, swap, connection or output.
+        let other_hashvalue = Hash160::hash(&[2; 32]);
+        assert!(!verifies(&sign(&other_hashvalue, &handshake_hash), &p2wpkh));
+        assert!(!verifies(&sign(&hashvalue, &[7; 32]), &p2wpkh));
+        assert!(
+            verify_utxo_proof(&proof, &other_pubkey, &hashvalue, &handshake_hash, &p2wpkh).is_err()
+        );
+        let other_p2wpkh = ScriptBuf::new_p2wpkh(&other_pubkey.wpubkey_hash().unwrap());
+        assert!(!verifies(&proof, &other_p2wpkh));
+        let mut moved = proof.clone();
+        moved.outpoint.vout = 0;
+        assert!(!verifies(&moved, &p2wpkh));
+
+        // A 2-of-2 multisig output.
+        let redeemscript = create_multisig_redeemscript(&pubkey, &other_pubkey);
+        let p2wsh = redeemscript_to_scriptpubkey(&redeemscript).unwrap();
+        let proof = sign_utxo_proof(
+            outpoint,
+            &privkey,
+            Some(redeemscript),
+            &tweakable_point,
+            &hashvalue,
+            &handshake_hash,
+        );
+        assert!(verifies(&proof, &p2wsh));
+        // The key must be in the multisig.
+        let foreign_redeemscript = create_multisig_redeemscript(&other_pubkey, &other_pubkey);
+        let foreign_p2wsh = redeemscript_to_scriptpubkey(&foreign_redeemscript).unwrap();
+        let proof = sign_utxo_proof(
+            outpoint,
+            &privkey,
+            Some(foreign_redeemscript),
+            &tweakable_point,
+            &hashvalue,
+            &handshake_hash,
+        );
+        assert!(!verifies(&proof, &foreign_p2wsh));
+    }
 }
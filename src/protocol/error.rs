@@ -25,6 +25,13 @@ pub enum ProtocolError {
         /// The received message type.
         received: String,
     },
+    /// Error when the peer's protocol version range has no version in common with ours.
+    UnsupportedVersion {
+        /// Lowest protocol version of the peer.
+        min: u32,
+        /// Highest protocol version of the peer.
+        max: u32,
+    },
     /// Error when the number of signatures does not match the expected count.
     WrongNumOfSigs {
         /// The expected number of signatures.
@@ -68,17 +68,84 @@ use bitcoin::hashes::hash160::Hash as Hash160;
 
 use crate::wallet::FidelityBond;
 
+use super::error::ProtocolError;
+
 /// Defines the length of the Preimage.
 pub(crate) const PREIMAGE_LEN: usize = 32;
 
 /// Type for Preimage.
 pub(crate) type Preimage = [u8; PREIMAGE_LEN];
 
+/// Lowest protocol version spoken by this implementation.
+pub(crate) const PROTOCOL_VERSION_MIN: u32 = 1;
+
+/// Highest protocol version spoken by this implementation.
+pub(crate) const PROTOCOL_VERSION_MAX: u32 = 1;
+
+/// Set of optional protocol features, exchanged in the handshake. Each bit is one feature.
+///
+/// Peers only use the features both of them have set, so new features can be rolled out without
+/// a protocol version bump.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Features(u64);
+
+impl Features {
+    /// No features.
+    pub(crate) const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Refunding a failed swap with refund txs signed by both sides of each hop, see
+    /// [TakerToMakerMessage::ReqRefundTxs].
+    pub(crate) const COOPERATIVE_ABORT: Features = Features(1);
+
+    /// The features set in both.
+    pub(crate) fn intersection(&self, other: Features) -> Features {
+        Self(self.0 & other.0)
+    }
+
+    /// Whether all the features of `other` are set.
+    pub(crate) fn contains(&self, other: Features) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+/// Optional features supported by this implementation.
+pub(crate) const SUPPORTED_FEATURES: Features = Features::COOPERATIVE_ABORT;
+
+/// Leading zero bits of the proof-of-work a Taker does on each connection, see [TakerHello::pow_nonce].
+#[cfg(not(feature = "integration-test"))]
+pub(crate) const CONNECTION_POW_BITS: u32 = 20;
+#[cfg(feature = "integration-test")]
+pub(crate) const CONNECTION_POW_BITS: u32 = 8;
+
+/// Type of the funding and contract outputs of a swap, negotiated in the handshake.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum ContractType {
+    /// P2WSH 2-of-2 multisig funding outputs, and P2WSH hashlock/timelock contracts.
+    #[default]
+    P2wsh,
+    /// Taproot outputs, with a MuSig2 key path for the cooperative spend and tapscript leaves for the
+    /// hashlock and timelock. Not supported yet, but known so that peers offering it can still
+    /// settle on [ContractType::P2wsh].
+    Taproot,
+}
+
 /// Represents the initial handshake message sent from Taker to Maker.
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct TakerHello {
     pub(crate) protocol_version_min: u32,
     pub(crate) protocol_version_max: u32,
+    /// Contract types supported by the Taker, in order of preference.
+    #[serde(default)]
+    pub(crate) contract_types: Vec<ContractType>,
+    /// Optional features supported by the Taker.
+    #[serde(default)]
+    pub(crate) features: Features,
+    /// Proof-of-work of [CONNECTION_POW_BITS] on the connection's Noise handshake. Makers that
+    /// can't tell Takers apart, like over Tor, require it so connections aren't free.
+    #[serde(default)]
+    pub(crate) pow_nonce: u64,
 }
 
 /// Represents a request to give an offer.
@@ -96,12 +163,32 @@ pub(crate) struct ContractTxInfoForSender {
     pub(crate) funding_input_value: Amount,
 }
 
+/// Proof that the Taker can spend an unspent output.
+///
+/// The signature commits to the outpoint, the Maker's tweakable point, the swap's hashvalue and the
+/// handshake hash of the connection it's sent on, so a proof can't be replayed to another Maker, for
+/// another swap, or on another connection. The Maker's ephemeral key in the handshake makes the
+/// handshake hash a fresh nonce of the Maker.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct UtxoOwnershipProof {
+    pub(crate) outpoint: bitcoin::OutPoint,
+    pub(crate) pubkey: PublicKey,
+    /// The 2-of-2 multisig redeemscript of a P2WSH output, `None` for a P2WPKH output.
+    pub(crate) multisig_redeemscript: Option<ScriptBuf>,
+    pub(crate) sig: bitcoin::secp256k1::ecdsa::Signature,
+}
+
 /// Request for Contract Sigs **for** the Sender side of the hop.
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct ReqContractSigsForSender {
     pub(crate) txs_info: Vec<ContractTxInfoForSender>,
     pub(crate) hashvalue: Hash160,
     pub(crate) locktime: LockTime,
+    /// Unspent outputs of the Taker worth at least the funding amount, the ones its funding txs spend.
+    /// Required if the Maker's [`Offer`] says so. Only the first hop is funded by the Taker, so
+    /// such Makers are only asked for the first hop.
+    #[serde(default)]
+    pub(crate) utxo_proofs: Vec<UtxoOwnershipProof>,
 }
 
 /// Contract Sigs requesting information for the Receiver side of the hop.
@@ -143,7 +230,8 @@ pub(crate) struct ProofOfFunding {
     // TODO: Directly use Vec of Pubkeys.
     pub(crate) next_coinswap_info: Vec<NextHopInfo>,
     pub(crate) refund_locktime: LockTime,
-    pub(crate) contract_feerate: u64,
+    /// Feerate in sats/vB for the next hop's contract transactions.
+    pub(crate) contract_feerate: f64,
     pub(crate) id: String,
 }
 
@@ -185,6 +273,39 @@ pub(crate) struct PrivKeyHandover {
     pub(crate) multisig_privkeys: Vec<MultisigPrivkey>,
 }
 
+/// Refund transaction of one funding output of a hop. See [TakerToMakerMessage::ReqRefundTxs].
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(crate) struct RefundTxInfo {
+    pub(crate) multisig_redeemscript: ScriptBuf,
+    pub(crate) refund_tx: Transaction,
+}
+
+/// Request for the refund txs of the Maker's outgoing swapcoins in a swap.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ReqRefundTxs {
+    pub(crate) id: String,
+}
+
+/// Refund txs of the Maker's outgoing swapcoins, with the Maker's signatures.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RefundTxs {
+    pub(crate) refunds: Vec<RefundTxInfo>,
+    pub(crate) sigs: Vec<Signature>,
+}
+
+/// Request for signatures on the refund txs of the Maker's incoming swapcoins in a swap.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ReqRefundSigs {
+    pub(crate) id: String,
+    pub(crate) refunds: Vec<RefundTxInfo>,
+}
+
+/// Signatures of the Maker on the refund txs of its incoming swapcoins.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RefundSigs {
+    pub(crate) sigs: Vec<Signature>,
+}
+
 /// All messages sent from Taker to Maker.
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) enum TakerToMakerMessage {
@@ -204,6 +325,11 @@ pub(crate) enum TakerToMakerMessage {
     RespHashPreimage(HashPreimage),
     /// Respond by handing over the Private Keys of coinswap multisig. This denotes the completion of the whole swap.
     RespPrivKeyHandover(PrivKeyHandover),
+    /// Request the refund txs of a failed swap, for the hop sent by the Maker. Only before the hash preimage is revealed.
+    ReqRefundTxs(ReqRefundTxs),
+    /// Request the Maker's signatures on the refund txs of a failed swap, for the hop received by the Maker.
+    /// The Maker signs only once its own outgoing swapcoins are refunded or were never funded.
+    ReqRefundSigs(ReqRefundSigs),
 }
 
 impl Display for TakerToMakerMessage {
@@ -219,6 +345,8 @@ impl Display for TakerToMakerMessage {
             Self::ReqContractSigsForRecvr(_) => write!(f, "ReqContractSigsForRecvr"),
             Self::RespHashPreimage(_) => write!(f, "RespHashPreimage"),
             Self::RespPrivKeyHandover(_) => write!(f, "RespPrivKeyHandover"),
+            Self::ReqRefundTxs(_) => write!(f, "ReqRefundTxs"),
+            Self::ReqRefundSigs(_) => write!(f, "ReqRefundSigs"),
         }
     }
 }
@@ -228,6 +356,45 @@ impl Display for TakerToMakerMessage {
 pub(crate) struct MakerHello {
     pub(crate) protocol_version_min: u32,
     pub(crate) protocol_version_max: u32,
+    /// Contract type chosen by the Maker for this swap.
+    #[serde(default)]
+    pub(crate) contract_type: ContractType,
+    /// Optional features supported by the Maker.
+    #[serde(default)]
+    pub(crate) features: Features,
+}
+
+/// Protocol parameters agreed in the handshake with a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct NegotiatedProtocol {
+    /// Highest protocol version spoken by both peers.
+    pub(crate) version: u32,
+    /// Optional features supported by both peers.
+    pub(crate) features: Features,
+    /// Contract type of the swap.
+    pub(crate) contract_type: ContractType,
+}
+
+impl Default for NegotiatedProtocol {
+    fn default() -> Self {
+        Self {
+            version: PROTOCOL_VERSION_MIN,
+            features: Features::empty(),
+            contract_type: ContractType::default(),
+        }
+    }
+}
+
+/// The highest protocol version within both our and the peer's version range.
+pub(crate) fn negotiate_version(peer_min: u32, peer_max: u32) -> Result<u32, ProtocolError> {
+    let version = peer_max.min(PROTOCOL_VERSION_MAX);
+    if peer_min > peer_max || version < peer_min.max(PROTOCOL_VERSION_MIN) {
+        return Err(ProtocolError::UnsupportedVersion {
+            min: peer_min,
+            max: peer_max,
+        });
+    }
+    Ok(version)
 }
 
 /// Contains proof data related to fidelity bond.
@@ -250,6 +417,10 @@ pub(crate) struct Offer {
     pub(crate) min_size: u64,
     pub(crate) tweakable_point: PublicKey,
     pub(crate) fidelity: FidelityProof,
+    /// Whether contract signing requests must prove ownership of the funds with
+    /// [`UtxoOwnershipProof`]s.
+    #[serde(default)]
+    pub(crate) requires_utxo_proof: bool,
 }
 
 /// Contract Tx signatures provided by a Sender of a Coinswap.
@@ -300,6 +471,10 @@ pub(crate) enum MakerToTakerMessage {
     RespContractSigsForRecvr(ContractSigsForRecvr),
     /// Send the multisig private keys of the swap, declaring completion of the contract.
     RespPrivKeyHandover(PrivKeyHandover),
+    /// Send the signed refund txs of the hop sent by the Maker.
+    RespRefundTxs(RefundTxs),
+    /// Send the signatures on the refund txs of the hop received by the Maker.
+    RespRefundSigs(RefundSigs),
 }
 
 impl Display for MakerToTakerMessage {
@@ -315,6 +490,8 @@ impl Display for MakerToTakerMessage {
                 write!(f, "RespContractSigsForRecvr")
             }
             Self::RespPrivKeyHandover(_) => write!(f, "RespPrivKeyHandover"),
+            Self::RespRefundTxs(_) => write!(f, "RespRefundTxs"),
+            Self::RespRefundSigs(_) => write!(f, "RespRefundSigs"),
         }
     }
 }
@@ -352,3 +529,69 @@ pub enum DnsRequest {
         vout: u32,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_version() {
+        // Overlapping ranges agree on the highest common version.
+        assert_eq!(
+            negotiate_version(PROTOCOL_VERSION_MIN, PROTOCOL_VERSION_MAX).unwrap(),
+            PROTOCOL_VERSION_MAX
+        );
+        assert_eq!(
+            negotiate_version(PROTOCOL_VERSION_MIN, PROTOCOL_VERSION_MAX + 5).unwrap(),
+            PROTOCOL_VERSION_MAX
+        );
+        assert_eq!(
+            negotiate_version(0, PROTOCOL_VERSION_MIN).unwrap(),
+            PROTOCOL_VERSION_MIN
+        );
+
+        // Disjoint or invalid ranges are rejected.
+        assert!(matches!(
+            negotiate_version(PROTOCOL_VERSION_MAX + 1, PROTOCOL_VERSION_MAX + 3),
+            Err(ProtocolError::UnsupportedVersion { .. })
+        ));
+        assert!(matches!(
+            negotiate_version(0, PROTOCOL_VERSION_MIN - 1),
+            Err(ProtocolError::UnsupportedVersion { .. })
+        ));
+        assert!(matches!(
+            negotiate_version(PROTOCOL_VERSION_MAX, PROTOCOL_VERSION_MIN - 1),
+            Err(ProtocolError::UnsupportedVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn test_hello_features_default() {
+        assert_eq!(
+            Features(0b101).intersection(Features(0b110)),
+            Features(0b100)
+        );
+        assert_eq!(
+            SUPPORTED_FEATURES.intersection(Features(u64::MAX)),
+            SUPPORTED_FEATURES
+        );
+        assert!(Features(0b110).contains(Features(0b100)));
+        assert!(!Features(0b110).contains(Features(0b101)));
+        assert!(!Features::empty().contains(Features::COOPERATIVE_ABORT));
+
+        // Hellos from peers without feature bits or contract types still parse.
+        #[derive(Serialize)]
+        struct LegacyHello {
+            protocol_version_min: u32,
+            protocol_version_max: u32,
+        }
+        let bytes = serde_cbor::to_vec(&LegacyHello {
+            protocol_version_min: 1,
+            protocol_version_max: 1,
+        })
+        .unwrap();
+        let hello: MakerHello = serde_cbor::from_slice(&bytes).unwrap();
+        assert_eq!(hello.features, Features::empty());
+        assert_eq!(hello.contract_type, ContractType::P2wsh);
+    }
+}
@@ -3,6 +3,7 @@
 pub(crate) mod contract;
 pub mod error;
 pub mod messages;
+pub(crate) mod noise;
 
 pub(crate) use contract::Hash160;
 
@@ -0,0 +1,410 @@
+//! Authenticated and encrypted transport between Takers and Makers.
+//!
+//! Every Taker-Maker connection starts with a Noise `NX` handshake (`Noise_NX_secp256k1_AESGCM_SHA256`).
+//! The Maker proves possession of its static key, which is the key of its fidelity bond, so a Taker
+//! knows it's talking to the Maker that advertised the bond. The Taker stays anonymous, with a fresh
+//! ephemeral key for each connection.
+//!
+//! After the handshake, every message is encrypted and authenticated with AES-256-GCM, with a separate
+//! key for each direction. Handshake and transport messages use the same length-prefixed framing as
+//! [`send_message`](crate::utill::send_message).
+
+use std::{
+    io,
+    net::{SocketAddr, TcpStream},
+    time::Duration,
+};
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use bitcoin::{
+    hashes::{
+        hmac::{Hmac, HmacEngine},
+        sha256, Hash, HashEngine,
+    },
+    secp256k1::{ecdh::SharedSecret, rand::rngs::OsRng, Keypair, PublicKey, Secp256k1},
+};
+
+use crate::{
+    error::NetError,
+    utill::{read_message_with_limits, send_frame, MessageLimits},
+};
+
+/// Noise protocol name. It is exactly 32 bytes, so it is used as the initial handshake hash as is.
+const PROTOCOL_NAME: &[u8; 32] = b"Noise_NX_secp256k1_AESGCM_SHA256";
+
+/// Prologue mixed into the handshake, so it can't be mistaken for another protocol's.
+const PROLOGUE: &[u8] = b"coinswap";
+
+/// Length of a serialized compressed public key.
+const PUBKEY_LEN: usize = 33;
+
+/// Length of the AES-GCM authentication tag.
+const TAG_LEN: usize = 16;
+
+fn hmac(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let mut engine = HmacEngine::<sha256::Hash>::new(key);
+    for d in data {
+        engine.input(d);
+    }
+    Hmac::<sha256::Hash>::from_engine(engine).to_byte_array()
+}
+
+/// HKDF with two outputs, as defined by Noise.
+fn hkdf(chaining_key: &[u8; 32], ikm: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let temp_key = hmac(chaining_key, &[ikm]);
+    let out1 = hmac(&temp_key, &[&[1]]);
+    let out2 = hmac(&temp_key, &[&out1, &[2]]);
+    (out1, out2)
+}
+
+/// ECDH, as the SHA256 of the compressed shared point.
+fn dh(keypair: &Keypair, pubkey: &PublicKey) -> [u8; 32] {
+    SharedSecret::new(pubkey, &keypair.secret_key()).secret_bytes()
+}
+
+fn parse_pubkey(bytes: &[u8]) -> Result<PublicKey, NetError> {
+    PublicKey::from_slice(bytes).map_err(|_| NetError::Encryption("Invalid handshake public key"))
+}
+
+/// An AES-256-GCM key and its message counter.
+struct CipherState {
+    cipher: Aes256Gcm,
+    nonce: u64,
+}
+
+impl CipherState {
+    fn new(key: [u8; 32]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+            nonce: 0,
+        }
+    }
+
+    /// The 96 bit nonce for the next message: 32 zero bits, then the big-endian counter.
+    fn next_nonce(&mut self) -> Result<[u8; 12], NetError> {
+        // The maximum counter value is reserved by Noise.
+        if self.nonce == u64::MAX {
+            return Err(NetError::Encryption("Transport nonce exhausted"));
+        }
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&self.nonce.to_be_bytes());
+        self.nonce += 1;
+        Ok(nonce)
+    }
+
+    fn encrypt(&mut self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, NetError> {
+        let nonce = self.next_nonce()?;
+        self.cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|_| NetError::Encryption("Failed to encrypt message"))
+    }
+
+    fn decrypt(&mut self, aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, NetError> {
+        let nonce = self.next_nonce()?;
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| NetError::Encryption("Failed to decrypt message"))
+    }
+}
+
+/// Whether `nonce` proves `bits` of work on the connection with `handshake_hash`.
+///
+/// The work is bound to the handshake, so it can't be reused for another connection.
+pub(crate) fn check_pow(handshake_hash: &[u8; 32], nonce: u64, bits: u32) -> bool {
+    let mut engine = sha256::Hash::engine();
+    engine.input(handshake_hash);
+    engine.input(&nonce.to_le_bytes());
+    let hash = sha256::Hash::from_engine(engine).to_byte_array();
+    let mut leading = [0u8; 16];
+    leading.copy_from_slice(&hash[..16]);
+    u128::from_be_bytes(leading).leading_zeros() >= bits
+}
+
+/// The chaining key and handshake hash of an ongoing handshake.
+struct SymmetricState {
+    chaining_key: [u8; 32],
+    handshake_hash: [u8; 32],
+    cipher: Option<CipherState>,
+}
+
+impl SymmetricState {
+    fn new() -> Self {
+        let mut state = Self {
+            chaining_key: *PROTOCOL_NAME,
+            handshake_hash: *PROTOCOL_NAME,
+            cipher: None,
+        };
+        state.mix_hash(PROLOGUE);
+        state
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) {
+        let mut engine = sha256::Hash::engine();
+        engine.input(&self.handshake_hash);
+        engine.input(data);
+        self.handshake_hash = sha256::Hash::from_engine(engine).to_byte_array();
+    }
+
+    fn mix_key(&mut self, ikm: &[u8]) {
+        let (chaining_key, key) = hkdf(&self.chaining_key, ikm);
+        self.chaining_key = chaining_key;
+        self.cipher = Some(CipherState::new(key));
+    }
+
+    fn encrypt_and_hash(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, NetError> {
+        let ciphertext = match self.cipher.as_mut() {
+            Some(cipher) => cipher.encrypt(&self.handshake_hash, plaintext)?,
+            None => plaintext.to_vec(),
+        };
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, NetError> {
+        let plaintext = match self.cipher.as_mut() {
+            Some(cipher) => cipher.decrypt(&self.handshake_hash, ciphertext)?,
+            None => ciphertext.to_vec(),
+        };
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    /// Keys for the initiator to responder, and the responder to initiator direction.
+    fn split(&self) -> (CipherState, CipherState) {
+        let (k1, k2) = hkdf(&self.chaining_key, &[]);
+        (CipherState::new(k1), CipherState::new(k2))
+    }
+}
+
+/// A TCP stream between a Taker and a Maker, after the Noise handshake.
+pub(crate) struct NoiseStream {
+    stream: TcpStream,
+    send: CipherState,
+    recv: CipherState,
+    remote_static: Option<PublicKey>,
+    handshake_hash: [u8; 32],
+    limits: MessageLimits,
+}
+
+impl NoiseStream {
+    /// Taker side of the handshake.
+    ///
+    /// If the Maker's fidelity bond key is known, the handshake fails unless the Maker authenticates
+    /// with it. Otherwise the key the Maker authenticated with is available at [`NoiseStream::remote_static`].
+    pub(crate) fn connect(
+        mut stream: TcpStream,
+        maker_key: Option<&PublicKey>,
+    ) -> Result<Self, NetError> {
+        let secp = Secp256k1::new();
+        let mut state = SymmetricState::new();
+        let limits = MessageLimits::default();
+
+        // -> e
+        let ephemeral = Keypair::new(&secp, &mut OsRng);
+        let mut msg = ephemeral.public_key().serialize().to_vec();
+        state.mix_hash(&msg);
+        msg.extend(state.encrypt_and_hash(&[])?);
+        send_frame(&mut stream, &msg)?;
+
+        // <- e, ee, s, es
+        let msg = read_message_with_limits(&mut stream, &limits)?;
+        if msg.len() != 2 * PUBKEY_LEN + 2 * TAG_LEN {
+            return Err(NetError::Encryption("Invalid handshake message length"));
+        }
+        let (remote_ephemeral, msg) = msg.split_at(PUBKEY_LEN);
+        let (encrypted_static, payload) = msg.split_at(PUBKEY_LEN + TAG_LEN);
+        state.mix_hash(remote_ephemeral);
+        state.mix_key(&dh(&ephemeral, &parse_pubkey(remote_ephemeral)?));
+        let remote_static = parse_pubkey(&state.decrypt_and_hash(encrypted_static)?)?;
+        state.mix_key(&dh(&ephemeral, &remote_static));
+        state.decrypt_and_hash(payload)?;
+
+        if matches!(maker_key, Some(key) if *key != remote_static) {
+            return Err(NetError::Encryption(
+                "Maker didn't authenticate with its fidelity bond key",
+            ));
+        }
+
+        let (send, recv) = state.split();
+        Ok(Self {
+            stream,
+            send,
+            recv,
+            remote_static: Some(remote_static),
+            handshake_hash: state.handshake_hash,
+            limits,
+        })
+    }
+
+    /// Maker side of the handshake, authenticating with the fidelity bond keypair.
+    ///
+    /// All messages read from the Taker, including the handshake, are bounded by `limits`.
+    pub(crate) fn accept(
+        mut stream: TcpStream,
+        static_key: &Keypair,
+        limits: MessageLimits,
+    ) -> Result<Self, NetError> {
+        let secp = Secp256k1::new();
+        let mut state = SymmetricState::new();
+
+        // -> e
+        let msg = read_message_with_limits(&mut stream, &limits)?;
+        if msg.len() != PUBKEY_LEN {
+            return Err(NetError::Encryption("Invalid handshake message length"));
+        }
+        let remote_ephemeral = parse_pubkey(&msg)?;
+        state.mix_hash(&msg);
+        state.decrypt_and_hash(&[])?;
+
+        // <- e, ee, s, es
+        let ephemeral = Keypair::new(&secp, &mut OsRng);
+        let mut msg = ephemeral.public_key().serialize().to_vec();
+        state.mix_hash(&msg);
+        state.mix_key(&dh(&ephemeral, &remote_ephemeral));
+        msg.extend(state.encrypt_and_hash(&static_key.public_key().serialize())?);
+        state.mix_key(&dh(static_key, &remote_ephemeral));
+        msg.extend(state.encrypt_and_hash(&[])?);
+        send_frame(&mut stream, &msg)?;
+
+        let (recv, send) = state.split();
+        Ok(Self {
+            stream,
+            send,
+            recv,
+            remote_static: None,
+            handshake_hash: state.handshake_hash,
+            limits,
+        })
+    }
+
+    /// The static key the Maker authenticated with. Only known on the Taker side.
+    pub(crate) fn remote_static(&self) -> Option<PublicKey> {
+        self.remote_static
+    }
+
+    /// Hash of the whole handshake, unique to this connection.
+    pub(crate) fn handshake_hash(&self) -> [u8; 32] {
+        self.handshake_hash
+    }
+
+    /// Find a nonce proving `bits` of work on this connection, see [check_pow].
+    pub(crate) fn solve_pow(&self, bits: u32) -> u64 {
+        (0..)
+            .find(|nonce| check_pow(&self.handshake_hash, *nonce, bits))
+            .expect("a nonce exists")
+    }
+
+    /// How long to wait for the first byte of the next message. See [read_message_with_limits].
+    pub(crate) fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    /// Address of the peer.
+    pub(crate) fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Encrypt and send a message.
+    pub(crate) fn send_message(&mut self, message: &impl serde::Serialize) -> Result<(), NetError> {
+        let msg_bytes = serde_cbor::ser::to_vec(message)?;
+        let ciphertext = self.send.encrypt(&[], &msg_bytes)?;
+        send_frame(&mut self.stream, &ciphertext)
+    }
+
+    /// Read and decrypt a message.
+    pub(crate) fn read_message(&mut self) -> Result<Vec<u8>, NetError> {
+        let ciphertext = read_message_with_limits(&mut self.stream, &self.limits)?;
+        self.recv.decrypt(&[], &ciphertext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{net::TcpListener, thread};
+
+    fn connect_pair(
+        maker_key: &Keypair,
+        expected_key: Option<PublicKey>,
+    ) -> (Result<NoiseStream, NetError>, Result<NoiseStream, NetError>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let maker_key = *maker_key;
+        let maker = thread::spawn(move || {
+            let (socket, _) = listener.accept().unwrap();
+            NoiseStream::accept(socket, &maker_key, MessageLimits::default())
+        });
+        let taker =
+            NoiseStream::connect(TcpStream::connect(address).unwrap(), expected_key.as_ref());
+        (taker, maker.join().unwrap())
+    }
+
+    #[test]
+    fn test_noise_handshake_and_transport() {
+        let secp = Secp256k1::new();
+        let maker_key = Keypair::new(&secp, &mut OsRng);
+
+        let (taker, maker) = connect_pair(&maker_key, Some(maker_key.public_key()));
+        let (mut taker, mut maker) = (taker.unwrap(), maker.unwrap());
+        assert_eq!(taker.remote_static(), Some(maker_key.public_key()));
+        assert_eq!(maker.remote_static(), None);
+        assert_eq!(taker.handshake_hash, maker.handshake_hash);
+
+        // Work done on one connection is checked against the handshake both sides share.
+        let nonce = taker.solve_pow(8);
+        assert!(check_pow(&maker.handshake_hash(), nonce, 8));
+        let (other, _) = connect_pair(&maker_key, None);
+        assert_ne!(other.unwrap().handshake_hash, maker.handshake_hash);
+
+        for i in 0..3u32 {
+            taker.send_message(&format!("hello maker {}", i)).unwrap();
+            let msg: String = serde_cbor::from_slice(&maker.read_message().unwrap()).unwrap();
+            assert_eq!(msg, format!("hello maker {}", i));
+
+            maker.send_message(&i).unwrap();
+            let msg: u32 = serde_cbor::from_slice(&taker.read_message().unwrap()).unwrap();
+            assert_eq!(msg, i);
+        }
+
+        // Messages are not sent in the clear.
+        taker.send_message(&"secret".to_string()).unwrap();
+        let plaintext = serde_cbor::to_vec(&"secret".to_string()).unwrap();
+        let ciphertext = read_message_with_limits(&mut maker.stream, &maker.limits).unwrap();
+        assert_ne!(ciphertext, plaintext);
+        assert_eq!(maker.recv.decrypt(&[], &ciphertext).unwrap(), plaintext);
+
+        // Tampered messages are rejected.
+        let mut ciphertext = taker.send.encrypt(&[], b"tamper").unwrap();
+        ciphertext[0] ^= 1;
+        assert!(matches!(
+            maker.recv.decrypt(&[], &ciphertext),
+            Err(NetError::Encryption(_))
+        ));
+    }
+
+    #[test]
+    fn test_noise_wrong_maker_key() {
+        let secp = Secp256k1::new();
+        let maker_key = Keypair::new(&secp, &mut OsRng);
+        let bond_key = Keypair::new(&secp, &mut OsRng);
+
+        let (taker, _) = connect_pair(&maker_key, Some(bond_key.public_key()));
+        assert!(matches!(taker, Err(NetError::Encryption(_))));
+    }
+}
@@ -7,10 +7,12 @@
 //! [OngoingSwapState]: Represents the State of an ongoing swap round. All swap related data are stored in this state.
 //!
 //! [Taker::do_coinswap]: The routine running all other protocol subroutines.
+//! [Taker::start_swap] and [Taker::run_swaps]: Run several independent swaps concurrently.
+//! [Taker::resume_swap]: Continue a swap from its journal, after the Taker was restarted.
+//! [Taker::swap_history]: The finished swaps, with a privacy summary of each.
 
 use std::{
     collections::{HashMap, HashSet},
-    io::BufWriter,
     net::TcpStream,
     path::PathBuf,
     process::Child,
@@ -21,17 +23,19 @@ use std::{
 #[cfg(feature = "tor")]
 use std::io::Read;
 
-use bitcoind::bitcoincore_rpc::RpcApi;
+use bitcoind::bitcoincore_rpc::{json::ListUnspentResultEntry, RpcApi};
 
 #[cfg(feature = "tor")]
 use socks::Socks5Stream;
 
+use serde::{Deserialize, Serialize};
+
 use bitcoin::{
     consensus::encode::deserialize,
     hashes::{hash160::Hash as Hash160, Hash},
     hex::{Case, DisplayHex},
     secp256k1::{
-        rand::{rngs::OsRng, RngCore},
+        rand::{rngs::OsRng, Rng, RngCore},
         SecretKey,
     },
     Amount, BlockHash, OutPoint, PublicKey, ScriptBuf, Transaction, Txid,
@@ -39,23 +43,34 @@ use bitcoin::{
 
 use super::{
     error::TakerError,
-    offers::{fetch_addresses_from_dns, fetch_offer_from_makers, MakerAddress, OfferAndAddress},
+    history::{SwapHistory, SwapOutcome, SwapRecord, SwapReport},
+    offers::{
+        fetch_addresses_from_dns, fetch_offer_from_makers, MakerAddress, MakerSelector,
+        OfferAndAddress,
+    },
     routines::*,
 };
 use crate::{
     protocol::{
+        contract::{
+            apply_two_signatures_to_2of2_multisig_spend, calculate_coinswap_fee,
+            create_contract_redeemscript, create_refund_tx, find_funding_output_index,
+            sign_contract_tx, validate_refund_tx, verify_contract_tx_sig,
+        },
         error::ProtocolError,
         messages::{
             ContractSigsAsRecvrAndSender, ContractSigsForRecvr, ContractSigsForRecvrAndSender,
-            ContractSigsForSender, FundingTxInfo, MultisigPrivkey, Preimage, PrivKeyHandover,
-            TakerToMakerMessage,
+            ContractSigsForSender, Features, FundingTxInfo, MultisigPrivkey, Offer, Preimage,
+            PrivKeyHandover, RefundTxInfo, RefundTxs, TakerToMakerMessage,
         },
+        noise::NoiseStream,
     },
     taker::{config::TakerConfig, offers::OfferBook},
     utill::*,
     wallet::{
-        IncomingSwapCoin, OutgoingSwapCoin, RPCConfig, SwapCoin, Wallet, WalletError,
-        WalletSwapCoin, WatchOnlySwapCoin,
+        calculate_fee, contract_tx_vsize, funding_tx_vsize, refund_tx_vsize, timelock_spend_vsize,
+        IncomingSwapCoin, OutgoingSwapCoin, RPCConfig, SwapCoin, UTXOSpendInfo, Wallet,
+        WalletError, WalletSwapCoin, WatchOnlySwapCoin, MIN_SPLIT_AMOUNT,
     },
 };
 
@@ -65,6 +80,8 @@ use crate::tor::kill_tor_handles;
 // Default values for Taker configurations
 pub(crate) const REFUND_LOCKTIME: u16 = 20;
 pub(crate) const REFUND_LOCKTIME_STEP: u16 = 20;
+/// Maximum number of txs a swap amount can be split into.
+pub(crate) const MAX_TX_COUNT: u32 = 10;
 pub(crate) const FIRST_CONNECT_ATTEMPTS: u32 = 5;
 pub(crate) const FIRST_CONNECT_SLEEP_DELAY_SEC: u64 = 1;
 pub(crate) const FIRST_CONNECT_ATTEMPT_TIMEOUT_SEC: u64 = 30;
@@ -78,34 +95,66 @@ pub(crate) const RECONNECT_SHORT_SLEEP_DELAY: u64 = 1;
 pub(crate) const RECONNECT_LONG_SLEEP_DELAY: u64 = 5;
 pub(crate) const SHORT_LONG_SLEEP_DELAY_TRANSITION: u32 = 30;
 pub(crate) const TCP_TIMEOUT_SECONDS: u64 = 300;
-// TODO: Maker should decide this miner fee
-// This fee is used for both funding and contract txs.
-#[cfg(feature = "integration-test")]
-pub(crate) const MINER_FEE: u64 = 1000;
-
-/// This fee is used for both funding and contract txs.
-#[cfg(not(feature = "integration-test"))]
-pub(crate) const MINER_FEE: u64 = 300; // around 2 sats/vb for funding tx
-
 /// Swap specific parameters. These are user's policy and can differ among swaps.
 /// SwapParams govern the criteria to find suitable set of makers from the offerbook.
 ///
 /// If no maker matches with a given SwapParam, that coinswap round will fail.
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
 pub struct SwapParams {
     /// Total Amount to Swap.
     pub send_amount: Amount,
     /// How many hops.
     pub maker_count: usize,
-    /// How many splits
+    /// How many splits. The swap amount is randomly distributed across this many funding txs.
+    /// Each later hop is split into a random number of txs, between 2 and this count.
     pub tx_count: u32,
     // TODO: Following two should be moved to TakerConfig as global configuration.
     /// Confirmation count required for funding txs.
     pub required_confirms: u32,
 }
 
+/// Estimated cost of a coinswap with a set of candidate makers, calculated before anything is broadcasted.
+///
+/// The actual swap selects its makers again, so the maker fees can differ if other makers are chosen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapQuote {
+    /// Total amount to swap.
+    pub send_amount: Amount,
+    /// Feerate in sats/vB used for all the miner fee estimates.
+    pub feerate: f64,
+    /// The hops of the swap, in order.
+    pub hops: Vec<HopQuote>,
+    /// Miner fees of the Taker's own funding txs.
+    pub taker_funding_fee: Amount,
+    /// Amount received back by the Taker from the last maker.
+    pub receive_amount: Amount,
+    /// Total cost of the swap. All maker fees and miner fees paid by the Taker.
+    pub total_cost: Amount,
+    /// Worst case cost, if the swap fails and the Taker recovers its funds via the timelock of its contracts.
+    pub recovery_cost: Amount,
+    /// Blocks the Taker's funds are locked for in the worst case.
+    pub recovery_locktime: u16,
+}
+
+/// Estimated fees of a single hop of a coinswap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HopQuote {
+    /// The maker of this hop.
+    pub maker: MakerAddress,
+    /// Refund locktime of the maker's outgoing contracts, in blocks.
+    pub refund_locktime: u16,
+    /// Amount received by the maker.
+    pub incoming_amount: Amount,
+    /// Coinswap fee charged by the maker.
+    pub coinswap_fee: Amount,
+    /// Number of funding txs of the maker, quoted at the highest split count.
+    pub tx_count: u32,
+    /// Miner fees of the maker's funding txs, paid by the Taker.
+    pub funding_fee: Amount,
+}
+
 // Defines the Taker's position in the current ongoing swap.
-#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 enum TakerPosition {
     #[default]
     /// Taker is the First Peer of the swap (Sender Side)
@@ -120,8 +169,9 @@ enum TakerPosition {
 /// performing a swap. Various data are appended into the lists and are oly read from the last entry as the
 /// swap progresses. This ensures the swap state is always consistent.
 ///
-/// This states can be used to recover from a failed swap round.
-#[derive(Default)]
+/// This states can be used to recover from a failed swap round. It is journaled to disk at every step of
+/// the swap, so the swap can be resumed if the Taker is restarted. See [Taker::resume_swap].
+#[derive(Default, Serialize, Deserialize)]
 struct OngoingSwapState {
     /// SwapParams used in current swap round.
     pub(crate) swap_params: SwapParams,
@@ -142,10 +192,179 @@ struct OngoingSwapState {
     pub(crate) taker_position: TakerPosition,
     /// Unique ID for a swap
     pub(crate) id: String,
+    /// Feerate in sats/vB used for all funding and contract txs of this swap round.
+    pub(crate) feerate: f64,
+    /// Number of funding txs of each hop. The first entry is for the Taker's own funding txs,
+    /// and the last one for the txs received back by the Taker.
+    pub(crate) hop_tx_counts: Vec<u32>,
+    /// The next step of the swap.
+    pub(crate) step: SwapStep,
+    /// Whether the preimage was sent to the makers. The swap can't be aborted cooperatively after.
+    #[serde(default)]
+    pub(crate) preimage_revealed: bool,
+    /// When the swap started, in seconds since the unix epoch.
+    #[serde(default)]
+    pub(crate) started_at: u64,
+    /// Block height when the swap started.
+    #[serde(default)]
+    pub(crate) start_height: u64,
+    /// Miner fees of the Taker's funding txs.
+    #[serde(default)]
+    pub(crate) funding_fee: Amount,
+}
+
+impl OngoingSwapState {
+    /// Get the [Preimage] hash of the swap.
+    fn preimage_hash(&self) -> Hash160 {
+        Hash160::hash(&self.active_preimage)
+    }
+
+    /// The funding outputs of a hop. Hop `0` is funded by the Taker, and the last hop is received by
+    /// the Taker.
+    fn refund_outputs(&self, hop: usize) -> Vec<RefundOutput> {
+        if hop == 0 {
+            self.outgoing_swapcoins
+                .iter()
+                .map(|swapcoin| RefundOutput {
+                    multisig_redeemscript: swapcoin.get_multisig_redeemscript(),
+                    outpoint: swapcoin.contract_tx.input[0].previous_output,
+                    amount: swapcoin.funding_amount,
+                    sender_pubkey: swapcoin.get_my_pubkey(),
+                    receiver_pubkey: swapcoin.other_pubkey,
+                    my_privkey: Some(swapcoin.my_privkey),
+                })
+                .collect()
+        } else if hop == self.swap_params.maker_count {
+            self.incoming_swapcoins
+                .iter()
+                .map(|swapcoin| RefundOutput {
+                    multisig_redeemscript: swapcoin.get_multisig_redeemscript(),
+                    outpoint: swapcoin.contract_tx.input[0].previous_output,
+                    amount: swapcoin.funding_amount,
+                    sender_pubkey: swapcoin.other_pubkey,
+                    receiver_pubkey: swapcoin.get_my_pubkey(),
+                    my_privkey: Some(swapcoin.my_privkey),
+                })
+                .collect()
+        } else {
+            self.watchonly_swapcoins
+                .get(hop - 1)
+                .into_iter()
+                .flatten()
+                .map(|swapcoin| RefundOutput {
+                    multisig_redeemscript: swapcoin.get_multisig_redeemscript(),
+                    outpoint: swapcoin.contract_tx.input[0].previous_output,
+                    amount: swapcoin.funding_amount,
+                    sender_pubkey: swapcoin.sender_pubkey,
+                    receiver_pubkey: swapcoin.receiver_pubkey,
+                    my_privkey: None,
+                })
+                .collect()
+        }
+    }
+
+    /// The maker at `index` of the swap, if it supports cooperative aborts.
+    fn refund_peer(&self, index: usize) -> Result<&OfferAndAddress, TakerError> {
+        let maker = &self
+            .peer_infos
+            .get(index)
+            .ok_or(ProtocolError::General("maker of the hop not known"))?
+            .peer;
+        if !maker
+            .protocol
+            .features
+            .contains(Features::COOPERATIVE_ABORT)
+        {
+            return Err(ProtocolError::General("maker doesn't support cooperative aborts").into());
+        }
+        Ok(maker)
+    }
+}
+
+/// The steps of a swap, after its first hop is initiated. Each step either completes at once, or
+/// is polled until it does. This lets the Taker wait for the confirmations of several swaps at once.
+#[derive(Default, Serialize, Deserialize)]
+enum SwapStep {
+    /// Waiting for the Taker's funding txs to confirm.
+    TakerFunding(FundingWatch),
+    /// Initiating the hop from the maker at this index.
+    InitNextHop(usize),
+    /// Waiting for the funding txs of the maker at `maker_index` to confirm.
+    HopFunding {
+        maker_index: usize,
+        watch: FundingWatch,
+        funding_outpoints: Vec<OutPoint>,
+        multisig_redeemscripts: Vec<ScriptBuf>,
+    },
+    /// All contracts are set up, the swap can be settled.
+    Settle,
+    /// Recovering from a failed swap. The error, if any, is returned once the recovery completes.
+    /// It is not journaled.
+    Recover {
+        recovery: SwapRecovery,
+        #[serde(skip)]
+        error: Option<TakerError>,
+    },
+    /// Nothing left to do.
+    #[default]
+    Done,
+}
+
+/// Confirmed funding txs with their merkle proofs.
+type FundingTxsWithProofs = (Vec<Transaction>, Vec<String>);
+
+/// Funding txs being watched for confirmations. Only the txids are journaled, the watch starts
+/// over when the swap is resumed.
+#[derive(Serialize, Deserialize)]
+struct FundingWatch {
+    txids: Vec<Txid>,
+    required_confirmations: u32,
+    #[serde(skip, default = "Instant::now")]
+    start_time: Instant,
+    #[serde(skip)]
+    txids_seen_once: HashSet<Txid>,
+    #[serde(skip)]
+    txid_tx_map: HashMap<Txid, Transaction>,
+    #[serde(skip)]
+    txid_blockhash_map: HashMap<Txid, BlockHash>,
+}
+
+/// Outgoing contracts being recovered via their timelocks.
+#[derive(Serialize, Deserialize)]
+struct SwapRecovery {
+    /// The multisig redeemscript, contract tx, timelock and timelock spend of each outgoing swapcoin.
+    outgoing_infos: Vec<((ScriptBuf, Transaction), (u16, Transaction))>,
+    timelock_broadcasted: Vec<Txid>,
+    #[serde(skip, default = "Instant::now")]
+    next_check: Instant,
+}
+
+/// A funding output of a hop, to be spent back to its sender when aborting a swap cooperatively.
+struct RefundOutput {
+    multisig_redeemscript: ScriptBuf,
+    outpoint: OutPoint,
+    amount: Amount,
+    sender_pubkey: PublicKey,
+    receiver_pubkey: PublicKey,
+    /// The Taker's multisig privkey, if the Taker is the sender or receiver of the hop.
+    my_privkey: Option<SecretKey>,
+}
+
+/// Handle of a swap started with [Taker::start_swap].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SwapHandle {
+    id: String,
+}
+
+impl SwapHandle {
+    /// The unique id of the swap.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
 }
 
 /// Information for the next maker in the hop.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct NextPeerInfo {
     peer: OfferAndAddress,
     multisig_pubkeys: Vec<PublicKey>,
@@ -163,21 +382,28 @@ pub enum TakerBehavior {
     DropConnectionAfterFullSetup,
     /// Behavior to broadcast the contract after the full coinswap setup.
     BroadcastContractAfterFullSetup,
+    /// Behavior to abort the swap after the full coinswap setup, before revealing the preimage.
+    AbortAfterFullSetup,
+    /// Like [TakerBehavior::AbortAfterFullSetup], but asks the makers for their refund signatures
+    /// without waiting for the refunds of their outgoing hops to confirm.
+    AbortWithoutWaitingForRefunds,
 }
 
 /// The Taker structure that performs bulk of the coinswap protocol. Taker connects
 /// to multiple Makers and send protocol messages sequentially to them. The communication
 ///
-/// sequence and corresponding SwapCoin infos are stored in `ongoing_swap_state`.
+/// sequence and corresponding SwapCoin infos are stored in `ongoing_swaps`, keyed by the swap id.
 pub struct Taker {
     wallet: Wallet,
     /// Taker configuration with refund, connection, and sleep settings.
     pub config: TakerConfig,
     offerbook: OfferBook,
-    ongoing_swap_state: OngoingSwapState,
+    maker_selector: MakerSelector,
+    ongoing_swaps: HashMap<String, OngoingSwapState>,
     behavior: TakerBehavior,
     tor_handle: Option<Child>,
     data_dir: PathBuf,
+    history: SwapHistory,
 }
 
 impl Drop for Taker {
@@ -217,10 +443,14 @@ impl Taker {
     ///   - `Some(value)`: Attempt to load a wallet file named `value`. If it does not exist, a new wallet with the given name will be created.
     ///   - `None`: Create a new wallet file with the default name `taker-wallet`.
     /// - If `rpc_config` = `None`: Use the default [`RPCConfig`]
+    /// - `passphrase`:
+    ///   - `Some(value)`: Encrypt the wallet file with `value`. An existing unencrypted wallet file will be encrypted in place.
+    ///   - `None`: Keep the wallet file unencrypted. Loading an encrypted wallet file will fail.
     pub fn init(
         data_dir: Option<PathBuf>,
         wallet_file_name: Option<String>,
         rpc_config: Option<RPCConfig>,
+        passphrase: Option<String>,
         behavior: TakerBehavior,
         connection_type: Option<ConnectionType>,
     ) -> Result<Taker, TakerError> {
@@ -237,12 +467,12 @@ impl Taker {
 
         let mut wallet = if wallet_path.exists() {
             // wallet already exists , load the wallet
-            let wallet = Wallet::load(&wallet_path, &rpc_config)?;
+            let wallet = Wallet::load(&wallet_path, &rpc_config, passphrase)?;
             log::info!("Wallet file at {:?} successfully loaded.", wallet_path);
             wallet
         } else {
             // wallet doesn't exists at the given path , create a new one
-            let wallet = Wallet::init(&wallet_path, &rpc_config)?;
+            let wallet = Wallet::init(&wallet_path, &rpc_config, passphrase)?;
             log::info!("New Wallet created at : {:?}", wallet_path);
             wallet
         };
@@ -256,6 +486,9 @@ impl Taker {
 
         config.write_to_file(&data_dir.join("config.toml"))?;
 
+        wallet.cross_pool_policy = config.cross_pool_spend;
+        wallet.coin_selection = config.coin_selection;
+
         // Load offerbook. If doesn't exists, creates fresh file.
         let offerbook_path = data_dir.join("offerbook.dat");
         let offerbook = if offerbook_path.exists() {
@@ -275,12 +508,18 @@ impl Taker {
         } else {
             // Crewate a new offer book
             let empty_book = OfferBook::default();
-            let file = std::fs::File::create(&offerbook_path)?;
-            let writer = BufWriter::new(file);
-            serde_cbor::to_writer(writer, &empty_book)?;
+            empty_book.write_to_disk(&offerbook_path)?;
             empty_book
         };
 
+        // Load the swap history. It's not recreated if corrupted, so the records are not lost.
+        let history_path = data_dir.join("history.dat");
+        let history = if history_path.exists() {
+            SwapHistory::read_from_disk(&history_path)?
+        } else {
+            SwapHistory::default()
+        };
+
         log::info!("Initializing wallet sync");
         wallet.sync()?;
         log::info!("Completed wallet sync");
@@ -289,13 +528,47 @@ impl Taker {
             wallet,
             config,
             offerbook,
-            ongoing_swap_state: OngoingSwapState::default(),
+            maker_selector: MakerSelector::default(),
+            ongoing_swaps: HashMap::new(),
             behavior,
             tor_handle: None,
             data_dir,
+            history,
         })
     }
 
+    /// Restores a taker wallet from its BIP39 mnemonic.
+    ///
+    /// The wallet file is recreated in the taker's data directory, the blockchain is rescanned from
+    /// `birthday_height` and any fidelity bonds of the seed are recovered. The data directory, wallet
+    /// name and passphrase are interpreted the same way as in [`Taker::init`].
+    /// Errors if a wallet file with the same name already exists.
+    pub fn restore_wallet(
+        data_dir: Option<PathBuf>,
+        wallet_file_name: Option<String>,
+        rpc_config: Option<RPCConfig>,
+        passphrase: Option<String>,
+        mnemonic: &str,
+        birthday_height: u64,
+    ) -> Result<(), TakerError> {
+        let data_dir = data_dir.unwrap_or(get_taker_dir());
+        let wallet_file_name = wallet_file_name.unwrap_or_else(|| "taker-wallet".to_string());
+        let wallet_path = data_dir.join("wallets").join(&wallet_file_name);
+
+        let mut rpc_config = rpc_config.unwrap_or_default();
+        rpc_config.wallet_name = wallet_file_name;
+
+        Wallet::restore(
+            &wallet_path,
+            &rpc_config,
+            mnemonic,
+            passphrase,
+            birthday_height,
+        )?;
+        log::info!("Wallet restored at : {:?}", wallet_path);
+        Ok(())
+    }
+
     /// Get wallet
     pub fn get_wallet(&self) -> &Wallet {
         &self.wallet
@@ -355,7 +628,7 @@ impl Taker {
     }
 
     /// Perform a coinswap round with given [SwapParams]. The Taker will try to perform swap with makers
-    /// in it's [OfferBook] sequentially as per the maker_count given in swap params.
+    /// randomly selected from it's [OfferBook] by their bond value, as per the maker_count given in swap params.
     /// If [SwapParams] doesn't fit suitably with any available offers, or not enough makers
     /// respond back, the swap round will fail.
     ///
@@ -364,6 +637,23 @@ impl Taker {
     ///
     /// If that fails too. Open an issue at [our github](https://github.com/citadel-tech/coinswap/issues)
     pub(crate) fn send_coinswap(&mut self, swap_params: SwapParams) -> Result<(), TakerError> {
+        let handle = self.start_swap(swap_params)?;
+        self.run_swaps(&[handle])
+            .pop()
+            .expect("result of the swap expected")
+    }
+
+    /// Start a coinswap round with given [SwapParams], and return its [SwapHandle]. Makers are selected
+    /// the same way as in [Taker::do_coinswap]. The first hop is initiated and funded right away, the
+    /// rest of the swap is performed by [Taker::run_swaps].
+    ///
+    /// Several swaps can be started before running them. Each has its own makers, coins and recovery.
+    /// If the first hop fails, the swap is recovered from and the error is returned.
+    pub fn start_swap(&mut self, swap_params: SwapParams) -> Result<SwapHandle, TakerError> {
+        if self.tor_handle.is_none() {
+            self.tor_handle = self.setup_tor()?;
+        }
+
         log::info!("Syncing Offerbook");
         self.sync_offerbook()?;
 
@@ -377,29 +667,34 @@ impl Taker {
             return Err(TakerError::NotEnoughMakersInOfferBook);
         }
 
-        // Error early if less than 2 makers.
-        if swap_params.maker_count < 2 {
-            log::error!("Cannot swap with less than 2 makers");
-            return Err(ProtocolError::General("Swap maker count < 2").into());
+        // Error early on invalid swap params.
+        if let Err(e) = validate_swap_params(&swap_params) {
+            log::error!("Invalid swap params : {:?}", e);
+            return Err(e);
         }
 
         // Generate new random preimage and initiate the first hop.
         let mut preimage = [0u8; 32];
         OsRng.fill_bytes(&mut preimage);
 
-        let unique_id = preimage[0..8].to_hex_string(Case::Lower);
+        // The id is sent to every maker, so it must not reveal anything about the preimage.
+        let mut id_bytes = [0u8; 8];
+        OsRng.fill_bytes(&mut id_bytes);
+        let unique_id = id_bytes.to_hex_string(Case::Lower);
 
         log::info!("Initiating coinswap with id : {}", unique_id);
 
-        self.ongoing_swap_state.active_preimage = preimage;
-        self.ongoing_swap_state.swap_params = swap_params;
-        self.ongoing_swap_state.id = unique_id;
+        let feerate = self.wallet.estimate_feerate(&self.config.fee_estimator)?;
+        log::info!("Using feerate {} sats/vB for this swap", feerate);
+
+        let hop_tx_counts = random_split_counts(swap_params.tx_count, swap_params.maker_count);
+        log::info!("Funding txs count of each hop : {:?}", hop_tx_counts);
 
         let available = self.wallet.spendable_balance()?;
 
-        // TODO: Make more exact estimate of swap cost and ensure balanbce.
-        // For now ensure at least swap_amount + 10000 is available.
-        let required = swap_params.send_amount + Amount::from_sat(1000);
+        // Ensure the swap amount and the miner fees of our funding txs are covered.
+        let required = swap_params.send_amount
+            + calculate_fee(feerate, funding_tx_vsize()) * swap_params.tx_count as u64;
         if available < required {
             let err = WalletError::InsufficientFund {
                 available: available.to_btc(),
@@ -409,127 +704,273 @@ impl Taker {
             return Err(err.into());
         }
 
+        let mut swap = OngoingSwapState {
+            swap_params,
+            active_preimage: preimage,
+            id: unique_id.clone(),
+            feerate,
+            hop_tx_counts,
+            started_at: unix_time(),
+            start_height: self
+                .wallet
+                .rpc
+                .get_block_count()
+                .map_err(WalletError::Rpc)?,
+            ..Default::default()
+        };
+
         // Try first hop. Abort if error happens.
-        if let Err(e) = self.init_first_hop() {
+        if let Err(e) = self.init_first_hop(&mut swap) {
             log::error!("Could not initiate first hop: {:?}", e);
-            self.recover_from_swap()?;
+            self.recover_swap_coins(&swap)?;
+            // Nothing was funded if the first maker never signed.
+            if !swap.outgoing_swapcoins.is_empty() {
+                self.record_swap_history(&swap, false);
+            }
+            self.remove_swap_journal(&swap.id);
             return Err(e);
         }
 
-        // Iterate until `maker_count` numbers of Makers are found and initiate swap between them sequentially.
-        for maker_index in 0..self.ongoing_swap_state.swap_params.maker_count {
-            if maker_index == 0 {
-                self.ongoing_swap_state.taker_position = TakerPosition::FirstPeer;
-            } else if maker_index == self.ongoing_swap_state.swap_params.maker_count - 1 {
-                self.ongoing_swap_state.taker_position = TakerPosition::LastPeer;
-            } else {
-                self.ongoing_swap_state.taker_position = TakerPosition::WatchOnly;
-            }
-
-            // Refund lock time decreases by `refund_locktime_step` for each hop.
-            let maker_refund_locktime = REFUND_LOCKTIME
-                + REFUND_LOCKTIME_STEP
-                    * (self.ongoing_swap_state.swap_params.maker_count - maker_index - 1) as u16;
-
-            let funding_tx_infos = self.funding_info_for_next_maker();
+        self.ongoing_swaps.insert(unique_id.clone(), swap);
+        Ok(SwapHandle { id: unique_id })
+    }
 
-            // Attempt to initiate the next hop of the swap. If anything goes wrong, abort immediately.
-            // If succeeded, collect the funding_outpoints and multisig_reedemscripts of the next hop.
-            // If error then aborts from current swap. Ban the Peer.
-            let (funding_outpoints, multisig_reedemscripts) =
-                match self.send_sigs_init_next_hop(maker_refund_locktime, &funding_tx_infos) {
-                    Ok((next_peer_info, contract_sigs)) => {
-                        self.ongoing_swap_state.peer_infos.push(next_peer_info);
-                        let multisig_reedemscripts = contract_sigs
-                            .senders_contract_txs_info
-                            .iter()
-                            .map(|senders_contract_tx_info| {
-                                senders_contract_tx_info.multisig_redeemscript.clone()
-                            })
-                            .collect::<Vec<_>>();
-                        let funding_outpoints = contract_sigs
-                            .senders_contract_txs_info
-                            .iter()
-                            .map(|senders_contract_tx_info| {
-                                senders_contract_tx_info.contract_tx.input[0].previous_output
-                            })
-                            .collect::<Vec<OutPoint>>();
+    /// Run the swaps of the given handles to completion, and return the result of each, in order.
+    ///
+    /// The swaps are interleaved on the calling thread, not run in parallel. They take turns to make
+    /// progress, so a swap waiting for confirmations doesn't hold back the others, but a slow step of one
+    /// swap, like a maker taking long to respond, delays all of them.
+    ///
+    /// A failed swap is recovered from on its own. As in [Taker::do_coinswap], a swap failing after its
+    /// first hop is funded still returns `Ok` once it is recovered from. A swap whose journal can't be
+    /// written is stopped with the error, and can then be recovered from with [Taker::recover_swap].
+    pub fn run_swaps(&mut self, handles: &[SwapHandle]) -> Vec<Result<(), TakerError>> {
+        let mut results = handles
+            .iter()
+            .map(|_| None)
+            .collect::<Vec<Option<Result<(), TakerError>>>>();
 
-                        (funding_outpoints, multisig_reedemscripts)
+        while results.iter().any(Option::is_none) {
+            for (handle, result) in handles.iter().zip(results.iter_mut()) {
+                if result.is_some() {
+                    continue;
+                }
+                let Some(mut swap) = self.ongoing_swaps.remove(&handle.id) else {
+                    *result = Some(Err(TakerError::UnknownSwap(handle.id.clone())));
+                    continue;
+                };
+                let last_step = std::mem::discriminant(&swap.step);
+                match self.step_swap(&mut swap) {
+                    Ok(false) => {
+                        let journaled = if std::mem::discriminant(&swap.step) != last_step {
+                            self.journal_swap(&swap)
+                        } else {
+                            Ok(())
+                        };
+                        self.ongoing_swaps.insert(handle.id.clone(), swap);
+                        if let Err(e) = journaled {
+                            log::error!("Could not journal swap {} : {:?}", handle.id, e);
+                            *result = Some(Err(e));
+                        }
                     }
-                    Err(e) => {
-                        log::error!("Could not initiate next hop. Error : {:?}", e);
-                        log::warn!("Starting recovery from existing swap");
-                        self.recover_from_swap()?;
-                        return Ok(());
+                    Ok(true) => {
+                        self.remove_swap_journal(&handle.id);
+                        *result = Some(Ok(()));
                     }
-                };
-
-            // Watch for both expected and unexpected transactions.
-            // This errors in two cases.
-            // TakerError::ContractsBroadcasted and TakerError::FundingTxWaitTimeOut.
-            // For all cases, abort from swap immediately.
-            // For the timeout case also ban the Peer.
-            let txids_to_watch = funding_outpoints.iter().map(|op| op.txid).collect();
-            match self.watch_for_txs(&txids_to_watch) {
-                Ok(r) => self.ongoing_swap_state.funding_txs.push(r),
-                Err(e) => {
-                    log::error!("Error: {:?}", e);
-                    log::warn!("Starting recovery from existing swap");
-                    if let TakerError::FundingTxWaitTimeOut = e {
-                        let bad_maker = &self.ongoing_swap_state.peer_infos[maker_index].peer;
-                        self.offerbook.add_bad_maker(bad_maker);
+                    Err(e) => {
+                        log::error!("Swap {} failed : {:?}", handle.id, e);
+                        *result = Some(Err(e));
                     }
-                    self.recover_from_swap()?;
-                    return Ok(());
                 }
             }
+            if results.iter().any(Option::is_none) {
+                sleep(Duration::from_millis(1000));
+            }
+        }
 
-            // For the last hop, initiate the incoming swapcoins, and request the sigs for it.
-            if self.ongoing_swap_state.taker_position == TakerPosition::LastPeer {
-                let incoming_swapcoins =
-                    self.create_incoming_swapcoins(multisig_reedemscripts, funding_outpoints)?;
-                log::debug!("Incoming Swapcoins: {:?}", incoming_swapcoins);
-                self.ongoing_swap_state.incoming_swapcoins = incoming_swapcoins;
-                match self.request_sigs_for_incoming_swap() {
-                    Ok(_) => (),
-                    Err(e) => {
-                        log::error!("Incoming SwapCoin Generation failed : {:?}", e);
-                        log::warn!("Starting recovery from existing swap");
-                        self.recover_from_swap()?;
-                        return Ok(());
-                    }
+        results.into_iter().flatten().collect()
+    }
+
+    /// Recover from an ongoing swap, without affecting the other swaps. Its incoming contracts are
+    /// broadcasted, and its outgoing ones are spent from after their timelocks. Blocks until done.
+    pub fn recover_swap(&mut self, handle: &SwapHandle) -> Result<(), TakerError> {
+        let swap = self
+            .ongoing_swaps
+            .remove(&handle.id)
+            .ok_or_else(|| TakerError::UnknownSwap(handle.id.clone()))?;
+        self.recover_swap_coins(&swap)?;
+        self.record_swap_history(&swap, false);
+        self.remove_swap_journal(&handle.id);
+        Ok(())
+    }
+
+    /// Ids of the swaps with a journal on disk. These are the swaps that were not finished when the Taker
+    /// was last shut down, which can be continued with [Taker::resume_swap].
+    pub fn list_swap_journals(&self) -> Result<Vec<String>, TakerError> {
+        let journal_dir = self.data_dir.join("swaps");
+        if !journal_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut ids = Vec::new();
+        for entry in std::fs::read_dir(journal_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "dat") {
+                if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    ids.push(id.to_string());
                 }
             }
-        } // Contract establishment completed.
-
-        if self.behavior == TakerBehavior::DropConnectionAfterFullSetup {
-            log::error!("Dropping Swap Process after full setup");
-            return Ok(());
         }
+        ids.sort();
+        Ok(ids)
+    }
 
-        if self.behavior == TakerBehavior::BroadcastContractAfterFullSetup {
-            log::error!("Special Behavior BroadcastContractAfterFullSetup");
-            self.recover_from_swap()?;
-            return Ok(());
+    /// Resume a swap from its journal, e.g. after the Taker crashed in the middle of it.
+    ///
+    /// The swap continues from its last journaled step: waiting for funding txs, initiating the next
+    /// hop, settling or recovering. If the swap can't continue, e.g. because a maker is gone, it is
+    /// recovered from, the same way as in [Taker::do_coinswap].
+    pub fn resume_swap(&mut self, id: &str) -> Result<(), TakerError> {
+        let swap = read_with_backups(&self.swap_journal_path(id), |bytes| {
+            let plaintext = self.wallet.decrypt_data(bytes.to_vec())?;
+            Ok::<_, TakerError>(serde_cbor::from_slice::<OngoingSwapState>(&plaintext)?)
+        })
+        .map_err(|e| {
+            log::error!("Could not read the journal of swap {} : {:?}", id, e);
+            TakerError::UnknownSwap(id.to_string())
+        })?;
+
+        if self.tor_handle.is_none() {
+            self.tor_handle = self.setup_tor()?;
         }
 
-        match self.settle_all_swaps() {
-            Ok(_) => (),
-            Err(e) => {
-                log::error!("Swap Settlement Failed : {:?}", e);
-                log::warn!("Starting recovery from existing swap");
-                self.recover_from_swap()?;
-                return Ok(());
+        log::info!("Resuming coinswap with id : {}", id);
+        self.ongoing_swaps.insert(id.to_string(), swap);
+        self.run_swaps(&[SwapHandle { id: id.to_string() }])
+            .pop()
+            .expect("result of the swap expected")
+    }
+
+    /// The finished swaps, in the order they ended, each with a [PrivacySummary](super::PrivacySummary).
+    ///
+    /// The summary tells how many makers the coins were routed through, whether any contract hit the
+    /// chain, and whether the received swapcoins were later spent together with seed coins.
+    pub fn swap_history(&self) -> Result<Vec<SwapReport>, TakerError> {
+        let received_utxos = self.history.received_utxos();
+
+        // Wallet txs spending received swapcoins together with seed coins. The swapcoins can only be
+        // spent after their swap started.
+        let mut merging_txs = Vec::new();
+        for tx in self
+            .wallet
+            .find_spending_txs(&received_utxos, self.history.earliest_start_height())?
+        {
+            let mut spends_seed_coin = false;
+            for input in &tx.input {
+                if !received_utxos.contains(&input.previous_output)
+                    && self.wallet.is_seed_coin(&input.previous_output)?
+                {
+                    spends_seed_coin = true;
+                    break;
+                }
+            }
+            if spends_seed_coin {
+                merging_txs.push(tx);
             }
         }
 
-        log::info!("Initializing Sync and Save.");
-        self.wallet.sync()?;
-        self.save_and_reset_swap_round()?;
-        log::info!("Completed Sync and Save.");
-        log::info!("Successfully Completed Coinswap.");
-        Ok(())
+        Ok(self
+            .history
+            .list()
+            .iter()
+            .map(|record| SwapReport::new(record, &merging_txs))
+            .collect())
+    }
+
+    /// Quote the cost of a coinswap with given [SwapParams], without broadcasting anything.
+    ///
+    /// Candidate makers are selected from the [OfferBook] the same way as in a swap. Each hop's fee is
+    /// calculated from the maker's offer with the hop's refund locktime, and miner fees are estimated
+    /// from the current feerate.
+    ///
+    /// The split count of each later hop is only chosen during the swap, so all hops are quoted with
+    /// `tx_count` funding txs. The actual received amount is never less than the quoted one.
+    pub fn quote_swap(&mut self, swap_params: SwapParams) -> Result<SwapQuote, TakerError> {
+        validate_swap_params(&swap_params)?;
+
+        self.tor_handle = self.setup_tor()?;
+        log::info!("Syncing Offerbook");
+        self.sync_offerbook()?;
+
+        let feerate = self.wallet.estimate_feerate(&self.config.fee_estimator)?;
+        let tx_count = swap_params.tx_count as u64;
+        let funding_fee = calculate_fee(feerate, funding_tx_vsize()) * tx_count;
+
+        let mut hops = Vec::new();
+        let mut exclude = Vec::new();
+        let mut amount = swap_params.send_amount;
+        for maker_index in 0..swap_params.maker_count {
+            let maker = self
+                .maker_selector
+                .select(&self.offerbook, &exclude, |oa| {
+                    offer_accepts(&oa.offer, amount)
+                })
+                .ok_or(TakerError::NotEnoughMakersInOfferBook)?;
+            exclude.push(maker.offer.fidelity.bond.outpoint);
+
+            // Same refund locktimes as in the swap.
+            let refund_locktime = REFUND_LOCKTIME
+                + REFUND_LOCKTIME_STEP * (swap_params.maker_count - maker_index - 1) as u16;
+            let coinswap_fee = calculate_coinswap_fee(
+                amount.to_sat(),
+                refund_locktime,
+                maker.offer.base_fee,
+                maker.offer.amount_relative_fee_pct,
+                maker.offer.time_relative_fee_pct,
+            );
+
+            hops.push(HopQuote {
+                maker: maker.address.clone(),
+                refund_locktime,
+                incoming_amount: amount,
+                coinswap_fee,
+                tx_count: swap_params.tx_count,
+                funding_fee,
+            });
+
+            amount = hop_outgoing_amount(
+                &maker.offer,
+                amount,
+                refund_locktime,
+                feerate,
+                swap_params.tx_count,
+            )
+            .ok_or(ProtocolError::General(
+                "Swap fees are more than the swap amount",
+            ))?;
+        }
+
+        // Worst case, our outgoing contracts get broadcasted and we spend them after the timelock.
+        let recovery_locktime =
+            REFUND_LOCKTIME + REFUND_LOCKTIME_STEP * swap_params.maker_count as u16;
+        let contract_redeemscript = create_contract_redeemscript(
+            &generate_keypair().0,
+            &generate_keypair().0,
+            &Hash160::all_zeros(),
+            &recovery_locktime,
+        );
+        let contract_fee = calculate_fee(feerate, contract_tx_vsize())
+            + calculate_fee(feerate, timelock_spend_vsize(&contract_redeemscript));
+
+        Ok(SwapQuote {
+            send_amount: swap_params.send_amount,
+            feerate,
+            hops,
+            taker_funding_fee: funding_fee,
+            receive_amount: amount,
+            total_cost: swap_params.send_amount - amount + funding_fee,
+            recovery_cost: funding_fee + contract_fee * tx_count,
+            recovery_locktime,
+        })
     }
 
     // ######## PROTOCOL SUBROUTINES ############
@@ -537,33 +978,49 @@ impl Taker {
     /// Initiate the first coinswap hop. Makers are selected from the [OfferBook], and round will
     /// fail if no suitable makers are found.
     /// Creates and stores the [OutgoingSwapCoin] into [OngoingSwapState], and also saves it into the [Wallet] file.
-    fn init_first_hop(&mut self) -> Result<(), TakerError> {
+    /// The swap is journaled before the funding txs are broadcast, and left waiting for them to confirm.
+    fn init_first_hop(&mut self, swap: &mut OngoingSwapState) -> Result<(), TakerError> {
         log::info!("Initializing First Hop.");
         // Set the Taker Position state
-        self.ongoing_swap_state.taker_position = TakerPosition::FirstPeer;
+        swap.taker_position = TakerPosition::FirstPeer;
 
         // Locktime to be used for this swap.
-        let swap_locktime = REFUND_LOCKTIME
-            + REFUND_LOCKTIME_STEP * self.ongoing_swap_state.swap_params.maker_count as u16;
+        let swap_locktime =
+            REFUND_LOCKTIME + REFUND_LOCKTIME_STEP * swap.swap_params.maker_count as u16;
 
         // Loop until we find a live maker who responded to our signature request.
-        let (maker, funding_txs) = loop {
-            let maker = self.choose_next_maker()?.clone();
+        let funding_txs = loop {
+            let maker = self.choose_next_maker(swap, swap.swap_params.send_amount)?;
             log::info!("Choosing next maker: {}", maker.address);
+            // The wallet's coins, to prove the ones the funding txs spend, if the maker requires it.
+            let wallet_coins = if maker.offer.requires_utxo_proof {
+                self.wallet.list_all_utxo_spend_info(None)?
+            } else {
+                Vec::new()
+            };
             let (multisig_pubkeys, multisig_nonces, hashlock_pubkeys, hashlock_nonces) =
-                generate_maker_keys(
-                    &maker.offer.tweakable_point,
-                    self.ongoing_swap_state.swap_params.tx_count,
-                )?;
+                generate_maker_keys(&maker.offer.tweakable_point, swap.swap_params.tx_count)?;
             let (funding_txs, mut outgoing_swapcoins, funding_fee) =
                 self.wallet.initalize_coinswap(
-                    self.ongoing_swap_state.swap_params.send_amount,
+                    swap.swap_params.send_amount,
                     &multisig_pubkeys,
                     &hashlock_pubkeys,
-                    self.get_preimage_hash(),
+                    swap.preimage_hash(),
                     swap_locktime,
-                    Amount::from_sat(MINER_FEE),
+                    swap.feerate,
                 )?;
+            let funding_coins = funding_txs
+                .iter()
+                .flat_map(|tx| &tx.input)
+                .filter_map(|input| {
+                    wallet_coins
+                        .iter()
+                        .find(|(utxo, _)| {
+                            OutPoint::new(utxo.txid, utxo.vout) == input.previous_output
+                        })
+                        .cloned()
+                })
+                .collect::<Vec<_>>();
 
             let contract_reedemscripts = outgoing_swapcoins
                 .iter()
@@ -572,16 +1029,17 @@ impl Taker {
 
             // Request for Sender's Signatures
             let contract_sigs = match self.req_sigs_for_sender(
-                &maker.address,
+                &maker,
                 &outgoing_swapcoins,
                 &multisig_nonces,
                 &hashlock_nonces,
                 swap_locktime,
+                &funding_coins,
             ) {
                 Ok(contract_sigs) => contract_sigs,
                 Err(e) => {
-                    // Bad maker, mark it, and try next one.
-                    self.offerbook.add_bad_maker(&maker);
+                    // Failed maker, mark it, and try next one.
+                    self.offerbook.add_failed_maker(&maker, &e);
                     log::error!(
                         "Failed to obtain sender's contract signatures from first_maker {}: {:?}",
                         maker.address,
@@ -593,7 +1051,7 @@ impl Taker {
 
             // // Maker has returned a valid signature, save all the data in memory,
             // // and persist in disk.
-            self.ongoing_swap_state.peer_infos.push(NextPeerInfo {
+            swap.peer_infos.push(NextPeerInfo {
                 peer: maker.clone(),
                 multisig_pubkeys,
                 multisig_nonces,
@@ -614,79 +1072,278 @@ impl Taker {
             }
             self.wallet.save_to_disk()?;
 
-            self.ongoing_swap_state.outgoing_swapcoins = outgoing_swapcoins;
+            swap.outgoing_swapcoins = outgoing_swapcoins;
+            swap.funding_fee = funding_fee;
 
             log::info!("Total Funding Txs Fees: {}", funding_fee);
 
-            break (maker, funding_txs);
+            break funding_txs;
         };
 
-        log::debug!(
-            "Outgoing SwapCoins: {:?}",
-            self.ongoing_swap_state.outgoing_swapcoins
-        );
-
-        // Broadcast and wait for funding txs to confirm
-        let funding_txids = funding_txs
-            .iter()
-            .map(|tx| {
-                let txid = self.wallet.send_tx(tx)?;
-                log::info!("Funding Txid: {}", txid);
-                assert_eq!(txid, tx.compute_txid());
-                Ok(txid)
-            })
-            .collect::<Result<_, TakerError>>()?;
-
-        // Watch for the funding transactions to be confirmed.
-        // This errors in two cases.
-        // TakerError::ContractsBroadcasted and TakerError::FundingTxWaitTimeOut.
-        // For all cases, abort from swap immediately.
-        // For the contract-broadcasted case also ban the Peer.
-        match self.watch_for_txs(&funding_txids) {
-            Ok(stuffs) => {
-                self.ongoing_swap_state.funding_txs.push(stuffs);
-            }
-            Err(e) => {
-                log::error!("Error: {:?}", e);
-                if let TakerError::ContractsBroadcasted(_) = e {
-                    self.offerbook.add_bad_maker(&maker);
-                }
-                return Err(e);
+        log::debug!("Outgoing SwapCoins: {:?}", swap.outgoing_swapcoins);
+
+        // Watch the funding txs until they confirm. The swap is journaled first, so it can be
+        // resumed if we stop any time after the funds are broadcast.
+        let funding_txids = funding_txs.iter().map(Transaction::compute_txid).collect();
+        swap.step = SwapStep::TakerFunding(self.watch_funding_txs(swap, funding_txids));
+        if let Err(e) = self.journal_swap(swap) {
+            // Nothing is broadcast yet. Forget the swapcoins, so there is nothing to recover.
+            for swapcoin in std::mem::take(&mut swap.outgoing_swapcoins) {
+                self.wallet
+                    .remove_outgoing_swapcoin(&swapcoin.get_multisig_redeemscript())?;
             }
+            self.wallet.save_to_disk()?;
+            return Err(e);
         }
 
+        for tx in &funding_txs {
+            let txid = self.wallet.send_tx(tx)?;
+            log::info!("Funding Txid: {}", txid);
+        }
         Ok(())
     }
 
-    /// Return a list of confirmed funding txs with their corresponding merkle proofs.
-    /// Errors if any watching contract txs have been broadcasted during the time too.
-    /// The error contanis the list of broadcasted contract [Txid]s.
-    fn watch_for_txs(
-        &self,
-        funding_txids: &Vec<Txid>,
-    ) -> Result<(Vec<Transaction>, Vec<String>), TakerError> {
-        let mut txid_tx_map = HashMap::<Txid, Transaction>::new();
-        let mut txid_blockhash_map = HashMap::<Txid, BlockHash>::new();
+    /// Perform the next step of a swap. Returns whether the swap is finished.
+    ///
+    /// Failures are recovered from, by moving the swap to [SwapStep::Recover]. Only errors of the
+    /// recovery itself are returned right away.
+    fn step_swap(&mut self, swap: &mut OngoingSwapState) -> Result<bool, TakerError> {
+        match std::mem::take(&mut swap.step) {
+            SwapStep::TakerFunding(mut watch) => {
+                // This errors in two cases.
+                // TakerError::ContractsBroadcasted and TakerError::FundingTxWaitTimeOut.
+                // For all cases, abort from swap immediately.
+                // For the contract-broadcasted case also ban the Peer.
+                match self.poll_funding_txs(swap, &mut watch) {
+                    Ok(Some(stuffs)) => {
+                        swap.funding_txs.push(stuffs);
+                        swap.step = SwapStep::InitNextHop(0);
+                    }
+                    Ok(None) => swap.step = SwapStep::TakerFunding(watch),
+                    Err(e) => {
+                        log::error!("Error: {:?}", e);
+                        if let TakerError::ContractsBroadcasted(_) = e {
+                            self.offerbook.add_bad_maker(&swap.peer_infos[0].peer);
+                        }
+                        self.begin_recovery(swap, Some(e))?;
+                    }
+                }
+            }
+            SwapStep::InitNextHop(maker_index) => {
+                let maker_count = swap.swap_params.maker_count;
+                swap.taker_position = if maker_index == 0 {
+                    TakerPosition::FirstPeer
+                } else if maker_index == maker_count - 1 {
+                    TakerPosition::LastPeer
+                } else {
+                    TakerPosition::WatchOnly
+                };
+
+                // Refund lock time decreases by `refund_locktime_step` for each hop.
+                let maker_refund_locktime =
+                    REFUND_LOCKTIME + REFUND_LOCKTIME_STEP * (maker_count - maker_index - 1) as u16;
+
+                let funding_tx_infos = self.funding_info_for_next_maker(swap);
+
+                // Attempt to initiate the next hop of the swap. If anything goes wrong, abort immediately.
+                // If succeeded, collect the funding_outpoints and multisig_reedemscripts of the next hop.
+                // If error then aborts from current swap. Ban the Peer.
+                match self.send_sigs_init_next_hop(swap, maker_refund_locktime, &funding_tx_infos) {
+                    Ok((next_peer_info, contract_sigs)) => {
+                        swap.peer_infos.push(next_peer_info);
+                        let multisig_redeemscripts = contract_sigs
+                            .senders_contract_txs_info
+                            .iter()
+                            .map(|senders_contract_tx_info| {
+                                senders_contract_tx_info.multisig_redeemscript.clone()
+                            })
+                            .collect::<Vec<_>>();
+                        let funding_outpoints = contract_sigs
+                            .senders_contract_txs_info
+                            .iter()
+                            .map(|senders_contract_tx_info| {
+                                senders_contract_tx_info.contract_tx.input[0].previous_output
+                            })
+                            .collect::<Vec<OutPoint>>();
+
+                        // Watch for both expected and unexpected transactions.
+                        let txids_to_watch = funding_outpoints.iter().map(|op| op.txid).collect();
+                        swap.step = SwapStep::HopFunding {
+                            maker_index,
+                            watch: self.watch_funding_txs(swap, txids_to_watch),
+                            funding_outpoints,
+                            multisig_redeemscripts,
+                        };
+                    }
+                    Err(e) => {
+                        log::error!("Could not initiate next hop. Error : {:?}", e);
+                        self.begin_recovery(swap, None)?;
+                    }
+                }
+            }
+            SwapStep::HopFunding {
+                maker_index,
+                mut watch,
+                funding_outpoints,
+                multisig_redeemscripts,
+            } => {
+                // This errors in two cases.
+                // TakerError::ContractsBroadcasted and TakerError::FundingTxWaitTimeOut.
+                // For all cases, abort from swap immediately.
+                // For the timeout case also ban the Peer.
+                match self.poll_funding_txs(swap, &mut watch) {
+                    Ok(Some(r)) => swap.funding_txs.push(r),
+                    Ok(None) => {
+                        swap.step = SwapStep::HopFunding {
+                            maker_index,
+                            watch,
+                            funding_outpoints,
+                            multisig_redeemscripts,
+                        };
+                        return Ok(false);
+                    }
+                    Err(e) => {
+                        log::error!("Error: {:?}", e);
+                        if let TakerError::FundingTxWaitTimeOut = e {
+                            let bad_maker = &swap.peer_infos[maker_index].peer;
+                            self.offerbook.add_timed_out_maker(bad_maker);
+                        }
+                        self.begin_recovery(swap, None)?;
+                        return Ok(false);
+                    }
+                }
+
+                // For the last hop, initiate the incoming swapcoins, and request the sigs for it.
+                if swap.taker_position == TakerPosition::LastPeer {
+                    let incoming_swapcoins = match self.create_incoming_swapcoins(
+                        swap,
+                        multisig_redeemscripts,
+                        funding_outpoints,
+                    ) {
+                        Ok(incoming_swapcoins) => incoming_swapcoins,
+                        Err(e) => {
+                            log::error!("Incoming SwapCoin Generation failed : {:?}", e);
+                            self.begin_recovery(swap, Some(e))?;
+                            return Ok(false);
+                        }
+                    };
+                    log::debug!("Incoming Swapcoins: {:?}", incoming_swapcoins);
+                    swap.incoming_swapcoins = incoming_swapcoins;
+                    if let Err(e) = self.request_sigs_for_incoming_swap(swap) {
+                        log::error!("Incoming SwapCoin Generation failed : {:?}", e);
+                        self.begin_recovery(swap, None)?;
+                        return Ok(false);
+                    }
+                }
+
+                swap.step = if maker_index + 1 == swap.swap_params.maker_count {
+                    // Contract establishment completed.
+                    SwapStep::Settle
+                } else {
+                    SwapStep::InitNextHop(maker_index + 1)
+                };
+            }
+            SwapStep::Settle => {
+                if self.behavior == TakerBehavior::DropConnectionAfterFullSetup {
+                    log::error!("Dropping Swap Process after full setup");
+                    return Ok(true);
+                }
+
+                if self.behavior == TakerBehavior::BroadcastContractAfterFullSetup {
+                    log::error!("Special Behavior BroadcastContractAfterFullSetup");
+                    self.begin_recovery(swap, None)?;
+                    return Ok(false);
+                }
+
+                if self.behavior == TakerBehavior::AbortAfterFullSetup
+                    || self.behavior == TakerBehavior::AbortWithoutWaitingForRefunds
+                {
+                    log::error!("Special Behavior AbortAfterFullSetup");
+                    self.begin_recovery(swap, None)?;
+                    return Ok(false);
+                }
+
+                // Without a journal, the swap couldn't be resumed once the preimage is out.
+                swap.preimage_revealed = true;
+                if let Err(e) = self.journal_swap(swap) {
+                    swap.preimage_revealed = false;
+                    self.begin_recovery(swap, Some(e))?;
+                    return Ok(false);
+                }
+                if let Err(e) = self.settle_all_swaps(swap) {
+                    log::error!("Swap Settlement Failed : {:?}", e);
+                    self.begin_recovery(swap, None)?;
+                    return Ok(false);
+                }
 
+                for peer_info in &swap.peer_infos {
+                    self.offerbook.record_success(&peer_info.peer);
+                }
+
+                log::info!("Initializing Sync and Save.");
+                self.wallet.sync()?;
+                self.save_swap_round(swap)?;
+                log::info!("Completed Sync and Save.");
+                self.record_swap_history(swap, true);
+                log::info!("Successfully Completed Coinswap.");
+                return Ok(true);
+            }
+            SwapStep::Recover {
+                mut recovery,
+                error,
+            } => {
+                if !self.poll_recovery(&mut recovery)? {
+                    swap.step = SwapStep::Recover { recovery, error };
+                    return Ok(false);
+                }
+                log::info!("Recovery completed.");
+                self.record_swap_history(swap, false);
+                self.remove_swap_journal(&swap.id);
+                return match error {
+                    Some(e) => Err(e),
+                    None => Ok(true),
+                };
+            }
+            SwapStep::Done => return Ok(true),
+        }
+        Ok(false)
+    }
+
+    /// Start watching the funding txs of the current hop for confirmations.
+    fn watch_funding_txs(&self, swap: &OngoingSwapState, txids: Vec<Txid>) -> FundingWatch {
         // Required confirmation target for the funding txs.
-        let required_confirmations =
-            if self.ongoing_swap_state.taker_position == TakerPosition::LastPeer {
-                self.ongoing_swap_state.swap_params.required_confirms
-            } else {
-                self.ongoing_swap_state
-                    .peer_infos
-                    .last()
-                    .expect("Maker information expected in swap state")
-                    .peer
-                    .offer
-                    .required_confirms
-            };
+        let required_confirmations = if swap.taker_position == TakerPosition::LastPeer {
+            swap.swap_params.required_confirms
+        } else {
+            swap.peer_infos
+                .last()
+                .expect("Maker information expected in swap state")
+                .peer
+                .offer
+                .required_confirms
+        };
         log::info!(
             "Waiting for funding transaction confirmations ({} conf required)",
             required_confirmations
         );
-        let mut txids_seen_once = HashSet::<Txid>::new();
+        FundingWatch {
+            txids,
+            required_confirmations,
+            start_time: Instant::now(),
+            txids_seen_once: HashSet::new(),
+            txid_tx_map: HashMap::new(),
+            txid_blockhash_map: HashMap::new(),
+        }
+    }
 
+    /// Check the watched funding txs. Once all are confirmed, return them with their corresponding merkle proofs.
+    /// Errors if any watching contract txs have been broadcasted during the time too.
+    /// The error contanis the list of broadcasted contract [Txid]s.
+    fn poll_funding_txs(
+        &self,
+        swap: &OngoingSwapState,
+        watch: &mut FundingWatch,
+    ) -> Result<Option<FundingTxsWithProofs>, TakerError> {
         // Wait for this much time for txs to appear in mempool.
         let wait_time = if cfg!(feature = "integration-test") {
             10u64 // 10 secs for the tests
@@ -694,125 +1351,128 @@ impl Taker {
             60 * 5 // 5mins for production
         };
 
-        let start_time = Instant::now();
+        // Abort if any of the contract transaction is broadcasted
+        // TODO: Find the culprit Maker, and ban it's fidelity bond.
+        let contracts_broadcasted = self.check_for_broadcasted_contract_txes(swap);
+        if !contracts_broadcasted.is_empty() {
+            log::info!(
+                "Contract transactions were broadcasted : {:?}",
+                contracts_broadcasted
+            );
+            return Err(TakerError::ContractsBroadcasted(contracts_broadcasted));
+        }
 
-        loop {
-            // Abort if any of the contract transaction is broadcasted
-            // TODO: Find the culprit Maker, and ban it's fidelity bond.
-            let contracts_broadcasted = self.check_for_broadcasted_contract_txes();
-            if !contracts_broadcasted.is_empty() {
-                log::info!(
-                    "Contract transactions were broadcasted : {:?}",
-                    contracts_broadcasted
-                );
-                return Err(TakerError::ContractsBroadcasted(contracts_broadcasted));
+        // Check for funding transactions
+        for txid in &watch.txids {
+            if watch.txid_tx_map.contains_key(txid) {
+                continue;
             }
-
-            // Check for funding transactions
-            for txid in funding_txids {
-                if txid_tx_map.contains_key(txid) {
+            let gettx = match self.wallet.rpc.get_raw_transaction_info(txid, None) {
+                Ok(r) => r,
+                // Transaction haven't arrived in our mempool, keep looping.
+                Err(_e) => {
+                    let elapsed = watch.start_time.elapsed().as_secs();
+                    log::info!("Waiting for mempool transaction for {}secs", elapsed);
+                    if elapsed > wait_time {
+                        return Err(TakerError::FundingTxWaitTimeOut);
+                    }
                     continue;
                 }
-                let gettx = match self.wallet.rpc.get_raw_transaction_info(txid, None) {
-                    Ok(r) => r,
-                    // Transaction haven't arrived in our mempool, keep looping.
-                    Err(_e) => {
-                        let elapsed = start_time.elapsed().as_secs();
-                        log::info!("Waiting for mempool transaction for {}secs", elapsed);
-                        if elapsed > wait_time {
-                            return Err(TakerError::FundingTxWaitTimeOut);
+            };
+            if !watch.txids_seen_once.contains(txid) {
+                watch.txids_seen_once.insert(*txid);
+                if gettx.confirmations.is_none() {
+                    let mempool_tx = match self.wallet.rpc.get_mempool_entry(txid) {
+                        Ok(m) => m,
+                        Err(_e) => {
+                            continue;
                         }
-                        continue;
-                    }
-                };
-                if !txids_seen_once.contains(txid) {
-                    txids_seen_once.insert(*txid);
-                    if gettx.confirmations.is_none() {
-                        let mempool_tx = match self.wallet.rpc.get_mempool_entry(txid) {
-                            Ok(m) => m,
-                            Err(_e) => {
-                                continue;
-                            }
-                        };
-                        log::info!(
-                            "Tx {} Seen in Mempool | [{:.1} sat/vbyte]",
-                            txid,
-                            (mempool_tx.fees.base.to_sat() as f32) / (mempool_tx.vsize as f32)
-                        );
-                    }
-                }
-                //TODO handle confirm<0
-                if gettx.confirmations >= Some(required_confirmations) {
-                    txid_tx_map.insert(
-                        *txid,
-                        deserialize::<Transaction>(&gettx.hex).map_err(WalletError::from)?,
+                    };
+                    log::info!(
+                        "Tx {} Seen in Mempool | [{:.1} sat/vbyte]",
+                        txid,
+                        (mempool_tx.fees.base.to_sat() as f32) / (mempool_tx.vsize as f32)
                     );
-                    txid_blockhash_map.insert(*txid, gettx.blockhash.expect("Blockhash expected"));
-                    log::info!("Tx {} | Confirmed at {}", txid, required_confirmations);
                 }
             }
-            if txid_tx_map.len() == funding_txids.len() {
-                let txes = funding_txids
-                    .iter()
-                    .map(|txid| {
-                        txid_tx_map
-                            .get(txid)
-                            .expect("txid expected in the map")
-                            .clone()
-                    })
-                    .collect::<Vec<Transaction>>();
-                let merkleproofs = funding_txids
-                    .iter()
-                    .map(|&txid| {
-                        self.wallet
-                            .rpc
-                            .get_tx_out_proof(
-                                &[txid],
-                                Some(
-                                    txid_blockhash_map
-                                        .get(&txid)
-                                        .expect("txid expected in the map"),
-                                ),
-                            )
-                            .map(|gettxoutproof_result| gettxoutproof_result.to_lower_hex_string())
-                    })
-                    .collect::<Result<Vec<String>, _>>()
-                    .map_err(WalletError::from)?;
-                return Ok((txes, merkleproofs));
+            //TODO handle confirm<0
+            if gettx.confirmations >= Some(watch.required_confirmations) {
+                watch.txid_tx_map.insert(
+                    *txid,
+                    deserialize::<Transaction>(&gettx.hex).map_err(WalletError::from)?,
+                );
+                watch
+                    .txid_blockhash_map
+                    .insert(*txid, gettx.blockhash.expect("Blockhash expected"));
+                log::info!(
+                    "Tx {} | Confirmed at {}",
+                    txid,
+                    watch.required_confirmations
+                );
             }
-            sleep(Duration::from_millis(1000));
         }
+        if watch.txid_tx_map.len() < watch.txids.len() {
+            return Ok(None);
+        }
+
+        let txes = watch
+            .txids
+            .iter()
+            .map(|txid| {
+                watch
+                    .txid_tx_map
+                    .get(txid)
+                    .expect("txid expected in the map")
+                    .clone()
+            })
+            .collect::<Vec<Transaction>>();
+        let merkleproofs = watch
+            .txids
+            .iter()
+            .map(|&txid| {
+                self.wallet
+                    .rpc
+                    .get_tx_out_proof(
+                        &[txid],
+                        Some(
+                            watch
+                                .txid_blockhash_map
+                                .get(&txid)
+                                .expect("txid expected in the map"),
+                        ),
+                    )
+                    .map(|gettxoutproof_result| gettxoutproof_result.to_lower_hex_string())
+            })
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(WalletError::from)?;
+        Ok(Some((txes, merkleproofs)))
     }
 
     /// Create [FundingTxInfo] for the "next_maker". Next maker is the last stored [NextPeerInfo] in the swp state.
     /// All other data from the swap state's last entries are collected and a [FundingTxInfo] protocol message data is generated.
-    fn funding_info_for_next_maker(&self) -> Vec<FundingTxInfo> {
+    fn funding_info_for_next_maker(&self, swap: &OngoingSwapState) -> Vec<FundingTxInfo> {
         // Get the reedemscripts.
         let (this_maker_multisig_redeemscripts, this_maker_contract_redeemscripts) =
-            if self.ongoing_swap_state.taker_position == TakerPosition::FirstPeer {
+            if swap.taker_position == TakerPosition::FirstPeer {
                 (
-                    self.ongoing_swap_state
-                        .outgoing_swapcoins
+                    swap.outgoing_swapcoins
                         .iter()
                         .map(|s| s.get_multisig_redeemscript())
                         .collect::<Vec<_>>(),
-                    self.ongoing_swap_state
-                        .outgoing_swapcoins
+                    swap.outgoing_swapcoins
                         .iter()
                         .map(|s| s.get_contract_redeemscript())
                         .collect::<Vec<_>>(),
                 )
             } else {
                 (
-                    self.ongoing_swap_state
-                        .watchonly_swapcoins
+                    swap.watchonly_swapcoins
                         .last()
                         .expect("swapcoin expected")
                         .iter()
                         .map(|s| s.get_multisig_redeemscript())
                         .collect::<Vec<_>>(),
-                    self.ongoing_swap_state
-                        .watchonly_swapcoins
+                    swap.watchonly_swapcoins
                         .last()
                         .expect("swapcoin expected")
                         .iter()
@@ -822,15 +1482,13 @@ impl Taker {
             };
 
         // Get the nonces.
-        let maker_multisig_nonces = self
-            .ongoing_swap_state
+        let maker_multisig_nonces = swap
             .peer_infos
             .last()
             .expect("maker should exist")
             .multisig_nonces
             .iter();
-        let maker_hashlock_nonces = self
-            .ongoing_swap_state
+        let maker_hashlock_nonces = swap
             .peer_infos
             .last()
             .expect("maker should exist")
@@ -838,8 +1496,7 @@ impl Taker {
             .iter();
 
         // Get the funding txs and merkle proofs.
-        let (funding_txs, funding_txs_merkleproof) = self
-            .ongoing_swap_state
+        let (funding_txs, funding_txs_merkleproof) = swap
             .funding_txs
             .last()
             .expect("funding txs should be known");
@@ -884,6 +1541,7 @@ impl Taker {
     /// If no suitable makers are found in [OfferBook], next swap will not initiate and the swap round will fail.
     fn send_sigs_init_next_hop(
         &mut self,
+        swap: &mut OngoingSwapState,
         maker_refund_locktime: u16,
         funding_tx_infos: &[FundingTxInfo],
     ) -> Result<(NextPeerInfo, ContractSigsAsRecvrAndSender), TakerError> {
@@ -903,8 +1561,7 @@ impl Taker {
 
         let mut ii = 0;
 
-        let maker_oa = self
-            .ongoing_swap_state
+        let maker_oa = swap
             .peer_infos
             .last()
             .expect("at least one active maker expected")
@@ -913,7 +1570,7 @@ impl Taker {
 
         loop {
             ii += 1;
-            match self.send_sigs_init_next_hop_once(maker_refund_locktime, funding_tx_infos) {
+            match self.send_sigs_init_next_hop_once(swap, maker_refund_locktime, funding_tx_infos) {
                 Ok(ret) => return Ok(ret),
                 Err(e) => {
                     log::warn!(
@@ -934,7 +1591,7 @@ impl Taker {
                         ));
                         continue;
                     } else {
-                        self.offerbook.add_bad_maker(&maker_oa);
+                        self.offerbook.add_timed_out_maker(&maker_oa);
                         return Err(e);
                     }
                 }
@@ -945,24 +1602,25 @@ impl Taker {
     /// [Internal] Single attempt to send signatures and initiate next hop.
     fn send_sigs_init_next_hop_once(
         &mut self,
+        swap: &mut OngoingSwapState,
         maker_refund_locktime: u16,
         funding_tx_infos: &[FundingTxInfo],
     ) -> Result<(NextPeerInfo, ContractSigsAsRecvrAndSender), TakerError> {
-        let this_maker = &self
-            .ongoing_swap_state
+        let this_maker = swap
             .peer_infos
             .last()
             .expect("at least one active maker expected")
-            .peer;
+            .peer
+            .clone();
 
-        let previous_maker = self.ongoing_swap_state.peer_infos.iter().rev().nth(1);
+        let previous_maker = swap.peer_infos.iter().rev().nth(1).cloned();
 
         log::info!(
             "Connecting to {} | Send Sigs Init Next Hop",
             this_maker.address
         );
         let address = this_maker.address.to_string();
-        let mut socket = match self.config.connection_type {
+        let socket = match self.config.connection_type {
             ConnectionType::CLEARNET => TcpStream::connect(address)?,
             #[cfg(feature = "tor")]
             ConnectionType::TOR => Socks5Stream::connect(
@@ -977,7 +1635,31 @@ impl Taker {
         socket.set_read_timeout(Some(reconnect_timeout))?;
         socket.set_write_timeout(Some(reconnect_timeout))?;
 
+        let mut socket =
+            NoiseStream::connect(socket, Some(&this_maker.offer.fidelity.bond.pubkey.inner))?;
+
         handshake_maker(&mut socket)?;
+
+        // Split count of the next hop, and the amount this maker will forward to it.
+        let next_tx_count = swap.hop_tx_counts[swap.peer_infos.len()];
+        let this_amount = funding_tx_infos
+            .iter()
+            .map(|funding_info| -> Result<Amount, TakerError> {
+                let funding_output_index = find_funding_output_index(funding_info)?;
+                Ok(funding_info.funding_tx.output[funding_output_index as usize].value)
+            })
+            .sum::<Result<Amount, TakerError>>()?;
+        let next_amount = hop_outgoing_amount(
+            &this_maker.offer,
+            this_amount,
+            maker_refund_locktime,
+            swap.feerate,
+            next_tx_count,
+        )
+        .ok_or(ProtocolError::General(
+            "Swap fees are more than the swap amount",
+        ))?;
+
         let mut next_maker = this_maker.clone();
         let (
             next_peer_multisig_pubkeys,
@@ -994,15 +1676,11 @@ impl Taker {
                 next_peer_multisig_keys_or_nonces,
                 next_peer_hashlock_pubkeys,
                 next_peer_hashlock_keys_or_nonces,
-            ) = if self.ongoing_swap_state.taker_position == TakerPosition::LastPeer {
+            ) = if swap.taker_position == TakerPosition::LastPeer {
                 let (my_recv_ms_pubkeys, my_recv_ms_nonce): (Vec<_>, Vec<_>) =
-                    (0..self.ongoing_swap_state.swap_params.tx_count)
-                        .map(|_| generate_keypair())
-                        .unzip();
-                let (my_recv_hashlock_pubkeys, my_recv_hashlock_nonce): (Vec<_>, Vec<_>) = (0
-                    ..self.ongoing_swap_state.swap_params.tx_count)
-                    .map(|_| generate_keypair())
-                    .unzip();
+                    (0..next_tx_count).map(|_| generate_keypair()).unzip();
+                let (my_recv_hashlock_pubkeys, my_recv_hashlock_nonce): (Vec<_>, Vec<_>) =
+                    (0..next_tx_count).map(|_| generate_keypair()).unzip();
                 (
                     my_recv_ms_pubkeys,
                     my_recv_ms_nonce,
@@ -1010,31 +1688,25 @@ impl Taker {
                     my_recv_hashlock_nonce,
                 )
             } else {
-                next_maker = self.choose_next_maker()?.clone();
+                next_maker = self.choose_next_maker(swap, next_amount)?;
                 //next_maker is only ever accessed when the next peer is a maker, not a taker
                 //i.e. if its ever used when is_taker_next_peer == true, then thats a bug
-                generate_maker_keys(
-                    &next_maker.offer.tweakable_point,
-                    self.ongoing_swap_state.swap_params.tx_count,
-                )?
+                generate_maker_keys(&next_maker.offer.tweakable_point, next_tx_count)?
             };
 
-            let this_maker_contract_txs =
-                if self.ongoing_swap_state.taker_position == TakerPosition::FirstPeer {
-                    self.ongoing_swap_state
-                        .outgoing_swapcoins
-                        .iter()
-                        .map(|os| os.get_contract_tx())
-                        .collect::<Vec<_>>()
-                } else {
-                    self.ongoing_swap_state
-                        .watchonly_swapcoins
-                        .last()
-                        .expect("at least one outgoing swpcoin expected")
-                        .iter()
-                        .map(|wos| wos.get_contract_tx())
-                        .collect()
-                };
+            let this_maker_contract_txs = if swap.taker_position == TakerPosition::FirstPeer {
+                swap.outgoing_swapcoins
+                    .iter()
+                    .map(|os| os.get_contract_tx())
+                    .collect::<Vec<_>>()
+            } else {
+                swap.watchonly_swapcoins
+                    .last()
+                    .expect("at least one outgoing swpcoin expected")
+                    .iter()
+                    .map(|wos| wos.get_contract_tx())
+                    .collect()
+            };
 
             log::info!("===> Sending ProofOfFunding to {}", this_maker.address);
 
@@ -1056,6 +1728,7 @@ impl Taker {
                 funding_tx_infos: funding_tx_infos.to_vec(),
                 this_maker_contract_txs,
                 this_maker_refund_locktime: maker_refund_locktime,
+                contract_feerate: swap.feerate,
             };
 
             let (contract_sigs_as_recvr_sender, next_swap_contract_redeemscripts) =
@@ -1063,8 +1736,8 @@ impl Taker {
                     &mut socket,
                     this_maker_info,
                     next_maker_info,
-                    self.get_preimage_hash(),
-                    self.ongoing_swap_state.id.clone(),
+                    swap.preimage_hash(),
+                    swap.id.clone(),
                 )?;
             log::info!(
                 "<=== Recieved ContractSigsAsRecvrAndSender from {}",
@@ -1072,8 +1745,7 @@ impl Taker {
             );
 
             // If This Maker is the Sender, and we (the Taker) are the Receiver (Last Hop). We provide the Sender's Contact Tx Sigs.
-            let senders_sigs = if self.ongoing_swap_state.taker_position == TakerPosition::LastPeer
-            {
+            let senders_sigs = if swap.taker_position == TakerPosition::LastPeer {
                 log::info!("Taker is next peer. Signing Sender's Contract Txs");
                 // Sign the seder's contract transactions with our multisig privkey.
                 next_peer_multisig_keys_or_nonces
@@ -1101,27 +1773,27 @@ impl Taker {
                     &next_peer_multisig_pubkeys,
                     &next_swap_contract_redeemscripts,
                 )?;
+                // Later hops are funded by the previous maker, there are no coins of ours to prove.
                 let sigs = match self.req_sigs_for_sender(
-                    &next_maker.address,
+                    &next_maker,
                     &watchonly_swapcoins,
                     &next_peer_multisig_keys_or_nonces,
                     &next_peer_hashlock_keys_or_nonces,
                     maker_refund_locktime,
+                    &[],
                 ) {
                     Ok(r) => r,
                     Err(e) => {
-                        self.offerbook.add_bad_maker(&next_maker);
+                        self.offerbook.add_failed_maker(&next_maker, &e);
                         log::info!(
-                            "Failed to obtain sender's contract tx signature from next_maker {}, Banning Maker: {:?}",
+                            "Failed to obtain sender's contract tx signature from next_maker {}: {:?}",
                             next_maker.address,
                             e
                         );
                         continue; //go back to the start of the loop and try another maker
                     }
                 };
-                self.ongoing_swap_state
-                    .watchonly_swapcoins
-                    .push(watchonly_swapcoins);
+                swap.watchonly_swapcoins.push(watchonly_swapcoins);
                 sigs.sigs
             };
             break (
@@ -1135,13 +1807,13 @@ impl Taker {
         };
 
         // If This Maker is the Reciver, and We (The Taker) are the Sender (First Hop), Sign the Contract Tx.
-        let receivers_sigs = if self.ongoing_swap_state.taker_position == TakerPosition::FirstPeer {
+        let receivers_sigs = if swap.taker_position == TakerPosition::FirstPeer {
             log::info!("Taker is previous peer. Signing Receivers Contract Txs");
             // Sign the receiver's contract using our [OutgoingSwapCoin].
             contract_sigs_as_recvr_sender
                 .receivers_contract_txs
                 .iter()
-                .zip(self.ongoing_swap_state.outgoing_swapcoins.iter())
+                .zip(swap.outgoing_swapcoins.iter())
                 .map(|(receivers_contract_tx, outgoing_swapcoin)| {
                     outgoing_swapcoin.sign_contract_tx_with_my_privkey(receivers_contract_tx)
                 })
@@ -1154,28 +1826,24 @@ impl Taker {
                 "===> Sending SignReceiversContractTx, previous maker is {}",
                 previous_maker_addr
             );
-            let previous_maker_watchonly_swapcoins =
-                if self.ongoing_swap_state.taker_position == TakerPosition::LastPeer {
-                    self.ongoing_swap_state
-                        .watchonly_swapcoins
-                        .last()
-                        .expect("swapcoin expected")
+            let previous_maker_watchonly_swapcoins =
+                if swap.taker_position == TakerPosition::LastPeer {
+                    swap.watchonly_swapcoins.last().expect("swapcoin expected")
                 } else {
                     //if the next peer is a maker not a taker, then that maker's swapcoins are last
-                    &self.ongoing_swap_state.watchonly_swapcoins
-                        [self.ongoing_swap_state.watchonly_swapcoins.len() - 2]
+                    &swap.watchonly_swapcoins[swap.watchonly_swapcoins.len() - 2]
                 };
 
             match self.req_sigs_for_recvr(
-                previous_maker_addr,
+                &previous_maker.peer,
                 previous_maker_watchonly_swapcoins,
                 &contract_sigs_as_recvr_sender.receivers_contract_txs,
             ) {
                 Ok(s) => s.sigs,
                 Err(e) => {
                     log::error!("Could not get Receiver's signatures : {:?}", e);
-                    log::warn!("Banning Maker : {}", previous_maker.peer.address);
-                    self.offerbook.add_bad_maker(&previous_maker.peer);
+                    log::warn!("Marking Maker as failed : {}", previous_maker.peer.address);
+                    self.offerbook.add_failed_maker(&previous_maker.peer, &e);
                     return Err(e);
                 }
             }
@@ -1184,17 +1852,14 @@ impl Taker {
             "===> Sending ContractSigsAsReceiverAndSender to {}",
             this_maker.address
         );
-        let id = self.ongoing_swap_state.id.clone();
-        send_message(
-            &mut socket,
-            &TakerToMakerMessage::RespContractSigsForRecvrAndSender(
-                ContractSigsForRecvrAndSender {
-                    receivers_sigs,
-                    senders_sigs,
-                    id,
-                },
-            ),
-        )?;
+        let id = swap.id.clone();
+        socket.send_message(&TakerToMakerMessage::RespContractSigsForRecvrAndSender(
+            ContractSigsForRecvrAndSender {
+                receivers_sigs,
+                senders_sigs,
+                id,
+            },
+        ))?;
 
         let next_swap_info = NextPeerInfo {
             peer: next_maker.clone(),
@@ -1243,11 +1908,11 @@ impl Taker {
     /// and the sender side is the laste Maker in the route.
     fn create_incoming_swapcoins(
         &mut self,
+        swap: &OngoingSwapState,
         multisig_redeemscripts: Vec<ScriptBuf>,
         funding_outpoints: Vec<OutPoint>,
     ) -> Result<Vec<IncomingSwapCoin>, TakerError> {
-        let (funding_txs, funding_txs_merkleproofs) = self
-            .ongoing_swap_state
+        let (funding_txs, funding_txs_merkleproofs) = swap
             .funding_txs
             .last()
             .expect("funding transactions expected");
@@ -1276,8 +1941,7 @@ impl Taker {
             .iter()
             .zip(last_makers_funding_tx_values.iter())
             .zip(
-                self.ongoing_swap_state
-                    .peer_infos
+                swap.peer_infos
                     .last()
                     .expect("expected")
                     .contract_reedemscripts
@@ -1292,18 +1956,14 @@ impl Taker {
                         previous_funding_output,
                         maker_funding_tx_value,
                         next_contract_redeemscript,
-                        Amount::from_sat(MINER_FEE),
+                        calculate_fee(swap.feerate, contract_tx_vsize()),
                     )
                 },
             )
             .collect::<Result<Vec<Transaction>, _>>()?;
 
         let mut incoming_swapcoins = Vec::<IncomingSwapCoin>::new();
-        let next_swap_info = self
-            .ongoing_swap_state
-            .peer_infos
-            .last()
-            .expect("next swap info expected");
+        let next_swap_info = swap.peer_infos.last().expect("next swap info expected");
         for (
             (
                 (
@@ -1360,7 +2020,7 @@ impl Taker {
                 hashlock_privkey,
                 maker_funding_tx_value,
             )?;
-            incoming_swapcoin.hash_preimage = Some(self.ongoing_swap_state.active_preimage);
+            incoming_swapcoin.hash_preimage = Some(swap.active_preimage);
             incoming_swapcoins.push(incoming_swapcoin);
         }
 
@@ -1368,10 +2028,12 @@ impl Taker {
     }
 
     /// Request signatures for the [IncomingSwapCoin] from the last maker of the swap round.
-    fn request_sigs_for_incoming_swap(&mut self) -> Result<(), TakerError> {
+    fn request_sigs_for_incoming_swap(
+        &mut self,
+        swap: &mut OngoingSwapState,
+    ) -> Result<(), TakerError> {
         // Intermediate hops completed. Perform the last receiving hop.
-        let last_maker = self
-            .ongoing_swap_state
+        let last_maker = swap
             .peer_infos
             .iter()
             .rev()
@@ -1384,10 +2046,9 @@ impl Taker {
             last_maker.address
         );
         let receiver_contract_sig = match self.req_sigs_for_recvr(
-            &last_maker.address,
-            &self.ongoing_swap_state.incoming_swapcoins,
-            &self
-                .ongoing_swap_state
+            &last_maker,
+            &swap.incoming_swapcoins,
+            &swap
                 .incoming_swapcoins
                 .iter()
                 .map(|swapcoin| swapcoin.contract_tx.clone())
@@ -1395,20 +2056,19 @@ impl Taker {
         ) {
             Ok(s) => s,
             Err(e) => {
-                log::warn!("Banning Maker : {}", last_maker.address);
-                self.offerbook.add_bad_maker(&last_maker);
+                log::warn!("Marking Maker as failed : {}", last_maker.address);
+                self.offerbook.add_failed_maker(&last_maker, &e);
                 return Err(e);
             }
         };
-        for (incoming_swapcoin, &receiver_contract_sig) in self
-            .ongoing_swap_state
+        for (incoming_swapcoin, &receiver_contract_sig) in swap
             .incoming_swapcoins
             .iter_mut()
             .zip(receiver_contract_sig.sigs.iter())
         {
             incoming_swapcoin.others_contract_sig = Some(receiver_contract_sig);
         }
-        for incoming_swapcoin in &self.ongoing_swap_state.incoming_swapcoins {
+        for incoming_swapcoin in &swap.incoming_swapcoins {
             self.wallet.add_incoming_swapcoin(incoming_swapcoin);
         }
 
@@ -1419,13 +2079,16 @@ impl Taker {
 
     /// Request signatures for sender side of the swap.
     /// Keep trying until `first_connect_attempts` limit, with time delay of `first_connect_sleep_delay_sec`.
+    ///
+    /// If the maker requires it, the ownership of `funding_coins` is proven on the connection.
     fn req_sigs_for_sender<S: SwapCoin>(
         &self,
-        maker_address: &MakerAddress,
+        maker: &OfferAndAddress,
         outgoing_swapcoins: &[S],
         maker_multisig_nonces: &[SecretKey],
         maker_hashlock_nonces: &[SecretKey],
         locktime: u16,
+        funding_coins: &[(ListUnspentResultEntry, UTXOSpendInfo)],
     ) -> Result<ContractSigsForSender, TakerError> {
         let reconnect_time_out = Duration::from_secs(FIRST_CONNECT_ATTEMPT_TIMEOUT_SEC);
         // Configurable reconnection attempts for testing
@@ -1444,9 +2107,9 @@ impl Taker {
 
         let mut ii = 0;
 
-        let maker_addr_str = maker_address.to_string();
+        let maker_addr_str = maker.address.to_string();
 
-        let mut socket = match self.config.connection_type {
+        let socket = match self.config.connection_type {
             ConnectionType::CLEARNET => TcpStream::connect(maker_addr_str.clone())?,
             #[cfg(feature = "tor")]
             ConnectionType::TOR => Socks5Stream::connect(
@@ -1459,6 +2122,22 @@ impl Taker {
         socket.set_read_timeout(Some(reconnect_time_out))?;
         socket.set_write_timeout(Some(reconnect_time_out))?;
 
+        let mut socket =
+            NoiseStream::connect(socket, Some(&maker.offer.fidelity.bond.pubkey.inner))?;
+
+        let hashvalue = outgoing_swapcoins[0].get_hashvalue()?;
+        let prove_utxos = |handshake_hash: &[u8; 32]| {
+            if !maker.offer.requires_utxo_proof {
+                return Ok(Vec::new());
+            }
+            self.wallet.prove_utxo_ownership(
+                funding_coins,
+                &maker.offer.tweakable_point,
+                &hashvalue,
+                handshake_hash,
+            )
+        };
+
         loop {
             ii += 1;
             log::info!("Connecting to {} | Req Sigs for Sender", maker_addr_str);
@@ -1468,6 +2147,7 @@ impl Taker {
                 maker_multisig_nonces,
                 maker_hashlock_nonces,
                 locktime,
+                &prove_utxos,
             ) {
                 Ok(ret) => return Ok(ret),
                 Err(e) => {
@@ -1507,7 +2187,7 @@ impl Taker {
     /// after `short_long_sleep_delay_transition` time.
     fn req_sigs_for_recvr<S: SwapCoin>(
         &self,
-        maker_address: &MakerAddress,
+        maker: &OfferAndAddress,
         incoming_swapcoins: &[S],
         receivers_contract_txes: &[Transaction],
     ) -> Result<ContractSigsForRecvr, TakerError> {
@@ -1529,8 +2209,8 @@ impl Taker {
 
         let mut ii = 0;
 
-        let maker_addr_str = maker_address.to_string();
-        let mut socket = match self.config.connection_type {
+        let maker_addr_str = maker.address.to_string();
+        let socket = match self.config.connection_type {
             ConnectionType::CLEARNET => TcpStream::connect(maker_addr_str.clone())?,
             #[cfg(feature = "tor")]
             ConnectionType::TOR => Socks5Stream::connect(
@@ -1543,6 +2223,9 @@ impl Taker {
         socket.set_read_timeout(Some(reconnect_time_out))?;
         socket.set_write_timeout(Some(reconnect_time_out))?;
 
+        let mut socket =
+            NoiseStream::connect(socket, Some(&maker.offer.fidelity.bond.pubkey.inner))?;
+
         loop {
             ii += 1;
             log::info!("Connecting to {} | Req Sigs for Receiver", maker_addr_str);
@@ -1583,64 +2266,59 @@ impl Taker {
     /// Settle all the ongoing swaps. This routine sends the hash preimage to all the makers.
     /// Pass around the Maker's multisig privatekeys. Saves all the data in wallet file. This marks
     /// the ends of swap round.
-    fn settle_all_swaps(&mut self) -> Result<(), TakerError> {
+    fn settle_all_swaps(&mut self, swap: &mut OngoingSwapState) -> Result<(), TakerError> {
         let mut outgoing_privkeys: Option<Vec<MultisigPrivkey>> = None;
 
         // Because the last peer info is the Taker, we take upto (0..n-1), where n = peer_info.len()
-        let maker_addresses = self.ongoing_swap_state.peer_infos
-            [0..self.ongoing_swap_state.peer_infos.len() - 1]
+        let maker_addresses = swap.peer_infos[0..swap.peer_infos.len() - 1]
             .iter()
             .map(|si| si.peer.clone())
             .collect::<Vec<_>>();
 
         for (index, maker_address) in maker_addresses.iter().enumerate() {
             if index == 0 {
-                self.ongoing_swap_state.taker_position = TakerPosition::FirstPeer;
-            } else if index == (self.ongoing_swap_state.swap_params.maker_count - 1) {
-                self.ongoing_swap_state.taker_position = TakerPosition::LastPeer;
+                swap.taker_position = TakerPosition::FirstPeer;
+            } else if index == (swap.swap_params.maker_count - 1) {
+                swap.taker_position = TakerPosition::LastPeer;
             } else {
-                self.ongoing_swap_state.taker_position = TakerPosition::WatchOnly;
+                swap.taker_position = TakerPosition::WatchOnly;
             }
 
-            let senders_multisig_redeemscripts =
-                if self.ongoing_swap_state.taker_position == TakerPosition::FirstPeer {
-                    self.ongoing_swap_state
-                        .outgoing_swapcoins
-                        .iter()
-                        .map(|sc| sc.get_multisig_redeemscript())
-                        .collect::<Vec<_>>()
-                } else {
-                    self.ongoing_swap_state
-                        .watchonly_swapcoins
-                        .get(index - 1)
-                        .expect("Watchonly coins expected")
-                        .iter()
-                        .map(|sc| sc.get_multisig_redeemscript())
-                        .collect::<Vec<_>>()
-                };
-            let receivers_multisig_redeemscripts =
-                if self.ongoing_swap_state.taker_position == TakerPosition::LastPeer {
-                    self.ongoing_swap_state
-                        .incoming_swapcoins
-                        .iter()
-                        .map(|sc| sc.get_multisig_redeemscript())
-                        .collect::<Vec<_>>()
-                } else {
-                    self.ongoing_swap_state
-                        .watchonly_swapcoins
-                        .get(index)
-                        .expect("watchonly coins expected")
-                        .iter()
-                        .map(|sc| sc.get_multisig_redeemscript())
-                        .collect::<Vec<_>>()
-                };
+            let senders_multisig_redeemscripts = if swap.taker_position == TakerPosition::FirstPeer
+            {
+                swap.outgoing_swapcoins
+                    .iter()
+                    .map(|sc| sc.get_multisig_redeemscript())
+                    .collect::<Vec<_>>()
+            } else {
+                swap.watchonly_swapcoins
+                    .get(index - 1)
+                    .expect("Watchonly coins expected")
+                    .iter()
+                    .map(|sc| sc.get_multisig_redeemscript())
+                    .collect::<Vec<_>>()
+            };
+            let receivers_multisig_redeemscripts = if swap.taker_position == TakerPosition::LastPeer
+            {
+                swap.incoming_swapcoins
+                    .iter()
+                    .map(|sc| sc.get_multisig_redeemscript())
+                    .collect::<Vec<_>>()
+            } else {
+                swap.watchonly_swapcoins
+                    .get(index)
+                    .expect("watchonly coins expected")
+                    .iter()
+                    .map(|sc| sc.get_multisig_redeemscript())
+                    .collect::<Vec<_>>()
+            };
 
             let reconnect_time_out = Duration::from_secs(TCP_TIMEOUT_SECONDS);
 
             let mut ii = 0;
 
             let maker_addr_str = maker_address.address.to_string();
-            let mut socket = match self.config.connection_type {
+            let socket = match self.config.connection_type {
                 ConnectionType::CLEARNET => TcpStream::connect(maker_addr_str.clone())?,
                 #[cfg(feature = "tor")]
                 ConnectionType::TOR => Socks5Stream::connect(
@@ -1653,6 +2331,11 @@ impl Taker {
             socket.set_read_timeout(Some(reconnect_time_out))?;
             socket.set_write_timeout(Some(reconnect_time_out))?;
 
+            let mut socket = NoiseStream::connect(
+                socket,
+                Some(&maker_address.offer.fidelity.bond.pubkey.inner),
+            )?;
+
             // Configurable reconnection attempts for testing
             let reconnect_attempts = if cfg!(feature = "integration-test") {
                 10
@@ -1671,6 +2354,7 @@ impl Taker {
                 ii += 1;
                 log::info!("Connecting to {} || Settle Swap", maker_addr_str);
                 match self.settle_one_coinswap(
+                    swap,
                     &mut socket,
                     index,
                     &mut outgoing_privkeys,
@@ -1702,7 +2386,7 @@ impl Taker {
                                         reattempt limit exceeded",
                                 &maker_address.address,
                             );
-                            self.offerbook.add_bad_maker(maker_address);
+                            self.offerbook.add_timed_out_maker(maker_address);
                             return Err(e);
                         }
                     }
@@ -1714,8 +2398,9 @@ impl Taker {
 
     /// [Internal] Setlle one swap. This is recursively called for all the makers.
     fn settle_one_coinswap(
-        &mut self,
-        socket: &mut TcpStream,
+        &self,
+        swap: &mut OngoingSwapState,
+        socket: &mut NoiseStream,
         index: usize,
         outgoing_privkeys: &mut Option<Vec<MultisigPrivkey>>, // TODO: Instead of Option, just take a vector, where empty vector denotes the `None` equivalent.
         senders_multisig_redeemscripts: &[ScriptBuf],
@@ -1728,16 +2413,15 @@ impl Taker {
             socket,
             senders_multisig_redeemscripts,
             receivers_multisig_redeemscripts,
-            &self.ongoing_swap_state.active_preimage,
+            &swap.active_preimage,
         )?;
         log::info!(
             "<=== Received PrivateKeyHandover from {}",
             socket.peer_addr()?
         );
 
-        let privkeys_reply = if self.ongoing_swap_state.taker_position == TakerPosition::FirstPeer {
-            self.ongoing_swap_state
-                .outgoing_swapcoins
+        let privkeys_reply = if swap.taker_position == TakerPosition::FirstPeer {
+            swap.outgoing_swapcoins
                 .iter()
                 .map(|outgoing_swapcoin| MultisigPrivkey {
                     multisig_redeemscript: outgoing_swapcoin.get_multisig_redeemscript(),
@@ -1753,15 +2437,14 @@ impl Taker {
             *outgoing_privkeys = None;
             reply
         };
-        (if self.ongoing_swap_state.taker_position == TakerPosition::LastPeer {
+        (if swap.taker_position == TakerPosition::LastPeer {
             check_and_apply_maker_private_keys(
-                &mut self.ongoing_swap_state.incoming_swapcoins,
+                &mut swap.incoming_swapcoins,
                 &maker_private_key_handover.multisig_privkeys,
             )
         } else {
             let ret = check_and_apply_maker_private_keys(
-                self.ongoing_swap_state
-                    .watchonly_swapcoins
+                swap.watchonly_swapcoins
                     .get_mut(index)
                     .expect("watchonly coins expected"),
                 &maker_private_key_handover.multisig_privkeys,
@@ -1770,55 +2453,47 @@ impl Taker {
             ret
         })?;
         log::info!("===> Sending PrivateKeyHandover to {}", socket.peer_addr()?);
-        send_message(
-            socket,
-            &TakerToMakerMessage::RespPrivKeyHandover(PrivKeyHandover {
-                multisig_privkeys: privkeys_reply,
-            }),
-        )?;
+        socket.send_message(&TakerToMakerMessage::RespPrivKeyHandover(PrivKeyHandover {
+            multisig_privkeys: privkeys_reply,
+        }))?;
         Ok(())
     }
 
     // ######## UTILITY AND HELPERS ############
 
-    /// Choose a suitable **untried** maker address from the offerbook that fits the swap params.
-    fn choose_next_maker(&self) -> Result<&OfferAndAddress, TakerError> {
-        let send_amount = self.ongoing_swap_state.swap_params.send_amount;
-        if send_amount == Amount::ZERO {
+    /// Randomly choose an **untried** maker address from the offerbook that fits the swap params.
+    /// Makers are weighted by their fidelity bond value, see [MakerSelector].
+    fn choose_next_maker(
+        &mut self,
+        swap: &OngoingSwapState,
+        amount: Amount,
+    ) -> Result<OfferAndAddress, TakerError> {
+        if amount == Amount::ZERO {
             return Err(TakerError::SendAmountNotSet);
         }
 
-        // Ensure that we don't select a maker we are already swaping with.
-        Ok(self
-            .offerbook
-            .all_good_makers()
+        // Ensure that we don't select a bond we are already swaping with.
+        let swapping_bonds = swap
+            .peer_infos
             .iter()
-            .find(|oa| {
-                send_amount >= Amount::from_sat(oa.offer.min_size)
-                    && send_amount <= Amount::from_sat(oa.offer.max_size)
-                    && !self
-                        .ongoing_swap_state
-                        .peer_infos
-                        .iter()
-                        .map(|pi| &pi.peer)
-                        .any(|noa| noa == **oa)
-            })
-            .ok_or(TakerError::NotEnoughMakersInOfferBook)?)
-    }
+            .map(|pi| pi.peer.offer.fidelity.bond.outpoint)
+            .collect::<Vec<_>>();
 
-    /// Get the [Preimage] of the ongoing swap. If no swap is in progress will return a `[0u8; 32]`.
-    fn get_preimage(&self) -> &Preimage {
-        &self.ongoing_swap_state.active_preimage
-    }
+        // Makers requiring a proof of our coins can only be the first one, later hops are funded
+        // by the previous maker.
+        let first_hop = swap.outgoing_swapcoins.is_empty();
 
-    /// Get the [Preimage] hash for the ongoing swap. If no swap is in progress will return `hash160([0u8; 32])`.
-    fn get_preimage_hash(&self) -> Hash160 {
-        Hash160::hash(self.get_preimage())
+        self.maker_selector
+            .select(&self.offerbook, &swapping_bonds, |oa| {
+                offer_accepts(&oa.offer, amount) && (first_hop || !oa.offer.requires_utxo_proof)
+            })
+            .cloned()
+            .ok_or(TakerError::NotEnoughMakersInOfferBook)
     }
 
-    /// Clear the [OngoingSwapState].
-    fn clear_ongoing_swaps(&mut self) {
-        self.ongoing_swap_state = OngoingSwapState::default();
+    /// Seed the random maker selection, to make it deterministic.
+    pub fn seed_maker_selection(&mut self, seed: u64) {
+        self.maker_selector = MakerSelector::from_seed(seed);
     }
 
     /// Get all the bad makers
@@ -1826,9 +2501,9 @@ impl Taker {
         self.offerbook.get_bad_makers()
     }
 
-    /// Save all the finalized swap data and reset the [OngoingSwapState].
-    fn save_and_reset_swap_round(&mut self) -> Result<(), TakerError> {
-        for incoming_swapcoin in &self.ongoing_swap_state.incoming_swapcoins {
+    /// Save all the finalized swap data of the [OngoingSwapState].
+    fn save_swap_round(&mut self, swap: &OngoingSwapState) -> Result<(), TakerError> {
+        for incoming_swapcoin in &swap.incoming_swapcoins {
             self.wallet
                 .find_incoming_swapcoin_mut(&incoming_swapcoin.get_multisig_redeemscript())
                 .expect("Incoming swapcoin expeted")
@@ -1836,29 +2511,117 @@ impl Taker {
         }
         self.wallet.save_to_disk()?;
 
-        self.clear_ongoing_swaps();
+        Ok(())
+    }
+
+    /// Path of the journal of a swap.
+    fn swap_journal_path(&self, id: &str) -> PathBuf {
+        self.data_dir.join("swaps").join(format!("{}.dat", id))
+    }
 
+    /// Write the [OngoingSwapState] into its journal.
+    ///
+    /// The journal holds the swap's keys and preimage, so it is encrypted like the wallet file.
+    fn journal_swap(&self, swap: &OngoingSwapState) -> Result<(), TakerError> {
+        let path = self.swap_journal_path(&swap.id);
+        std::fs::create_dir_all(path.parent().expect("journal directory expected"))?;
+        let journal = self.wallet.encrypt_data(serde_cbor::to_vec(swap)?)?;
+        write_atomic(&path, &journal)?;
         Ok(())
     }
 
+    /// Remove the journal of a finished swap.
+    fn remove_swap_journal(&self, id: &str) {
+        if let Err(e) = remove_with_backups(&self.swap_journal_path(id)) {
+            log::error!("Could not remove the journal of swap {} : {:?}", id, e);
+        }
+    }
+
+    /// Record a finished swap into the history. Failures to save it are only logged, as the swap itself
+    /// is already finished.
+    fn record_swap_history(&mut self, swap: &OngoingSwapState, completed: bool) {
+        // Confirmed contracts are only found by txid with -txindex. But the wallet also knows the ones
+        // spending the Taker's own swapcoins.
+        let contracts_broadcasted = !self.check_for_broadcasted_contract_txes(swap).is_empty()
+            || swap
+                .incoming_swapcoins
+                .iter()
+                .map(|sc| sc.contract_tx.compute_txid())
+                .chain(
+                    swap.outgoing_swapcoins
+                        .iter()
+                        .map(|sc| sc.contract_tx.compute_txid()),
+                )
+                .any(|txid| self.wallet.rpc.get_transaction(&txid, Some(true)).is_ok());
+
+        let outcome = if completed {
+            SwapOutcome::Completed
+        } else if contracts_broadcasted {
+            SwapOutcome::Recovered
+        } else {
+            SwapOutcome::Aborted
+        };
+        let (receive_amount, received_utxos) = if completed {
+            (
+                swap.incoming_swapcoins
+                    .iter()
+                    .map(|sc| sc.funding_amount)
+                    .sum(),
+                swap.incoming_swapcoins
+                    .iter()
+                    .map(|sc| sc.contract_tx.input[0].previous_output)
+                    .collect(),
+            )
+        } else {
+            (Amount::ZERO, Vec::new())
+        };
+
+        self.history.record(SwapRecord {
+            id: swap.id.clone(),
+            started_at: swap.started_at,
+            start_height: swap.start_height,
+            finished_at: unix_time(),
+            // The peer after the last maker is the Taker itself.
+            makers: swap
+                .peer_infos
+                .iter()
+                .take(swap.swap_params.maker_count)
+                .map(|peer_info| peer_info.peer.address.to_string())
+                .collect(),
+            send_amount: swap
+                .outgoing_swapcoins
+                .iter()
+                .map(|sc| sc.funding_amount)
+                .sum(),
+            receive_amount,
+            mining_fee: swap.funding_fee,
+            received_utxos,
+            contracts_broadcasted,
+            outcome,
+        });
+        if let Err(e) = self
+            .history
+            .write_to_disk(&self.data_dir.join("history.dat"))
+        {
+            log::error!("Could not save the history of swap {} : {:?}", swap.id, e);
+        }
+    }
+
     /// Checks if any contreact transactions have been broadcasted.
     /// Returns the txid list of all the broadcasted contract transaction.
     /// Empty vector if nothing is nothing is broadcasted. (usual case).
-    pub(crate) fn check_for_broadcasted_contract_txes(&self) -> Vec<Txid> {
-        let contract_txids = self
-            .ongoing_swap_state
+    fn check_for_broadcasted_contract_txes(&self, swap: &OngoingSwapState) -> Vec<Txid> {
+        let contract_txids = swap
             .incoming_swapcoins
             .iter()
             .map(|sc| sc.contract_tx.compute_txid())
             .chain(
-                self.ongoing_swap_state
-                    .outgoing_swapcoins
+                swap.outgoing_swapcoins
                     .iter()
                     .map(|sc| sc.contract_tx.compute_txid()),
             )
             .chain(
-                self.ongoing_swap_state
-                    .watchonly_swapcoins
+                swap.watchonly_swapcoins
                     .iter()
                     .flatten()
                     .map(|sc| sc.contract_tx.compute_txid()),
@@ -1876,10 +2639,334 @@ impl Taker {
         seen_txids
     }
 
-    /// Recover from a bad swap
+    /// Recover from all bad swaps. The incoming contracts of all unfinished swapcoins in the wallet are
+    /// broadcasted, and the outgoing ones are spent from after their timelocks. Blocks until done, and
+    /// clears all the ongoing swaps. Use [Taker::recover_swap] to recover from a single swap.
     pub fn recover_from_swap(&mut self) -> Result<(), TakerError> {
+        let swaps = std::mem::take(&mut self.ongoing_swaps);
+        for swap in swaps.values() {
+            self.try_cooperative_abort(swap);
+        }
+        let (incomings, outgoings) = self.wallet.find_unfinished_swapcoins();
+        let mut recovery = self.start_recovery(incomings, outgoings)?;
+        self.wait_for_recovery(&mut recovery)?;
+        for swap in swaps.values() {
+            self.record_swap_history(swap, false);
+        }
+        self.ongoing_swaps.clear();
+        for id in self.list_swap_journals()? {
+            self.remove_swap_journal(&id);
+        }
+        log::info!("Recovery completed.");
+        Ok(())
+    }
+
+    /// The unfinished swapcoins in the wallet that belong to a swap.
+    fn find_unfinished_swapcoins_of(
+        &self,
+        swap: &OngoingSwapState,
+    ) -> (Vec<IncomingSwapCoin>, Vec<OutgoingSwapCoin>) {
         let (incomings, outgoings) = self.wallet.find_unfinished_swapcoins();
+        let incomings = incomings
+            .into_iter()
+            .filter(|incoming| {
+                swap.incoming_swapcoins.iter().any(|swapcoin| {
+                    swapcoin.get_multisig_redeemscript() == incoming.get_multisig_redeemscript()
+                })
+            })
+            .collect();
+        let outgoings = outgoings
+            .into_iter()
+            .filter(|outgoing| {
+                swap.outgoing_swapcoins.iter().any(|swapcoin| {
+                    swapcoin.get_multisig_redeemscript() == outgoing.get_multisig_redeemscript()
+                })
+            })
+            .collect();
+        (incomings, outgoings)
+    }
+
+    /// Recover from a single swap. Blocks until done.
+    fn recover_swap_coins(&mut self, swap: &OngoingSwapState) -> Result<(), TakerError> {
+        log::warn!("Starting recovery from swap {}", swap.id);
+        self.try_cooperative_abort(swap);
+        let (incomings, outgoings) = self.find_unfinished_swapcoins_of(swap);
+        let mut recovery = self.start_recovery(incomings, outgoings)?;
+        self.wait_for_recovery(&mut recovery)?;
+        log::info!("Recovery completed.");
+        Ok(())
+    }
+
+    /// Move a swap to recovery. The recovery is then polled by [Taker::run_swaps].
+    fn begin_recovery(
+        &mut self,
+        swap: &mut OngoingSwapState,
+        error: Option<TakerError>,
+    ) -> Result<(), TakerError> {
+        log::warn!("Starting recovery from swap {}", swap.id);
+        self.try_cooperative_abort(swap);
+        let (incomings, outgoings) = self.find_unfinished_swapcoins_of(swap);
+        let recovery = self.start_recovery(incomings, outgoings)?;
+        swap.step = SwapStep::Recover { recovery, error };
+        Ok(())
+    }
+
+    /// Abort a swap cooperatively, if enabled in the config and the preimage is not revealed yet.
+    /// Whatever is not refunded is left to the recovery via the contracts.
+    fn try_cooperative_abort(&mut self, swap: &OngoingSwapState) {
+        if !self.config.cooperative_abort
+            || swap.preimage_revealed
+            || self.behavior == TakerBehavior::BroadcastContractAfterFullSetup
+        {
+            return;
+        }
+        if let Err(e) = self.abort_cooperatively(swap) {
+            log::warn!(
+                "Could not abort swap {} cooperatively, recovering via the contracts : {:?}",
+                swap.id,
+                e
+            );
+        }
+    }
+
+    /// Abort a swap by spending the funding outputs of its hops straight back to their senders, with
+    /// refund txs signed by both sides of each hop.
+    ///
+    /// The hops are refunded from the last one backwards, as a maker only signs away its incoming
+    /// swapcoins once the refunds of its outgoing ones are confirmed. Stops at the first hop that can't
+    /// be refunded.
+    /// The refunded swapcoins of the Taker are removed from the wallet.
+    ///
+    /// This is only safe before the preimage is revealed. After, the receiver of a hop could still
+    /// claim it via the hashlock after signing away its own coins.
+    fn abort_cooperatively(&mut self, swap: &OngoingSwapState) -> Result<(), TakerError> {
+        let maker_count = swap.swap_params.maker_count;
+        // Refund txids of the hop after the current one.
+        let mut next_hop_refunds = Vec::new();
+        for hop in (0..=maker_count).rev() {
+            let outputs = swap.refund_outputs(hop);
+            if outputs.is_empty() {
+                continue;
+            }
+
+            // Skip the hops that were never funded, or are spent already.
+            let mut funded_count = 0;
+            for output in &outputs {
+                if self
+                    .wallet
+                    .rpc
+                    .get_tx_out(&output.outpoint.txid, output.outpoint.vout, Some(true))
+                    .map_err(WalletError::Rpc)?
+                    .is_some()
+                {
+                    funded_count += 1;
+                }
+            }
+            if funded_count == 0 {
+                continue;
+            }
+            if funded_count != outputs.len() {
+                return Err(ProtocolError::General("hop is only partially funded").into());
+            }
+
+            // `None` is the Taker.
+            let sender = match hop {
+                0 => None,
+                _ => Some(swap.refund_peer(hop - 1)?),
+            };
+            let receiver = if hop == maker_count {
+                None
+            } else {
+                Some(swap.refund_peer(hop)?)
+            };
+
+            // The refund txs, signed by the sender.
+            let RefundTxs { refunds, sigs } = match sender {
+                None => {
+                    let destination =
+                        self.wallet.get_next_internal_addresses(1)?[0].script_pubkey();
+                    let fee = calculate_fee(swap.feerate, refund_tx_vsize());
+                    let mut refunds = Vec::new();
+                    let mut sigs = Vec::new();
+                    for output in &outputs {
+                        let refund_tx = create_refund_tx(
+                            output.outpoint,
+                            output.amount,
+                            destination.clone(),
+                            fee,
+                        )?;
+                        sigs.push(sign_contract_tx(
+                            &refund_tx,
+                            &output.multisig_redeemscript,
+                            output.amount,
+                            &output.my_privkey.expect("taker privkey expected"),
+                        )?);
+                        refunds.push(RefundTxInfo {
+                            multisig_redeemscript: output.multisig_redeemscript.clone(),
+                            refund_tx,
+                        });
+                    }
+                    RefundTxs { refunds, sigs }
+                }
+                Some(maker) => req_refund_txs_once(&mut self.connect_to_maker(maker)?, &swap.id)?,
+            };
+
+            // Order the refunds as the outputs, and check them.
+            let mut hop_refunds = Vec::new();
+            let mut sender_sigs = Vec::new();
+            for output in &outputs {
+                let (refund, sig) = refunds
+                    .iter()
+                    .zip(sigs.iter())
+                    .find(|(refund, _)| {
+                        refund.multisig_redeemscript == output.multisig_redeemscript
+                    })
+                    .ok_or(ProtocolError::General("refund tx missing"))?;
+                validate_refund_tx(&refund.refund_tx, &output.outpoint)?;
+                verify_contract_tx_sig(
+                    &refund.refund_tx,
+                    &output.multisig_redeemscript,
+                    output.amount,
+                    &output.sender_pubkey,
+                    &sig.signature,
+                )?;
+                hop_refunds.push(refund.clone());
+                sender_sigs.push(*sig);
+            }
+
+            // The receiver's signatures.
+            let receiver_sigs = match receiver {
+                None => outputs
+                    .iter()
+                    .zip(hop_refunds.iter())
+                    .map(|(output, refund)| {
+                        sign_contract_tx(
+                            &refund.refund_tx,
+                            &output.multisig_redeemscript,
+                            output.amount,
+                            &output.my_privkey.expect("taker privkey expected"),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                Some(maker) => {
+                    self.wait_for_refunds(&next_hop_refunds)?;
+                    let sigs = req_refund_sigs_once(
+                        &mut self.connect_to_maker(maker)?,
+                        &swap.id,
+                        &hop_refunds,
+                    )?
+                    .sigs;
+                    for ((output, refund), sig) in
+                        outputs.iter().zip(hop_refunds.iter()).zip(sigs.iter())
+                    {
+                        verify_contract_tx_sig(
+                            &refund.refund_tx,
+                            &output.multisig_redeemscript,
+                            output.amount,
+                            &output.receiver_pubkey,
+                            &sig.signature,
+                        )?;
+                    }
+                    sigs
+                }
+            };
+
+            next_hop_refunds.clear();
+            for (((output, refund), sender_sig), receiver_sig) in outputs
+                .iter()
+                .zip(hop_refunds)
+                .zip(sender_sigs)
+                .zip(receiver_sigs)
+            {
+                let mut refund_tx = refund.refund_tx;
+                apply_two_signatures_to_2of2_multisig_spend(
+                    &output.sender_pubkey,
+                    &output.receiver_pubkey,
+                    &sender_sig,
+                    &receiver_sig,
+                    &mut refund_tx.input[0],
+                    &output.multisig_redeemscript,
+                );
+                let txid = self.wallet.send_tx(&refund_tx)?;
+                log::info!("Broadcasted refund tx of hop {} : {}", hop, txid);
+                next_hop_refunds.push(txid);
+
+                if sender.is_none() {
+                    self.wallet
+                        .remove_outgoing_swapcoin(&output.multisig_redeemscript)?;
+                }
+                if receiver.is_none() {
+                    self.wallet
+                        .remove_incoming_swapcoin(&output.multisig_redeemscript)?;
+                }
+            }
+            self.wallet.sync()?;
+            self.wallet.save_to_disk()?;
+        }
+        log::info!("Swap {} aborted cooperatively", swap.id);
+        Ok(())
+    }
+
+    /// Wait until the refund txs have [REQUIRED_CONFIRMS] confirmations. Fails if one of them is
+    /// replaced, or dropped from the mempool.
+    fn wait_for_refunds(&self, txids: &[Txid]) -> Result<(), TakerError> {
+        if self.behavior == TakerBehavior::AbortWithoutWaitingForRefunds {
+            return Ok(());
+        }
+        // Block wait time is varied between prod. and test builds.
+        let poll_interval = if cfg!(feature = "integration-test") {
+            Duration::from_secs(1)
+        } else {
+            Duration::from_secs(60)
+        };
+        for txid in txids {
+            loop {
+                let confirmations = self
+                    .wallet
+                    .rpc
+                    .get_raw_transaction_info(txid, None)
+                    .map_err(WalletError::Rpc)?
+                    .confirmations;
+                if confirmations >= Some(REQUIRED_CONFIRMS) {
+                    break;
+                }
+                log::info!("Waiting for refund tx {} to confirm", txid);
+                sleep(poll_interval);
+            }
+        }
+        Ok(())
+    }
+
+    /// Open a connection to a maker.
+    fn connect_to_maker(&self, maker: &OfferAndAddress) -> Result<NoiseStream, TakerError> {
+        let maker_addr_str = maker.address.to_string();
+        let socket = match self.config.connection_type {
+            ConnectionType::CLEARNET => TcpStream::connect(maker_addr_str)?,
+            #[cfg(feature = "tor")]
+            ConnectionType::TOR => Socks5Stream::connect(
+                format!("127.0.0.1:{}", self.config.socks_port).as_str(),
+                &*maker_addr_str,
+            )?
+            .into_inner(),
+        };
 
+        let time_out = Duration::from_secs(TCP_TIMEOUT_SECONDS);
+        socket.set_read_timeout(Some(time_out))?;
+        socket.set_write_timeout(Some(time_out))?;
+
+        Ok(NoiseStream::connect(
+            socket,
+            Some(&maker.offer.fidelity.bond.pubkey.inner),
+        )?)
+    }
+
+    /// Broadcast the incoming contracts and remove them from the wallet. Broadcast the outgoing contracts,
+    /// and prepare their timelock spends.
+    fn start_recovery(
+        &mut self,
+        incomings: Vec<IncomingSwapCoin>,
+        outgoings: Vec<OutgoingSwapCoin>,
+    ) -> Result<SwapRecovery, TakerError> {
         let incoming_contracts = incomings
             .iter()
             .map(|incoming| {
@@ -1914,6 +3001,7 @@ impl Taker {
         }
 
         let mut outgoing_infos = Vec::new();
+        let feerate = self.wallet.estimate_feerate(&self.config.fee_estimator)?;
 
         // Broadcast the Outgoing Contracts
         for outgoing in outgoings {
@@ -1935,94 +3023,127 @@ impl Taker {
             let reedemscript = outgoing.get_multisig_redeemscript();
             let timelock = outgoing.get_timelock()?;
             let next_internal = &self.wallet.get_next_internal_addresses(1)?[0];
-            let timelock_spend = outgoing.create_timelock_spend(next_internal)?;
+            let timelock_spend = outgoing.create_timelock_spend(next_internal, feerate)?;
             outgoing_infos.push(((reedemscript, contract_tx), (timelock, timelock_spend)));
         }
 
-        // Check for contract confirmations and broadcast timelocked transaction
-        let mut timelock_boardcasted = Vec::new();
-
         // Save the wallet file here before going into the expensive loop.
         self.wallet.sync()?;
         self.wallet.save_to_disk()?;
         log::info!("Wallet file synced and saved.");
 
-        // Start the loop to keep checking for timelock maturity, and spend from the contract asap.
-        loop {
-            // Break early if nothing to broadcast.
-            // This happens only when init_first_hop() fails at `NotEnoughMakersInOfferBook`
-            if outgoing_infos.is_empty() {
-                break;
+        Ok(SwapRecovery {
+            outgoing_infos,
+            timelock_broadcasted: Vec::new(),
+            next_check: Instant::now(),
+        })
+    }
+
+    /// Keep checking the recovery until all outgoing contracts are spent from.
+    fn wait_for_recovery(&mut self, recovery: &mut SwapRecovery) -> Result<(), TakerError> {
+        while !self.poll_recovery(recovery)? {
+            sleep(
+                recovery
+                    .next_check
+                    .saturating_duration_since(Instant::now()),
+            );
+        }
+        Ok(())
+    }
+
+    /// Check for timelock maturity of the outgoing contracts, and spend from them asap.
+    /// Returns whether all of them are spent from.
+    fn poll_recovery(&mut self, recovery: &mut SwapRecovery) -> Result<bool, TakerError> {
+        // Nothing to broadcast.
+        // This happens only when init_first_hop() fails at `NotEnoughMakersInOfferBook`
+        if recovery.outgoing_infos.is_empty() {
+            return Ok(true);
+        }
+
+        let now = Instant::now();
+        if now < recovery.next_check {
+            return Ok(false);
+        }
+        // Block wait time is varied between prod. and test builds.
+        let block_wait_time = if cfg!(feature = "integration-test") {
+            Duration::from_secs(10)
+        } else {
+            Duration::from_secs(10 * 60)
+        };
+        recovery.next_check = now + block_wait_time;
+
+        for ((reedemscript, contract), (timelock, timelocked_tx)) in recovery.outgoing_infos.iter()
+        {
+            // We have already broadcasted this tx, so skip
+            if recovery
+                .timelock_broadcasted
+                .contains(&timelocked_tx.compute_txid())
+            {
+                continue;
             }
-            for ((reedemscript, contract), (timelock, timelocked_tx)) in outgoing_infos.iter() {
-                // We have already broadcasted this tx, so skip
-                if timelock_boardcasted.contains(&timelocked_tx) {
-                    continue;
-                }
-                // Check if the contract tx has reached required maturity
-                // Failure here means the transaction hasn't been broadcasted yet. So do nothing and try again.
-                if let Ok(result) = self
-                    .wallet
-                    .rpc
-                    .get_raw_transaction_info(&contract.compute_txid(), None)
-                {
-                    log::info!(
-                        "Contract Tx : {}, reached confirmation : {:?}, required : {}",
-                        contract.compute_txid(),
-                        result.confirmations,
-                        timelock
-                    );
-                    if let Some(confirmation) = result.confirmations {
-                        // Now the transaction is confirmed in a block, check for required maturity
-                        if confirmation > (*timelock as u32) {
-                            log::info!(
-                                "Timelock maturity of {} blocks for Contract Tx is reached : {}",
-                                timelock,
-                                contract.compute_txid()
-                            );
+            // Check if the contract tx has reached required maturity
+            // Failure here means the transaction hasn't been broadcasted yet. So do nothing and try again.
+            if let Ok(result) = self
+                .wallet
+                .rpc
+                .get_raw_transaction_info(&contract.compute_txid(), None)
+            {
+                log::info!(
+                    "Contract Tx : {}, reached confirmation : {:?}, required : {}",
+                    contract.compute_txid(),
+                    result.confirmations,
+                    timelock
+                );
+                if let Some(confirmation) = result.confirmations {
+                    // Now the transaction is confirmed in a block, check for required maturity
+                    if confirmation > (*timelock as u32) {
+                        log::info!(
+                            "Timelock maturity of {} blocks for Contract Tx is reached : {}",
+                            timelock,
+                            contract.compute_txid()
+                        );
+                        // The timelocked tx can be already broadcasted, if the recovery was resumed.
+                        if self
+                            .wallet
+                            .rpc
+                            .get_raw_transaction_info(&timelocked_tx.compute_txid(), None)
+                            .is_ok()
+                        {
+                            log::info!("Timelocked tx already broadcasted");
+                        } else {
                             log::info!(
                                 "Broadcasting timelocked tx: {}",
                                 timelocked_tx.compute_txid()
                             );
                             self.wallet.send_tx(timelocked_tx)?;
-                            timelock_boardcasted.push(timelocked_tx);
+                        }
+                        recovery
+                            .timelock_broadcasted
+                            .push(timelocked_tx.compute_txid());
 
-                            let outgoing_removed = self
-                                .wallet
-                                .remove_outgoing_swapcoin(reedemscript)?
-                                .expect("outgoing swapcoin expected");
+                        if let Some(outgoing_removed) =
+                            self.wallet.remove_outgoing_swapcoin(reedemscript)?
+                        {
                             log::info!(
                                 "Removed Outgoing Swapcoin from Wallet, Contract Txid: {}",
                                 outgoing_removed.contract_tx.compute_txid()
                             );
-                            log::info!("Initializing Wallet sync and save");
-                            self.wallet.sync()?;
-                            self.wallet.save_to_disk()?;
-                            log::info!("Completed wallet sync and save");
                         }
+                        log::info!("Initializing Wallet sync and save");
+                        self.wallet.sync()?;
+                        self.wallet.save_to_disk()?;
+                        log::info!("Completed wallet sync and save");
                     }
                 }
             }
-
-            // Everything is broadcasted. Clear the connectionstate and break the loop
-            if timelock_boardcasted.len() == outgoing_infos.len() {
-                log::info!("All outgoing contracts reedemed. Cleared ongoing swap state");
-                // TODO: Reevaluate this.
-                self.clear_ongoing_swaps(); // This could be a bug if Taker is in middle of multiple swaps. For now we assume Taker will only do one swap at a time.
-                break;
-            }
-
-            // Block wait time is varied between prod. and test builds.
-            let block_wait_time = if cfg!(feature = "integration-test") {
-                Duration::from_secs(10)
-            } else {
-                Duration::from_secs(10 * 60)
-            };
-            std::thread::sleep(block_wait_time);
         }
-        log::info!("Recovery completed.");
 
-        Ok(())
+        // Everything is broadcasted.
+        if recovery.timelock_broadcasted.len() == recovery.outgoing_infos.len() {
+            log::info!("All outgoing contracts reedemed.");
+            return Ok(true);
+        }
+        Ok(false)
     }
 
     /// Synchronizes the offer book with addresses obtained from directory servers and local configurations.
@@ -2077,12 +3198,9 @@ impl Taker {
         // if a maker changes their offer without changing tor address, the taker will not ask them again for updated offer.
         // TODO: Add smarter update mechanism, where DNS would keep a flag for every update of maker offers and taker
         // will selectively redownload the offer from those makers only.
-        // Further TODO: The Offer book needs to be restructured to store a unqiue value per fidelity bond. Similar to DNS.
         let offers = fetch_offer_from_makers(addresses_from_dns, &self.config)?;
 
-        // TODO: Use better logic to update offerbook than to just rewrite everything.
-        self.offerbook = OfferBook::default();
-
+        let mut responded = Vec::new();
         for offer in offers {
             log::info!(
                 "Found offer from {}. Verifying Fidelity Proof",
@@ -2098,12 +3216,21 @@ impl Taker {
                     e,
                     offer.address.to_string()
                 );
-                self.offerbook.add_bad_maker(&offer);
+                self.offerbook.add_unverified_offer(&offer);
             } else {
                 log::info!("Fideity Bond verification succes. Adding offer to our OfferBook");
-                self.offerbook.add_new_offer(&offer);
+                let bond_value = self
+                    .wallet
+                    .calculate_fidelity_bond_value(&offer.offer.fidelity.bond)
+                    .unwrap_or_else(|e| {
+                        log::warn!("Could not calculate fidelity bond value: {:?}", e);
+                        Amount::ZERO
+                    });
+                responded.push(offer.offer.fidelity.bond.outpoint);
+                self.offerbook.add_new_offer(&offer, bond_value);
             }
         }
+        self.offerbook.record_missed_makers(&responded);
         Ok(())
     }
 
@@ -2115,3 +3242,164 @@ impl Taker {
         Ok(&self.offerbook)
     }
 }
+
+/// Checks the [SwapParams] before any maker is contacted.
+fn validate_swap_params(swap_params: &SwapParams) -> Result<(), TakerError> {
+    if swap_params.send_amount == Amount::ZERO {
+        return Err(TakerError::SendAmountNotSet);
+    }
+    if swap_params.maker_count < 2 {
+        return Err(ProtocolError::General("Swap maker count < 2").into());
+    }
+    if swap_params.tx_count == 0 || swap_params.tx_count > MAX_TX_COUNT {
+        return Err(ProtocolError::General("Swap tx count out of range").into());
+    }
+    // Every split of every hop must stay above the split lower limit, even after the fees.
+    if swap_params.send_amount
+        < Amount::from_sat(MIN_SPLIT_AMOUNT * 2) * swap_params.tx_count as u64
+    {
+        return Err(ProtocolError::General("Swap amount too small for the tx count").into());
+    }
+    Ok(())
+}
+
+/// Random number of funding txs for each hop of the swap.
+///
+/// The Taker funds the first hop with exactly `tx_count` txs. Every later hop, including the txs
+/// received back by the Taker, is split into a random count between 2 and `tx_count`, so the
+/// split count doesn't link the hops together.
+fn random_split_counts(tx_count: u32, maker_count: usize) -> Vec<u32> {
+    let min_count = tx_count.min(2);
+    std::iter::once(tx_count)
+        .chain((0..maker_count).map(|_| OsRng.gen_range(min_count..=tx_count)))
+        .collect()
+}
+
+/// Whether the maker's offer accepts a swap of the given amount.
+fn offer_accepts(offer: &Offer, amount: Amount) -> bool {
+    amount >= Amount::from_sat(offer.min_size) && amount <= Amount::from_sat(offer.max_size)
+}
+
+/// Amount a maker forwards to the next hop, after deducting its coinswap fee and the miner fees
+/// of its `next_tx_count` funding txs. Returns None if the fees exceed the incoming amount.
+fn hop_outgoing_amount(
+    offer: &Offer,
+    incoming_amount: Amount,
+    refund_locktime: u16,
+    feerate: f64,
+    next_tx_count: u32,
+) -> Option<Amount> {
+    let coinswap_fee = calculate_coinswap_fee(
+        incoming_amount.to_sat(),
+        refund_locktime,
+        offer.base_fee,
+        offer.amount_relative_fee_pct,
+        offer.time_relative_fee_pct,
+    );
+    let funding_fee = calculate_fee(feerate, funding_tx_vsize()) * next_tx_count as u64;
+    incoming_amount.checked_sub(coinswap_fee + funding_fee)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_split_counts() {
+        for tx_count in 1..=MAX_TX_COUNT {
+            let counts = random_split_counts(tx_count, 3);
+            assert_eq!(counts.len(), 4);
+            assert_eq!(counts[0], tx_count);
+            assert!(counts[1..]
+                .iter()
+                .all(|c| *c >= tx_count.min(2) && *c <= tx_count));
+        }
+    }
+
+    #[test]
+    fn test_validate_swap_params() {
+        let valid = SwapParams {
+            send_amount: Amount::from_sat(500_000),
+            maker_count: 2,
+            tx_count: 3,
+            required_confirms: 1,
+        };
+        assert!(validate_swap_params(&valid).is_ok());
+
+        for invalid in [
+            SwapParams {
+                send_amount: Amount::ZERO,
+                ..valid
+            },
+            SwapParams {
+                maker_count: 1,
+                ..valid
+            },
+            SwapParams {
+                tx_count: 0,
+                ..valid
+            },
+            SwapParams {
+                tx_count: MAX_TX_COUNT + 1,
+                ..valid
+            },
+            SwapParams {
+                send_amount: Amount::from_sat(MIN_SPLIT_AMOUNT * 2 * 3 - 1),
+                ..valid
+            },
+        ] {
+            assert!(validate_swap_params(&invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn test_swap_journal_roundtrip() {
+        let txid = Txid::from_byte_array([1; 32]);
+        let swap = OngoingSwapState {
+            swap_params: SwapParams {
+                send_amount: Amount::from_sat(500_000),
+                maker_count: 2,
+                tx_count: 3,
+                required_confirms: 1,
+            },
+            active_preimage: [2; 32],
+            id: "0102030405060708".to_string(),
+            taker_position: TakerPosition::WatchOnly,
+            hop_tx_counts: vec![3, 2, 3],
+            step: SwapStep::HopFunding {
+                maker_index: 1,
+                watch: FundingWatch {
+                    txids: vec![txid],
+                    required_confirmations: 2,
+                    start_time: Instant::now(),
+                    txids_seen_once: HashSet::from([txid]),
+                    txid_tx_map: HashMap::new(),
+                    txid_blockhash_map: HashMap::new(),
+                },
+                funding_outpoints: vec![OutPoint::new(txid, 0)],
+                multisig_redeemscripts: vec![ScriptBuf::new()],
+            },
+            ..Default::default()
+        };
+
+        let journal = serde_cbor::to_vec(&swap).unwrap();
+        let resumed = serde_cbor::from_slice::<OngoingSwapState>(&journal).unwrap();
+        assert_eq!(resumed.id, swap.id);
+        assert_eq!(resumed.active_preimage, swap.active_preimage);
+        assert_eq!(resumed.preimage_hash(), swap.preimage_hash());
+        assert_eq!(resumed.taker_position, TakerPosition::WatchOnly);
+        assert_eq!(resumed.hop_tx_counts, swap.hop_tx_counts);
+
+        // The watch starts over, with the same funding txs.
+        let SwapStep::HopFunding {
+            maker_index, watch, ..
+        } = resumed.step
+        else {
+            panic!("funding step expected");
+        };
+        assert_eq!(maker_index, 1);
+        assert_eq!(watch.txids, vec![txid]);
+        assert_eq!(watch.required_confirmations, 2);
+        assert!(watch.txids_seen_once.is_empty());
+    }
+}
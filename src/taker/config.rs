@@ -3,7 +3,10 @@
 //!  Represents the configuration options for the Taker module, controlling behaviors
 //! such as refund locktime, connection attempts, sleep delays, and timeouts.
 
-use crate::utill::{get_taker_dir, parse_field, parse_toml, ConnectionType};
+use crate::{
+    utill::{get_taker_dir, parse_field, parse_toml, ConnectionType},
+    wallet::{CoinSelectionAlgorithm, CrossPoolPolicy, FeeEstimator},
+};
 use std::{io, io::Write, path::Path};
 
 /// Taker configuration with refund, connection, and sleep settings.
@@ -17,6 +20,14 @@ pub struct TakerConfig {
     pub directory_server_address: String,
     /// Connection type
     pub connection_type: ConnectionType,
+    /// Feerate estimation for the swap transactions
+    pub fee_estimator: FeeEstimator,
+    /// Try to refund a failed swap with the makers, before falling back to its contracts
+    pub cooperative_abort: bool,
+    /// How spends merging seed coins with swap coins are handled
+    pub cross_pool_spend: CrossPoolPolicy,
+    /// The coin selection algorithm of the wallet
+    pub coin_selection: CoinSelectionAlgorithm,
 }
 
 impl Default for TakerConfig {
@@ -36,6 +47,10 @@ impl Default for TakerConfig {
                     ConnectionType::CLEARNET
                 }
             },
+            fee_estimator: FeeEstimator::default(),
+            cooperative_abort: false,
+            cross_pool_spend: CrossPoolPolicy::default(),
+            coin_selection: CoinSelectionAlgorithm::default(),
         }
     }
 }
@@ -84,6 +99,32 @@ impl TakerConfig {
                 config_map.get("connection_type"),
                 default_config.connection_type,
             ),
+            fee_estimator: FeeEstimator {
+                conf_target: parse_field(
+                    config_map.get("fee_conf_target"),
+                    default_config.fee_estimator.conf_target,
+                ),
+                min_feerate: parse_field(
+                    config_map.get("min_feerate"),
+                    default_config.fee_estimator.min_feerate,
+                ),
+                max_feerate: parse_field(
+                    config_map.get("max_feerate"),
+                    default_config.fee_estimator.max_feerate,
+                ),
+            },
+            cooperative_abort: parse_field(
+                config_map.get("cooperative_abort"),
+                default_config.cooperative_abort,
+            ),
+            cross_pool_spend: parse_field(
+                config_map.get("cross_pool_spend"),
+                default_config.cross_pool_spend,
+            ),
+            coin_selection: parse_field(
+                config_map.get("coin_selection"),
+                default_config.coin_selection,
+            ),
         })
     }
 
@@ -93,8 +134,23 @@ impl TakerConfig {
             "network_port = {}
 socks_port = {}
 directory_server_address = {}
-connection_type = {:?}",
-            self.network_port, self.socks_port, self.directory_server_address, self.connection_type
+connection_type = {:?}
+fee_conf_target = {}
+min_feerate = {}
+max_feerate = {}
+cooperative_abort = {}
+cross_pool_spend = {}
+coin_selection = {}",
+            self.network_port,
+            self.socks_port,
+            self.directory_server_address,
+            self.connection_type,
+            self.fee_estimator.conf_target,
+            self.fee_estimator.min_feerate,
+            self.fee_estimator.max_feerate,
+            self.cooperative_abort,
+            self.cross_pool_spend,
+            self.coin_selection,
         );
         std::fs::create_dir_all(path.parent().expect("Path should NOT be root!"))?;
         let mut file = std::fs::File::create(path)?;
@@ -175,6 +231,9 @@ mod tests {
         let contents = r#"
             [taker_config]
             socks_port = 19051
+            cooperative_abort = true
+            cross_pool_spend = warn
+            coin_selection = knapsack
         "#;
         let config_path = create_temp_config(contents, "different_data_taker_config.toml");
         let config = TakerConfig::new(Some(&config_path)).unwrap();
@@ -182,6 +241,9 @@ mod tests {
         assert_eq!(REFUND_LOCKTIME, 20);
         assert_eq!(
             TakerConfig {
+                cooperative_abort: true,
+                cross_pool_spend: CrossPoolPolicy::Warn,
+                coin_selection: CoinSelectionAlgorithm::Knapsack,
                 socks_port: 19051,        // Configurable via TOML.
                 ..TakerConfig::default()  // Use default for other values.
             },
@@ -189,6 +251,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_fee_estimator_config() {
+        let contents = r#"
+            fee_conf_target = 6
+            min_feerate = 1.5
+            max_feerate = 50.0
+        "#;
+        let config_path = create_temp_config(contents, "fee_estimator_taker_config.toml");
+        let config = TakerConfig::new(Some(&config_path)).unwrap();
+        remove_temp_config(&config_path);
+        assert_eq!(
+            TakerConfig {
+                fee_estimator: FeeEstimator {
+                    conf_target: 6,
+                    min_feerate: 1.5,
+                    max_feerate: 50.0,
+                },
+                ..TakerConfig::default()
+            },
+            config
+        );
+    }
+
     #[test]
     fn test_missing_file() {
         let config_path = get_taker_dir().join("taker.toml");
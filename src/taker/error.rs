@@ -27,6 +27,8 @@ pub enum TakerError {
     SendAmountNotSet,
     /// Error indicating a timeout while waiting for the funding transaction.
     FundingTxWaitTimeOut,
+    /// No ongoing swap with this id.
+    UnknownSwap(String),
     /// Error deserializing data, typically related to CBOR-encoded data.
     Deserialize(serde_cbor::Error),
     /// Error indicating an MPSC channel failure.
@@ -35,6 +37,24 @@ pub enum TakerError {
     MPSC(String),
 }
 
+impl TakerError {
+    /// Whether the error proves that the maker broke the protocol.
+    ///
+    /// Network failures and local errors are not the maker's fault for sure, so they must not
+    /// get the maker banned.
+    pub(crate) fn is_protocol_violation(&self) -> bool {
+        match self {
+            Self::ContractsBroadcasted(_) | Self::Deserialize(_) => true,
+            Self::Net(e) => matches!(
+                e,
+                NetError::Cbor(_) | NetError::Encryption(_) | NetError::MessageTooLarge { .. }
+            ),
+            Self::Wallet(e) => matches!(e, WalletError::Protocol(_) | WalletError::Secp(_)),
+            _ => false,
+        }
+    }
+}
+
 impl From<serde_cbor::Error> for TakerError {
     fn from(value: serde_cbor::Error) -> Self {
         Self::Deserialize(value)
@@ -0,0 +1,334 @@
+//! The Taker's history of swaps.
+//!
+//! The wallet forgets about a swap once its swapcoins are spent. The [`SwapHistory`] keeps a [`SwapRecord`]
+//! of every finished swap, with the makers used, amounts, fees, timings and received utxos. It's stored in
+//! the `history.dat` file of the data directory, and read via [Taker::swap_history](super::Taker::swap_history),
+//! which adds a [`PrivacySummary`] of each swap.
+
+use std::{fmt::Display, path::Path};
+
+use bitcoin::{Amount, OutPoint, Transaction, Txid};
+use serde::{Deserialize, Serialize};
+
+use crate::utill::{read_with_backups, write_atomic};
+
+use super::error::TakerError;
+
+/// How a swap ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwapOutcome {
+    /// The swap completed, and the swapcoins were received.
+    Completed,
+    /// The swap was refunded cooperatively with the makers, before any contract was broadcasted.
+    Aborted,
+    /// The swap failed, and the Taker recovered its coins via the contract transactions.
+    Recovered,
+}
+
+impl Display for SwapOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Completed => write!(f, "completed"),
+            Self::Aborted => write!(f, "aborted"),
+            Self::Recovered => write!(f, "recovered"),
+        }
+    }
+}
+
+/// A finished swap of the Taker.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwapRecord {
+    /// The swap id.
+    pub id: String,
+    /// When the swap started, in seconds since the unix epoch.
+    pub started_at: u64,
+    /// Block height when the swap started. Zero for swaps recorded without it.
+    #[serde(default)]
+    pub start_height: u64,
+    /// When the swap ended, in seconds since the unix epoch.
+    pub finished_at: u64,
+    /// Addresses of the makers of each hop, in order. Only the makers reached before a failure are listed.
+    pub makers: Vec<String>,
+    /// Total amount sent in the Taker's funding txs.
+    pub send_amount: Amount,
+    /// Total amount received in the incoming swapcoins. Zero if the swap didn't complete.
+    pub receive_amount: Amount,
+    /// Miner fees paid by the Taker for its funding txs.
+    pub mining_fee: Amount,
+    /// The funding outpoints of the received swapcoins.
+    pub received_utxos: Vec<OutPoint>,
+    /// Whether any contract tx of the swap was broadcasted.
+    pub contracts_broadcasted: bool,
+    /// How the swap ended.
+    pub outcome: SwapOutcome,
+}
+
+impl SwapRecord {
+    /// Total cost of a completed swap: the makers' fees and the miner fees of all funding txs.
+    /// Zero if the swap didn't complete.
+    pub fn total_fee(&self) -> Amount {
+        if self.outcome != SwapOutcome::Completed {
+            return Amount::ZERO;
+        }
+        self.send_amount
+            .checked_sub(self.receive_amount)
+            .unwrap_or(Amount::ZERO)
+            + self.mining_fee
+    }
+
+    /// How long the swap took, in seconds.
+    pub fn duration(&self) -> u64 {
+        self.finished_at.saturating_sub(self.started_at)
+    }
+}
+
+impl Display for SwapRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} | started {} | took {}s | {} | sent {} sats | received {} sats | total fee {} sats | makers {}",
+            self.id,
+            self.started_at,
+            self.duration(),
+            self.outcome,
+            self.send_amount.to_sat(),
+            self.receive_amount.to_sat(),
+            self.total_fee().to_sat(),
+            self.makers.join(", ")
+        )
+    }
+}
+
+/// What a swap reveals about the Taker's coins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrivacySummary {
+    /// Number of makers the coins were routed through.
+    pub hop_count: usize,
+    /// Whether any contract tx hit the chain, which links the hops of the swap.
+    pub contracts_broadcasted: bool,
+    /// Txs spending the received swapcoins together with seed coins, which links them to the coins
+    /// sent into the swap. Empty if the swapcoins were never merged.
+    pub merged_with_seed_coins: Vec<Txid>,
+}
+
+impl Display for PrivacySummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "hops {} | contracts on chain: {} | merged with seed coins: {}",
+            self.hop_count,
+            if self.contracts_broadcasted {
+                "yes"
+            } else {
+                "no"
+            },
+            if self.merged_with_seed_coins.is_empty() {
+                "no".to_string()
+            } else {
+                format!(
+                    "yes ({})",
+                    self.merged_with_seed_coins
+                        .iter()
+                        .map(Txid::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+        )
+    }
+}
+
+/// A finished swap, with its privacy summary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapReport {
+    /// The recorded swap.
+    pub record: SwapRecord,
+    /// What the swap reveals about the Taker's coins.
+    pub privacy: PrivacySummary,
+}
+
+impl SwapReport {
+    /// Builds the report of a swap, given the wallet txs spending swapcoins together with seed coins.
+    pub(crate) fn new(record: &SwapRecord, merging_txs: &[Transaction]) -> Self {
+        let merged_with_seed_coins = merging_txs
+            .iter()
+            .filter(|tx| {
+                tx.input
+                    .iter()
+                    .any(|input| record.received_utxos.contains(&input.previous_output))
+            })
+            .map(Transaction::compute_txid)
+            .collect();
+        Self {
+            privacy: PrivacySummary {
+                hop_count: record.makers.len(),
+                contracts_broadcasted: record.contracts_broadcasted,
+                merged_with_seed_coins,
+            },
+            record: record.clone(),
+        }
+    }
+}
+
+impl Display for SwapReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\n    {}", self.record, self.privacy)
+    }
+}
+
+/// All the finished swaps of the Taker, in the order they ended.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct SwapHistory {
+    swaps: Vec<SwapRecord>,
+}
+
+impl SwapHistory {
+    /// Records a finished swap. Replaces an earlier record of the same swap.
+    pub(crate) fn record(&mut self, record: SwapRecord) {
+        self.swaps.retain(|swap| swap.id != record.id);
+        self.swaps.push(record);
+    }
+
+    pub(crate) fn list(&self) -> &[SwapRecord] {
+        &self.swaps
+    }
+
+    /// The funding outpoints of the swapcoins received in all swaps.
+    pub(crate) fn received_utxos(&self) -> Vec<OutPoint> {
+        self.swaps
+            .iter()
+            .flat_map(|swap| swap.received_utxos.iter().copied())
+            .collect()
+    }
+
+    /// The lowest start height of the swaps that received swapcoins. Zero if there are none.
+    pub(crate) fn earliest_start_height(&self) -> u64 {
+        self.swaps
+            .iter()
+            .filter(|swap| !swap.received_utxos.is_empty())
+            .map(|swap| swap.start_height)
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Atomically writes the history to the path, keeping the previous file as a backup.
+    pub(crate) fn write_to_disk(&self, path: &Path) -> Result<(), TakerError> {
+        Ok(write_atomic(path, &serde_cbor::to_vec(&self)?)?)
+    }
+
+    /// Reads from a path (errors if path doesn't exist).
+    ///
+    /// Falls back to the newest valid backup if the file is corrupted.
+    pub(crate) fn read_from_disk(path: &Path) -> Result<Self, TakerError> {
+        read_with_backups(path, |bytes| Ok(serde_cbor::from_slice::<Self>(bytes)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{absolute::LockTime, hashes::Hash, transaction::Version, TxIn};
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint {
+            txid: Txid::all_zeros(),
+            vout,
+        }
+    }
+
+    fn record(id: &str, received_utxos: Vec<OutPoint>) -> SwapRecord {
+        SwapRecord {
+            id: id.to_string(),
+            started_at: 100,
+            start_height: 200,
+            finished_at: 160,
+            makers: vec!["maker1:6102".to_string(), "maker2:16102".to_string()],
+            send_amount: Amount::from_sat(100_000),
+            receive_amount: Amount::from_sat(97_000),
+            mining_fee: Amount::from_sat(500),
+            received_utxos,
+            contracts_broadcasted: false,
+            outcome: SwapOutcome::Completed,
+        }
+    }
+
+    fn spending_tx(outpoints: &[OutPoint]) -> Transaction {
+        Transaction {
+            version: Version::TWO,
+            lock_time: LockTime::ZERO,
+            input: outpoints
+                .iter()
+                .map(|outpoint| TxIn {
+                    previous_output: *outpoint,
+                    ..Default::default()
+                })
+                .collect(),
+            output: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_record_swaps() {
+        let mut history = SwapHistory::default();
+        history.record(record("swap-1", vec![outpoint(0)]));
+        history.record(record("swap-2", vec![outpoint(1), outpoint(2)]));
+
+        let swap = &history.list()[0];
+        assert_eq!(swap.total_fee(), Amount::from_sat(3_500));
+        assert_eq!(swap.duration(), 60);
+        assert_eq!(
+            history.received_utxos(),
+            vec![outpoint(0), outpoint(1), outpoint(2)]
+        );
+        assert_eq!(history.earliest_start_height(), 200);
+
+        // A failed swap costs no swap fees.
+        let mut recovered = record("swap-1", Vec::new());
+        recovered.receive_amount = Amount::ZERO;
+        recovered.outcome = SwapOutcome::Recovered;
+        assert_eq!(recovered.total_fee(), Amount::ZERO);
+
+        // Recording a swap again replaces it.
+        history.record(recovered);
+        assert_eq!(history.list().len(), 2);
+        assert_eq!(history.list()[1].outcome, SwapOutcome::Recovered);
+
+        // Swaps without received swapcoins don't matter for the start height.
+        let mut earlier = record("swap-3", Vec::new());
+        earlier.start_height = 150;
+        history.record(earlier);
+        assert_eq!(history.earliest_start_height(), 200);
+        assert_eq!(SwapHistory::default().earliest_start_height(), 0);
+    }
+
+    #[test]
+    fn test_privacy_summary() {
+        let swap = record("swap-1", vec![outpoint(0), outpoint(1)]);
+        let merging_tx = spending_tx(&[outpoint(1), outpoint(5)]);
+        let other_tx = spending_tx(&[outpoint(6), outpoint(7)]);
+
+        let report = SwapReport::new(&swap, &[merging_tx.clone(), other_tx.clone()]);
+        assert_eq!(report.privacy.hop_count, 2);
+        assert!(!report.privacy.contracts_broadcasted);
+        assert_eq!(
+            report.privacy.merged_with_seed_coins,
+            vec![merging_tx.compute_txid()]
+        );
+
+        let report = SwapReport::new(&swap, &[other_tx]);
+        assert!(report.privacy.merged_with_seed_coins.is_empty());
+    }
+
+    #[test]
+    fn test_history_persistence() {
+        let temp_dir = bitcoind::tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("history.dat");
+
+        let mut history = SwapHistory::default();
+        history.record(record("swap-1", vec![outpoint(0)]));
+        history.write_to_disk(&path).unwrap();
+
+        let read = SwapHistory::read_from_disk(&path).unwrap();
+        assert_eq!(read.list(), history.list());
+    }
+}
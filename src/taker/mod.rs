@@ -7,9 +7,11 @@
 pub mod api;
 mod config;
 pub mod error;
+mod history;
 pub(crate) mod offers;
 mod routines;
 
 pub use self::api::TakerBehavior;
-pub use api::{SwapParams, Taker};
+pub use api::{HopQuote, SwapHandle, SwapParams, SwapQuote, Taker};
 pub use config::TakerConfig;
+pub use history::{PrivacySummary, SwapOutcome, SwapRecord, SwapReport};
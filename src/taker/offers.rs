@@ -1,21 +1,27 @@
 //! Download, process and store Maker offers from the directory-server.
 //!
 //! It defines structures like [OfferAndAddress] and [MakerAddress] for representing maker offers and addresses.
-//! The [OfferBook] struct keeps track of good and bad makers, and it provides methods for managing offers.
+//! The [OfferBook] struct keeps a persistent record of all known makers, ranks them by their fidelity bond
+//! and past behaviour, and provides methods for managing offers. Makers for a swap are picked from the book
+//! by the [MakerSelector].
 //! The module handles the syncing of the offer book with addresses obtained from directory servers and local configurations.
 //! It uses asynchronous channels for concurrent processing of maker offers.
 
 use std::{
+    collections::BTreeMap,
     convert::TryFrom,
     fmt,
-    fs::read,
-    io::{BufWriter, Write},
+    io::Write,
     net::TcpStream,
     path::Path,
     sync::mpsc,
     thread::{self, Builder},
 };
 
+use bitcoin::{
+    secp256k1::rand::{rngs::StdRng, Rng, SeedableRng},
+    Amount, OutPoint,
+};
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "tor")]
@@ -23,8 +29,11 @@ use socks::Socks5Stream;
 
 use crate::{
     error::NetError,
-    protocol::messages::{DnsRequest, Offer},
-    utill::{read_message, send_message, ConnectionType, GLOBAL_PAUSE, NET_TIMEOUT},
+    protocol::messages::{DnsRequest, NegotiatedProtocol, Offer},
+    utill::{
+        read_message, read_with_backups, send_message, write_atomic, ConnectionType, GLOBAL_PAUSE,
+        NET_TIMEOUT,
+    },
 };
 
 use super::{config::TakerConfig, error::TakerError, routines::download_maker_offer};
@@ -35,6 +44,9 @@ pub struct OfferAndAddress {
     pub(crate) offer: Offer,
     /// All maker addresses
     pub address: MakerAddress,
+    /// Protocol parameters agreed with the maker when the offer was downloaded.
+    #[serde(default)]
+    pub(crate) protocol: NegotiatedProtocol,
 }
 
 const _REGTEST_MAKER_ADDRESSES_PORT: &[&str] = &["6102", "16102", "26102", "36102", "46102"];
@@ -79,86 +91,286 @@ impl TryFrom<&mut TcpStream> for MakerAddress {
     }
 }
 
-/// An ephemeral Offerbook tracking good and bad makers. Currently, Offerbook is initiated
-/// at start of every swap. So good and bad maker list will ot be persisted.
-// TODO: Persist the offerbook in disk.
+/// Persistent record of a maker in the [OfferBook], along with its interaction history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MakerRecord {
+    /// The latest offer and address of the maker.
+    pub offer_and_address: OfferAndAddress,
+    /// Value of the maker's fidelity bond, as of the latest offer sync.
+    pub bond_value: Amount,
+    /// Number of offer syncs the maker responded to.
+    pub seen: u32,
+    /// Number of offer syncs the maker didn't respond to, since it was first seen.
+    pub missed: u32,
+    /// Number of completed swaps with the maker.
+    pub successes: u32,
+    /// Number of swaps the maker misbehaved in.
+    pub failures: u32,
+    /// Number of swaps the maker stopped responding in. Halved on every completed swap, so
+    /// network failures are forgiven over time.
+    pub timeouts: u32,
+    /// Banned makers are never selected for swaps again.
+    pub banned: bool,
+    /// Makers that timed out are not selected again in the current session.
+    #[serde(skip)]
+    pub(crate) excluded: bool,
+}
+
+impl MakerRecord {
+    fn new(offer_and_address: OfferAndAddress, bond_value: Amount) -> Self {
+        Self {
+            offer_and_address,
+            bond_value,
+            seen: 0,
+            missed: 0,
+            successes: 0,
+            failures: 0,
+            timeouts: 0,
+            banned: false,
+            excluded: false,
+        }
+    }
+
+    /// Fraction of offer syncs the maker responded to.
+    pub fn uptime(&self) -> f64 {
+        if self.seen + self.missed == 0 {
+            return 1.0;
+        }
+        self.seen as f64 / (self.seen + self.missed) as f64
+    }
+
+    /// Fraction of swaps that didn't fail with the maker. A fresh maker starts with full reliability.
+    pub fn reliability(&self) -> f64 {
+        (self.successes + 1) as f64 / (self.successes + self.failures + self.timeouts + 1) as f64
+    }
+
+    /// Whether the maker can be selected for swaps.
+    pub fn is_good(&self) -> bool {
+        !self.banned && !self.excluded
+    }
+
+    /// Ranking score of the maker. Banned makers score zero.
+    ///
+    /// The fidelity bond value is weighted by the maker's uptime and reliability. A big bond
+    /// doesn't help a maker that is often offline or fails swaps.
+    pub fn score(&self) -> f64 {
+        if self.banned {
+            return 0.0;
+        }
+        self.bond_value.to_sat() as f64 * self.uptime() * self.reliability()
+    }
+}
+
+/// An Offerbook ranking makers by their fidelity bond and past behaviour.
+///
+/// Makers are keyed by their fidelity bond outpoint, so a maker keeps its history across address
+/// changes. The book is persisted in the taker's data directory, and updated on every offer sync.
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct OfferBook {
-    pub(super) all_makers: Vec<OfferAndAddress>,
-    pub(super) bad_makers: Vec<OfferAndAddress>,
+    makers: BTreeMap<OutPoint, MakerRecord>,
+    /// Bad makers that can't be attributed to a known bond, like offers with invalid fidelity proofs.
+    /// These are only kept for the current session.
+    #[serde(skip)]
+    unknown_bad_makers: Vec<OfferAndAddress>,
 }
 
 impl OfferBook {
-    // TODO: design a better offerbook:
-    // - unique key.
-    // - clear good-bad separations.
-    // - ranking system.
-    // - various categories of livelynesss, to smartly distribute try counts.
-
-    /// Gets all "not-bad" offers.
+    /// Gets all "not-bad" offers, ranked from the highest to the lowest score.
     pub fn all_good_makers(&self) -> Vec<&OfferAndAddress> {
-        self.all_makers
-            .iter()
-            .filter(|offer| !self.bad_makers.contains(offer))
+        let mut good_makers = self
+            .makers
+            .values()
+            .filter(|record| record.is_good())
+            .collect::<Vec<_>>();
+        good_makers.sort_by(|a, b| b.score().total_cmp(&a.score()));
+        good_makers
+            .into_iter()
+            .map(|record| &record.offer_and_address)
             .collect()
     }
 
-    /// Adds a new offer to the offer book.
-    pub(crate) fn add_new_offer(&mut self, offer: &OfferAndAddress) -> bool {
-        if !self.all_makers.contains(offer) {
-            self.all_makers.push(offer.clone());
-            true
+    /// Gets the record of the maker with the given fidelity bond outpoint.
+    pub fn get_maker(&self, bond_outpoint: &OutPoint) -> Option<&MakerRecord> {
+        self.makers.get(bond_outpoint)
+    }
+
+    /// Adds a new offer to the offer book, or updates the offer of a known maker.
+    ///
+    /// Counts as a response of the maker to the current offer sync. Returns true if the maker is new.
+    pub(crate) fn add_new_offer(&mut self, offer: &OfferAndAddress, bond_value: Amount) -> bool {
+        let bond_outpoint = offer.offer.fidelity.bond.outpoint;
+        let is_new = !self.makers.contains_key(&bond_outpoint);
+        let record = self
+            .makers
+            .entry(bond_outpoint)
+            .or_insert_with(|| MakerRecord::new(offer.clone(), bond_value));
+        record.offer_and_address = offer.clone();
+        record.bond_value = bond_value;
+        record.seen += 1;
+        is_new
+    }
+
+    /// Counts an offer sync as missed for all known makers that didn't respond to it.
+    pub(crate) fn record_missed_makers(&mut self, responded: &[OutPoint]) {
+        for (bond_outpoint, record) in self.makers.iter_mut() {
+            if !responded.contains(bond_outpoint) {
+                record.missed += 1;
+            }
+        }
+    }
+
+    /// Records a completed swap with the maker, and decays its count of timeouts.
+    pub(crate) fn record_success(&mut self, maker: &OfferAndAddress) {
+        if let Some(record) = self.makers.get_mut(&maker.offer.fidelity.bond.outpoint) {
+            record.successes += 1;
+            record.timeouts /= 2;
+        }
+    }
+
+    /// Records a swap that failed with the maker.
+    ///
+    /// The maker is banned only if the error is a protocol violation. Network and local errors
+    /// count as a timeout, and only exclude the maker for the rest of the session.
+    pub(crate) fn add_failed_maker(&mut self, maker: &OfferAndAddress, error: &TakerError) -> bool {
+        if error.is_protocol_violation() {
+            self.add_bad_maker(maker)
         } else {
-            false
+            self.add_timed_out_maker(maker)
+        }
+    }
+
+    /// Records a maker that stopped responding in a swap, and excludes it for the rest of the session.
+    pub(crate) fn add_timed_out_maker(&mut self, maker: &OfferAndAddress) -> bool {
+        match self.makers.get_mut(&maker.offer.fidelity.bond.outpoint) {
+            Some(record) => {
+                record.timeouts += 1;
+                let newly_excluded = !record.excluded;
+                record.excluded = true;
+                newly_excluded
+            }
+            None => self.add_unknown_bad_maker(maker),
         }
     }
 
-    /// Adds a bad maker to the offer book.
+    /// Records a maker that misbehaved in a swap, and bans it.
     pub(crate) fn add_bad_maker(&mut self, bad_maker: &OfferAndAddress) -> bool {
-        if !self.bad_makers.contains(bad_maker) {
-            self.bad_makers.push(bad_maker.clone());
+        match self.makers.get_mut(&bad_maker.offer.fidelity.bond.outpoint) {
+            Some(record) => {
+                record.failures += 1;
+                let newly_banned = !record.banned;
+                record.banned = true;
+                newly_banned
+            }
+            None => self.add_unknown_bad_maker(bad_maker),
+        }
+    }
+
+    /// Records an offer whose fidelity proof failed verification.
+    ///
+    /// The proof can't be trusted to identify the bond, so no maker record is affected.
+    pub(crate) fn add_unverified_offer(&mut self, offer: &OfferAndAddress) -> bool {
+        self.add_unknown_bad_maker(offer)
+    }
+
+    fn add_unknown_bad_maker(&mut self, bad_maker: &OfferAndAddress) -> bool {
+        if !self.unknown_bad_makers.contains(bad_maker) {
+            self.unknown_bad_makers.push(bad_maker.clone());
             true
         } else {
             false
         }
     }
 
-    /// Gets the list of bad makers.
+    /// Gets the list of bad makers. These are the banned makers, the makers excluded for the
+    /// current session and the offers with invalid fidelity proofs.
     pub(crate) fn get_bad_makers(&self) -> Vec<&OfferAndAddress> {
-        self.bad_makers.iter().collect()
+        self.makers
+            .values()
+            .filter(|record| !record.is_good())
+            .map(|record| &record.offer_and_address)
+            .chain(self.unknown_bad_makers.iter())
+            .collect()
     }
 
-    /// Load existing file, updates it, writes it back (errors if path doesn't exist).
+    /// Atomically writes the offerbook to the path, keeping the previous file as a backup.
     pub fn write_to_disk(&self, path: &Path) -> Result<(), TakerError> {
-        let wallet_file = std::fs::OpenOptions::new().write(true).open(path)?;
-        let writer = BufWriter::new(wallet_file);
-        Ok(serde_cbor::to_writer(writer, &self)?)
+        Ok(write_atomic(path, &serde_cbor::to_vec(&self)?)?)
     }
 
     /// Reads from a path (errors if path doesn't exist).
+    ///
+    /// Falls back to the newest valid backup if the file is corrupted.
     pub fn read_from_disk(path: &Path) -> Result<Self, TakerError> {
-        //let wallet_file = File::open(path)?;
-        let mut reader = read(path)?;
-        let book = match serde_cbor::from_slice::<Self>(&reader) {
-            Ok(book) => book,
-            Err(e) => {
-                let err_string = format!("{:?}", e);
-                if err_string.contains("code: TrailingData") {
-                    log::info!("Offerbook has trailing data, trying to restore");
-                    loop {
-                        // pop the last byte and try again.
-                        reader.pop();
-                        match serde_cbor::from_slice::<Self>(&reader) {
-                            Ok(book) => break book,
-                            Err(_) => continue,
-                        }
-                    }
-                } else {
-                    return Err(e.into());
-                }
+        read_with_backups(path, |bytes| Ok(serde_cbor::from_slice::<Self>(bytes)?))
+    }
+}
+
+/// Randomly selects makers from the [OfferBook], with probability proportional to their score.
+///
+/// The score of a well behaving maker is its fidelity bond value. So a Sybil attacker flooding the
+/// directory with makers has to lock up proportionally more coins to get selected, like in JoinMarket.
+/// Each bond is a single candidate, no matter how many offers claim it.
+///
+/// The selector can be seeded, to make the selection deterministic in tests.
+#[derive(Debug)]
+pub struct MakerSelector {
+    rng: StdRng,
+}
+
+impl Default for MakerSelector {
+    fn default() -> Self {
+        Self {
+            rng: StdRng::from_entropy(),
+        }
+    }
+}
+
+impl MakerSelector {
+    /// Creates a selector with a deterministic random sequence.
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Selects a good maker from the offerbook, whose offer passes the `filter`.
+    ///
+    /// Makers with a bond outpoint in `exclude` are skipped. Returns `None` if no maker with a
+    /// positive score is left.
+    pub(crate) fn select<'a>(
+        &mut self,
+        offerbook: &'a OfferBook,
+        exclude: &[OutPoint],
+        filter: impl Fn(&OfferAndAddress) -> bool,
+    ) -> Option<&'a OfferAndAddress> {
+        let candidates = offerbook
+            .makers
+            .iter()
+            .filter(|(bond_outpoint, record)| {
+                record.is_good()
+                    && !exclude.contains(bond_outpoint)
+                    && filter(&record.offer_and_address)
+            })
+            .map(|(_, record)| (record, record.score()))
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect::<Vec<_>>();
+
+        let total_weight = candidates.iter().map(|(_, weight)| weight).sum::<f64>();
+        if candidates.is_empty() || !total_weight.is_finite() {
+            return None;
+        }
+
+        let mut target = self.rng.gen_range(0.0..total_weight);
+        for (record, weight) in &candidates {
+            if target < *weight {
+                return Some(&record.offer_and_address);
             }
-        };
-        Ok(book)
+            target -= weight;
+        }
+        // Floating point rounding can leave the target just past the last weight.
+        candidates
+            .last()
+            .map(|(record, _)| &record.offer_and_address)
     }
 }
 
@@ -261,3 +473,256 @@ pub fn fetch_addresses_from_dns(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        protocol::{error::ProtocolError, messages::FidelityProof},
+        wallet::FidelityBond,
+    };
+    use bitcoin::{
+        absolute,
+        hashes::Hash,
+        relative::LockTime,
+        secp256k1::{Message, Secp256k1, SecretKey},
+        PublicKey, Txid,
+    };
+    use bitcoind::tempfile::tempdir;
+
+    fn offer_with_bond(vout: u32, address: &str) -> OfferAndAddress {
+        let secp = Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&[1u8; 32]).unwrap();
+        let pubkey = PublicKey::new(secret_key.public_key(&secp));
+        let bond = FidelityBond {
+            outpoint: OutPoint::new(Txid::all_zeros(), vout),
+            amount: Amount::from_sat(50_000),
+            lock_time: absolute::LockTime::from_height(500).unwrap(),
+            pubkey,
+            conf_height: 100,
+            cert_expiry: 1,
+        };
+        let cert_hash = bond.generate_cert_hash(address);
+        let cert_sig = secp.sign_ecdsa(
+            &Message::from_digest(cert_hash.to_byte_array()),
+            &secret_key,
+        );
+
+        OfferAndAddress {
+            offer: Offer {
+                base_fee: 1000,
+                amount_relative_fee_pct: 0.025,
+                time_relative_fee_pct: 0.001,
+                required_confirms: 1,
+                minimum_locktime: LockTime::from_height(20),
+                max_size: 1_000_000,
+                min_size: 10_000,
+                tweakable_point: pubkey,
+                fidelity: FidelityProof {
+                    bond,
+                    cert_hash,
+                    cert_sig,
+                },
+                requires_utxo_proof: false,
+            },
+            address: MakerAddress::new(address).unwrap(),
+            protocol: NegotiatedProtocol::default(),
+        }
+    }
+
+    fn bond_outpoint(offer: &OfferAndAddress) -> OutPoint {
+        offer.offer.fidelity.bond.outpoint
+    }
+
+    #[test]
+    fn test_offerbook_ranking() {
+        let maker_a = offer_with_bond(0, "maker-a.onion:6102");
+        let maker_b = offer_with_bond(1, "maker-b.onion:6102");
+        let maker_c = offer_with_bond(2, "maker-c.onion:6102");
+
+        let mut offerbook = OfferBook::default();
+        assert!(offerbook.add_new_offer(&maker_a, Amount::from_sat(100)));
+        assert!(offerbook.add_new_offer(&maker_b, Amount::from_sat(300)));
+        assert!(offerbook.add_new_offer(&maker_c, Amount::from_sat(200)));
+        assert!(!offerbook.add_new_offer(&maker_a, Amount::from_sat(100)));
+
+        // Ranked by bond value.
+        assert_eq!(
+            offerbook.all_good_makers(),
+            vec![&maker_b, &maker_c, &maker_a]
+        );
+
+        // Maker b stops responding to offer syncs, and drops in the ranking.
+        for _ in 0..3 {
+            offerbook.record_missed_makers(&[bond_outpoint(&maker_a), bond_outpoint(&maker_c)]);
+        }
+        let record_b = offerbook.get_maker(&bond_outpoint(&maker_b)).unwrap();
+        assert_eq!(record_b.uptime(), 0.25);
+        assert_eq!(
+            offerbook.all_good_makers(),
+            vec![&maker_c, &maker_a, &maker_b]
+        );
+
+        // Completed swaps keep maker a at full reliability, while a timeout halves maker c's.
+        offerbook.record_success(&maker_a);
+        offerbook.add_timed_out_maker(&maker_c);
+        let record_a = offerbook.get_maker(&bond_outpoint(&maker_a)).unwrap();
+        let record_c = offerbook.get_maker(&bond_outpoint(&maker_c)).unwrap();
+        assert_eq!((record_a.successes, record_a.reliability()), (1, 1.0));
+        assert_eq!((record_c.timeouts, record_c.reliability()), (1, 0.5));
+    }
+
+    #[test]
+    fn test_maker_selector() {
+        let maker_a = offer_with_bond(0, "maker-a.onion:6102");
+        let maker_b = offer_with_bond(1, "maker-b.onion:6102");
+        let maker_c = offer_with_bond(2, "maker-c.onion:6102");
+        let maker_d = offer_with_bond(3, "maker-d.onion:6102");
+
+        let mut offerbook = OfferBook::default();
+        offerbook.add_new_offer(&maker_a, Amount::from_sat(100));
+        offerbook.add_new_offer(&maker_b, Amount::from_sat(900));
+        offerbook.add_new_offer(&maker_c, Amount::ZERO);
+        offerbook.add_new_offer(&maker_d, Amount::from_sat(500));
+        offerbook.add_bad_maker(&maker_d);
+
+        // Makers are picked in proportion to their bond value. Zero valued bonds and banned makers are never picked.
+        let mut selector = MakerSelector::from_seed(7);
+        let mut picks = BTreeMap::new();
+        for _ in 0..1000 {
+            let maker = selector.select(&offerbook, &[], |_| true).unwrap();
+            *picks.entry(maker.address.to_string()).or_insert(0) += 1;
+        }
+        assert_eq!(picks.len(), 2);
+        assert!((50..150).contains(&picks["maker-a.onion:6102"]));
+        assert!((850..950).contains(&picks["maker-b.onion:6102"]));
+
+        // Excluded bonds and filtered offers are skipped.
+        let exclude = [bond_outpoint(&maker_b)];
+        assert_eq!(
+            selector.select(&offerbook, &exclude, |_| true),
+            Some(&maker_a)
+        );
+        assert_eq!(
+            selector.select(&offerbook, &[], |offer| offer.address != maker_b.address),
+            Some(&maker_a)
+        );
+        assert_eq!(
+            selector.select(&offerbook, &[bond_outpoint(&maker_a)], |offer| offer
+                .address
+                != maker_b.address),
+            None
+        );
+
+        // The same seed gives the same selections.
+        let select_all = |seed| {
+            let mut selector = MakerSelector::from_seed(seed);
+            (0..20)
+                .map(|_| {
+                    selector
+                        .select(&offerbook, &[], |_| true)
+                        .unwrap()
+                        .address
+                        .clone()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(select_all(42), select_all(42));
+    }
+
+    #[test]
+    fn test_offerbook_bad_makers() {
+        let maker_a = offer_with_bond(0, "maker-a.onion:6102");
+        let maker_b = offer_with_bond(1, "maker-b.onion:6102");
+        let maker_c = offer_with_bond(2, "maker-c.onion:6102");
+
+        let mut offerbook = OfferBook::default();
+        offerbook.add_new_offer(&maker_a, Amount::from_sat(100));
+        offerbook.add_new_offer(&maker_b, Amount::from_sat(100));
+        offerbook.add_new_offer(&maker_c, Amount::from_sat(100));
+
+        assert!(offerbook.add_bad_maker(&maker_a));
+        assert!(!offerbook.add_bad_maker(&maker_a));
+        assert!(offerbook.add_timed_out_maker(&maker_b));
+
+        // An invalid offer claiming maker c's bond doesn't affect maker c.
+        let impostor = offer_with_bond(2, "impostor.onion:6102");
+        assert!(offerbook.add_unverified_offer(&impostor));
+
+        assert_eq!(offerbook.all_good_makers(), vec![&maker_c]);
+        assert_eq!(
+            offerbook.get_bad_makers(),
+            vec![&maker_a, &maker_b, &impostor]
+        );
+
+        let record_a = offerbook.get_maker(&bond_outpoint(&maker_a)).unwrap();
+        assert!(record_a.banned);
+        assert_eq!(record_a.failures, 2);
+        assert_eq!(record_a.score(), 0.0);
+    }
+
+    #[test]
+    fn test_offerbook_persistence() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("offerbook.dat");
+
+        let maker_a = offer_with_bond(0, "maker-a.onion:6102");
+        let maker_b = offer_with_bond(1, "maker-b.onion:6102");
+        let maker_c = offer_with_bond(2, "maker-c.onion:6102");
+
+        let mut offerbook = OfferBook::default();
+        offerbook.add_new_offer(&maker_a, Amount::from_sat(100));
+        offerbook.add_new_offer(&maker_b, Amount::from_sat(100));
+        offerbook.add_new_offer(&maker_c, Amount::from_sat(100));
+        offerbook.record_success(&maker_a);
+        offerbook.add_bad_maker(&maker_b);
+        offerbook.add_timed_out_maker(&maker_c);
+        offerbook.write_to_disk(&path).unwrap();
+
+        let offerbook = OfferBook::read_from_disk(&path).unwrap();
+
+        // History and bans persist, timeout exclusions only last for a session.
+        assert_eq!(offerbook.all_good_makers(), vec![&maker_a, &maker_c]);
+        assert_eq!(offerbook.get_bad_makers(), vec![&maker_b]);
+        let record_a = offerbook.get_maker(&bond_outpoint(&maker_a)).unwrap();
+        let record_c = offerbook.get_maker(&bond_outpoint(&maker_c)).unwrap();
+        assert_eq!(record_a.successes, 1);
+        assert_eq!(record_c.timeouts, 1);
+    }
+
+    #[test]
+    fn test_offerbook_failed_makers() {
+        let maker_a = offer_with_bond(0, "maker-a.onion:6102");
+        let maker_b = offer_with_bond(1, "maker-b.onion:6102");
+
+        let mut offerbook = OfferBook::default();
+        offerbook.add_new_offer(&maker_a, Amount::from_sat(100));
+        offerbook.add_new_offer(&maker_b, Amount::from_sat(100));
+
+        // Network failures don't ban the maker.
+        let timed_out = TakerError::Net(NetError::ReadDeadlineExceeded);
+        assert!(!timed_out.is_protocol_violation());
+        assert!(offerbook.add_failed_maker(&maker_a, &timed_out));
+        offerbook.add_failed_maker(&maker_a, &TakerError::Net(NetError::ReachedEOF));
+
+        // Wrong messages do.
+        let wrong_message = TakerError::from(ProtocolError::General("wrong message"));
+        assert!(wrong_message.is_protocol_violation());
+        assert!(offerbook.add_failed_maker(&maker_b, &wrong_message));
+
+        let record_a = offerbook.get_maker(&bond_outpoint(&maker_a)).unwrap();
+        let record_b = offerbook.get_maker(&bond_outpoint(&maker_b)).unwrap();
+        assert!(!record_a.banned);
+        assert_eq!(record_a.timeouts, 2);
+        assert!(record_b.banned);
+
+        // Timeouts decay with completed swaps.
+        offerbook.record_success(&maker_a);
+        let record_a = offerbook.get_maker(&bond_outpoint(&maker_a)).unwrap();
+        assert_eq!(record_a.timeouts, 1);
+        offerbook.record_success(&maker_a);
+        let record_a = offerbook.get_maker(&bond_outpoint(&maker_a)).unwrap();
+        assert_eq!(record_a.timeouts, 0);
+        assert_eq!(record_a.reliability(), 1.0);
+    }
+}
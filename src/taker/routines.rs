@@ -15,21 +15,23 @@ use crate::{
     protocol::{
         contract::{
             calculate_coinswap_fee, create_contract_redeemscript, find_funding_output_index,
-            validate_contract_tx,
+            validate_contract_tx, SUPPORTED_CONTRACT_TYPES,
         },
         error::ProtocolError,
         messages::{
-            ContractSigsAsRecvrAndSender, ContractSigsForRecvr, ContractSigsForSender,
-            ContractTxInfoForRecvr, ContractTxInfoForSender, FundingTxInfo, GiveOffer,
-            HashPreimage, MakerToTakerMessage, NextHopInfo, Offer, Preimage, PrivKeyHandover,
-            ProofOfFunding, ReqContractSigsForRecvr, ReqContractSigsForSender, TakerHello,
-            TakerToMakerMessage,
+            negotiate_version, ContractSigsAsRecvrAndSender, ContractSigsForRecvr,
+            ContractSigsForSender, ContractTxInfoForRecvr, ContractTxInfoForSender, FundingTxInfo,
+            GiveOffer, HashPreimage, MakerToTakerMessage, NegotiatedProtocol, NextHopInfo, Offer,
+            Preimage, PrivKeyHandover, ProofOfFunding, RefundSigs, RefundTxInfo, RefundTxs,
+            ReqContractSigsForRecvr, ReqContractSigsForSender, ReqRefundSigs, ReqRefundTxs,
+            TakerHello, TakerToMakerMessage, UtxoOwnershipProof, CONNECTION_POW_BITS,
+            PROTOCOL_VERSION_MAX, PROTOCOL_VERSION_MIN, SUPPORTED_FEATURES,
         },
+        noise::NoiseStream,
         Hash160,
     },
-    taker::api::MINER_FEE,
-    utill::{read_message, send_message, ConnectionType},
-    wallet::WalletError,
+    utill::ConnectionType,
+    wallet::{calculate_fee, funding_tx_vsize, WalletError},
 };
 use bitcoin::{secp256k1::SecretKey, Amount, PublicKey, ScriptBuf, Transaction, relative::LockTime};
 
@@ -61,34 +63,35 @@ pub(crate) struct ContractsInfo {
 }
 
 /// Make a handshake with a maker.
-/// Ensures that the Maker is alive and responding.
-///
-// In future, handshake can be used to find protocol compatibility across multiple versions.
-pub(crate) fn handshake_maker(socket: &mut TcpStream) -> Result<(), TakerError> {
-    send_message(
-        socket,
-        &TakerToMakerMessage::TakerHello(TakerHello {
-            protocol_version_min: 1,
-            protocol_version_max: 1,
-        }),
-    )?;
-    let msg_bytes = read_message(socket)?;
+/// Ensures that the Maker is alive and responding, and returns the protocol version, features and
+/// contract type agreed with it.
+pub(crate) fn handshake_maker(socket: &mut NoiseStream) -> Result<NegotiatedProtocol, TakerError> {
+    socket.send_message(&TakerToMakerMessage::TakerHello(TakerHello {
+        protocol_version_min: PROTOCOL_VERSION_MIN,
+        protocol_version_max: PROTOCOL_VERSION_MAX,
+        contract_types: SUPPORTED_CONTRACT_TYPES.to_vec(),
+        features: SUPPORTED_FEATURES,
+        pow_nonce: socket.solve_pow(CONNECTION_POW_BITS),
+    }))?;
+    let msg_bytes = socket.read_message()?;
     let msg: MakerToTakerMessage = serde_cbor::from_slice(&msg_bytes)?;
 
-    // Check that protocol version is always 1.
+    // Check that we share a protocol version, and the contract type is one we offered.
     match msg {
         MakerToTakerMessage::MakerHello(m) => {
-            if m.protocol_version_max == 1 && m.protocol_version_min == 1 {
-                Ok(())
-            } else {
+            let version = negotiate_version(m.protocol_version_min, m.protocol_version_max)?;
+            if !SUPPORTED_CONTRACT_TYPES.contains(&m.contract_type) {
                 Err(ProtocolError::WrongMessage {
-                    expected: "Only protocol version 1 is allowed".to_string(),
-                    received: format!(
-                        "min/max version  = {}/{}",
-                        m.protocol_version_min, m.protocol_version_max
-                    ),
+                    expected: format!("One of contract types {:?}", SUPPORTED_CONTRACT_TYPES),
+                    received: format!("{:?}", m.contract_type),
                 }
                 .into())
+            } else {
+                Ok(NegotiatedProtocol {
+                    version,
+                    features: SUPPORTED_FEATURES.intersection(m.features),
+                    contract_type: m.contract_type,
+                })
             }
         }
         any => Err((ProtocolError::WrongMessage {
@@ -100,12 +103,15 @@ pub(crate) fn handshake_maker(socket: &mut TcpStream) -> Result<(), TakerError>
 }
 
 /// Request signatures for sender side of the hop. Attempt once.
+///
+/// `prove_utxos` makes the [UtxoOwnershipProof]s for the handshake hash of the connection.
 pub(crate) fn req_sigs_for_sender_once<S: SwapCoin>(
-    socket: &mut TcpStream,
+    socket: &mut NoiseStream,
     outgoing_swapcoins: &[S],
     maker_multisig_nonces: &[SecretKey],
     maker_hashlock_nonces: &[SecretKey],
     locktime: LockTime,
+    prove_utxos: &impl Fn(&[u8; 32]) -> Result<Vec<UtxoOwnershipProof>, WalletError>,
 ) -> Result<ContractSigsForSender, TakerError> {
     handshake_maker(socket)?;
     log::info!(
@@ -131,16 +137,16 @@ pub(crate) fn req_sigs_for_sender_once<S: SwapCoin>(
         )
         .collect::<Result<Vec<ContractTxInfoForSender>, WalletError>>()?;
 
-    send_message(
-        socket,
-        &TakerToMakerMessage::ReqContractSigsForSender(ReqContractSigsForSender {
+    socket.send_message(&TakerToMakerMessage::ReqContractSigsForSender(
+        ReqContractSigsForSender {
             txs_info,
             hashvalue: outgoing_swapcoins[0].get_hashvalue()?,
             locktime,
-        }),
-    )?;
+            utxo_proofs: prove_utxos(&socket.handshake_hash())?,
+        },
+    ))?;
 
-    let msg_bytes = read_message(socket)?;
+    let msg_bytes = socket.read_message()?;
     let msg: MakerToTakerMessage = serde_cbor::from_slice(&msg_bytes)?;
     let contract_sigs_for_sender = match msg {
         MakerToTakerMessage::RespContractSigsForSender(m) => {
@@ -179,7 +185,7 @@ pub(crate) fn req_sigs_for_sender_once<S: SwapCoin>(
 
 /// Request signatures for receiver side of the hop. Attempt once.
 pub(crate) fn req_sigs_for_recvr_once<S: SwapCoin>(
-    socket: &mut TcpStream,
+    socket: &mut NoiseStream,
     incoming_swapcoins: &[S],
     receivers_contract_txes: &[Transaction],
 ) -> Result<ContractSigsForRecvr, TakerError> {
@@ -194,12 +200,11 @@ pub(crate) fn req_sigs_for_recvr_once<S: SwapCoin>(
         })
         .collect::<Vec<ContractTxInfoForRecvr>>();
 
-    send_message(
-        socket,
-        &TakerToMakerMessage::ReqContractSigsForRecvr(ReqContractSigsForRecvr { txs: txs_info }),
-    )?;
+    socket.send_message(&TakerToMakerMessage::ReqContractSigsForRecvr(
+        ReqContractSigsForRecvr { txs: txs_info },
+    ))?;
 
-    let msg_bytes = read_message(socket)?;
+    let msg_bytes = socket.read_message()?;
     let msg: MakerToTakerMessage = serde_cbor::from_slice(&msg_bytes)?;
     let contract_sigs_for_recvr = match msg {
         MakerToTakerMessage::RespContractSigsForRecvr(m) => {
@@ -245,6 +250,7 @@ pub(crate) struct ThisMakerInfo {
     pub(crate) funding_tx_infos: Vec<FundingTxInfo>,
     pub(crate) this_maker_contract_txs: Vec<Transaction>,
     pub this_maker_refund_locktime: LockTime,
+    pub(crate) contract_feerate: f64,
 }
 
 // Type for information related to the next peer // why not next Maker?
@@ -256,7 +262,7 @@ pub struct NextMakerInfo {
 
 /// [Internal] Send a Proof funding to the maker and init next hop.
 pub(crate) fn send_proof_of_funding_and_init_next_hop(
-    socket: &mut TcpStream,
+    socket: &mut NoiseStream,
     tmi: ThisMakerInfo,
     npi: NextMakerInfo,
     hashvalue: Hash160,
@@ -279,14 +285,14 @@ pub(crate) fn send_proof_of_funding_and_init_next_hop(
         confirmed_funding_txes: tmi.funding_tx_infos.clone(),
         next_coinswap_info,
         refund_locktime: tmi.this_maker_refund_locktime,
-        contract_feerate: MINER_FEE,
+        contract_feerate: tmi.contract_feerate,
         id,
     });
 
-    send_message(socket, &pof_msg)?;
+    socket.send_message(&pof_msg)?;
 
     // Recv ContractSigsAsRecvrAndSender.
-    let msg_bytes = read_message(socket)?;
+    let msg_bytes = socket.read_message()?;
     let msg: MakerToTakerMessage = serde_cbor::from_slice(&msg_bytes)?;
     let contract_sigs_as_recvr_and_sender = match msg {
         MakerToTakerMessage::ReqContractSigsAsRecvrAndSender(m) => {
@@ -346,7 +352,10 @@ pub(crate) fn send_proof_of_funding_and_init_next_hop(
         tmi.this_maker.offer.time_relative_fee_pct,
     );
 
-    let miner_fees_paid_by_taker = (tmi.funding_tx_infos.len() as u64) * MINER_FEE;
+    // The Maker charges the miner fees of its funding txs at our feerate.
+    // The next hop can be split into a different number of txs than this one.
+    let miner_fees_paid_by_taker = (npi.next_peer_multisig_pubkeys.len() as u64)
+        * calculate_fee(tmi.contract_feerate, funding_tx_vsize()).to_sat();
     let calculated_next_amount = this_amount - coinswap_fees.to_sat() - miner_fees_paid_by_taker;
 
     if Amount::from_sat(calculated_next_amount) != next_amount {
@@ -407,7 +416,7 @@ pub(crate) fn send_proof_of_funding_and_init_next_hop(
 
 /// Send hash preimage via the writer and read the response.
 pub(crate) fn send_hash_preimage_and_get_private_keys(
-    socket: &mut TcpStream,
+    socket: &mut NoiseStream,
     senders_multisig_redeemscripts: &[ScriptBuf],
     receivers_multisig_redeemscripts: &[ScriptBuf],
     preimage: &Preimage,
@@ -418,9 +427,9 @@ pub(crate) fn send_hash_preimage_and_get_private_keys(
         preimage: *preimage,
     });
 
-    send_message(socket, &hash_preimage_msg)?;
+    socket.send_message(&hash_preimage_msg)?;
 
-    let msg_bytes = read_message(socket)?;
+    let msg_bytes = socket.read_message()?;
     let msg: MakerToTakerMessage = serde_cbor::from_slice(&msg_bytes)?;
     let privkey_handover = match msg {
         MakerToTakerMessage::RespPrivKeyHandover(m) => {
@@ -446,13 +455,94 @@ pub(crate) fn send_hash_preimage_and_get_private_keys(
     Ok(privkey_handover)
 }
 
+/// Request the signed refund txs of the hop sent by a maker, to abort a swap. Attempt once.
+pub(crate) fn req_refund_txs_once(
+    socket: &mut NoiseStream,
+    id: &str,
+) -> Result<RefundTxs, TakerError> {
+    handshake_maker(socket)?;
+    log::info!("===> Sending ReqRefundTxs to {}", socket.peer_addr()?);
+
+    socket.send_message(&TakerToMakerMessage::ReqRefundTxs(ReqRefundTxs {
+        id: id.to_string(),
+    }))?;
+
+    let msg_bytes = socket.read_message()?;
+    let msg: MakerToTakerMessage = serde_cbor::from_slice(&msg_bytes)?;
+    let refund_txs = match msg {
+        MakerToTakerMessage::RespRefundTxs(m) => {
+            if m.sigs.len() != m.refunds.len() {
+                return Err((ProtocolError::WrongNumOfSigs {
+                    expected: m.refunds.len(),
+                    received: m.sigs.len(),
+                })
+                .into());
+            } else {
+                m
+            }
+        }
+        any => {
+            return Err((ProtocolError::WrongMessage {
+                expected: "RespRefundTxs".to_string(),
+                received: format!("{}", any),
+            })
+            .into());
+        }
+    };
+
+    log::info!("<=== Received RefundTxs from {}", socket.peer_addr()?);
+    Ok(refund_txs)
+}
+
+/// Request the signatures of a maker on the refund txs of the hop it receives, to abort a swap.
+/// Attempt once.
+pub(crate) fn req_refund_sigs_once(
+    socket: &mut NoiseStream,
+    id: &str,
+    refunds: &[RefundTxInfo],
+) -> Result<RefundSigs, TakerError> {
+    handshake_maker(socket)?;
+    log::info!("===> Sending ReqRefundSigs to {}", socket.peer_addr()?);
+
+    socket.send_message(&TakerToMakerMessage::ReqRefundSigs(ReqRefundSigs {
+        id: id.to_string(),
+        refunds: refunds.to_vec(),
+    }))?;
+
+    let msg_bytes = socket.read_message()?;
+    let msg: MakerToTakerMessage = serde_cbor::from_slice(&msg_bytes)?;
+    let refund_sigs = match msg {
+        MakerToTakerMessage::RespRefundSigs(m) => {
+            if m.sigs.len() != refunds.len() {
+                return Err((ProtocolError::WrongNumOfSigs {
+                    expected: refunds.len(),
+                    received: m.sigs.len(),
+                })
+                .into());
+            } else {
+                m
+            }
+        }
+        any => {
+            return Err((ProtocolError::WrongMessage {
+                expected: "RespRefundSigs".to_string(),
+                received: format!("{}", any),
+            })
+            .into());
+        }
+    };
+
+    log::info!("<=== Received RefundSigs from {}", socket.peer_addr()?);
+    Ok(refund_sigs)
+}
+
 fn download_maker_offer_attempt_once(
     addr: &MakerAddress,
     config: &TakerConfig,
-) -> Result<Offer, TakerError> {
+) -> Result<(Offer, NegotiatedProtocol), TakerError> {
     let maker_addr = addr.to_string();
     log::info!("Attempting to download Offer from {}", maker_addr);
-    let mut socket = match config.connection_type {
+    let socket = match config.connection_type {
         ConnectionType::CLEARNET => TcpStream::connect(&maker_addr)?,
         #[cfg(feature = "tor")]
         ConnectionType::TOR => Socks5Stream::connect(
@@ -465,11 +555,14 @@ fn download_maker_offer_attempt_once(
     socket.set_read_timeout(Some(Duration::from_secs(FIRST_CONNECT_ATTEMPT_TIMEOUT_SEC)))?;
     socket.set_write_timeout(Some(Duration::from_secs(FIRST_CONNECT_ATTEMPT_TIMEOUT_SEC)))?;
 
-    handshake_maker(&mut socket)?;
+    // The maker's bond isn't known before its offer, so check the maker's key against it below.
+    let mut socket = NoiseStream::connect(socket, None)?;
+
+    let protocol = handshake_maker(&mut socket)?;
 
-    send_message(&mut socket, &TakerToMakerMessage::ReqGiveOffer(GiveOffer))?;
+    socket.send_message(&TakerToMakerMessage::ReqGiveOffer(GiveOffer))?;
 
-    let msg_bytes = read_message(&mut socket)?;
+    let msg_bytes = socket.read_message()?;
     let msg: MakerToTakerMessage = serde_cbor::from_slice(&msg_bytes)?;
     let offer = match msg {
         MakerToTakerMessage::RespOffer(offer) => offer,
@@ -482,9 +575,22 @@ fn download_maker_offer_attempt_once(
         }
     };
 
-    log::info!("Got offer from : {} | {:?}", maker_addr, offer);
+    if socket.remote_static() != Some(offer.fidelity.bond.pubkey.inner) {
+        return Err(ProtocolError::WrongMessage {
+            expected: "Offer with a fidelity bond of the connected maker".to_string(),
+            received: format!("Offer with bond key {}", offer.fidelity.bond.pubkey),
+        }
+        .into());
+    }
+
+    log::info!(
+        "Got offer from : {} | {:?} | {:?}",
+        maker_addr,
+        offer,
+        protocol
+    );
 
-    Ok(*offer)
+    Ok((*offer, protocol))
 }
 
 pub(crate) fn download_maker_offer(
@@ -496,7 +602,13 @@ pub(crate) fn download_maker_offer(
     loop {
         ii += 1;
         match download_maker_offer_attempt_once(&address, &config) {
-            Ok(offer) => return Some(OfferAndAddress { offer, address }),
+            Ok((offer, protocol)) => {
+                return Some(OfferAndAddress {
+                    offer,
+                    address,
+                    protocol,
+                })
+            }
             Err(e) => {
                 if ii <= FIRST_CONNECT_ATTEMPTS {
                     log::warn!(
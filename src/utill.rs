@@ -16,7 +16,7 @@ use log4rs::{
 use serde::{Deserialize, Serialize};
 use std::{
     env, fmt,
-    io::{BufReader, BufWriter, ErrorKind, Read},
+    io::{BufWriter, ErrorKind, Read},
     net::TcpStream,
     path::{Path, PathBuf},
     str::FromStr,
@@ -28,7 +28,7 @@ use std::{
     fs::{self, File},
     io::{self, BufRead, Write},
     thread,
-    time::Duration,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
@@ -269,8 +269,13 @@ pub fn send_message(
     socket_writer: &mut TcpStream,
     message: &impl serde::Serialize,
 ) -> Result<(), NetError> {
-    let mut writer = BufWriter::new(socket_writer);
     let msg_bytes = serde_cbor::ser::to_vec(message)?;
+    send_frame(socket_writer, &msg_bytes)
+}
+
+/// Sends raw bytes, prefixed with their length, through the stream.
+pub(crate) fn send_frame(socket_writer: &mut TcpStream, msg_bytes: &[u8]) -> Result<(), NetError> {
+    let mut writer = BufWriter::new(socket_writer);
     let msg_len = (msg_bytes.len() as u32).to_be_bytes();
     let mut to_send = Vec::with_capacity(msg_bytes.len() + msg_len.len());
     to_send.extend(msg_len);
@@ -280,30 +285,103 @@ pub fn send_message(
     Ok(())
 }
 
-/// Reads a response byte_array from a given stream.
-/// Response can be any length-appended data, where the first byte is the length of the actual message.
+/// Limits on reading a single message from a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageLimits {
+    /// Largest accepted message, in bytes.
+    pub max_size: u32,
+    /// Time allowed for a whole message to arrive, from its first byte.
+    pub read_timeout: Duration,
+}
+
+impl Default for MessageLimits {
+    fn default() -> Self {
+        Self {
+            max_size: 16 * 1024 * 1024,
+            read_timeout: NET_TIMEOUT,
+        }
+    }
+}
+
+/// Reads a response byte_array from a given stream, within the default [MessageLimits].
+/// Response can be any length-appended data, where the first 4 bytes are the length of the actual message.
 pub fn read_message(reader: &mut TcpStream) -> Result<Vec<u8>, NetError> {
-    let mut reader = BufReader::new(reader);
+    read_message_with_limits(reader, &MessageLimits::default())
+}
+
+/// Reads a length-appended message from a given stream, within the given limits.
+///
+/// Waiting for a message to start is bounded by the stream's own read timeout, or by `limits.read_timeout`
+/// if the stream has none, so an idle peer can't hold the reader forever ([NetError::IdleTimeout]). Once it
+/// started, the whole message must arrive within `limits.read_timeout`, so a peer can't hold the reader by
+/// trickling bytes ([NetError::ReadDeadlineExceeded]).
+/// The length is checked before anything is allocated for the message.
+pub fn read_message_with_limits(
+    reader: &mut TcpStream,
+    limits: &MessageLimits,
+) -> Result<Vec<u8>, NetError> {
+    let idle_timeout = reader.read_timeout()?;
+    let message = read_frame(reader, limits, idle_timeout.unwrap_or(limits.read_timeout));
+    reader.set_read_timeout(idle_timeout)?;
+    message
+}
+
+fn read_frame(
+    reader: &mut TcpStream,
+    limits: &MessageLimits,
+    idle_timeout: Duration,
+) -> Result<Vec<u8>, NetError> {
     // length of incoming data
     let mut len_buff = [0u8; 4];
-    reader.read_exact(&mut len_buff)?; // This can give UnexpectedEOF error if theres no data to read
+    read_until_deadline(reader, &mut len_buff[..1], Instant::now() + idle_timeout).map_err(
+        |e| match e {
+            NetError::ReadDeadlineExceeded => NetError::IdleTimeout,
+            e => e,
+        },
+    )?;
+    let deadline = Instant::now() + limits.read_timeout;
+    read_until_deadline(reader, &mut len_buff[1..], deadline)?;
     let length = u32::from_be_bytes(len_buff);
+    if length > limits.max_size {
+        return Err(NetError::MessageTooLarge {
+            size: length,
+            max: limits.max_size,
+        });
+    }
 
     // the actual data
     let mut buffer = vec![0; length as usize];
-    let mut total_read = 0;
+    read_until_deadline(reader, &mut buffer, deadline)?;
+    Ok(buffer)
+}
 
-    while total_read < length as usize {
+fn read_until_deadline(
+    reader: &mut TcpStream,
+    buffer: &mut [u8],
+    deadline: Instant,
+) -> Result<(), NetError> {
+    let mut total_read = 0;
+    while total_read < buffer.len() {
+        let remaining = deadline
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or(NetError::ReadDeadlineExceeded)?;
+        reader.set_read_timeout(Some(remaining))?;
         match reader.read(&mut buffer[total_read..]) {
             Ok(0) => return Err(NetError::ReachedEOF), // Connection closed
             Ok(n) => total_read += n,
-            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => {
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted
+                ) =>
+            {
                 continue
             }
             Err(e) => return Err(e.into()),
         }
     }
-    Ok(buffer)
+    Ok(())
 }
 
 /// Apply the maker's privatekey to swapcoins, and check it's the correct privkey for corresponding pubkey.
@@ -431,6 +509,126 @@ pub(crate) fn parse_field<T: std::str::FromStr>(value: Option<&String>, default:
         .unwrap_or(default)
 }
 
+/// Number of rotating backups kept for the wallet and offerbook files.
+pub(crate) const FILE_BACKUP_COUNT: usize = 3;
+
+/// Path of the `index`th backup of a data file. Backup `1` is the newest.
+fn backup_path(path: &Path, index: usize) -> PathBuf {
+    let mut file_name = path.file_name().expect("file name expected").to_os_string();
+    file_name.push(format!(".bak{}", index));
+    path.with_file_name(file_name)
+}
+
+/// Paths of all the backups of a data file, newest first. The files may not exist.
+pub(crate) fn backup_paths(path: &Path) -> impl Iterator<Item = PathBuf> + '_ {
+    (1..=FILE_BACKUP_COUNT).map(move |index| backup_path(path, index))
+}
+
+/// Crash-safe write of `data` into the file at `path`.
+///
+/// The previous content of `path` is kept as the newest of [`FILE_BACKUP_COUNT`] rotating backups,
+/// then the file is replaced with [`replace_atomic`].
+pub(crate) fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    if path.exists() {
+        for index in (1..FILE_BACKUP_COUNT).rev() {
+            let backup = backup_path(path, index);
+            if backup.exists() {
+                fs::rename(&backup, backup_path(path, index + 1))?;
+            }
+        }
+        fs::copy(path, backup_path(path, 1))?;
+    }
+
+    replace_atomic(path, data)
+}
+
+/// Crash-safe write of `data` into the file at `path`, without keeping a backup.
+///
+/// The data is first written to a temporary file and fsynced, which is then atomically renamed over `path`.
+/// A crash at any point leaves either the old or the new file in place, never a partially written one.
+pub(crate) fn replace_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut tmp_name = path.file_name().expect("file name expected").to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    let mut tmp_file = File::create(&tmp_path)?;
+    tmp_file.write_all(data)?;
+    tmp_file.sync_all()?;
+    drop(tmp_file);
+
+    fs::rename(&tmp_path, path)?;
+
+    // Sync the directory, so the rename itself survives a crash.
+    #[cfg(unix)]
+    {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            File::open(parent)?.sync_all()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads and parses a file written with [`write_atomic`].
+///
+/// If the file is missing or fails to parse, its backups are tried from the newest to the oldest.
+/// Errors with the failure of the main file, if no backup is usable either.
+pub(crate) fn read_with_backups<T, E: From<io::Error>>(
+    path: &Path,
+    parse: impl Fn(&[u8]) -> Result<T, E>,
+) -> Result<T, E> {
+    read_with_backups_unless(path, parse, |_| false)
+}
+
+/// Like [`read_with_backups`], but the backups are not tried if the main file fails with an error
+/// for which `is_fatal` holds. E.g. a wrong passphrase must not fall back to an older backup, which
+/// may not be encrypted with the same passphrase, or at all.
+pub(crate) fn read_with_backups_unless<T, E: From<io::Error>>(
+    path: &Path,
+    parse: impl Fn(&[u8]) -> Result<T, E>,
+    is_fatal: impl Fn(&E) -> bool,
+) -> Result<T, E> {
+    let read_and_parse = |path: &Path| parse(&fs::read(path)?);
+
+    let err = match read_and_parse(path) {
+        Ok(value) => return Ok(value),
+        Err(e) if is_fatal(&e) => return Err(e),
+        Err(e) => e,
+    };
+
+    for backup in backup_paths(path) {
+        if let Ok(value) = read_and_parse(&backup) {
+            log::warn!(
+                "Failed to read {:?}, recovered from backup {:?}",
+                path,
+                backup
+            );
+            return Ok(value);
+        }
+    }
+
+    Err(err)
+}
+
+/// Removes a file written with [`write_atomic`], along with its backups. Missing files are ignored.
+pub(crate) fn remove_with_backups(path: &Path) -> io::Result<()> {
+    for path in std::iter::once(path.to_path_buf()).chain(backup_paths(path)) {
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Current time as seconds since the unix epoch.
+pub(crate) fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
 /// Function to check if tor log contains a pattern
 pub(crate) fn monitor_log_for_completion(log_file: &Path, pattern: &str) -> io::Result<()> {
     // TODO: Make this logic work for existing file with previous logs.
@@ -529,6 +727,72 @@ pub(crate) fn compute_checksum(descriptor: &str) -> Result<String, WalletError>
     Ok(checksum_chars)
 }
 
+/// Environment variable holding the wallet passphrase, for non-interactive use of the apps.
+pub const PASSPHRASE_ENV_VAR: &str = "COINSWAP_WALLET_PASSPHRASE";
+
+/// Reads the wallet passphrase from [`PASSPHRASE_ENV_VAR`] if it's set, else prompts for it on the
+/// terminal if `prompt` is set. Returns `None` if neither gives a non-empty passphrase.
+///
+/// An empty passphrase leaves the wallet file unencrypted. It is warned about if it's set in the
+/// environment, and has to be confirmed if it's typed in.
+///
+/// The passphrase is never taken as a command line argument, where other users could see it with `ps`.
+pub fn read_passphrase(prompt: bool) -> io::Result<Option<String>> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV_VAR) {
+        if passphrase.is_empty() {
+            eprintln!(
+                "Warning: {} is empty, the wallet file is not encrypted.",
+                PASSPHRASE_ENV_VAR
+            );
+        }
+        return Ok(Some(passphrase).filter(|p| !p.is_empty()));
+    }
+    if !prompt {
+        return Ok(None);
+    }
+
+    let passphrase = prompt_hidden("Wallet passphrase: ")?;
+    if passphrase.is_empty() {
+        eprint!("Empty passphrase, the wallet file will not be encrypted. Continue? [y/N]: ");
+        io::stderr().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        if !answer.trim().eq_ignore_ascii_case("y") {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "Empty wallet passphrase",
+            ));
+        }
+        return Ok(None);
+    }
+    Ok(Some(passphrase))
+}
+
+/// Prompts for a line on the terminal, without echoing what is typed if stdin is a terminal.
+fn prompt_hidden(prompt: &str) -> io::Result<String> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    // Hide the typed passphrase, if stdin is a terminal.
+    #[cfg(unix)]
+    let echo_off = std::process::Command::new("stty")
+        .arg("-echo")
+        .status()
+        .is_ok_and(|status| status.success());
+
+    let mut passphrase = String::new();
+    let read = io::stdin().read_line(&mut passphrase);
+
+    #[cfg(unix)]
+    if echo_off {
+        let _ = std::process::Command::new("stty").arg("echo").status();
+        eprintln!();
+    }
+
+    read?;
+    Ok(passphrase.trim_end_matches(['\r', '\n']).to_string())
+}
+
 /// Parse the proxy (Socket:Port) argument from the cli input.
 pub fn parse_proxy_auth(s: &str) -> Result<(String, String), NetError> {
     let parts: Vec<_> = s.split(':').collect();
@@ -637,7 +901,7 @@ mod tests {
         PubkeyHash,
     };
 
-    use crate::protocol::messages::{MakerHello, MakerToTakerMessage};
+    use crate::protocol::messages::{ContractType, Features, MakerHello, MakerToTakerMessage};
 
     use super::*;
 
@@ -649,6 +913,8 @@ mod tests {
         let message = MakerToTakerMessage::MakerHello(MakerHello {
             protocol_version_min: 1,
             protocol_version_max: 100,
+            contract_type: ContractType::P2wsh,
+            features: Features::empty(),
         });
 
         thread::spawn(move || {
@@ -670,6 +936,137 @@ mod tests {
         send_message(&mut stream, &message).unwrap();
     }
 
+    #[test]
+    fn test_read_message_limits() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let limits = MessageLimits {
+            max_size: 8,
+            read_timeout: Duration::from_millis(200),
+        };
+
+        let peer = thread::spawn(move || {
+            // A message within the limits.
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(&[0, 0, 0, 3, 1, 2, 3]).unwrap();
+            // An oversized message, announcing 4 GiB.
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(&[0xff; 4]).unwrap();
+            // A message trickling in slower than the deadline.
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(&[0, 0, 0, 4, 1]).unwrap();
+            // An idle peer.
+            let _idle = TcpStream::connect(address).unwrap();
+            thread::sleep(Duration::from_millis(500));
+        });
+
+        let (mut socket, _) = listener.accept().unwrap();
+        assert_eq!(
+            read_message_with_limits(&mut socket, &limits).unwrap(),
+            vec![1, 2, 3]
+        );
+
+        let (mut socket, _) = listener.accept().unwrap();
+        assert!(matches!(
+            read_message_with_limits(&mut socket, &limits),
+            Err(NetError::MessageTooLarge {
+                size: u32::MAX,
+                max: 8
+            })
+        ));
+
+        let (mut socket, _) = listener.accept().unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        assert!(matches!(
+            read_message_with_limits(&mut socket, &limits),
+            Err(NetError::ReadDeadlineExceeded)
+        ));
+        // The stream's own timeout is restored.
+        assert_eq!(
+            socket.read_timeout().unwrap(),
+            Some(Duration::from_secs(10))
+        );
+
+        // Without a timeout of its own, the stream waits for a message within the limits.
+        let (mut socket, _) = listener.accept().unwrap();
+        assert!(matches!(
+            read_message_with_limits(&mut socket, &limits),
+            Err(NetError::IdleTimeout)
+        ));
+        assert_eq!(socket.read_timeout().unwrap(), None);
+
+        peer.join().unwrap();
+    }
+
+    #[test]
+    fn test_write_atomic_with_backups() {
+        let temp_dir = bitcoind::tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("data.dat");
+        let parse = |bytes: &[u8]| -> io::Result<Vec<u8>> {
+            if bytes.starts_with(b"data") {
+                Ok(bytes.to_vec())
+            } else {
+                Err(io::Error::new(ErrorKind::InvalidData, "corrupted"))
+            }
+        };
+
+        for i in 0..5 {
+            write_atomic(&path, format!("data{}", i).as_bytes()).unwrap();
+        }
+        assert_eq!(read_with_backups(&path, parse).unwrap(), b"data4");
+
+        // Only the latest backups are kept, newest first.
+        for index in 1..=FILE_BACKUP_COUNT {
+            let expected = format!("data{}", 4 - index);
+            assert_eq!(
+                fs::read(backup_path(&path, index)).unwrap(),
+                expected.as_bytes()
+            );
+        }
+        assert!(!backup_path(&path, FILE_BACKUP_COUNT + 1).exists());
+
+        // A corrupted file falls back to the newest valid backup.
+        fs::write(&path, b"garbage").unwrap();
+        fs::write(backup_path(&path, 1), b"").unwrap();
+        assert_eq!(read_with_backups(&path, parse).unwrap(), b"data2");
+
+        // The error of the main file is returned if no backup is usable.
+        for index in 1..=FILE_BACKUP_COUNT {
+            fs::remove_file(backup_path(&path, index)).unwrap();
+        }
+        let err = read_with_backups(&path, parse).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // Fatal errors of the main file don't fall back to the backups.
+        write_atomic(&path, b"data5").unwrap();
+        write_atomic(&path, b"data6").unwrap();
+        fs::write(&path, b"garbage").unwrap();
+        let is_fatal = |e: &io::Error| e.kind() == ErrorKind::InvalidData;
+        let err = read_with_backups_unless(&path, parse, is_fatal).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(read_with_backups(&path, parse).unwrap(), b"data5");
+    }
+
+    #[test]
+    fn test_remove_with_backups() {
+        let temp_dir = bitcoind::tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("data.dat");
+        for i in 0..3 {
+            write_atomic(&path, format!("data{}", i).as_bytes()).unwrap();
+        }
+        assert!(backup_path(&path, 2).exists());
+
+        remove_with_backups(&path).unwrap();
+        assert!(!path.exists());
+        assert!(!backup_path(&path, 1).exists());
+        assert!(!backup_path(&path, 2).exists());
+
+        // Removing again is fine.
+        remove_with_backups(&path).unwrap();
+    }
+
     #[test]
     fn test_redeemscript_to_scriptpubkey_custom() {
         // Create a custom puzzle script
@@ -5,7 +5,7 @@
 
 use std::{convert::TryFrom, path::PathBuf, str::FromStr};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use bip39::Mnemonic;
 use bitcoin::{
@@ -20,17 +20,22 @@ use bitcoind::bitcoincore_rpc::{bitcoincore_rpc_json::ListUnspentResultEntry, Cl
 use std::path::Path;
 
 use crate::{
-    protocol::contract,
+    protocol::{contract, messages::UtxoOwnershipProof},
     utill::{
-        compute_checksum, generate_keypair, get_hd_path_from_descriptor,
+        backup_paths, compute_checksum, generate_keypair, get_hd_path_from_descriptor,
         redeemscript_to_scriptpubkey,
     },
 };
 
 use super::{
+    calculate_fee,
+    coin_control::{select_coins, CoinPool, CrossPoolPolicy},
+    coin_selection::CoinSelectionAlgorithm,
+    contract_tx_vsize,
     error::WalletError,
+    reservation::LiquidityLedger,
     rpc::RPCConfig,
-    storage::WalletStore,
+    storage::{KeyMaterial, WalletStore, WALLET_FILE_VERSION},
     swapcoin::{IncomingSwapCoin, OutgoingSwapCoin, SwapCoin, WalletSwapCoin},
 };
 
@@ -45,6 +50,14 @@ pub struct Wallet {
     pub(crate) rpc: Client,
     wallet_file_path: PathBuf,
     pub(crate) store: WalletStore,
+    /// Encryption key for the wallet file. `None` if the wallet is stored unencrypted.
+    key_material: Option<KeyMaterial>,
+    /// Utxos reserved for in-flight swaps. Not persisted, as unbroadcasted funding txs don't survive a restart.
+    pub(crate) reservations: LiquidityLedger,
+    /// How spends merging seed coins with swap coins are handled. Set from the Taker or Maker config.
+    pub(crate) cross_pool_policy: CrossPoolPolicy,
+    /// The coin selection algorithm. Set from the Taker or Maker config.
+    pub(crate) coin_selection: CoinSelectionAlgorithm,
 }
 
 /// Speicfy the keychain derivation path from [`HARDENDED_DERIVATION`]
@@ -53,13 +66,16 @@ pub struct Wallet {
 pub(crate) enum KeychainKind {
     External = 0isize,
     Internal,
+    /// Change of txs spending swap coins, kept apart from the seed coins.
+    SwapChange,
 }
 
 impl KeychainKind {
-    fn index_num(&self) -> u32 {
+    pub(super) fn index_num(&self) -> u32 {
         match self {
             Self::External => 0,
             Self::Internal => 1,
+            Self::SwapChange => 2,
         }
     }
 }
@@ -139,7 +155,13 @@ impl Wallet {
     ///
     /// The path should include the full path for a wallet file.
     /// If the wallet file doesn't exist it will create a new wallet file.
-    pub fn init(path: &Path, rpc_config: &RPCConfig) -> Result<Self, WalletError> {
+    ///
+    /// If a `passphrase` is provided, the wallet file will be encrypted with it.
+    pub fn init(
+        path: &Path,
+        rpc_config: &RPCConfig,
+        passphrase: Option<String>,
+    ) -> Result<Self, WalletError> {
         let rpc = Client::try_from(rpc_config)?;
         let network = rpc.get_blockchain_info()?.chain;
 
@@ -161,19 +183,137 @@ impl Wallet {
             .to_string();
 
         let wallet_birthday = rpc.get_block_count()?;
-        let store = WalletStore::init(file_name, path, network, master_key, Some(wallet_birthday))?;
+        let key_material = passphrase.as_deref().map(KeyMaterial::new);
+        let store = WalletStore::init(
+            file_name,
+            path,
+            network,
+            master_key,
+            Some(wallet_birthday),
+            key_material.as_ref(),
+        )?;
 
         Ok(Self {
             rpc,
             wallet_file_path: path.to_path_buf(),
             store,
+            key_material,
+            reservations: LiquidityLedger::default(),
+            cross_pool_policy: CrossPoolPolicy::default(),
+            coin_selection: CoinSelectionAlgorithm::default(),
         })
     }
 
+    /// Restore a wallet from its BIP39 mnemonic at a given path.
+    ///
+    /// Recreates the wallet file from the mnemonic words, rescans the blockchain from `birthday_height`
+    /// and recovers the fidelity bonds created with this seed. Spendable coins are found by the rescan.
+    /// Use a `birthday_height` at or before the wallet's creation height, or `0` to scan the whole chain.
+    ///
+    /// If a `passphrase` is provided, the restored wallet file will be encrypted with it.
+    /// Errors if a wallet file already exists at the path. The wallet file is only written once the
+    /// restore succeeded, so a failed restore can be retried.
+    pub fn restore(
+        path: &Path,
+        rpc_config: &RPCConfig,
+        mnemonic: &str,
+        passphrase: Option<String>,
+        birthday_height: u64,
+    ) -> Result<Self, WalletError> {
+        if path.exists() {
+            return Err(WalletError::General(format!(
+                "Wallet file already exists at {:?}",
+                path
+            )));
+        }
+
+        let rpc = Client::try_from(rpc_config)?;
+        let network = rpc.get_blockchain_info()?.chain;
+
+        let mnemonic = Mnemonic::parse(mnemonic)?;
+        let master_key = Xpriv::new_master(network, &mnemonic.to_entropy())?;
+
+        let file_name = path
+            .file_name()
+            .expect("file name expected")
+            .to_str()
+            .expect("expected")
+            .to_string();
+
+        // The wallet is saved while syncing. Keep it next to the final path until it's restored.
+        let mut restoring_name = path.file_name().expect("file name expected").to_os_string();
+        restoring_name.push(".restoring");
+        let restoring_path = path.with_file_name(restoring_name);
+
+        let key_material = passphrase.as_deref().map(KeyMaterial::new);
+        let store = WalletStore::init(
+            file_name,
+            &restoring_path,
+            network,
+            master_key,
+            Some(birthday_height),
+            key_material.as_ref(),
+        )?;
+
+        let mut wallet = Self {
+            rpc,
+            wallet_file_path: restoring_path.clone(),
+            store,
+            key_material,
+            reservations: LiquidityLedger::default(),
+            cross_pool_policy: CrossPoolPolicy::default(),
+            coin_selection: CoinSelectionAlgorithm::default(),
+        };
+
+        log::info!(
+            "Restoring wallet. Rescanning from block {}",
+            birthday_height
+        );
+        let restored = wallet.rescan_restored();
+
+        for file in std::iter::once(restoring_path.clone()).chain(backup_paths(&restoring_path)) {
+            if file.exists() {
+                std::fs::remove_file(file)?;
+            }
+        }
+        let recovered = restored?;
+        log::info!("Wallet restored. Recovered {} fidelity bonds.", recovered);
+
+        wallet.wallet_file_path = path.to_path_buf();
+        wallet.save_to_disk()?;
+        if let Some(key_material) = &wallet.key_material {
+            // Backups of an overwritten wallet of the same name must not stay readable.
+            WalletStore::encrypt_backups(path, key_material)?;
+        }
+        Ok(wallet)
+    }
+
+    /// Rescans the chain for a restored wallet and recovers its fidelity bonds. Returns the number of
+    /// recovered bonds.
+    fn rescan_restored(&mut self) -> Result<u32, WalletError> {
+        self.sync()?;
+
+        let recovered = self.recover_fidelity_bonds()?;
+        if recovered > 0 {
+            // Rescan again from birthday, to pick up the recovered bond utxos.
+            self.store.last_synced_height = None;
+            self.sync()?;
+        }
+        Ok(recovered)
+    }
+
     /// Load wallet data from file and connects to a core RPC.
     /// The core rpc wallet name, and wallet_id field in the file should match.
-    pub(crate) fn load(path: &Path, rpc_config: &RPCConfig) -> Result<Wallet, WalletError> {
-        let store = WalletStore::read_from_disk(path)?;
+    ///
+    /// An encrypted wallet file requires its `passphrase`, loading fails if it is missing or wrong.
+    /// If a `passphrase` is provided for an unencrypted wallet file, the file is encrypted in place.
+    pub(crate) fn load(
+        path: &Path,
+        rpc_config: &RPCConfig,
+        passphrase: Option<String>,
+    ) -> Result<Wallet, WalletError> {
+        let (store, key_material, file_version) =
+            WalletStore::read_from_disk(path, passphrase.as_deref())?;
         if rpc_config.wallet_name != store.file_name {
             return Err(WalletError::General(format!(
                 "Wallet name of database file and core missmatch, expected {}, found {}",
@@ -200,11 +340,39 @@ impl Wallet {
             store.outgoing_swapcoins.len()
         );
 
-        Ok(Self {
+        // A passphrase given for an unencrypted wallet file migrates it to an encrypted one.
+        let encrypt = key_material.is_none() && passphrase.is_some();
+        let key_material = key_material.or_else(|| passphrase.as_deref().map(KeyMaterial::new));
+
+        let wallet = Self {
             rpc,
             wallet_file_path: path.to_path_buf(),
             store,
-        })
+            key_material,
+            reservations: LiquidityLedger::default(),
+            cross_pool_policy: CrossPoolPolicy::default(),
+            coin_selection: CoinSelectionAlgorithm::default(),
+        };
+
+        if encrypt {
+            wallet.save_to_disk()?;
+            log::info!("Encrypted existing wallet file at {:?}", path);
+        } else if file_version < WALLET_FILE_VERSION {
+            wallet.save_to_disk()?;
+            log::info!(
+                "Upgraded wallet file at {:?} from version {} to {}",
+                path,
+                file_version,
+                WALLET_FILE_VERSION
+            );
+        }
+
+        // Backups written before the wallet was encrypted still hold it in plaintext.
+        if let Some(key_material) = &wallet.key_material {
+            WalletStore::encrypt_backups(path, key_material)?;
+        }
+
+        Ok(wallet)
     }
 
     /// Update external index and saves to disk.
@@ -218,7 +386,29 @@ impl Wallet {
 
     /// Update the existing file. Error if path does not exist.
     pub(crate) fn save_to_disk(&self) -> Result<(), WalletError> {
-        self.store.write_to_disk(&self.wallet_file_path)
+        self.store
+            .write_to_disk(&self.wallet_file_path, self.key_material.as_ref())
+    }
+
+    /// Returns true if the wallet file is encrypted at rest.
+    pub fn is_encrypted(&self) -> bool {
+        self.key_material.is_some()
+    }
+
+    /// Encrypts data kept next to the wallet file with the wallet's key, if the wallet is encrypted.
+    pub(crate) fn encrypt_data(&self, plaintext: Vec<u8>) -> Result<Vec<u8>, WalletError> {
+        match &self.key_material {
+            Some(key_material) => key_material.encrypt_data(&plaintext),
+            None => Ok(plaintext),
+        }
+    }
+
+    /// Decrypts data written with [`Wallet::encrypt_data`].
+    pub(crate) fn decrypt_data(&self, bytes: Vec<u8>) -> Result<Vec<u8>, WalletError> {
+        match &self.key_material {
+            Some(key_material) => key_material.decrypt_data(&bytes),
+            None => Ok(bytes),
+        }
     }
 
     /// Finds an incoming swap coin with the specified multisig redeem script.
@@ -313,6 +503,26 @@ impl Wallet {
             .fold(Amount::ZERO, |sum, (utxo, _)| sum + utxo.amount))
     }
 
+    /// Calculates the balance of the utxos reserved for in-flight swaps, which are still unspent.
+    /// Optionally takes in a list of UTXOs to reduce rpc call. If None is provided, the full list is fetched from core rpc.
+    pub fn balance_reserved(
+        &self,
+        all_utxos: Option<&Vec<ListUnspentResultEntry>>,
+    ) -> Result<Amount, WalletError> {
+        let all_utxos = if let Some(utxos) = all_utxos {
+            utxos.clone()
+        } else {
+            self.get_all_utxo()?
+        };
+        Ok(all_utxos
+            .iter()
+            .filter(|utxo| {
+                self.reservations
+                    .is_reserved(&OutPoint::new(utxo.txid, utxo.vout))
+            })
+            .fold(Amount::ZERO, |sum, utxo| sum + utxo.amount))
+    }
+
     /// Calculates the descriptor utxo balance of the wallet.
     /// Optionally takes in a list of UTXOs to reduce rpc call. If None is provided, the full list is fetched from core rpc.
     pub fn balance_descriptor_utxo(
@@ -393,7 +603,7 @@ impl Wallet {
 
     //pub(crate) fn get_recovery_phrase_from_file()
 
-    /// Wallet descriptors are derivable. Currently only supports two KeychainKind. Internal and External.
+    /// Wallet descriptors are derivable, one for each [KeychainKind].
     fn get_wallet_descriptors(&self) -> Result<HashMap<KeychainKind, String>, WalletError> {
         let secp = Secp256k1::new();
         let wallet_xpub = Xpub::from_priv(
@@ -404,20 +614,23 @@ impl Wallet {
                 .derive_priv(&secp, &DerivationPath::from_str(HARDENDED_DERIVATION)?)?,
         );
 
-        // Get descriptors for external and internal keychain. Other chains are not supported yet.
-        [KeychainKind::External, KeychainKind::Internal]
-            .iter()
-            .map(|keychain| {
-                let descriptor_without_checksum =
-                    format!("wpkh({}/{}/*)", wallet_xpub, keychain.index_num());
-                let decriptor = format!(
-                    "{}#{}",
-                    descriptor_without_checksum,
-                    compute_checksum(&descriptor_without_checksum)?
-                );
-                Ok((*keychain, decriptor))
-            })
-            .collect()
+        [
+            KeychainKind::External,
+            KeychainKind::Internal,
+            KeychainKind::SwapChange,
+        ]
+        .iter()
+        .map(|keychain| {
+            let descriptor_without_checksum =
+                format!("wpkh({}/{}/*)", wallet_xpub, keychain.index_num());
+            let decriptor = format!(
+                "{}#{}",
+                descriptor_without_checksum,
+                compute_checksum(&descriptor_without_checksum)?
+            );
+            Ok((*keychain, decriptor))
+        })
+        .collect()
     }
 
     /// Checks if the addresses derived from the wallet descriptor is imported upto full index range.
@@ -605,6 +818,70 @@ impl Wallet {
             .list_unspent(Some(0), Some(9999999), None, None, None)?;
         Ok(all_utxos)
     }
+
+    /// Returns the wallet transactions spending any of the given outpoints, confirmed in blocks after
+    /// `since_height` or unconfirmed. Searches the whole wallet history if `since_height` is zero.
+    pub(crate) fn find_spending_txs(
+        &self,
+        outpoints: &[OutPoint],
+        since_height: u64,
+    ) -> Result<Vec<Transaction>, WalletError> {
+        if outpoints.is_empty() {
+            return Ok(Vec::new());
+        }
+        let since_block = match since_height {
+            0 => None,
+            height => Some(self.rpc.get_block_hash(height)?),
+        };
+        let mut txids = self
+            .rpc
+            .list_since_block(since_block.as_ref(), None, Some(true), None)?
+            .transactions
+            .into_iter()
+            .map(|entry| entry.info.txid)
+            .collect::<Vec<_>>();
+        txids.sort();
+        txids.dedup();
+
+        let mut spending_txs = Vec::new();
+        for txid in txids {
+            let tx = self.rpc.get_transaction(&txid, Some(true))?.transaction()?;
+            if tx
+                .input
+                .iter()
+                .any(|input| outpoints.contains(&input.previous_output))
+            {
+                spending_txs.push(tx);
+            }
+        }
+        Ok(spending_txs)
+    }
+
+    /// Checks if an output is a seed coin of this wallet, i.e. paid to an address derived from the wallet seed.
+    /// Swapcoins, contracts and fidelity bonds are imported as plain scripts, and have no derivation path.
+    /// Change of swap coins is derived from the seed, but belongs to the swap pool.
+    pub(crate) fn is_seed_coin(&self, outpoint: &OutPoint) -> Result<bool, WalletError> {
+        // Txs unknown to the wallet can't pay to it.
+        let Ok(tx) = self.rpc.get_transaction(&outpoint.txid, Some(true)) else {
+            return Ok(false);
+        };
+        let tx = tx.transaction()?;
+        let Some(txout) = tx.output.get(outpoint.vout as usize) else {
+            return Ok(false);
+        };
+        let Ok(address) = Address::from_script(&txout.script_pubkey, self.store.network) else {
+            return Ok(false);
+        };
+        let info = self.rpc.get_address_info(&address)?;
+        let swap_change = ChildNumber::Normal {
+            index: KeychainKind::SwapChange.index_num(),
+        };
+        Ok(info.is_mine == Some(true)
+            && info
+                .hd_key_path
+                .is_some_and(|path| path.len() < 2 || path[path.len() - 2] != swap_change))
+    }
+
     /// Returns a list all utxos with their spend info tracked by the wallet.
     /// Optionally takes in an Utxo list to reduce RPC calls. If None is given, the
     /// full list of utxo is fetched from core rpc.
@@ -726,6 +1003,29 @@ impl Wallet {
         (unfinished_incomins, unfinished_outgoings)
     }
 
+    /// Finds the index after the highest external address that ever received coins, whether they are
+    /// spent or not.
+    pub(super) fn find_used_external_index(&self) -> Result<u32, WalletError> {
+        let received = self
+            .rpc
+            .list_received_by_address(None, Some(0), Some(false), Some(true))?
+            .into_iter()
+            .map(|entry| entry.address)
+            .collect::<HashSet<_>>();
+        let descriptors = self.get_wallet_descriptors()?;
+        let receive_branch_descriptor = descriptors
+            .get(&KeychainKind::External)
+            .expect("external keychain expected");
+        let addresses = self.rpc.derive_addresses(
+            receive_branch_descriptor,
+            Some([0, self.get_addrss_import_count() - 1]),
+        )?;
+        Ok(addresses
+            .iter()
+            .rposition(|address| received.contains(address))
+            .map_or(0, |index| index as u32 + 1))
+    }
+
     /// Finds the next unused index in the HD keychain.
     ///
     /// It will only return an unused address; i.e, an address that doesn't have a transaction associated with it.
@@ -774,11 +1074,33 @@ impl Wallet {
         &self,
         count: u32,
     ) -> Result<Vec<Address>, WalletError> {
-        let next_change_addr_index = self.find_hd_next_index(KeychainKind::Internal)?;
+        self.get_next_keychain_addresses(KeychainKind::Internal, count)
+    }
+
+    /// Gets the next change addresses of a [CoinPool]. Change of the swap pool goes to a keychain of
+    /// its own, so it stays in the swap pool when it's spent later.
+    pub(crate) fn get_next_change_addresses(
+        &self,
+        pool: CoinPool,
+        count: u32,
+    ) -> Result<Vec<Address>, WalletError> {
+        let keychain = match pool {
+            CoinPool::Seed => KeychainKind::Internal,
+            CoinPool::Swap => KeychainKind::SwapChange,
+        };
+        self.get_next_keychain_addresses(keychain, count)
+    }
+
+    fn get_next_keychain_addresses(
+        &self,
+        keychain: KeychainKind,
+        count: u32,
+    ) -> Result<Vec<Address>, WalletError> {
+        let next_change_addr_index = self.find_hd_next_index(keychain)?;
         let descriptors = self.get_wallet_descriptors()?;
         let change_branch_descriptor = descriptors
-            .get(&KeychainKind::Internal)
-            .expect("Internal Keychain expected");
+            .get(&keychain)
+            .expect("keychain descriptor expected");
         let addresses = self.rpc.derive_addresses(
             change_branch_descriptor,
             Some([next_change_addr_index, next_change_addr_index + count]),
@@ -791,13 +1113,49 @@ impl Wallet {
     }
 
     /// Refreshes the offer maximum size cache based on the current wallet's unspent transaction outputs (UTXOs).
+    ///
+    /// Utxos reserved for in-flight swaps are not counted.
     pub(crate) fn refresh_offer_maxsize_cache(&mut self) -> Result<(), WalletError> {
-        let swap_balance = self.balance_swap_coins(None)?;
-        let seed_balance = self.balance_descriptor_utxo(None)?;
-        self.store.offer_maxsize = (seed_balance + swap_balance).to_sat();
+        let all_utxos = self.get_all_utxo()?;
+        let swap_balance = self.balance_swap_coins(Some(&all_utxos))?;
+        let seed_balance = self.balance_descriptor_utxo(Some(&all_utxos))?;
+        let reserved_balance = self.balance_reserved(Some(&all_utxos))?;
+        self.store.offer_maxsize = (seed_balance + swap_balance)
+            .checked_sub(reserved_balance)
+            .unwrap_or(Amount::ZERO)
+            .to_sat();
         Ok(())
     }
 
+    /// Reserves the utxos spent by the funding txs of a swap, until it's released with [`Wallet::release_utxos`].
+    ///
+    /// Reserved utxos are not selected for other transactions, and are not counted in the offer maximum size.
+    pub(crate) fn reserve_utxos(
+        &mut self,
+        id: &str,
+        funding_txes: &[Transaction],
+    ) -> Result<(), WalletError> {
+        let outpoints = funding_txes
+            .iter()
+            .flat_map(|tx| tx.input.iter().map(|txin| txin.previous_output))
+            .collect();
+        self.reservations.reserve(id, outpoints);
+        self.refresh_offer_maxsize_cache()
+    }
+
+    /// Releases the utxos reserved for a swap, once it's completed or aborted.
+    pub(crate) fn release_utxos(&mut self, id: &str) -> Result<(), WalletError> {
+        if self.reservations.release(id).is_empty() {
+            return Ok(());
+        }
+        log::info!(
+            "Released utxos reserved for swap {}. {} swaps still hold reservations.",
+            id,
+            self.reservations.swap_count()
+        );
+        self.refresh_offer_maxsize_cache()
+    }
+
     /// Gets a tweakable key pair from the master key of the wallet.
     pub(crate) fn get_tweakable_keypair(&self) -> Result<(SecretKey, PublicKey), WalletError> {
         let secp = Secp256k1::new();
@@ -900,10 +1258,74 @@ impl Wallet {
         Ok(())
     }
 
-    /// Largerst to lowest coinselect algorithm
+    /// Proves the ownership of `coins` to the Maker of `tweakable_point`, for the swap of `hashvalue`,
+    /// on the connection of `handshake_hash`.
+    pub(crate) fn prove_utxo_ownership(
+        &self,
+        coins: &[(ListUnspentResultEntry, UTXOSpendInfo)],
+        tweakable_point: &PublicKey,
+        hashvalue: &Hash160,
+        handshake_hash: &[u8; 32],
+    ) -> Result<Vec<UtxoOwnershipProof>, WalletError> {
+        let secp = Secp256k1::new();
+        let master_private_key = self
+            .store
+            .master_key
+            .derive_priv(&secp, &DerivationPath::from_str(HARDENDED_DERIVATION)?)?;
+
+        coins
+            .iter()
+            .map(|(utxo, spend_info)| {
+                let (privkey, multisig_redeemscript) = match spend_info.clone() {
+                    UTXOSpendInfo::SeedCoin { path, .. } => (
+                        master_private_key
+                            .derive_priv(&secp, &DerivationPath::from_str(&path)?)?
+                            .private_key,
+                        None,
+                    ),
+                    UTXOSpendInfo::SwapCoin {
+                        multisig_redeemscript,
+                    } => {
+                        let privkey = match self.find_incoming_swapcoin(&multisig_redeemscript) {
+                            Some(swapcoin) => swapcoin.my_privkey,
+                            None => {
+                                self.find_outgoing_swapcoin(&multisig_redeemscript)
+                                    .ok_or(WalletError::General(
+                                        "Swapcoin of the utxo not found".to_string(),
+                                    ))?
+                                    .my_privkey
+                            }
+                        };
+                        (privkey, Some(multisig_redeemscript))
+                    }
+                    _ => {
+                        return Err(WalletError::General(
+                            "Can't prove ownership of a contract or fidelity bond utxo".to_string(),
+                        ))
+                    }
+                };
+                Ok(contract::sign_utxo_proof(
+                    OutPoint::new(utxo.txid, utxo.vout),
+                    &privkey,
+                    multisig_redeemscript,
+                    tweakable_point,
+                    hashvalue,
+                    handshake_hash,
+                ))
+            })
+            .collect()
+    }
+
+    /// Selects coins whose effective values at `fee_rate`, i.e. their values minus the fees of spending
+    /// them, add up to `amount`, with the wallet's coin selection algorithm.
+    ///
+    /// Coins exceeding the amount by at most `change_threshold` are preferred by the algorithms avoiding
+    /// change outputs. The spending tx should then leave out its change.
     pub fn coin_select(
         &self,
         amount: Amount,
+        fee_rate: f64,
+        change_threshold: Amount,
     ) -> Result<Vec<(ListUnspentResultEntry, UTXOSpendInfo)>, WalletError> {
         let all_utxos = self.get_all_locked_utxo()?;
 
@@ -911,28 +1333,61 @@ impl Wallet {
         let mut swap_coin_utxo = self.list_swap_coin_utxo_spend_info(Some(&all_utxos))?;
         seed_coin_utxo.append(&mut swap_coin_utxo);
 
-        // Fetch utxos, filter out existing fidelity coins
-        let mut unspents = seed_coin_utxo
+        // Fetch utxos, filter out existing fidelity coins, and the coins reserved for in-flight swaps.
+        let unspents = seed_coin_utxo
             .into_iter()
             .filter(|(_, spend_info)| !matches!(spend_info, UTXOSpendInfo::FidelityBondCoin { .. }))
+            .filter(|(utxo, _)| {
+                !self
+                    .reservations
+                    .is_reserved(&OutPoint::new(utxo.txid, utxo.vout))
+            })
             .collect::<Vec<_>>();
 
-        unspents.sort_by(|a, b| b.0.amount.cmp(&a.0.amount));
+        // Seed coins and swap coins are not merged, unless the cross pool policy allows it.
+        select_coins(
+            unspents,
+            amount,
+            fee_rate,
+            change_threshold,
+            self.cross_pool_policy,
+            self.coin_selection.selector(),
+        )
+    }
 
-        let mut selected_utxo = Vec::new();
-        let mut remaining = amount;
+    /// Returns the spend info of the given utxos, to spend them explicitly instead of selecting coins.
+    ///
+    /// Errors if any of them is not a spendable seed or swap coin of the wallet, or is reserved for a swap.
+    pub fn select_utxos(
+        &self,
+        outpoints: &[OutPoint],
+    ) -> Result<Vec<(ListUnspentResultEntry, UTXOSpendInfo)>, WalletError> {
+        let all_utxos = self.get_all_locked_utxo()?;
 
-        // the simplest largest first coinselection.
-        for unspent in unspents {
-            if remaining.checked_sub(unspent.0.amount).is_none() {
-                selected_utxo.push(unspent);
-                break;
-            } else {
-                remaining -= unspent.0.amount;
-                selected_utxo.push(unspent);
-            }
-        }
-        Ok(selected_utxo)
+        let mut spendable = self.list_descriptor_utxo_spend_info(Some(&all_utxos))?;
+        spendable.append(&mut self.list_swap_coin_utxo_spend_info(Some(&all_utxos))?);
+
+        outpoints
+            .iter()
+            .map(|outpoint| {
+                if self.reservations.is_reserved(outpoint) {
+                    return Err(WalletError::General(format!(
+                        "Utxo {} is reserved for a swap",
+                        outpoint
+                    )));
+                }
+                spendable
+                    .iter()
+                    .find(|(utxo, spend_info)| {
+                        OutPoint::new(utxo.txid, utxo.vout) == *outpoint
+                            && !matches!(spend_info, UTXOSpendInfo::FidelityBondCoin { .. })
+                    })
+                    .cloned()
+                    .ok_or_else(|| {
+                        WalletError::General(format!("Utxo {} is not a spendable coin", outpoint))
+                    })
+            })
+            .collect()
     }
 
     pub(crate) fn get_utxo(
@@ -990,7 +1445,7 @@ impl Wallet {
         hashlock_pubkeys: &[PublicKey],
         hashvalue: Hash160,
         locktime: u16,
-        fee_rate: Amount,
+        fee_rate: f64,
     ) -> Result<(Vec<Transaction>, Vec<OutgoingSwapCoin>, Amount), WalletError> {
         let (coinswap_addresses, my_multisig_privkeys): (Vec<_>, Vec<_>) = other_multisig_pubkeys
             .iter()
@@ -1037,7 +1492,7 @@ impl Wallet {
                 },
                 funding_amount,
                 &contract_redeemscript,
-                fee_rate,
+                calculate_fee(fee_rate, contract_tx_vsize()),
             )?;
 
             // self.import_wallet_contract_redeemscript(&contract_redeemscript)?;
@@ -0,0 +1,295 @@
+//! Coin control, keeping the seed coins and the swap coins of the wallet apart.
+//!
+//! Spending seed coins together with coins received in a swap links them on chain, and undoes the privacy
+//! the swap bought. Coin selection picks coins from a single [`CoinPool`] whenever it can. Spending coins of
+//! both pools together, either selected or given explicitly, is refused, warned about or allowed as set by
+//! the [`CrossPoolPolicy`].
+
+use std::{fmt::Display, str::FromStr};
+
+use bitcoin::Amount;
+use bitcoind::bitcoincore_rpc::json::ListUnspentResultEntry;
+
+use super::{
+    api::{KeychainKind, UTXOSpendInfo},
+    coin_selection::{effective_value, CoinSelector},
+    error::WalletError,
+};
+
+/// The pools of spendable coins in the wallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoinPool {
+    /// Coins received on addresses derived from the wallet seed.
+    Seed,
+    /// Coins received in swaps.
+    Swap,
+}
+
+impl CoinPool {
+    /// The pool of a coin. `None` for fidelity bonds and contracts, which are never selected.
+    ///
+    /// Change of swap coins is paid to the seed's swap change keychain, and stays in the swap pool.
+    pub(crate) fn of(spend_info: &UTXOSpendInfo) -> Option<Self> {
+        match spend_info {
+            UTXOSpendInfo::SeedCoin { path, .. }
+                if path.starts_with(&format!("m/{}/", KeychainKind::SwapChange.index_num())) =>
+            {
+                Some(Self::Swap)
+            }
+            UTXOSpendInfo::SeedCoin { .. } => Some(Self::Seed),
+            UTXOSpendInfo::SwapCoin { .. } => Some(Self::Swap),
+            _ => None,
+        }
+    }
+
+    /// The pool the change of a tx spending the coins goes back to. Change stays in the swap pool only
+    /// if all the coins are swap coins.
+    pub(crate) fn of_change(coins: &[(ListUnspentResultEntry, UTXOSpendInfo)]) -> Self {
+        let mut pools = coins
+            .iter()
+            .filter_map(|(_, spend_info)| Self::of(spend_info))
+            .peekable();
+        if pools.peek().is_some() && pools.all(|pool| pool == Self::Swap) {
+            Self::Swap
+        } else {
+            Self::Seed
+        }
+    }
+}
+
+/// How spending coins of both pools in one tx is handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CrossPoolPolicy {
+    /// Refuse the spend.
+    #[default]
+    Refuse,
+    /// Log a warning and spend.
+    Warn,
+    /// Spend without warning.
+    Allow,
+}
+
+impl FromStr for CrossPoolPolicy {
+    type Err = WalletError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "refuse" => Ok(Self::Refuse),
+            "warn" => Ok(Self::Warn),
+            "allow" => Ok(Self::Allow),
+            _ => Err(WalletError::General(format!(
+                "Unknown cross pool policy {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for CrossPoolPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Refuse => write!(f, "refuse"),
+            Self::Warn => write!(f, "warn"),
+            Self::Allow => write!(f, "allow"),
+        }
+    }
+}
+
+/// Checks coins to be spent together against the policy. Errors with [`WalletError::CrossPoolSpend`]
+/// if they mix both pools and the policy refuses it.
+pub(crate) fn check_cross_pool(
+    coins: &[(ListUnspentResultEntry, UTXOSpendInfo)],
+    policy: CrossPoolPolicy,
+) -> Result<(), WalletError> {
+    let pools = coins
+        .iter()
+        .filter_map(|(_, spend_info)| CoinPool::of(spend_info))
+        .collect::<Vec<_>>();
+    if !(pools.contains(&CoinPool::Seed) && pools.contains(&CoinPool::Swap)) {
+        return Ok(());
+    }
+    match policy {
+        CrossPoolPolicy::Refuse => Err(WalletError::CrossPoolSpend),
+        CrossPoolPolicy::Warn => {
+            log::warn!("Spending seed coins together with swap coins. This links them on chain.");
+            Ok(())
+        }
+        CrossPoolPolicy::Allow => Ok(()),
+    }
+}
+
+/// Selects coins whose effective values at `fee_rate` add up to `amount` from a single pool with the
+/// `selector`, seed coins first. If neither pool covers the amount alone, coins of both pools are selected
+/// as allowed by the policy.
+///
+/// Coins that are not of any pool, or not worth spending at the feerate, are ignored. If all coins together
+/// don't cover the amount, all are returned, and it's up to the caller to report the missing funds.
+pub(crate) fn select_coins(
+    unspents: Vec<(ListUnspentResultEntry, UTXOSpendInfo)>,
+    amount: Amount,
+    fee_rate: f64,
+    change_threshold: Amount,
+    policy: CrossPoolPolicy,
+    selector: &dyn CoinSelector,
+) -> Result<Vec<(ListUnspentResultEntry, UTXOSpendInfo)>, WalletError> {
+    let (seed_coins, swap_coins): (Vec<_>, Vec<_>) = unspents
+        .into_iter()
+        .filter(|coin| {
+            CoinPool::of(&coin.1).is_some() && effective_value(coin, fee_rate) > Amount::ZERO
+        })
+        .partition(|(_, spend_info)| CoinPool::of(spend_info) == Some(CoinPool::Seed));
+
+    let total = |coins: &[(ListUnspentResultEntry, UTXOSpendInfo)]| {
+        coins
+            .iter()
+            .map(|coin| effective_value(coin, fee_rate))
+            .sum::<Amount>()
+    };
+    for pool in [&seed_coins, &swap_coins] {
+        if total(pool) >= amount {
+            return Ok(selector.select(pool.clone(), amount, change_threshold, fee_rate));
+        }
+    }
+
+    let all_coins = [seed_coins, swap_coins].concat();
+    if total(&all_coins) < amount {
+        return Ok(all_coins);
+    }
+    let selected = selector.select(all_coins, amount, change_threshold, fee_rate);
+    check_cross_pool(&selected, policy)?;
+    Ok(selected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::coin_selection::LargestFirst;
+    use bitcoin::{hashes::Hash, ScriptBuf, Txid};
+
+    fn coin(vout: u32, sats: u64, pool: CoinPool) -> (ListUnspentResultEntry, UTXOSpendInfo) {
+        let spend_info = match pool {
+            CoinPool::Seed => UTXOSpendInfo::SeedCoin {
+                path: format!("m/0/{}", vout),
+                input_value: Amount::from_sat(sats),
+            },
+            CoinPool::Swap => UTXOSpendInfo::SwapCoin {
+                multisig_redeemscript: ScriptBuf::new(),
+            },
+        };
+        let utxo = ListUnspentResultEntry {
+            txid: Txid::all_zeros(),
+            vout,
+            address: None,
+            label: None,
+            redeem_script: None,
+            witness_script: None,
+            script_pub_key: ScriptBuf::new(),
+            amount: Amount::from_sat(sats),
+            confirmations: 1,
+            spendable: true,
+            solvable: true,
+            descriptor: None,
+            safe: true,
+        };
+        (utxo, spend_info)
+    }
+
+    fn vouts(coins: &[(ListUnspentResultEntry, UTXOSpendInfo)]) -> Vec<u32> {
+        coins.iter().map(|(utxo, _)| utxo.vout).collect()
+    }
+
+    fn select(
+        coins: Vec<(ListUnspentResultEntry, UTXOSpendInfo)>,
+        sats: u64,
+        policy: CrossPoolPolicy,
+    ) -> Result<Vec<(ListUnspentResultEntry, UTXOSpendInfo)>, WalletError> {
+        select_coins(
+            coins,
+            Amount::from_sat(sats),
+            0.0,
+            Amount::ZERO,
+            policy,
+            &LargestFirst,
+        )
+    }
+
+    #[test]
+    fn test_select_from_single_pool() {
+        let coins = vec![
+            coin(0, 30_000, CoinPool::Seed),
+            coin(1, 20_000, CoinPool::Seed),
+            coin(2, 80_000, CoinPool::Swap),
+        ];
+
+        // Seed coins are preferred when they cover the amount.
+        let selected = select(coins.clone(), 40_000, Default::default());
+        assert_eq!(vouts(&selected.unwrap()), vec![0, 1]);
+
+        // Otherwise swap coins are used alone.
+        let selected = select(coins.clone(), 60_000, Default::default());
+        assert_eq!(vouts(&selected.unwrap()), vec![2]);
+
+        // Not enough coins in total. Everything is returned for the caller to report.
+        let selected = select(coins, 200_000, Default::default());
+        assert_eq!(vouts(&selected.unwrap()), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_swap_change_pool() {
+        let swap_change = |vout: u32, sats: u64| {
+            let (utxo, _) = coin(vout, sats, CoinPool::Seed);
+            let spend_info = UTXOSpendInfo::SeedCoin {
+                path: format!("m/2/{}", vout),
+                input_value: Amount::from_sat(sats),
+            };
+            (utxo, spend_info)
+        };
+
+        // Change of swap coins is in the swap pool, and is not merged with seed coins.
+        assert_eq!(CoinPool::of(&swap_change(0, 1_000).1), Some(CoinPool::Swap));
+        let coins = vec![coin(0, 30_000, CoinPool::Seed), swap_change(1, 50_000)];
+        assert!(matches!(
+            check_cross_pool(&coins, CrossPoolPolicy::Refuse),
+            Err(WalletError::CrossPoolSpend)
+        ));
+
+        // Change goes back to the swap pool only if all spent coins are swap coins.
+        assert_eq!(CoinPool::of_change(&coins[1..]), CoinPool::Swap);
+        assert_eq!(
+            CoinPool::of_change(&[coin(2, 10_000, CoinPool::Swap), swap_change(3, 1_000)]),
+            CoinPool::Swap
+        );
+        assert_eq!(CoinPool::of_change(&coins), CoinPool::Seed);
+        assert_eq!(CoinPool::of_change(&coins[..1]), CoinPool::Seed);
+        assert_eq!(CoinPool::of_change(&[]), CoinPool::Seed);
+    }
+
+    #[test]
+    fn test_cross_pool_policy() {
+        let coins = vec![
+            coin(0, 30_000, CoinPool::Seed),
+            coin(1, 50_000, CoinPool::Swap),
+        ];
+        assert!(matches!(
+            select(coins.clone(), 70_000, CrossPoolPolicy::Refuse),
+            Err(WalletError::CrossPoolSpend)
+        ));
+        for policy in [CrossPoolPolicy::Warn, CrossPoolPolicy::Allow] {
+            let selected = select(coins.clone(), 70_000, policy).unwrap();
+            assert_eq!(vouts(&selected), vec![1, 0]);
+        }
+
+        assert!(matches!(
+            check_cross_pool(&coins, CrossPoolPolicy::Refuse),
+            Err(WalletError::CrossPoolSpend)
+        ));
+        assert!(check_cross_pool(&coins[..1], CrossPoolPolicy::Refuse).is_ok());
+
+        assert_eq!(
+            "warn".parse::<CrossPoolPolicy>().unwrap(),
+            CrossPoolPolicy::Warn
+        );
+        assert_eq!(CrossPoolPolicy::Refuse.to_string(), "refuse");
+        assert!("merge".parse::<CrossPoolPolicy>().is_err());
+    }
+}
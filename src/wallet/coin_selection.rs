@@ -0,0 +1,528 @@
+//! Coin selection algorithms.
+//!
+//! A [`CoinSelector`] picks the coins to spend out of a single coin pool. The wallet uses the one set by
+//! the [`CoinSelectionAlgorithm`] of its config, for the funding txs of swaps, direct sends and fidelity bonds.
+//!
+//! A change output is an easy link between the coins spent in a tx and the wallet's later txs. The
+//! [`BranchAndBound`] selector looks for coins worth just enough to skip the change output, and is the default.
+
+use std::{fmt::Display, str::FromStr};
+
+use bitcoin::{
+    secp256k1::rand::{rngs::OsRng, RngCore},
+    Amount,
+};
+use bitcoind::bitcoincore_rpc::json::ListUnspentResultEntry;
+
+use super::{
+    api::UTXOSpendInfo,
+    error::WalletError,
+    fee::{calculate_fee, coin_input_vsize},
+};
+
+/// Maximum number of branches explored by [`BranchAndBound`] before giving up.
+const BNB_MAX_TRIES: u32 = 100_000;
+
+/// Number of random subsets tried by [`Knapsack`].
+const KNAPSACK_ITERATIONS: u32 = 1000;
+
+/// The value of a coin minus the fee of spending it at `fee_rate`. Zero if it's not worth spending.
+pub(crate) fn effective_value(
+    coin: &(ListUnspentResultEntry, UTXOSpendInfo),
+    fee_rate: f64,
+) -> Amount {
+    coin.0
+        .amount
+        .checked_sub(calculate_fee(fee_rate, coin_input_vsize(&coin.1)))
+        .unwrap_or(Amount::ZERO)
+}
+
+/// Picks the coins to spend for a target amount.
+pub trait CoinSelector {
+    /// Selects coins whose effective values, i.e. their values minus the fees of spending them at
+    /// `fee_rate`, add up to at least `target`, out of `coins` which together cover it.
+    ///
+    /// `target` is the amount to pay plus the fee of the tx without its inputs. A selection exceeding the
+    /// target by at most `change_threshold` is spent without a change output, the excess going to the miners.
+    fn select(
+        &self,
+        coins: Vec<(ListUnspentResultEntry, UTXOSpendInfo)>,
+        target: Amount,
+        change_threshold: Amount,
+        fee_rate: f64,
+    ) -> Vec<(ListUnspentResultEntry, UTXOSpendInfo)>;
+}
+
+/// Sorts the coins by effective value, largest first, and returns their effective values.
+fn sort_by_effective_value(
+    coins: &mut [(ListUnspentResultEntry, UTXOSpendInfo)],
+    fee_rate: f64,
+) -> Vec<Amount> {
+    coins.sort_by_key(|coin| std::cmp::Reverse(effective_value(coin, fee_rate)));
+    coins
+        .iter()
+        .map(|coin| effective_value(coin, fee_rate))
+        .collect()
+}
+
+/// Spends as few coins as possible, to link as few of the wallet's coins together as possible.
+///
+/// Picks the smallest coin covering the target alone if there's one, else the largest coins first.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LargestFirst;
+
+impl CoinSelector for LargestFirst {
+    fn select(
+        &self,
+        mut coins: Vec<(ListUnspentResultEntry, UTXOSpendInfo)>,
+        target: Amount,
+        _change_threshold: Amount,
+        fee_rate: f64,
+    ) -> Vec<(ListUnspentResultEntry, UTXOSpendInfo)> {
+        let values = sort_by_effective_value(&mut coins, fee_rate);
+
+        if let Some(pos) = values.iter().rposition(|value| *value >= target) {
+            return vec![coins.swap_remove(pos)];
+        }
+
+        let mut selected = Vec::new();
+        let mut total = Amount::ZERO;
+        for (coin, value) in coins.into_iter().zip(values) {
+            if total >= target {
+                break;
+            }
+            total += value;
+            selected.push(coin);
+        }
+        selected
+    }
+}
+
+/// Searches for coins worth between the target and the target plus the change threshold, which are
+/// spent without a change output. Picks the one with the least excess.
+///
+/// Falls back to [`LargestFirst`] if there's no such selection, or the search gives up.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BranchAndBound;
+
+impl BranchAndBound {
+    /// Depth first search of the selections of `values`, sorted largest first. `remaining` is the sum of
+    /// the values from `index` on. Keeps the selection with the least excess in `best`.
+    #[allow(clippy::too_many_arguments)]
+    fn search(
+        values: &[Amount],
+        index: usize,
+        selected: &mut Vec<usize>,
+        total: Amount,
+        remaining: Amount,
+        (target, upper_bound): (Amount, Amount),
+        tries: &mut u32,
+        best: &mut Option<(Amount, Vec<usize>)>,
+    ) {
+        if *tries == 0 || total > upper_bound || total + remaining < target {
+            return;
+        }
+        *tries -= 1;
+
+        if total >= target {
+            let excess = total - target;
+            if best
+                .as_ref()
+                .is_none_or(|(best_excess, _)| excess < *best_excess)
+            {
+                *best = Some((excess, selected.clone()));
+            }
+            // Nothing beats an exact match.
+            if excess == Amount::ZERO {
+                *tries = 0;
+            }
+            return;
+        }
+        if index == values.len() {
+            return;
+        }
+
+        let value = values[index];
+        selected.push(index);
+        Self::search(
+            values,
+            index + 1,
+            selected,
+            total + value,
+            remaining - value,
+            (target, upper_bound),
+            tries,
+            best,
+        );
+        selected.pop();
+        Self::search(
+            values,
+            index + 1,
+            selected,
+            total,
+            remaining - value,
+            (target, upper_bound),
+            tries,
+            best,
+        );
+    }
+}
+
+impl CoinSelector for BranchAndBound {
+    fn select(
+        &self,
+        mut coins: Vec<(ListUnspentResultEntry, UTXOSpendInfo)>,
+        target: Amount,
+        change_threshold: Amount,
+        fee_rate: f64,
+    ) -> Vec<(ListUnspentResultEntry, UTXOSpendInfo)> {
+        let values = sort_by_effective_value(&mut coins, fee_rate);
+
+        let mut best = None;
+        let mut tries = BNB_MAX_TRIES;
+        Self::search(
+            &values,
+            0,
+            &mut Vec::new(),
+            Amount::ZERO,
+            values.iter().copied().sum(),
+            (target, target + change_threshold),
+            &mut tries,
+            &mut best,
+        );
+
+        match best {
+            Some((_, indices)) => indices.into_iter().map(|i| coins[i].clone()).collect(),
+            None => LargestFirst.select(coins, target, change_threshold, fee_rate),
+        }
+    }
+}
+
+/// Tries random subsets of the coins, and picks the one with the least value covering the target.
+///
+/// Like Bitcoin Core's knapsack solver, it prefers a selection within the change threshold of the target,
+/// spent without a change output. Otherwise it aims for the target plus the change threshold, so the
+/// change is worth its output.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Knapsack;
+
+impl Knapsack {
+    /// Selects with the random subsets drawn from `rng`.
+    fn select_with_rng(
+        &self,
+        mut coins: Vec<(ListUnspentResultEntry, UTXOSpendInfo)>,
+        target: Amount,
+        change_threshold: Amount,
+        fee_rate: f64,
+        rng: &mut dyn RngCore,
+    ) -> Vec<(ListUnspentResultEntry, UTXOSpendInfo)> {
+        let values = sort_by_effective_value(&mut coins, fee_rate);
+
+        let best = match Self::search(&values, target, rng) {
+            changeless @ Some((total, _)) if total <= target + change_threshold => changeless,
+            best => Self::search(&values, target + change_threshold, rng).or(best),
+        };
+
+        match best {
+            Some((_, included)) => coins
+                .into_iter()
+                .zip(included)
+                .filter_map(|(coin, included)| included.then_some(coin))
+                .collect(),
+            None => coins,
+        }
+    }
+
+    /// The subset of `values`, sorted largest first, with the least total reaching `target`, out of random
+    /// subsets. `None` if all values together don't reach it.
+    fn search(
+        values: &[Amount],
+        target: Amount,
+        rng: &mut dyn RngCore,
+    ) -> Option<(Amount, Vec<bool>)> {
+        // Start from the smallest coin covering the target alone, if there's one.
+        let mut best = values
+            .iter()
+            .rposition(|value| *value >= target)
+            .map(|pos| {
+                let mut included = vec![false; values.len()];
+                included[pos] = true;
+                (values[pos], included)
+            });
+
+        for _ in 0..KNAPSACK_ITERATIONS {
+            if best.as_ref().is_some_and(|(total, _)| *total == target) {
+                break;
+            }
+            let mut included = vec![false; values.len()];
+            let mut total = Amount::ZERO;
+            let mut reached = false;
+            // First pass adds random coins, the second adds all the others, until the target is reached.
+            for pass in 0..2 {
+                if reached {
+                    break;
+                }
+                for (i, value) in values.iter().enumerate() {
+                    let pick = if pass == 0 {
+                        rng.next_u32() & 1 == 1
+                    } else {
+                        !included[i]
+                    };
+                    if !pick {
+                        continue;
+                    }
+                    total += *value;
+                    included[i] = true;
+                    if total >= target {
+                        reached = true;
+                        if best
+                            .as_ref()
+                            .is_none_or(|(best_total, _)| total < *best_total)
+                        {
+                            best = Some((total, included.clone()));
+                        }
+                        // Look for a smaller subset without this coin.
+                        total -= *value;
+                        included[i] = false;
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+impl CoinSelector for Knapsack {
+    fn select(
+        &self,
+        coins: Vec<(ListUnspentResultEntry, UTXOSpendInfo)>,
+        target: Amount,
+        change_threshold: Amount,
+        fee_rate: f64,
+    ) -> Vec<(ListUnspentResultEntry, UTXOSpendInfo)> {
+        self.select_with_rng(coins, target, change_threshold, fee_rate, &mut OsRng)
+    }
+}
+
+/// The coin selection algorithm used by the wallet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CoinSelectionAlgorithm {
+    /// [`BranchAndBound`], falling back to [`LargestFirst`].
+    #[default]
+    BranchAndBound,
+    /// [`Knapsack`].
+    Knapsack,
+    /// [`LargestFirst`].
+    LargestFirst,
+}
+
+impl CoinSelectionAlgorithm {
+    /// The selector implementing the algorithm.
+    pub fn selector(&self) -> &'static dyn CoinSelector {
+        match self {
+            Self::BranchAndBound => &BranchAndBound,
+            Self::Knapsack => &Knapsack,
+            Self::LargestFirst => &LargestFirst,
+        }
+    }
+}
+
+impl FromStr for CoinSelectionAlgorithm {
+    type Err = WalletError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "branch_and_bound" => Ok(Self::BranchAndBound),
+            "knapsack" => Ok(Self::Knapsack),
+            "largest_first" => Ok(Self::LargestFirst),
+            _ => Err(WalletError::General(format!(
+                "Unknown coin selection algorithm {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl Display for CoinSelectionAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BranchAndBound => write!(f, "branch_and_bound"),
+            Self::Knapsack => write!(f, "knapsack"),
+            Self::LargestFirst => write!(f, "largest_first"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wallet::fee::{calculate_fee, coin_input_vsize};
+    use bitcoin::{hashes::Hash, secp256k1::rand::Error, ScriptBuf, Txid};
+
+    /// Deterministic xorshift rng, so the knapsack tests don't depend on the OS rng.
+    struct TestRng(u64);
+
+    impl RngCore for TestRng {
+        fn next_u32(&mut self) -> u32 {
+            self.next_u64() as u32
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.iter_mut()
+                .for_each(|byte| *byte = self.next_u32() as u8);
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    fn coins(values: &[u64]) -> Vec<(ListUnspentResultEntry, UTXOSpendInfo)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(vout, sats)| {
+                let utxo = ListUnspentResultEntry {
+                    txid: Txid::all_zeros(),
+                    vout: vout as u32,
+                    address: None,
+                    label: None,
+                    redeem_script: None,
+                    witness_script: None,
+                    script_pub_key: ScriptBuf::new(),
+                    amount: Amount::from_sat(*sats),
+                    confirmations: 1,
+                    spendable: true,
+                    solvable: true,
+                    descriptor: None,
+                    safe: true,
+                };
+                let spend_info = UTXOSpendInfo::SeedCoin {
+                    path: format!("m/0/{}", vout),
+                    input_value: Amount::from_sat(*sats),
+                };
+                (utxo, spend_info)
+            })
+            .collect()
+    }
+
+    fn total(coins: &[(ListUnspentResultEntry, UTXOSpendInfo)]) -> u64 {
+        coins.iter().map(|(utxo, _)| utxo.amount.to_sat()).sum()
+    }
+
+    #[test]
+    fn test_largest_first() {
+        let coins = coins(&[10_000, 50_000, 30_000, 40_000]);
+        let no_threshold = Amount::ZERO;
+
+        // The smallest coin covering the target alone.
+        let selected =
+            LargestFirst.select(coins.clone(), Amount::from_sat(35_000), no_threshold, 0.0);
+        assert_eq!(total(&selected), 40_000);
+
+        // Otherwise the largest coins.
+        let selected = LargestFirst.select(coins, Amount::from_sat(85_000), no_threshold, 0.0);
+        assert_eq!(total(&selected), 90_000);
+        assert_eq!(selected.len(), 2);
+    }
+
+    #[test]
+    fn test_branch_and_bound() {
+        let coins = coins(&[10_000, 50_000, 30_000, 40_000, 25_000]);
+
+        // 30_000 + 25_000 is within the threshold of the target, and needs no change.
+        let selected = BranchAndBound.select(
+            coins.clone(),
+            Amount::from_sat(54_800),
+            Amount::from_sat(300),
+            0.0,
+        );
+        assert_eq!(total(&selected), 55_000);
+
+        // The exact match is preferred over any other changeless selection.
+        let selected = BranchAndBound.select(
+            coins.clone(),
+            Amount::from_sat(65_000),
+            Amount::from_sat(5_000),
+            0.0,
+        );
+        assert_eq!(total(&selected), 65_000);
+
+        // No changeless selection, falls back to largest first.
+        let selected = BranchAndBound.select(
+            coins.clone(),
+            Amount::from_sat(42_000),
+            Amount::from_sat(300),
+            0.0,
+        );
+        assert_eq!(total(&selected), 50_000);
+
+        // With a feerate, each coin pays for its own input. 30_000 + 25_000 covers the target less
+        // the fees of both inputs, while no single coin does.
+        let fee_rate = 10.0;
+        let input_fee = calculate_fee(fee_rate, coin_input_vsize(&coins[0].1)).to_sat();
+        let selected = BranchAndBound.select(
+            coins,
+            Amount::from_sat(55_000 - 2 * input_fee),
+            Amount::from_sat(300),
+            fee_rate,
+        );
+        assert_eq!(total(&selected), 55_000);
+    }
+
+    #[test]
+    fn test_knapsack() {
+        let coins = coins(&[10_000, 50_000, 30_000, 40_000, 25_000]);
+
+        let select = |target: u64, threshold: u64| {
+            Knapsack.select_with_rng(
+                coins.clone(),
+                Amount::from_sat(target),
+                Amount::from_sat(threshold),
+                0.0,
+                &mut TestRng(0x2545_f491_4f6c_dd1d),
+            )
+        };
+
+        // The exact match is found among the random subsets.
+        assert_eq!(total(&select(75_000, 0)), 75_000);
+        assert_eq!(total(&select(154_000, 0)), 155_000);
+
+        // A selection within the threshold of the target needs no change.
+        assert_eq!(total(&select(54_800, 300)), 55_000);
+
+        // Otherwise the change must be at least the threshold.
+        let selected = select(52_000, 1_000);
+        assert!(total(&selected) >= 53_000);
+    }
+
+    #[test]
+    fn test_algorithm_parsing() {
+        for algorithm in [
+            CoinSelectionAlgorithm::BranchAndBound,
+            CoinSelectionAlgorithm::Knapsack,
+            CoinSelectionAlgorithm::LargestFirst,
+        ] {
+            assert_eq!(
+                algorithm
+                    .to_string()
+                    .parse::<CoinSelectionAlgorithm>()
+                    .unwrap(),
+                algorithm
+            );
+        }
+        assert_eq!(
+            "knapsack".parse::<CoinSelectionAlgorithm>().unwrap(),
+            CoinSelectionAlgorithm::Knapsack
+        );
+        assert!("random".parse::<CoinSelectionAlgorithm>().is_err());
+    }
+}
@@ -14,7 +14,12 @@ use bitcoind::bitcoincore_rpc::{json::ListUnspentResultEntry, RawTx, RpcApi};
 
 use crate::wallet::api::UTXOSpendInfo;
 
-use super::{error::WalletError, Wallet};
+use super::{
+    calculate_fee, change_threshold,
+    coin_control::{check_cross_pool, CoinPool},
+    error::WalletError,
+    Wallet, MIN_RELAY_FEERATE,
+};
 
 /// Represents options for specifying the amount to be sent in a transaction.
 #[derive(Debug, Clone, PartialEq)]
@@ -66,9 +71,11 @@ impl Wallet {
     /// API to perform spending from wallet UTXOs, including descriptor coins and swap coins.
     ///
     /// The caller needs to specify a list of UTXO data and their corresponding `spend_info`.
-    /// These can be extracted using various `list_utxo_*` Wallet APIs.
+    /// These can be selected with [Wallet::coin_select_with_fee], or extracted using various
+    /// `list_utxo_*` Wallet APIs.
     ///
-    /// The caller must also specify a total fee and a destination address.
+    /// The caller must also specify a feerate in sats/vbyte and a destination address.
+    /// The fee is calculated from the signed size of the transaction.
     /// Using [Destination::Wallet] will create a transaction to an internal wallet change address.
     ///
     /// ### Note
@@ -76,20 +83,31 @@ impl Wallet {
     /// (e.g., Hashlock or Timelock contracts). These UTXOs will be automatically skipped
     /// and not considered when creating the transaction.
     ///
+    /// Spending seed coins together with swap coins is refused, warned about or allowed as set by the
+    /// wallet's [CrossPoolPolicy](super::CrossPoolPolicy).
+    ///
     /// ### Behavior
     /// - If [SendAmount::Max] is used, the function creates a transaction for the maximum possible
     ///   value to the specified destination.
     /// - If [SendAmount::Amount] is used, a custom value is sent, and any remaining funds
-    ///    are held in a change address, if applicable.
+    ///    are held in a change address, if applicable. Remaining funds not worth a change output,
+    ///    as for coins selected by the changeless [BranchAndBound](super::BranchAndBound), go to the miners.
     pub fn spend_from_wallet(
         &mut self,
-        fee: Amount,
+        fee_rate: f64,
         send_amount: SendAmount,
         destination: Destination,
         coins_to_spend: &[(ListUnspentResultEntry, UTXOSpendInfo)],
     ) -> Result<Transaction, WalletError> {
         log::info!("Creating Direct-Spend from Wallet.");
 
+        if !fee_rate.is_finite() || fee_rate < MIN_RELAY_FEERATE {
+            return Err(WalletError::General(format!(
+                "Feerate {} sats/vb is below the minimum relay feerate",
+                fee_rate
+            )));
+        }
+
         // Set the Anti-Fee-Snipping locktime
         let current_height = self.rpc.get_block_count()?;
         let lock_time = LockTime::from_height(current_height as u32)?;
@@ -101,7 +119,11 @@ impl Wallet {
             output: vec![],
         };
 
+        // Seed coins and swap coins are not merged, unless the cross pool policy allows it.
+        check_cross_pool(coins_to_spend, self.cross_pool_policy)?;
+
         let mut total_input_value = Amount::ZERO;
+        let mut spend_infos = Vec::new();
 
         for (utxo_data, spend_info) in coins_to_spend {
             // filter all contract and fidelity utxos.
@@ -121,21 +143,13 @@ impl Wallet {
             });
 
             total_input_value += utxo_data.amount;
+            spend_infos.push(spend_info);
         }
 
-        if let SendAmount::Amount(a) = send_amount {
-            if a + fee > total_input_value {
-                return Err(WalletError::InsufficientFund {
-                    available: total_input_value.to_btc(),
-                    required: (a + fee).to_btc(),
-                });
-            }
-        }
-
-        log::info!("Total Input Amount: {} | Fees: {}", total_input_value, fee);
-
+        // Coins sent back to the wallet, and change, stay in the pool of the spent coins.
+        let change_pool = CoinPool::of_change(coins_to_spend);
         let dest_addr = match destination {
-            Destination::Wallet => self.get_next_internal_addresses(1)?[0].clone(),
+            Destination::Wallet => self.get_next_change_addresses(change_pool, 1)?[0].clone(),
             Destination::Address(a) => {
                 //testnet and signet addresses have the same vbyte
                 //so a.network is always testnet even if the address is signet
@@ -154,6 +168,39 @@ impl Wallet {
             }
         };
 
+        // Size the tx with all of its outputs to get the fee. Value of the outputs doesn't affect the size.
+        tx.output.push(TxOut {
+            script_pubkey: dest_addr.script_pubkey(),
+            value: Amount::ZERO,
+        });
+        let change_spk = match send_amount {
+            SendAmount::Max => None,
+            SendAmount::Amount(_) => {
+                let internal_spk =
+                    self.get_next_change_addresses(change_pool, 1)?[0].script_pubkey();
+                tx.output.push(TxOut {
+                    script_pubkey: internal_spk.clone(),
+                    value: Amount::ZERO,
+                });
+                Some(internal_spk)
+            }
+        };
+        let fee = calculate_fee(fee_rate, self.estimate_vsize(&tx, spend_infos.into_iter())?);
+        tx.output.clear();
+
+        let required = match send_amount {
+            SendAmount::Max => fee,
+            SendAmount::Amount(a) => a + fee,
+        };
+        if required > total_input_value {
+            return Err(WalletError::InsufficientFund {
+                available: total_input_value.to_btc(),
+                required: required.to_btc(),
+            });
+        }
+
+        log::info!("Total Input Amount: {} | Fees: {}", total_input_value, fee);
+
         let txout = {
             let value = match send_amount {
                 SendAmount::Max => total_input_value - fee,
@@ -169,11 +216,10 @@ impl Wallet {
 
         tx.output.push(txout);
 
-        // Only include change if remaining > dust
-        if let SendAmount::Amount(amount) = send_amount {
-            let internal_spk = self.get_next_internal_addresses(1)?[0].script_pubkey();
+        // Only include change if it's worth creating
+        if let (SendAmount::Amount(amount), Some(internal_spk)) = (send_amount, change_spk) {
             let remaining = total_input_value - amount - fee;
-            if remaining > internal_spk.minimal_non_dust() {
+            if remaining >= change_threshold(fee_rate) {
                 log::info!("Adding Change {}: {}", internal_spk, remaining);
                 tx.output.push(TxOut {
                     script_pubkey: internal_spk,
@@ -181,7 +227,7 @@ impl Wallet {
                 });
             } else {
                 log::info!(
-                    "Remaining change {} sats is below dust threshold. Skipping change output.",
+                    "Remaining change {} sats is below the change threshold. Skipping change output.",
                     remaining
                 );
             }
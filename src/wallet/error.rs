@@ -73,6 +73,33 @@ pub enum WalletError {
         /// The amount of funds needed to complete the operation.
         required: f64,
     },
+
+    /// Represents an error when a spend would merge seed coins with swap coins, and the wallet's
+    /// cross pool policy refuses it.
+    ///
+    /// Spend the coins of each pool separately, or change the `cross_pool_spend` config.
+    CrossPoolSpend,
+
+    /// Represents an error when the wallet file is encrypted but no passphrase was provided.
+    ///
+    /// Supply the passphrase used at wallet creation to load the wallet.
+    PassphraseRequired,
+
+    /// Represents an error when the wallet file cannot be decrypted with the given passphrase.
+    ///
+    /// This occurs with a wrong passphrase, or if the encrypted file has been tampered with.
+    WrongPassphrase,
+
+    /// Represents an error when the wallet file was written by a newer version of the software.
+    ///
+    /// - `found`: The version of the wallet file.
+    /// - `supported`: The latest wallet file version this software can read.
+    UnsupportedFileVersion {
+        /// The version of the wallet file.
+        found: u32,
+        /// The latest wallet file version this software can read.
+        supported: u32,
+    },
 }
 
 impl From<std::io::Error> for WalletError {
@@ -0,0 +1,393 @@
+//! Feerate estimation and transaction size calculation.
+//!
+//! All feerates are in sats/vbyte. The [FeeEstimator] queries Bitcoin Core's `estimatesmartfee`
+//! and bounds the estimate within a configured floor and ceiling.
+//!
+//! Transaction sizes are calculated before signing, from the unsigned transaction and the largest
+//! possible witness of each input. This gives the exact vsize of the signed transaction, or
+//! overshoots it by a byte for each signature that gets a shorter DER encoding.
+
+use bitcoin::{
+    absolute::LockTime, consensus::encode::VarInt, hashes::Hash, transaction::Version, Amount,
+    OutPoint, Script, ScriptBuf, Sequence, Transaction, TxIn, TxOut, WPubkeyHash, WScriptHash,
+    Witness,
+};
+use bitcoind::bitcoincore_rpc::{json::ListUnspentResultEntry, RpcApi};
+
+use super::{api::UTXOSpendInfo, error::WalletError, Wallet};
+
+/// Size of an ECDSA signature with the sighash flag, in its largest DER encoding.
+const MAX_SIGNATURE_SIZE: usize = 73;
+
+/// Size of a compressed public key.
+const PUBKEY_SIZE: usize = 33;
+
+/// Size of the hashlock preimage.
+const PREIMAGE_SIZE: usize = 32;
+
+/// Size of the 2-of-2 multisig redeemscript of a swapcoin.
+const MULTISIG_REDEEMSCRIPT_SIZE: usize = 71;
+
+/// Minimum feerate for a transaction to be relayed by Bitcoin Core nodes, in sats/vbyte.
+pub(crate) const MIN_RELAY_FEERATE: f64 = 1.0;
+
+/// Serialized size of a witness element of `len` bytes.
+fn element_size(len: usize) -> usize {
+    VarInt(len as u64).size() + len
+}
+
+/// Witness size of a P2WPKH spend: `<sig> <pubkey>`.
+pub(crate) fn p2wpkh_witness_size() -> usize {
+    1 + element_size(MAX_SIGNATURE_SIZE) + element_size(PUBKEY_SIZE)
+}
+
+/// Witness size of a 2-of-2 multisig spend: `<> <sig> <sig> <redeemscript>`.
+pub(crate) fn multisig_witness_size() -> usize {
+    1 + element_size(0)
+        + 2 * element_size(MAX_SIGNATURE_SIZE)
+        + element_size(MULTISIG_REDEEMSCRIPT_SIZE)
+}
+
+/// Witness size of a contract spend via the timelock branch: `<sig> <> <redeemscript>`.
+pub(crate) fn timelock_witness_size(contract_redeemscript: &Script) -> usize {
+    1 + element_size(MAX_SIGNATURE_SIZE)
+        + element_size(0)
+        + element_size(contract_redeemscript.len())
+}
+
+/// Witness size of a contract spend via the hashlock branch: `<sig> <preimage> <redeemscript>`.
+pub(crate) fn hashlock_witness_size(contract_redeemscript: &Script) -> usize {
+    1 + element_size(MAX_SIGNATURE_SIZE)
+        + element_size(PREIMAGE_SIZE)
+        + element_size(contract_redeemscript.len())
+}
+
+/// Vsize of `tx` once signed, given the witness size of each of its inputs.
+///
+/// `tx` should be unsigned, i.e have empty witnesses.
+pub(crate) fn signed_vsize(
+    tx: &Transaction,
+    witness_sizes: impl IntoIterator<Item = usize>,
+) -> u64 {
+    // 2 bytes for the segwit marker and flag.
+    let witness_size = 2 + witness_sizes.into_iter().sum::<usize>();
+    let weight = tx.base_size() * 4 + witness_size;
+    weight.div_ceil(4) as u64
+}
+
+/// Vsize an input spending a seed or swap coin adds to a tx, with its witness rounded up to a whole vbyte.
+/// Other coins are never selected for spending.
+pub(crate) fn coin_input_vsize(spend_info: &UTXOSpendInfo) -> u64 {
+    let witness_size = match spend_info {
+        UTXOSpendInfo::SwapCoin { .. } => multisig_witness_size(),
+        _ => p2wpkh_witness_size(),
+    };
+    (TxIn::default().base_size() + witness_size.div_ceil(4)) as u64
+}
+
+/// Fee for a transaction of `vsize` at `feerate` sats/vbyte, rounded up to the next sat.
+pub(crate) fn calculate_fee(feerate: f64, vsize: u64) -> Amount {
+    Amount::from_sat((feerate * vsize as f64).ceil() as u64)
+}
+
+/// Smallest change worth adding to a tx at `feerate`: the fee of a P2WPKH change output and of spending it
+/// later, and at least its dust limit. A smaller change goes to the miners instead.
+pub(crate) fn change_threshold(feerate: f64) -> Amount {
+    let change = TxOut {
+        value: Amount::ZERO,
+        script_pubkey: ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros()),
+    };
+    let input = TxIn::default();
+    let vsize = (change.size() + input.base_size() + p2wpkh_witness_size().div_ceil(4)) as u64;
+    calculate_fee(feerate, vsize).max(change.script_pubkey.minimal_non_dust())
+}
+
+fn template_tx(outputs: Vec<ScriptBuf>) -> Transaction {
+    Transaction {
+        version: Version::TWO,
+        lock_time: LockTime::ZERO,
+        input: vec![TxIn {
+            previous_output: OutPoint::null(),
+            script_sig: ScriptBuf::new(),
+            sequence: Sequence::ZERO,
+            witness: Witness::new(),
+        }],
+        output: outputs
+            .into_iter()
+            .map(|script_pubkey| TxOut {
+                value: Amount::ZERO,
+                script_pubkey,
+            })
+            .collect(),
+    }
+}
+
+/// Vsize of a funding transaction, with one P2WPKH input, the P2WSH multisig output and a P2WPKH change.
+///
+/// The swap parties agree on the fees of a funding transaction with this size. A funding transaction
+/// spending more inputs pays the extra fee from its creator's change.
+pub(crate) fn funding_tx_vsize() -> u64 {
+    let tx = template_tx(vec![
+        ScriptBuf::new_p2wsh(&WScriptHash::all_zeros()),
+        ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros()),
+    ]);
+    signed_vsize(&tx, [p2wpkh_witness_size()])
+}
+
+/// Vsize of a contract transaction, spending the 2-of-2 multisig into the P2WSH contract.
+pub(crate) fn contract_tx_vsize() -> u64 {
+    let tx = template_tx(vec![ScriptBuf::new_p2wsh(&WScriptHash::all_zeros())]);
+    signed_vsize(&tx, [multisig_witness_size()])
+}
+
+/// Vsize of a refund transaction, spending the 2-of-2 multisig straight back into a P2WPKH output.
+pub(crate) fn refund_tx_vsize() -> u64 {
+    let tx = template_tx(vec![ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros())]);
+    signed_vsize(&tx, [multisig_witness_size()])
+}
+
+/// Vsize of a timelock spend of a contract into a P2WPKH output.
+pub(crate) fn timelock_spend_vsize(contract_redeemscript: &Script) -> u64 {
+    let tx = template_tx(vec![ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros())]);
+    signed_vsize(&tx, [timelock_witness_size(contract_redeemscript)])
+}
+
+/// Feerate estimation parameters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FeeEstimator {
+    /// Confirmation target in blocks, passed to `estimatesmartfee`.
+    pub conf_target: u16,
+    /// Lowest feerate to use, in sats/vbyte. Also used when Core has no estimate.
+    pub min_feerate: f64,
+    /// Highest feerate to use, in sats/vbyte.
+    pub max_feerate: f64,
+}
+
+impl Default for FeeEstimator {
+    fn default() -> Self {
+        Self {
+            conf_target: 3,
+            min_feerate: 2.0,
+            max_feerate: 100.0,
+        }
+    }
+}
+
+impl FeeEstimator {
+    /// Bounds a feerate within the floor and ceiling.
+    pub(crate) fn bound(&self, feerate: f64) -> f64 {
+        feerate.max(self.min_feerate).min(self.max_feerate)
+    }
+}
+
+impl Wallet {
+    /// Estimates the feerate in sats/vbyte for the estimator's confirmation target.
+    ///
+    /// Falls back to the estimator's floor if Core doesn't have enough data for an estimate,
+    /// which is always the case in regtest.
+    pub fn estimate_feerate(&self, estimator: &FeeEstimator) -> Result<f64, WalletError> {
+        let estimate = self.rpc.estimate_smart_fee(estimator.conf_target, None)?;
+        let feerate = match estimate.fee_rate {
+            // Core reports BTC/kvB.
+            Some(rate) => rate.to_sat() as f64 / 1000.0,
+            None => {
+                log::warn!(
+                    "No feerate estimate available, using the minimum feerate {} sats/vb. Errors: {:?}",
+                    estimator.min_feerate,
+                    estimate.errors.unwrap_or_default()
+                );
+                estimator.min_feerate
+            }
+        };
+        Ok(estimator.bound(feerate))
+    }
+
+    /// Witness size of an input, once signed as per its spend info.
+    pub(crate) fn witness_size(&self, spend_info: &UTXOSpendInfo) -> Result<usize, WalletError> {
+        Ok(match spend_info {
+            UTXOSpendInfo::SeedCoin { .. } => p2wpkh_witness_size(),
+            UTXOSpendInfo::SwapCoin { .. } => multisig_witness_size(),
+            UTXOSpendInfo::TimelockContract {
+                swapcoin_multisig_redeemscript,
+                ..
+            } => {
+                let swapcoin = self
+                    .find_outgoing_swapcoin(swapcoin_multisig_redeemscript)
+                    .ok_or(WalletError::General(
+                        "Outgoing swapcoin expected".to_string(),
+                    ))?;
+                timelock_witness_size(&swapcoin.contract_redeemscript)
+            }
+            UTXOSpendInfo::HashlockContract {
+                swapcoin_multisig_redeemscript,
+                ..
+            } => {
+                let swapcoin = self
+                    .find_incoming_swapcoin(swapcoin_multisig_redeemscript)
+                    .ok_or(WalletError::General(
+                        "Incoming swapcoin expected".to_string(),
+                    ))?;
+                hashlock_witness_size(&swapcoin.contract_redeemscript)
+            }
+            UTXOSpendInfo::FidelityBondCoin { index, .. } => {
+                let redeemscript = self.get_fidelity_reedemscript(*index)?;
+                1 + element_size(MAX_SIGNATURE_SIZE) + element_size(redeemscript.len())
+            }
+        })
+    }
+
+    /// Selects coins to pay `amount` in a tx with the given outputs, and the fee of that tx at `fee_rate`.
+    ///
+    /// Coins are selected by their effective values, so they cover the fee of the tx without its inputs
+    /// and the fees of their own inputs. Returns the selected coins and the fee. The fee includes the last
+    /// output, which is meant for the change and should be left out if the change is below [`change_threshold`].
+    pub(crate) fn coin_select_for_outputs(
+        &self,
+        amount: Amount,
+        fee_rate: f64,
+        outputs: &[ScriptBuf],
+    ) -> Result<(Vec<(ListUnspentResultEntry, UTXOSpendInfo)>, Amount), WalletError> {
+        let mut tx = template_tx(outputs.to_vec());
+        tx.input.clear();
+        let outputs_fee = calculate_fee(fee_rate, signed_vsize(&tx, []));
+        let selected =
+            self.coin_select(amount + outputs_fee, fee_rate, change_threshold(fee_rate))?;
+
+        tx.input = selected
+            .iter()
+            .map(|(unspent, _)| TxIn {
+                previous_output: OutPoint::new(unspent.txid, unspent.vout),
+                script_sig: ScriptBuf::new(),
+                sequence: Sequence::ZERO,
+                witness: Witness::new(),
+            })
+            .collect();
+        let fee = calculate_fee(
+            fee_rate,
+            self.estimate_vsize(&tx, selected.iter().map(|(_, info)| info))?,
+        );
+        Ok((selected, fee))
+    }
+
+    /// Selects coins to send `amount` with a change output, and the fee of spending them at `fee_rate` sats/vbyte.
+    pub fn coin_select_with_fee(
+        &self,
+        amount: Amount,
+        fee_rate: f64,
+    ) -> Result<Vec<(ListUnspentResultEntry, UTXOSpendInfo)>, WalletError> {
+        let p2wpkh = ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros());
+        let (selected, _) =
+            self.coin_select_for_outputs(amount, fee_rate, &[p2wpkh.clone(), p2wpkh])?;
+        Ok(selected)
+    }
+
+    /// Vsize of `tx` once signed, given the spend info of its inputs in order.
+    pub(crate) fn estimate_vsize<'a>(
+        &self,
+        tx: &Transaction,
+        inputs_info: impl Iterator<Item = &'a UTXOSpendInfo>,
+    ) -> Result<u64, WalletError> {
+        let witness_sizes = inputs_info
+            .map(|info| self.witness_size(info))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(signed_vsize(tx, witness_sizes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{
+        contract::{create_contract_redeemscript, create_multisig_redeemscript},
+        Hash160,
+    };
+    use bitcoin::PublicKey;
+    use std::str::FromStr;
+
+    fn pubkey(n: u8) -> PublicKey {
+        let keys = [
+            "032e58afe51f9ed8ad3cc7897f634d881fdbe49a81564629ded8156bebd2ffd1af",
+            "039b6347398505f5ec93826dc61c19f47c66c0283ee9be980e29ce325a0f4679ef",
+        ];
+        PublicKey::from_str(keys[n as usize]).unwrap()
+    }
+
+    /// Fills each input with a witness of the largest possible size for the given stack shape.
+    fn sign_dummy(tx: &mut Transaction, stack: &[usize]) {
+        for input in tx.input.iter_mut() {
+            input.witness =
+                Witness::from_slice(&stack.iter().map(|len| vec![0u8; *len]).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_template_vsizes() {
+        // One P2WPKH input, P2WSH + P2WPKH outputs.
+        assert_eq!(funding_tx_vsize(), 153);
+        // One 2-of-2 multisig input, one P2WSH output.
+        assert_eq!(contract_tx_vsize(), 150);
+
+        let multisig = create_multisig_redeemscript(&pubkey(0), &pubkey(1));
+        assert_eq!(multisig.len(), MULTISIG_REDEEMSCRIPT_SIZE);
+
+        let mut tx = template_tx(vec![ScriptBuf::new_p2wsh(&WScriptHash::all_zeros())]);
+        sign_dummy(
+            &mut tx,
+            &[0, MAX_SIGNATURE_SIZE, MAX_SIGNATURE_SIZE, multisig.len()],
+        );
+        assert_eq!(tx.vsize() as u64, contract_tx_vsize());
+
+        // One 2-of-2 multisig input, one P2WPKH output.
+        assert_eq!(refund_tx_vsize(), 138);
+        let mut tx = template_tx(vec![ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros())]);
+        sign_dummy(
+            &mut tx,
+            &[0, MAX_SIGNATURE_SIZE, MAX_SIGNATURE_SIZE, multisig.len()],
+        );
+        assert_eq!(tx.vsize() as u64, refund_tx_vsize());
+    }
+
+    #[test]
+    fn test_contract_spend_vsizes() {
+        let contract = create_contract_redeemscript(
+            &pubkey(0),
+            &pubkey(1),
+            &Hash160::from_byte_array([1u8; 20]),
+            &100,
+        );
+
+        let mut tx = template_tx(vec![ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros())]);
+        sign_dummy(&mut tx, &[MAX_SIGNATURE_SIZE, 0, contract.len()]);
+        assert_eq!(tx.vsize() as u64, timelock_spend_vsize(&contract));
+
+        let mut tx = template_tx(vec![ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros())]);
+        sign_dummy(
+            &mut tx,
+            &[MAX_SIGNATURE_SIZE, PREIMAGE_SIZE, contract.len()],
+        );
+        let unsigned = template_tx(vec![ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros())]);
+        assert_eq!(
+            tx.vsize() as u64,
+            signed_vsize(&unsigned, [hashlock_witness_size(&contract)])
+        );
+
+        // The hashlock branch carries the 32 byte preimage instead of an empty element.
+        assert_eq!(
+            hashlock_witness_size(&contract) - timelock_witness_size(&contract),
+            PREIMAGE_SIZE
+        );
+    }
+
+    #[test]
+    fn test_fee_calculation() {
+        assert_eq!(calculate_fee(2.0, 154), Amount::from_sat(308));
+        // Fees are rounded up.
+        assert_eq!(calculate_fee(1.5, 141), Amount::from_sat(212));
+        assert_eq!(calculate_fee(0.0, 141), Amount::ZERO);
+
+        let estimator = FeeEstimator::default();
+        assert_eq!(estimator.bound(0.5), estimator.min_feerate);
+        assert_eq!(estimator.bound(10.0), 10.0);
+        assert_eq!(estimator.bound(1000.0), estimator.max_feerate);
+    }
+}
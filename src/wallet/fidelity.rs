@@ -7,7 +7,6 @@ use std::{
 
 use crate::{
     protocol::messages::FidelityProof,
-    taker::api::MINER_FEE,
     utill::{redeemscript_to_scriptpubkey, verify_fidelity_checks},
     wallet::{UTXOSpendInfo, Wallet},
 };
@@ -21,18 +20,26 @@ use bitcoin::{
     secp256k1::{Keypair, Message, Secp256k1},
     transaction::Version,
     Address, Amount, OutPoint, PublicKey, ScriptBuf, Sequence, Transaction, TxIn, TxOut, Txid,
-    Witness,
+    WPubkeyHash, Witness,
 };
-use bitcoind::bitcoincore_rpc::RpcApi;
+use bitcoind::bitcoincore_rpc::{json::GetTransactionResultDetailCategory, RpcApi};
 use serde::{Deserialize, Serialize};
 
-use super::WalletError;
+use super::{change_threshold, WalletError};
 
 // To (strongly) disincentivize Sybil behavior, the value assessment of the bond
 // is based on the (time value of the bond)^x here x is the bond_value_exponent,
 // where x > 1.
 const BOND_VALUE_EXPONENT: f64 = 1.3;
 
+// TODO: Use a feerate estimate for fidelity bond redemption txs too.
+// Fixed miner fee for fidelity bond redemption txs.
+#[cfg(feature = "integration-test")]
+const FIDELITY_TX_FEE: u64 = 1000;
+
+#[cfg(not(feature = "integration-test"))]
+const FIDELITY_TX_FEE: u64 = 300; // around 2 sats/vb for fidelity tx
+
 // Interest rate used when calculating the value of fidelity bonds created
 // by locking bitcoins in timelocked addresses
 // See also:
@@ -43,6 +50,13 @@ const BOND_VALUE_INTEREST_RATE: f64 = 0.015;
 /// Constant representing the derivation path for fidelity addresses.
 const FIDELITY_DERIVATION_PATH: &str = "m/84'/0'/0'/2";
 
+// The bond locktime is not derivable from the seed. While recovering bonds, locktimes up to this
+// many blocks after the bond transaction are searched. Approx 2 years of blocks.
+const FIDELITY_RECOVERY_MAX_LOCKTIME: u32 = 105_120;
+
+// Recovery stops after this many consecutive fidelity key indexes without a bond.
+const FIDELITY_RECOVERY_GAP_LIMIT: u32 = 5;
+
 /// Error structure defining possible fidelity related errors
 #[derive(Debug)]
 pub enum FidelityError {
@@ -216,6 +230,21 @@ impl Wallet {
             .to_keypair(&secp))
     }
 
+    /// Get the [KeyPair] for the fidelity bond at given outpoint.
+    pub(crate) fn get_fidelity_keypair_by_outpoint(
+        &self,
+        outpoint: &OutPoint,
+    ) -> Result<Keypair, WalletError> {
+        let index = self
+            .store
+            .fidelity_bond
+            .iter()
+            .find(|(_, (bond, _, _))| bond.outpoint == *outpoint)
+            .map(|(i, _)| *i)
+            .ok_or(FidelityError::BondDoesNotExist)?;
+        self.get_fidelity_keypair(index)
+    }
+
     /// Derives the fidelity redeemscript from bond values at given index.
     pub(crate) fn get_fidelity_reedemscript(&self, index: u32) -> Result<ScriptBuf, WalletError> {
         let (bond, _, _) = self
@@ -266,6 +295,14 @@ impl Wallet {
             .fidelity_bond
             .get(&index)
             .ok_or(FidelityError::BondDoesNotExist)?;
+        self.calculate_fidelity_bond_value(bond)
+    }
+
+    /// Calculate the theoretical value of any fidelity bond, including the bonds of other makers.
+    pub(crate) fn calculate_fidelity_bond_value(
+        &self,
+        bond: &FidelityBond,
+    ) -> Result<Amount, WalletError> {
         let current_time = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("This can't error")
@@ -301,45 +338,27 @@ impl Wallet {
         Ok(bond_value)
     }
 
-    /// Create a new fidelity bond with given amount and locktime.
+    /// Create a new fidelity bond with given amount and locktime, paying `fee_rate` sats/vbyte.
     /// This functions creates the fidelity transaction, signs and broadcast it.
     /// Upon confirmation it stores the fidelity information in the wallet data.
     pub fn create_fidelity(
         &mut self,
         amount: Amount,
         locktime: LockTime, // The final locktime in blockheight or timestamp
+        fee_rate: f64,
     ) -> Result<u32, WalletError> {
         let (index, fidelity_addr, fidelity_pubkey) = self.get_next_fidelity_address(locktime)?;
 
-        let all_utxos = self.get_all_utxo()?;
-
-        let mut seed_coin_utxo = self.list_descriptor_utxo_spend_info(Some(&all_utxos))?;
-        let mut swap_coin_utxo = self.list_swap_coin_utxo_spend_info(Some(&all_utxos))?;
-        seed_coin_utxo.append(&mut swap_coin_utxo);
-
-        // Fetch utxos, filter out existing fidelity coins
-        let mut unspents = seed_coin_utxo
-            .into_iter()
-            .filter(|(_, spend_info)| !matches!(spend_info, UTXOSpendInfo::FidelityBondCoin { .. }))
-            .collect::<Vec<_>>();
-
-        unspents.sort_by(|a, b| b.0.amount.cmp(&a.0.amount));
-
-        let mut selected_utxo = Vec::new();
-        let mut remaining = amount;
-
-        // the simplest largest first coinselection.
-        for unspent in unspents {
-            if remaining.checked_sub(unspent.0.amount).is_none() {
-                selected_utxo.push(unspent);
-                break;
-            } else {
-                remaining -= unspent.0.amount;
-                selected_utxo.push(unspent);
-            }
-        }
-
-        let fee = Amount::from_sat(MINER_FEE); // TODO: Update this with the feerate
+        // Select from a single coin pool, like for any other spend. The change address is only
+        // derived once it's needed, any P2WPKH script sizes the tx.
+        let (selected_utxo, fee) = self.coin_select_for_outputs(
+            amount,
+            fee_rate,
+            &[
+                fidelity_addr.script_pubkey(),
+                ScriptBuf::new_p2wpkh(&WPubkeyHash::all_zeros()),
+            ],
+        )?;
 
         let total_input_amount = selected_utxo.iter().fold(Amount::ZERO, |acc, (unspet, _)| {
             acc.checked_add(unspet.amount)
@@ -370,9 +389,9 @@ impl Wallet {
         }];
 
         if let Some(change) = change_amount {
-            let change_addrs = self.get_next_internal_addresses(1)?[0].script_pubkey();
-            // check for dust
-            if change > change_addrs.minimal_non_dust() {
+            // Only include change if it's worth creating, otherwise it goes to the miners.
+            if change >= change_threshold(fee_rate) {
+                let change_addrs = self.get_next_internal_addresses(1)?[0].script_pubkey();
                 tx_outs.push(TxOut {
                     value: change,
                     script_pubkey: change_addrs,
@@ -469,8 +488,7 @@ impl Wallet {
             witness: Witness::new(),
         };
 
-        // TODO take feerate as user input
-        let fee = Amount::from_sat(MINER_FEE);
+        let fee = Amount::from_sat(FIDELITY_TX_FEE);
 
         let change_addr = &self.get_next_internal_addresses(1)?[0];
 
@@ -565,6 +583,110 @@ impl Wallet {
         let current_height = self.rpc.get_block_count()?;
         Ok((current_height + 2) /* safety buffer */ / 2016 + 5)
     }
+
+    /// Recover fidelity bonds created by this wallet's seed, from the blockchain.
+    ///
+    /// Used when restoring a wallet from its mnemonic. The wallet must be synced first, so that the
+    /// bond funding transactions are known to the core wallet. Bond transactions are identified by
+    /// brute-forcing the bond locktime, for each fidelity key index, against the first output of the
+    /// wallet's outgoing transactions.
+    ///
+    /// Returns the number of recovered bonds.
+    pub(crate) fn recover_fidelity_bonds(&mut self) -> Result<u32, WalletError> {
+        // Candidate bond outputs: the first output of confirmed transactions sent by this wallet.
+        let mut candidates = Vec::new();
+        for entry in self
+            .rpc
+            .list_since_block(None, None, Some(true), None)?
+            .transactions
+        {
+            let Some(conf_height) = entry.info.blockheight else {
+                continue;
+            };
+            if entry.detail.category != GetTransactionResultDetailCategory::Send
+                || entry.detail.vout != 0
+            {
+                continue;
+            }
+            let tx = self.rpc.get_raw_transaction(&entry.info.txid, None)?;
+            if !tx.output[0].script_pubkey.is_p2wsh() {
+                continue;
+            }
+            // Bond locktime is set relative to the height at bond creation, which is the
+            // anti-fee-snipping locktime of the bond transaction.
+            let start_height = match tx.lock_time {
+                LockTime::Blocks(height) => height.to_consensus_u32(),
+                LockTime::Seconds(_) => conf_height,
+            };
+            candidates.push((
+                entry.info.txid,
+                conf_height,
+                start_height,
+                tx.output[0].clone(),
+            ));
+        }
+
+        let mut recovered = 0;
+        let mut index = 0;
+        let mut gap = 0;
+        while gap < FIDELITY_RECOVERY_GAP_LIMIT {
+            let pubkey = PublicKey {
+                compressed: true,
+                inner: self.get_fidelity_keypair(index)?.public_key(),
+            };
+
+            let found = candidates
+                .iter()
+                .find_map(|(txid, conf_height, start, txout)| {
+                    (*start..=start.saturating_add(FIDELITY_RECOVERY_MAX_LOCKTIME)).find_map(
+                        |height| {
+                            let lock_time = LockTime::from_height(height).ok()?;
+                            let spk = redeemscript_to_scriptpubkey(&fidelity_redeemscript(
+                                &lock_time, &pubkey,
+                            ))
+                            .ok()?;
+                            (spk == txout.script_pubkey).then_some((
+                                *txid,
+                                *conf_height,
+                                lock_time,
+                                txout.value,
+                                spk,
+                            ))
+                        },
+                    )
+                });
+
+            if let Some((txid, conf_height, lock_time, amount, spk)) = found {
+                let is_spent = self.rpc.get_tx_out(&txid, 0, None)?.is_none();
+                let bond = FidelityBond {
+                    outpoint: OutPoint::new(txid, 0),
+                    amount,
+                    lock_time,
+                    pubkey,
+                    conf_height,
+                    cert_expiry: self.get_fidelity_expiry()?,
+                };
+                log::info!(
+                    "Recovered Fidelity Bond | Index: {} | Outpoint: {} | Amount: {} | Locktime: {} | Spent: {}",
+                    index,
+                    bond.outpoint,
+                    amount,
+                    lock_time,
+                    is_spent
+                );
+                self.store
+                    .fidelity_bond
+                    .insert(index, (bond, spk, is_spent));
+                recovered += 1;
+                gap = 0;
+            } else {
+                gap += 1;
+            }
+            index += 1;
+        }
+
+        Ok(recovered)
+    }
 }
 
 #[cfg(test)]
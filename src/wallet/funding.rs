@@ -15,12 +15,13 @@ use bitcoind::bitcoincore_rpc::{json::CreateRawTransactionInput, RpcApi};
 
 use bitcoin::secp256k1::rand::{rngs::OsRng, RngCore};
 
-use crate::taker::api::MINER_FEE;
-
-use super::Wallet;
+use super::{calculate_fee, change_threshold, coin_control::CoinPool, funding_tx_vsize, Wallet};
 
 use super::error::WalletError;
 
+/// Lower limit of each split of a swap amount, there should always be enough to pay miner fees.
+pub(crate) const MIN_SPLIT_AMOUNT: u64 = 5000;
+
 #[derive(Debug)]
 pub(crate) struct CreateFundingTxesResult {
     pub(crate) funding_txes: Vec<Transaction>,
@@ -35,7 +36,7 @@ impl Wallet {
         &self,
         coinswap_amount: Amount,
         destinations: &[Address],
-        fee_rate: Amount,
+        fee_rate: f64,
     ) -> Result<CreateFundingTxesResult, WalletError> {
         let ret = self.create_funding_txes_random_amounts(coinswap_amount, destinations, fee_rate);
         if ret.is_ok() {
@@ -100,8 +101,7 @@ impl Wallet {
         let mut output_values = Wallet::generate_amount_fractions_without_correction(
             count,
             total_amount,
-            5000, //use 5000 satoshi as the lower limit for now
-                  //there should always be enough to pay miner fees
+            MIN_SPLIT_AMOUNT,
         )?
         .iter()
         .map(|f| (*f * (total_amount.to_sat() as f32)) as u64)
@@ -129,9 +129,13 @@ impl Wallet {
         &self,
         coinswap_amount: Amount,
         destinations: &[Address],
-        fee_rate: Amount,
+        fee_rate: f64,
     ) -> Result<CreateFundingTxesResult, WalletError> {
-        let change_addresses = self.get_next_internal_addresses(destinations.len() as u32)?;
+        // Change goes back to the pool of the coins each tx spends.
+        let seed_change_addresses =
+            self.get_next_change_addresses(CoinPool::Seed, destinations.len() as u32)?;
+        let swap_change_addresses =
+            self.get_next_change_addresses(CoinPool::Swap, destinations.len() as u32)?;
 
         let output_values = Wallet::generate_amount_fractions(destinations.len(), coinswap_amount)?;
 
@@ -140,31 +144,45 @@ impl Wallet {
         let mut funding_txes = Vec::<Transaction>::new();
         let mut payment_output_positions = Vec::<u32>::new();
         let mut total_miner_fee = 0;
-        for ((address, &output_value), change_address) in destinations
-            .iter()
-            .zip(output_values.iter())
-            .zip(change_addresses.iter())
+        for (i, (address, &output_value)) in
+            destinations.iter().zip(output_values.iter()).enumerate()
         {
-            let mut outputs = HashMap::<String, Amount>::new();
-            outputs.insert(address.to_string(), Amount::from_sat(output_value));
-
-            let fee = fee_rate;
             let remaining = Amount::from_sat(output_value);
-            let selected_utxo = self.coin_select(remaining)?;
+            let payment_output = TxOut {
+                value: remaining,
+                script_pubkey: address.script_pubkey(),
+            };
+            // Both change addresses are of the same type, so either sizes the tx.
+            let (selected_utxo, fee) = self.coin_select_for_outputs(
+                remaining,
+                fee_rate,
+                &[
+                    payment_output.script_pubkey.clone(),
+                    seed_change_addresses[i].script_pubkey(),
+                ],
+            )?;
+            let change_spk = match CoinPool::of_change(&selected_utxo) {
+                CoinPool::Seed => seed_change_addresses[i].script_pubkey(),
+                CoinPool::Swap => swap_change_addresses[i].script_pubkey(),
+            };
             let total_input_amount = selected_utxo.iter().fold(Amount::ZERO, |acc, (unspet, _)| {
                 acc.checked_add(unspet.amount)
                     .expect("Amount sum overflowed")
             });
-            let change_amount = total_input_amount.checked_sub(remaining + fee);
-            let mut tx_outs = vec![TxOut {
-                value: Amount::from_sat(output_value),
-                script_pubkey: address.script_pubkey(),
-            }];
 
-            if let Some(change) = change_amount {
+            let change_amount = total_input_amount.checked_sub(remaining + fee).ok_or(
+                WalletError::InsufficientFund {
+                    available: total_input_amount.to_btc(),
+                    required: (remaining + fee).to_btc(),
+                },
+            )?;
+            let mut tx_outs = vec![payment_output];
+
+            // Only include change if it's worth creating, otherwise it goes to the miners.
+            if change_amount >= change_threshold(fee_rate) {
                 tx_outs.push(TxOut {
-                    value: change,
-                    script_pubkey: change_address.script_pubkey(),
+                    value: change_amount,
+                    script_pubkey: change_spk,
                 });
             }
             let tx_inputs = selected_utxo
@@ -203,9 +221,14 @@ impl Wallet {
 
             let payment_pos = 0;
 
+            let output_amount = funding_tx
+                .output
+                .iter()
+                .fold(Amount::ZERO, |acc, txout| acc + txout.value);
+
             funding_txes.push(funding_tx);
             payment_output_positions.push(payment_pos);
-            total_miner_fee += fee_rate.to_sat();
+            total_miner_fee += (total_input_amount - output_amount).to_sat();
         }
 
         Ok(CreateFundingTxesResult {
@@ -219,7 +242,7 @@ impl Wallet {
         &self,
         coinswap_amount: Amount,
         destinations: &[Address],
-        fee_rate: Amount,
+        fee_rate: f64,
         change_address: &Address,
         utxos: &mut dyn Iterator<Item = (Txid, u32, u64)>, //utxos item is (txid, vout, value)
                                                            //utxos should be sorted by size, largest first
@@ -267,7 +290,7 @@ impl Wallet {
 
             leftover_coinswap_amount -= funding_tx.output[0].value;
 
-            total_miner_fee += fee_rate.to_sat();
+            total_miner_fee += calculate_fee(fee_rate, funding_tx_vsize()).to_sat();
 
             funding_txes.push(funding_tx);
             payment_output_positions.push(0);
@@ -317,7 +340,7 @@ impl Wallet {
 
         leftover_coinswap_amount -= funding_tx.output[0].value;
 
-        total_miner_fee += fee_rate.to_sat();
+        total_miner_fee += calculate_fee(fee_rate, funding_tx_vsize()).to_sat();
 
         funding_txes.push(funding_tx);
         payment_output_positions.push(0);
@@ -358,7 +381,7 @@ impl Wallet {
         let mut info = iter::once(self.get_utxo((first_txid, first_vout))?.unwrap());
         self.sign_transaction(&mut funding_tx, &mut info)?;
 
-        total_miner_fee += fee_rate.to_sat();
+        total_miner_fee += calculate_fee(fee_rate, funding_tx_vsize()).to_sat();
 
         funding_txes.push(funding_tx);
         payment_output_positions.push(1);
@@ -374,7 +397,7 @@ impl Wallet {
         &self,
         coinswap_amount: Amount,
         destinations: &[Address],
-        fee_rate: Amount,
+        fee_rate: f64,
     ) -> Result<CreateFundingTxesResult, WalletError> {
         //this function creates funding txes by
         //using walletcreatefundedpsbt for the total amount, and if
@@ -387,11 +410,12 @@ impl Wallet {
 
         self.lock_unspendable_utxos()?;
 
-        let fee = Amount::from_sat(MINER_FEE);
+        let fee = calculate_fee(fee_rate, funding_tx_vsize());
 
         let remaining = coinswap_amount;
 
-        let selected_utxo = self.coin_select(remaining + fee)?;
+        let selected_utxo =
+            self.coin_select(remaining + fee, fee_rate, change_threshold(fee_rate))?;
 
         let total_input_amount = selected_utxo.iter().fold(Amount::ZERO, |acc, (unspet, _)| {
             acc.checked_add(unspet.amount)
@@ -460,7 +484,7 @@ impl Wallet {
         &self,
         coinswap_amount: Amount,
         destinations: &[Address],
-        fee_rate: Amount,
+        fee_rate: f64,
     ) -> Result<CreateFundingTxesResult, WalletError> {
         //this function will pick the top most valuable UTXOs and use them
         //to create funding transactions
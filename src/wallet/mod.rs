@@ -1,17 +1,31 @@
 //! The Coinswap Wallet (unsecured). Used by both the Taker and Maker.
 
 mod api;
+mod coin_control;
+mod coin_selection;
 mod direct_send;
 mod error;
+mod fee;
 mod fidelity;
 mod funding;
+mod reservation;
 mod rpc;
 mod storage;
 mod swapcoin;
 
 pub(crate) use api::{UTXOSpendInfo, Wallet};
+pub use coin_control::{CoinPool, CrossPoolPolicy};
+pub use coin_selection::{
+    BranchAndBound, CoinSelectionAlgorithm, CoinSelector, Knapsack, LargestFirst,
+};
 pub use direct_send::{Destination, SendAmount};
 pub use error::WalletError;
+pub(crate) use fee::{
+    calculate_fee, change_threshold, contract_tx_vsize, funding_tx_vsize, refund_tx_vsize,
+    timelock_spend_vsize, MIN_RELAY_FEERATE,
+};
+pub use fee::FeeEstimator;
+pub(crate) use funding::MIN_SPLIT_AMOUNT;
 pub(crate) use fidelity::{fidelity_redeemscript, FidelityBond, FidelityError};
 pub use rpc::RPCConfig;
 pub(crate) use swapcoin::{
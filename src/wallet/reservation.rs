@@ -0,0 +1,78 @@
+//! Reservations of wallet liquidity for in-flight swaps.
+//!
+//! The Maker signs its funding txs of the next hop as soon as a taker's [ProofOfFunding](crate::protocol::messages::ProofOfFunding)
+//! is accepted, but only broadcasts them once the contract signatures are exchanged. Until then, the coins spent
+//! by the funding txs are still unspent in the wallet. The [`LiquidityLedger`] reserves them for the swap, so they
+//! are not selected again for another swap, and are not counted in the offered maximum swap size.
+
+use std::collections::HashMap;
+
+use bitcoin::OutPoint;
+
+/// The utxos reserved for each in-flight swap, keyed by swap id.
+#[derive(Debug, Default)]
+pub(crate) struct LiquidityLedger {
+    reservations: HashMap<String, Vec<OutPoint>>,
+}
+
+impl LiquidityLedger {
+    /// Reserves the utxos for the swap. Replaces an earlier reservation of the same swap, as the taker
+    /// sends a new proof of funding when it has to retry a hop.
+    pub(crate) fn reserve(&mut self, id: &str, outpoints: Vec<OutPoint>) {
+        self.reservations.insert(id.to_string(), outpoints);
+    }
+
+    /// Releases the utxos reserved for the swap, once it's completed or aborted.
+    /// Returns the released utxos.
+    pub(crate) fn release(&mut self, id: &str) -> Vec<OutPoint> {
+        self.reservations.remove(id).unwrap_or_default()
+    }
+
+    /// Whether the utxo is reserved for any swap.
+    pub(crate) fn is_reserved(&self, outpoint: &OutPoint) -> bool {
+        self.reservations
+            .values()
+            .any(|outpoints| outpoints.contains(outpoint))
+    }
+
+    /// Number of swaps holding a reservation.
+    pub(crate) fn swap_count(&self) -> usize {
+        self.reservations.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin::{hashes::Hash, Txid};
+
+    fn outpoint(vout: u32) -> OutPoint {
+        OutPoint {
+            txid: Txid::all_zeros(),
+            vout,
+        }
+    }
+
+    #[test]
+    fn test_reserve_and_release() {
+        let mut ledger = LiquidityLedger::default();
+
+        ledger.reserve("swap-1", vec![outpoint(0), outpoint(1)]);
+        ledger.reserve("swap-2", vec![outpoint(2)]);
+        assert_eq!(ledger.swap_count(), 2);
+        assert!(ledger.is_reserved(&outpoint(1)));
+        assert!(ledger.is_reserved(&outpoint(2)));
+        assert!(!ledger.is_reserved(&outpoint(3)));
+
+        // A retried hop replaces the reservation of the swap.
+        ledger.reserve("swap-1", vec![outpoint(3)]);
+        assert_eq!(ledger.swap_count(), 2);
+        assert!(!ledger.is_reserved(&outpoint(0)));
+        assert!(ledger.is_reserved(&outpoint(3)));
+
+        assert_eq!(ledger.release("swap-1"), vec![outpoint(3)]);
+        assert!(!ledger.is_reserved(&outpoint(3)));
+        assert!(ledger.release("swap-1").is_empty());
+        assert_eq!(ledger.swap_count(), 1);
+    }
+}
@@ -139,8 +139,12 @@ impl Wallet {
             }
         }
 
-        let max_external_index = self.find_hd_next_index(KeychainKind::External)?;
-        self.update_external_index(max_external_index)?;
+        // Never hand out a receive address again, even if its coins are spent.
+        let next_external_index = self
+            .find_hd_next_index(KeychainKind::External)?
+            .max(self.find_used_external_index()?)
+            .max(self.store.external_index);
+        self.update_external_index(next_external_index)?;
         self.refresh_offer_maxsize_cache()?;
         Ok(())
     }
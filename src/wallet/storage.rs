@@ -1,20 +1,159 @@
 //! The Wallet Storage Interface.
 //!
-//! Wallet data is currently written in unencrypted CBOR files which are not directly human readable.
+//! Wallet data is written in CBOR files which are not directly human readable.
+//!
+//! If a passphrase is provided, the CBOR data is encrypted at rest with AES-256-GCM, using a key
+//! derived from the passphrase via PBKDF2-HMAC-SHA256. The salt and the number of PBKDF2 rounds are
+//! stored in the header of the encrypted file, so the cost of the key derivation can be raised without
+//! breaking existing files. Without a passphrase the file is stored unencrypted.
+//!
+//! Every file carries a layout version. Files written by older versions are upgraded to the current
+//! layout through the registered migrations when they are read.
+//!
+//! Writes are crash-safe: the file is replaced atomically, and a few rotating backups of previous
+//! versions are kept next to it. If the wallet file is corrupted, the newest valid backup is loaded.
+//! A missing or wrong passphrase never falls back to a backup. The backups of an encrypted wallet are
+//! encrypted as well, including the ones written before the wallet was encrypted.
 
-use bitcoin::{bip32::Xpriv, Network, OutPoint, ScriptBuf};
-use serde::{Deserialize, Serialize};
-use std::{
-    collections::HashMap,
-    fs::{self, read, File},
-    io::BufWriter,
-    path::Path,
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Key, Nonce,
 };
+use bitcoin::{
+    bip32::Xpriv,
+    secp256k1::rand::{rngs::OsRng, RngCore},
+    Network, OutPoint, ScriptBuf,
+};
+use serde::{Deserialize, Serialize};
+use serde_cbor::Value;
+use sha2::Sha256;
+use std::{collections::HashMap, fs, io, path::Path};
 
 use super::{error::WalletError, fidelity::FidelityBond};
+use crate::utill::{backup_paths, read_with_backups_unless, replace_atomic, write_atomic};
 
 use super::swapcoin::{IncomingSwapCoin, OutgoingSwapCoin};
 
+/// Layout version of the wallet files written by this software.
+///
+/// Version `0` is the unversioned layout, written before wallet files carried a version.
+/// Any layout change of [`WalletStore`] bumps the version, by registering a migration in [`MIGRATIONS`].
+///
+/// The header of an encrypted file is outside of the versioned envelope, which it encrypts. It holds the
+/// key derivation parameters, and its fields default to the values of older files when they're missing.
+pub(crate) const WALLET_FILE_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Upgrades a raw store of a wallet file version, to the layout of the next version.
+type Migration = fn(&mut Value) -> Result<(), WalletError>;
+
+/// All wallet file migrations, in order. `MIGRATIONS[n]` upgrades a version `n` store to version `n + 1`.
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1];
+
+/// The oldest unversioned files predate the `last_synced_height` and `wallet_birthday` fields.
+fn migrate_v0_to_v1(store: &mut Value) -> Result<(), WalletError> {
+    let Value::Map(fields) = store else {
+        return Err(WalletError::General(
+            "Wallet store is not a CBOR map".to_string(),
+        ));
+    };
+    for field in ["last_synced_height", "wallet_birthday"] {
+        fields
+            .entry(Value::Text(field.to_string()))
+            .or_insert(Value::Null);
+    }
+    Ok(())
+}
+
+/// Number of PBKDF2 iterations used to derive the encryption key of newly encrypted wallet files.
+/// Tests use fewer rounds to run fast. The rounds are stored in each file, so both kinds of files
+/// load with either build.
+#[cfg(not(test))]
+const PBKDF2_ROUNDS: u32 = 600_000;
+#[cfg(test)]
+const PBKDF2_ROUNDS: u32 = 1_000;
+
+/// Symmetric key derived from the wallet passphrase, along with the salt and rounds used to derive it.
+///
+/// The key is derived once when the wallet is loaded and reused for every subsequent write.
+#[derive(Clone)]
+pub(crate) struct KeyMaterial {
+    key: [u8; 32],
+    salt: [u8; 16],
+    rounds: u32,
+}
+
+impl KeyMaterial {
+    /// Derive a fresh key from the passphrase with a random salt.
+    pub(crate) fn new(passphrase: &str) -> Self {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        Self::derive(passphrase, salt, PBKDF2_ROUNDS)
+    }
+
+    /// Re-derive the key from the passphrase, and the salt and rounds of an existing file.
+    fn derive(passphrase: &str, salt: [u8; 16], rounds: u32) -> Self {
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &salt, rounds, &mut key);
+        Self { key, salt, rounds }
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&self.key))
+    }
+
+    /// Encrypt with a random nonce, which is returned along with the ciphertext.
+    fn seal(&self, plaintext: &[u8]) -> Result<([u8; 12], Vec<u8>), WalletError> {
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher()
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| WalletError::General("Wallet encryption failed".to_string()))?;
+        Ok((nonce, ciphertext))
+    }
+
+    /// Encrypt data kept next to the wallet file, like the Taker's swap journals.
+    pub(crate) fn encrypt_data(&self, plaintext: &[u8]) -> Result<Vec<u8>, WalletError> {
+        let (nonce, ciphertext) = self.seal(plaintext)?;
+        Ok(serde_cbor::to_vec(&EncryptedData { nonce, ciphertext })?)
+    }
+
+    /// Decrypt data written with [`KeyMaterial::encrypt_data`].
+    pub(crate) fn decrypt_data(&self, bytes: &[u8]) -> Result<Vec<u8>, WalletError> {
+        let envelope = serde_cbor::from_slice::<EncryptedData>(bytes)?;
+        self.cipher()
+            .decrypt(
+                Nonce::from_slice(&envelope.nonce),
+                envelope.ciphertext.as_ref(),
+            )
+            .map_err(|_| WalletError::WrongPassphrase)
+    }
+}
+
+/// On-disk envelope of a [`WalletStore`], tagged with its layout version.
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionedWalletStore<T> {
+    version: u32,
+    store: T,
+}
+
+/// On-disk envelope of an encrypted [`VersionedWalletStore`]. The header holds the key derivation
+/// parameters, and the nonce of the ciphertext.
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedWalletStore {
+    rounds: u32,
+    salt: [u8; 16],
+    nonce: [u8; 12],
+    ciphertext: Vec<u8>,
+}
+
+/// On-disk envelope of other data encrypted with the wallet's [`KeyMaterial`].
+#[derive(Debug, Serialize, Deserialize)]
+struct EncryptedData {
+    nonce: [u8; 12],
+    ciphertext: Vec<u8>,
+}
+
 /// Represents the internal data store for a Bitcoin wallet.
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub(crate) struct WalletStore {
@@ -36,9 +175,9 @@ pub(crate) struct WalletStore {
     pub(super) prevout_to_contract_map: HashMap<OutPoint, ScriptBuf>,
     /// Map for all the fidelity bond information. (index, (Bond, script_pubkey, is_spent)).
     pub(super) fidelity_bond: HashMap<u32, (FidelityBond, ScriptBuf, bool)>,
-    //TODO: Add last synced height and Wallet birthday.
+    /// The block height the wallet was last synced to.
     pub(super) last_synced_height: Option<u64>,
-
+    /// The block height the wallet was created at. Rescans start from here.
     pub(super) wallet_birthday: Option<u64>,
 }
 
@@ -50,6 +189,7 @@ impl WalletStore {
         network: Network,
         master_key: Xpriv,
         wallet_birthday: Option<u64>,
+        key_material: Option<&KeyMaterial>,
     ) -> Result<Self, WalletError> {
         let store = Self {
             file_name,
@@ -66,53 +206,209 @@ impl WalletStore {
         };
 
         std::fs::create_dir_all(path.parent().expect("Path should NOT be root!"))?;
-        // write: overwrites existing file.
-        // create: creates new file if doesn't exist.
-        let file = File::create(path)?;
-        let writer = BufWriter::new(file);
-        serde_cbor::to_writer(writer, &store)?;
+        store.write_to_disk(path, key_material)?;
+        if let Some(key_material) = key_material {
+            // Backups of an overwritten wallet of the same name must not stay readable.
+            Self::encrypt_backups(path, key_material)?;
+        }
 
         Ok(store)
     }
 
-    /// Load existing file, updates it, writes it back (errors if path doesn't exist).
-    pub(crate) fn write_to_disk(&self, path: &Path) -> Result<(), WalletError> {
-        let wallet_file = fs::OpenOptions::new().write(true).open(path)?;
-        let writer = BufWriter::new(wallet_file);
-        Ok(serde_cbor::to_writer(writer, &self)?)
+    /// Atomically writes the store to the path, keeping the previous file as a backup.
+    ///
+    /// If `key_material` is provided, the file is encrypted with it.
+    pub(crate) fn write_to_disk(
+        &self,
+        path: &Path,
+        key_material: Option<&KeyMaterial>,
+    ) -> Result<(), WalletError> {
+        write_atomic(path, &self.serialize(key_material)?)?;
+        Ok(())
+    }
+
+    /// Serialize the store with the current layout version, encrypting it if a key is provided.
+    fn serialize(&self, key_material: Option<&KeyMaterial>) -> Result<Vec<u8>, WalletError> {
+        let versioned = VersionedWalletStore {
+            version: WALLET_FILE_VERSION,
+            store: self,
+        };
+        match key_material {
+            Some(key_material) => {
+                let (nonce, ciphertext) = key_material.seal(&serde_cbor::to_vec(&versioned)?)?;
+                let envelope = EncryptedWalletStore {
+                    rounds: key_material.rounds,
+                    salt: key_material.salt,
+                    nonce,
+                    ciphertext,
+                };
+                Ok(serde_cbor::to_vec(&envelope)?)
+            }
+            None => Ok(serde_cbor::to_vec(&versioned)?),
+        }
+    }
+
+    /// Encrypts the unencrypted backups of the wallet file at `path` with the key, and removes the
+    /// unreadable ones.
+    ///
+    /// Encrypting an existing wallet file rotates its plaintext version into the backups. They'd keep
+    /// the master key readable on disk, and be loaded without a passphrase if the wallet file is corrupted.
+    pub(crate) fn encrypt_backups(
+        path: &Path,
+        key_material: &KeyMaterial,
+    ) -> Result<(), WalletError> {
+        for backup in backup_paths(path) {
+            let bytes = match fs::read(&backup) {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            if serde_cbor::from_slice::<EncryptedWalletStore>(&bytes).is_ok() {
+                continue;
+            }
+            match Self::from_plaintext(&bytes) {
+                Ok((store, _)) => replace_atomic(&backup, &store.serialize(Some(key_material))?)?,
+                Err(_) => fs::remove_file(&backup)?,
+            }
+            log::info!("Encrypted wallet backup {:?}", backup);
+        }
+        Ok(())
     }
 
     /// Reads from a path (errors if path doesn't exist).
-    pub(crate) fn read_from_disk(path: &Path) -> Result<Self, WalletError> {
-        //let wallet_file = File::open(path)?;
-        let mut reader = read(path)?;
-        let store = match serde_cbor::from_slice::<Self>(&reader) {
-            Ok(store) => store,
-            Err(e) => {
-                let err_string = format!("{:?}", e);
-                if err_string.contains("code: TrailingData") {
-                    log::info!("Wallet file has trailing data, trying to restore");
-                    loop {
-                        // pop the last byte and try again.
-                        reader.pop();
-                        match serde_cbor::from_slice::<Self>(&reader) {
-                            Ok(store) => break store,
-                            Err(_) => continue,
-                        }
-                    }
-                } else {
-                    return Err(e.into());
+    ///
+    /// Falls back to the newest valid backup if the file is corrupted. A missing or wrong passphrase
+    /// is returned as is, without trying the backups.
+    ///
+    /// Encrypted files require the passphrase they were written with. The derived [`KeyMaterial`]
+    /// is returned alongside the store, so subsequent writes can reuse it. For unencrypted files
+    /// the passphrase is ignored and `None` is returned as the key material.
+    ///
+    /// Files of an older layout version are migrated to the current layout in memory. The version
+    /// of the file is returned as well, so the caller can write the upgraded file back.
+    pub(crate) fn read_from_disk(
+        path: &Path,
+        passphrase: Option<&str>,
+    ) -> Result<(Self, Option<KeyMaterial>, u32), WalletError> {
+        read_with_backups_unless(
+            path,
+            |bytes| Self::from_bytes(bytes, passphrase),
+            |e| {
+                matches!(
+                    e,
+                    WalletError::PassphraseRequired | WalletError::WrongPassphrase
+                )
+            },
+        )
+    }
+
+    /// Deserialize a store from the raw file content, decrypting it if it is encrypted.
+    fn from_bytes(
+        bytes: &[u8],
+        passphrase: Option<&str>,
+    ) -> Result<(Self, Option<KeyMaterial>, u32), WalletError> {
+        if let Ok(envelope) = serde_cbor::from_slice::<EncryptedWalletStore>(bytes) {
+            let passphrase = passphrase.ok_or(WalletError::PassphraseRequired)?;
+            let key_material = KeyMaterial::derive(passphrase, envelope.salt, envelope.rounds);
+            let plaintext = key_material
+                .cipher()
+                .decrypt(
+                    Nonce::from_slice(&envelope.nonce),
+                    envelope.ciphertext.as_ref(),
+                )
+                .map_err(|_| WalletError::WrongPassphrase)?;
+            let (store, version) = Self::from_plaintext(&plaintext)?;
+            return Ok((store, Some(key_material), version));
+        }
+
+        let (store, version) = Self::from_plaintext(bytes)?;
+        Ok((store, None, version))
+    }
+
+    /// Deserialize a store of any supported layout version, migrating it to the current layout.
+    fn from_plaintext(bytes: &[u8]) -> Result<(Self, u32), WalletError> {
+        let (version, mut store) =
+            match serde_cbor::from_slice::<VersionedWalletStore<Value>>(bytes) {
+                Ok(versioned) => (versioned.version, versioned.store),
+                Err(_) => {
+                    // Unversioned files written before atomic writes may carry stale trailing bytes
+                    // from a previous, longer version of the file. Only the first CBOR value holds the store.
+                    let mut deserializer = serde_cbor::Deserializer::from_slice(bytes);
+                    (0, Value::deserialize(&mut deserializer)?)
                 }
-            }
-        };
-        Ok(store)
+            };
+
+        if version > WALLET_FILE_VERSION {
+            return Err(WalletError::UnsupportedFileVersion {
+                found: version,
+                supported: WALLET_FILE_VERSION,
+            });
+        }
+
+        for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            migration(&mut store)?;
+            log::info!(
+                "Migrated wallet store from version {} to {}",
+                from,
+                from + 1
+            );
+        }
+
+        Ok((serde_cbor::value::from_value(store)?, version))
     }
 }
 #[cfg(test)]
 mod tests {
     use super::*;
     use bip39::rand::{thread_rng, Rng};
+    use bitcoin::{absolute::LockTime, secp256k1::Secp256k1, Amount};
     use bitcoind::tempfile::tempdir;
+    use std::{
+        fs::{self, read},
+        str::FromStr,
+    };
+
+    fn is_encrypted(path: &Path) -> bool {
+        serde_cbor::from_slice::<EncryptedWalletStore>(&read(path).unwrap()).is_ok()
+    }
+
+    /// The wallet store saved in the historical wallet files at `tests/wallet_files`.
+    fn corpus_store() -> WalletStore {
+        let master_key = Xpriv::new_master(Network::Regtest, &[7u8; 16]).unwrap();
+        let pubkey = master_key.to_priv().public_key(&Secp256k1::new());
+        let bond = FidelityBond {
+            outpoint: OutPoint::from_str(
+                "5df6e0e2761359d30a8275058e299fcc0381534545f55cf43e41983f5d4c9456:0",
+            )
+            .unwrap(),
+            amount: Amount::from_sat(50_000),
+            lock_time: LockTime::from_height(500).unwrap(),
+            pubkey,
+            conf_height: 150,
+            cert_expiry: 1,
+        };
+        let bond_spk = bond.script_pub_key();
+
+        WalletStore {
+            file_name: "corpus-wallet".to_string(),
+            network: Network::Regtest,
+            master_key,
+            external_index: 5,
+            offer_maxsize: 100_000,
+            incoming_swapcoins: HashMap::new(),
+            outgoing_swapcoins: HashMap::new(),
+            prevout_to_contract_map: HashMap::from([(
+                OutPoint::from_str(
+                    "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b:1",
+                )
+                .unwrap(),
+                ScriptBuf::from_bytes(vec![0x51]),
+            )]),
+            fidelity_bond: HashMap::from([(0, (bond, bond_spk, false))]),
+            last_synced_height: Some(200),
+            wallet_birthday: Some(100),
+        }
+    }
 
     #[test]
     fn test_write_and_read_wallet_to_disk() {
@@ -130,12 +426,307 @@ mod tests {
             Network::Bitcoin,
             master_key,
             None,
+            None,
         )
         .unwrap();
 
-        original_wallet_store.write_to_disk(&file_path).unwrap();
+        original_wallet_store
+            .write_to_disk(&file_path, None)
+            .unwrap();
 
-        let read_wallet = WalletStore::read_from_disk(&file_path).unwrap();
+        let (read_wallet, key_material, _) = WalletStore::read_from_disk(&file_path, None).unwrap();
         assert_eq!(original_wallet_store, read_wallet);
+        assert!(key_material.is_none());
+    }
+
+    #[test]
+    fn test_encrypted_wallet_store() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test_wallet.cbor");
+
+        let master_key = {
+            let seed: [u8; 16] = thread_rng().gen();
+            Xpriv::new_master(Network::Bitcoin, &seed).unwrap()
+        };
+
+        let key_material = KeyMaterial::new("correct horse battery staple");
+        let original_wallet_store = WalletStore::init(
+            "test_wallet".to_string(),
+            &file_path,
+            Network::Bitcoin,
+            master_key,
+            None,
+            Some(&key_material),
+        )
+        .unwrap();
+
+        assert!(is_encrypted(&file_path));
+
+        // The master key must not be readable from the raw file.
+        let raw = read(&file_path).unwrap();
+        assert!(serde_cbor::from_slice::<WalletStore>(&raw).is_err());
+
+        assert!(matches!(
+            WalletStore::read_from_disk(&file_path, None),
+            Err(WalletError::PassphraseRequired)
+        ));
+        assert!(matches!(
+            WalletStore::read_from_disk(&file_path, Some("wrong passphrase")),
+            Err(WalletError::WrongPassphrase)
+        ));
+
+        let (read_wallet, key_material, _) =
+            WalletStore::read_from_disk(&file_path, Some("correct horse battery staple")).unwrap();
+        assert_eq!(original_wallet_store, read_wallet);
+
+        // Rewriting with the loaded key keeps the file readable with the same passphrase.
+        read_wallet
+            .write_to_disk(&file_path, key_material.as_ref())
+            .unwrap();
+        let (reread_wallet, _, _) =
+            WalletStore::read_from_disk(&file_path, Some("correct horse battery staple")).unwrap();
+        assert_eq!(original_wallet_store, reread_wallet);
+    }
+
+    #[test]
+    fn test_pbkdf2_rounds_in_header() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test_wallet.cbor");
+
+        // A file written with other rounds than the current ones, e.g. before they were raised.
+        let key_material = KeyMaterial::derive("passphrase", [3u8; 16], PBKDF2_ROUNDS / 2);
+        corpus_store()
+            .write_to_disk(&file_path, Some(&key_material))
+            .unwrap();
+
+        let envelope = serde_cbor::from_slice::<EncryptedWalletStore>(&read(&file_path).unwrap());
+        assert_eq!(envelope.unwrap().rounds, PBKDF2_ROUNDS / 2);
+
+        let (store, key_material, _) =
+            WalletStore::read_from_disk(&file_path, Some("passphrase")).unwrap();
+        assert_eq!(store, corpus_store());
+
+        // The file keeps its rounds when written back with the loaded key.
+        assert_eq!(key_material.as_ref().unwrap().rounds, PBKDF2_ROUNDS / 2);
+        store
+            .write_to_disk(&file_path, key_material.as_ref())
+            .unwrap();
+        assert!(WalletStore::read_from_disk(&file_path, Some("passphrase")).is_ok());
+    }
+
+    #[test]
+    fn test_migrate_unencrypted_wallet_store() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test_wallet.cbor");
+
+        let master_key = {
+            let seed: [u8; 16] = thread_rng().gen();
+            Xpriv::new_master(Network::Bitcoin, &seed).unwrap()
+        };
+
+        let original_wallet_store = WalletStore::init(
+            "test_wallet".to_string(),
+            &file_path,
+            Network::Bitcoin,
+            master_key,
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(!is_encrypted(&file_path));
+
+        // Encrypt the existing file in place.
+        let (store, _, _) = WalletStore::read_from_disk(&file_path, Some("passphrase")).unwrap();
+        store
+            .write_to_disk(&file_path, Some(&KeyMaterial::new("passphrase")))
+            .unwrap();
+
+        assert!(is_encrypted(&file_path));
+        let (read_wallet, key_material, _) =
+            WalletStore::read_from_disk(&file_path, Some("passphrase")).unwrap();
+        assert_eq!(original_wallet_store, read_wallet);
+        assert!(key_material.is_some());
+    }
+
+    #[test]
+    fn test_encrypted_wallet_backups() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test_wallet.cbor");
+
+        let mut wallet_store = corpus_store();
+        wallet_store.write_to_disk(&file_path, None).unwrap();
+        wallet_store.external_index = 6;
+        wallet_store.write_to_disk(&file_path, None).unwrap();
+
+        // Encrypting in place rotates the plaintext wallet into the backups.
+        let key_material = KeyMaterial::new("passphrase");
+        wallet_store
+            .write_to_disk(&file_path, Some(&key_material))
+            .unwrap();
+        assert!(!is_encrypted(&backup_paths(&file_path).next().unwrap()));
+
+        // Passphrase errors never fall back to the plaintext backups.
+        assert!(matches!(
+            WalletStore::read_from_disk(&file_path, None),
+            Err(WalletError::PassphraseRequired)
+        ));
+        assert!(matches!(
+            WalletStore::read_from_disk(&file_path, Some("wrong passphrase")),
+            Err(WalletError::WrongPassphrase)
+        ));
+
+        WalletStore::encrypt_backups(&file_path, &key_material).unwrap();
+        let backups = backup_paths(&file_path)
+            .filter(|backup| backup.exists())
+            .collect::<Vec<_>>();
+        assert_eq!(backups.len(), 2);
+        assert!(backups.iter().all(|backup| is_encrypted(backup)));
+
+        // A corrupted wallet file is still recovered from the encrypted backups.
+        fs::write(&file_path, b"garbage").unwrap();
+        let (read_wallet, _, _) =
+            WalletStore::read_from_disk(&file_path, Some("passphrase")).unwrap();
+        assert_eq!(read_wallet.external_index, 6);
+        assert!(WalletStore::read_from_disk(&file_path, None).is_err());
+    }
+
+    #[test]
+    fn test_wallet_store_backup_fallback() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test_wallet.cbor");
+
+        let master_key = {
+            let seed: [u8; 16] = thread_rng().gen();
+            Xpriv::new_master(Network::Bitcoin, &seed).unwrap()
+        };
+
+        let mut wallet_store = WalletStore::init(
+            "test_wallet".to_string(),
+            &file_path,
+            Network::Bitcoin,
+            master_key,
+            None,
+            None,
+        )
+        .unwrap();
+        wallet_store.external_index = 1;
+        wallet_store.write_to_disk(&file_path, None).unwrap();
+        wallet_store.external_index = 2;
+        wallet_store.write_to_disk(&file_path, None).unwrap();
+
+        // Simulate a torn write of the latest save.
+        let raw = read(&file_path).unwrap();
+        fs::write(&file_path, &raw[..raw.len() / 2]).unwrap();
+
+        // The previous save is recovered from the newest backup.
+        let (read_wallet, _, _) = WalletStore::read_from_disk(&file_path, None).unwrap();
+        assert_eq!(read_wallet.external_index, 1);
+    }
+
+    #[test]
+    fn test_read_wallet_store_with_trailing_data() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test_wallet.cbor");
+
+        let master_key = {
+            let seed: [u8; 16] = thread_rng().gen();
+            Xpriv::new_master(Network::Bitcoin, &seed).unwrap()
+        };
+
+        let original_wallet_store = WalletStore::init(
+            "test_wallet".to_string(),
+            &file_path,
+            Network::Bitcoin,
+            master_key,
+            None,
+            None,
+        )
+        .unwrap();
+
+        // Older versions wrote unversioned files without truncating them, leaving stale bytes behind.
+        let mut raw = serde_cbor::to_vec(&original_wallet_store).unwrap();
+        raw.extend_from_slice(&[0xff; 32]);
+        fs::write(&file_path, raw).unwrap();
+
+        let (read_wallet, _, _) = WalletStore::read_from_disk(&file_path, None).unwrap();
+        assert_eq!(original_wallet_store, read_wallet);
+    }
+
+    #[test]
+    fn test_historical_wallet_files() {
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/wallet_files");
+
+        // The oldest files predate the sync height and birthday fields.
+        let legacy_store = WalletStore {
+            last_synced_height: None,
+            wallet_birthday: None,
+            ..corpus_store()
+        };
+
+        let cases = [
+            ("wallet_v0_legacy.cbor", None, 0, legacy_store),
+            ("wallet_v0.cbor", None, 0, corpus_store()),
+            ("wallet_v1.cbor", None, 1, corpus_store()),
+            (
+                "wallet_v1_encrypted_kdf.cbor",
+                Some("coinswap"),
+                1,
+                corpus_store(),
+            ),
+        ];
+
+        let temp_dir = tempdir().unwrap();
+        for (file_name, passphrase, version, expected_store) in cases {
+            let (store, key_material, file_version) =
+                WalletStore::read_from_disk(&corpus.join(file_name), passphrase).unwrap();
+            assert_eq!(file_version, version, "{}", file_name);
+            assert_eq!(store, expected_store, "{}", file_name);
+
+            // Written back, the file is upgraded to the current version.
+            let upgraded_path = temp_dir.path().join(file_name);
+            store
+                .write_to_disk(&upgraded_path, key_material.as_ref())
+                .unwrap();
+            let (upgraded_store, _, upgraded_version) =
+                WalletStore::read_from_disk(&upgraded_path, passphrase).unwrap();
+            assert_eq!(upgraded_version, WALLET_FILE_VERSION, "{}", file_name);
+            assert_eq!(upgraded_store, expected_store, "{}", file_name);
+        }
+    }
+
+    #[test]
+    fn test_unsupported_wallet_file_version() {
+        let temp_dir = tempdir().unwrap();
+        let file_path = temp_dir.path().join("test_wallet.cbor");
+
+        let future_file = VersionedWalletStore {
+            version: WALLET_FILE_VERSION + 1,
+            store: corpus_store(),
+        };
+        fs::write(&file_path, serde_cbor::to_vec(&future_file).unwrap()).unwrap();
+
+        assert!(matches!(
+            WalletStore::read_from_disk(&file_path, None),
+            Err(WalletError::UnsupportedFileVersion { found, supported })
+                if found == WALLET_FILE_VERSION + 1 && supported == WALLET_FILE_VERSION
+        ));
+    }
+
+    #[test]
+    fn test_encrypt_data() {
+        let key_material = KeyMaterial::new("passphrase");
+        let plaintext = b"swap journal with a preimage".to_vec();
+
+        let encrypted = key_material.encrypt_data(&plaintext).unwrap();
+        assert!(!encrypted
+            .windows(plaintext.len())
+            .any(|window| window == plaintext.as_slice()));
+        assert_eq!(key_material.decrypt_data(&encrypted).unwrap(), plaintext);
+
+        assert!(matches!(
+            KeyMaterial::new("passphrase").decrypt_data(&encrypted),
+            Err(WalletError::WrongPassphrase)
+        ));
+        assert!(key_material.decrypt_data(&plaintext).is_err());
     }
 }
@@ -30,7 +30,7 @@ use crate::protocol::{
     Hash160,
 };
 
-use super::WalletError;
+use super::{calculate_fee, timelock_spend_vsize, WalletError};
 
 /// Defines an incoming swapcoin, which can either be currently active or successfully completed.
 ///
@@ -86,7 +86,7 @@ pub(crate) struct OutgoingSwapCoin {
 /// Represents a watch-only view of a coinswap between two makers.
 //like the Incoming/OutgoingSwapCoin structs but no privkey or signature information
 //used by the taker to monitor coinswaps between two makers
-#[derive(Debug, Clone)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub(crate) struct WatchOnlySwapCoin {
     /// Public key of the sender (maker).
     pub(crate) sender_pubkey: PublicKey,
@@ -430,8 +430,9 @@ impl OutgoingSwapCoin {
     pub(crate) fn create_timelock_spend(
         &self,
         destination_address: &Address,
+        fee_rate: f64,
     ) -> Result<Transaction, WalletError> {
-        let miner_fee = 128 * 2; //128 vbytes x 2 sat/vb, size calculated using testmempoolaccept
+        let miner_fee = calculate_fee(fee_rate, timelock_spend_vsize(&self.contract_redeemscript));
         let mut tx = Transaction {
             input: vec![TxIn {
                 previous_output: OutPoint {
@@ -444,7 +445,7 @@ impl OutgoingSwapCoin {
             }],
             output: vec![TxOut {
                 script_pubkey: destination_address.script_pubkey(),
-                value: Amount::from_sat(self.contract_tx.output[0].value.to_sat() - miner_fee),
+                value: self.contract_tx.output[0].value - miner_fee,
             }],
             lock_time: LockTime::ZERO,
             version: Version::TWO,
@@ -94,7 +94,7 @@ fn test_abort_case_2_move_on_with_other_makers() {
 
             let live_contract_balance = wallet.balance_live_contract(Some(&all_utxos)).unwrap();
 
-            assert_eq!(seed_balance, Amount::from_btc(0.14999).unwrap());
+            assert_eq!(seed_balance, Amount::from_btc(0.14999558).unwrap());
             assert_eq!(fidelity_balance, Amount::from_btc(0.05).unwrap());
             assert_eq!(swapcoin_balance, Amount::ZERO);
             assert_eq!(live_contract_balance, Amount::ZERO);
@@ -202,17 +202,12 @@ fn test_abort_case_2_move_on_with_other_makers() {
         .unwrap();
 
     let tx = taker_wallet_mut
-        .spend_from_wallet(
-            Amount::from_sat(1000),
-            SendAmount::Max,
-            Destination::Wallet,
-            &swap_coins,
-        )
+        .spend_from_wallet(2.0, SendAmount::Max, Destination::Wallet, &swap_coins)
         .unwrap();
 
     assert_eq!(
         tx.input.len(),
-        3,
+        swap_coins.len(),
         "Not all swap coin utxos got included in the spend transaction"
     );
 
@@ -100,7 +100,7 @@ fn test_abort_case_2_recover_if_no_makers_found() {
 
             let live_contract_balance = wallet.balance_live_contract(Some(&all_utxos)).unwrap();
 
-            assert_eq!(seed_balance, Amount::from_btc(0.14999).unwrap());
+            assert_eq!(seed_balance, Amount::from_btc(0.14999558).unwrap());
             assert_eq!(fidelity_balance, Amount::from_btc(0.05).unwrap());
             assert_eq!(swapcoin_balance, Amount::ZERO);
             assert_eq!(live_contract_balance, Amount::ZERO);
@@ -0,0 +1,117 @@
+#![cfg(feature = "integration-test")]
+use bitcoin::Amount;
+use coinswap::{
+    maker::{start_maker_server, MakerBehavior},
+    taker::{SwapParams, TakerBehavior},
+    utill::ConnectionType,
+};
+use std::sync::Arc;
+
+mod test_framework;
+use test_framework::*;
+
+use log::{info, warn};
+use std::{sync::atomic::Ordering::Relaxed, thread, time::Duration};
+
+/// This test starts two independent swaps from a single Taker with 2 Makers, and runs them
+/// concurrently. Both swaps must complete, and no contract is left live with anyone.
+#[test]
+fn test_concurrent_coinswaps() {
+    // ---- Setup ----
+
+    // 2 Makers with Normal behavior.
+    let makers_config_map = [
+        ((6102, None), MakerBehavior::Normal),
+        ((16102, None), MakerBehavior::Normal),
+    ];
+
+    // Initiate test framework, Makers and a Taker with default behavior.
+    let (test_framework, mut taker, makers, directory_server_instance, block_generation_handle) =
+        TestFramework::init(
+            makers_config_map.into(),
+            TakerBehavior::Normal,
+            ConnectionType::CLEARNET,
+        );
+
+    warn!("Running Test: Concurrent Coinswaps");
+    let bitcoind = &test_framework.bitcoind;
+
+    // Fund the Taker  with 4 utxos of 0.05 btc each.
+    fund_and_verify_taker(&mut taker, bitcoind, 4, Amount::from_btc(0.05).unwrap());
+
+    // Fund the Maker with 6 utxos of 0.05 btc each.
+    let makers_ref = makers.iter().map(Arc::as_ref).collect::<Vec<_>>();
+    fund_and_verify_maker(makers_ref, bitcoind, 6, Amount::from_btc(0.05).unwrap());
+
+    //  Start the Maker Server threads
+    log::info!("Initiating Maker...");
+
+    let maker_threads = makers
+        .iter()
+        .map(|maker| {
+            let maker_clone = maker.clone();
+            thread::spawn(move || {
+                start_maker_server(maker_clone).unwrap();
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Makers take time to fully setup.
+    makers.iter().for_each(|maker| {
+        while !maker.is_setup_complete.load(Relaxed) {
+            log::info!("Waiting for maker setup completion");
+            // Introduce a delay of 10 seconds to prevent write lock starvation.
+            thread::sleep(Duration::from_secs(10));
+        }
+    });
+
+    let swap_params = SwapParams {
+        send_amount: Amount::from_sat(500000),
+        maker_count: 2,
+        tx_count: 2,
+        required_confirms: 1,
+    };
+
+    // Start both swaps before running any of them.
+    info!("Initiating 2 concurrent coinswaps");
+    let first = taker.start_swap(swap_params).unwrap();
+    let second = taker.start_swap(swap_params).unwrap();
+    assert_ne!(first.id(), second.id());
+
+    let results = taker.run_swaps(&[first.clone(), second]);
+    assert_eq!(results.len(), 2);
+    results.into_iter().for_each(|result| result.unwrap());
+
+    // Finished swaps are not ongoing anymore.
+    assert!(taker.recover_swap(&first).is_err());
+
+    // Both swaps received their coins back.
+    let wallet = taker.get_wallet();
+    assert!(wallet.list_swap_coin_utxo_spend_info(None).unwrap().len() >= 4);
+    assert_eq!(wallet.balance_live_contract(None).unwrap(), Amount::ZERO);
+
+    // After Swaps are done, wait for maker threads to conclude.
+    makers
+        .iter()
+        .for_each(|maker| maker.shutdown.store(true, Relaxed));
+
+    maker_threads
+        .into_iter()
+        .for_each(|thread| thread.join().unwrap());
+
+    // No maker is left with live contracts.
+    makers.iter().for_each(|maker| {
+        let wallet = maker.wallet.read().unwrap();
+        assert_eq!(wallet.balance_live_contract(None).unwrap(), Amount::ZERO);
+    });
+
+    // Shutdown Directory Server
+    directory_server_instance.shutdown.store(true, Relaxed);
+
+    thread::sleep(Duration::from_secs(10));
+
+    info!("All checks successful. Terminating integration test case");
+
+    test_framework.stop();
+    block_generation_handle.join().unwrap();
+}
@@ -0,0 +1,181 @@
+#![cfg(feature = "integration-test")]
+use bitcoin::Amount;
+use coinswap::{
+    maker::{start_maker_server, MakerBehavior},
+    taker::{SwapOutcome, SwapParams, TakerBehavior},
+    utill::ConnectionType,
+};
+mod test_framework;
+use log::{info, warn};
+use std::{
+    sync::{atomic::Ordering::Relaxed, Arc},
+    thread,
+    time::Duration,
+};
+use test_framework::*;
+
+/// Cooperative Abort: TAKER Aborts After Full Setup.
+/// The Taker has `cooperative_abort` enabled and aborts the swap after all the funding transactions
+/// are confirmed, before revealing the preimage. Every hop is refunded straight back to its sender,
+/// with refund transactions signed by both sides, and no contract transaction is broadcasted.
+///
+/// Everyone gets their funds back, minus the mining fees of the funding and refund transactions.
+#[test]
+fn test_cooperative_abort_after_setup() {
+    // ---- Setup ----
+
+    // 2 Makers with Normal behavior.
+    let makers_config_map = [
+        ((6102, None), MakerBehavior::Normal),
+        ((16102, None), MakerBehavior::Normal),
+    ];
+
+    // Initiate test framework, Makers.
+    // Taker has a special behavior AbortAfterFullSetup.
+    let (test_framework, mut taker, makers, directory_server_instance, block_generation_handle) =
+        TestFramework::init(
+            makers_config_map.into(),
+            TakerBehavior::AbortAfterFullSetup,
+            ConnectionType::CLEARNET,
+        );
+    taker.config.cooperative_abort = true;
+
+    warn!("Running Test: Taker Aborts Cooperatively.");
+
+    // Fund the Taker  with 3 utxos of 0.05 btc each and do basic checks on the balance
+    let org_taker_spend_balance = fund_and_verify_taker(
+        &mut taker,
+        &test_framework.bitcoind,
+        3,
+        Amount::from_btc(0.05).unwrap(),
+    );
+
+    // Fund the Maker with 4 utxos of 0.05 btc each and do basic checks on the balance.
+    let makers_ref = makers.iter().map(Arc::as_ref).collect::<Vec<_>>();
+    fund_and_verify_maker(
+        makers_ref,
+        &test_framework.bitcoind,
+        4,
+        Amount::from_btc(0.05).unwrap(),
+    );
+
+    //  Start the Maker Server threads
+    log::info!("Initiating Maker...");
+
+    let maker_threads = makers
+        .iter()
+        .map(|maker| {
+            let maker_clone = maker.clone();
+            thread::spawn(move || {
+                start_maker_server(maker_clone).unwrap();
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Makers take time to fully setup.
+    let org_maker_spend_balances = makers
+        .iter()
+        .map(|maker| {
+            while !maker.is_setup_complete.load(Relaxed) {
+                log::info!("Waiting for maker setup completion");
+                // Introduce a delay of 10 seconds to prevent write lock starvation.
+                thread::sleep(Duration::from_secs(10));
+                continue;
+            }
+
+            let wallet = maker.wallet.read().unwrap();
+            let all_utxos = wallet.get_all_utxo().unwrap();
+            wallet.balance_descriptor_utxo(Some(&all_utxos)).unwrap()
+                + wallet.balance_swap_coins(Some(&all_utxos)).unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    // Initiate Coinswap
+    log::info!("Initiating coinswap protocol");
+
+    // Swap params for coinswap.
+    let swap_params = SwapParams {
+        send_amount: Amount::from_sat(500000),
+        maker_count: 2,
+        tx_count: 3,
+        required_confirms: 1,
+    };
+    taker.do_coinswap(swap_params).unwrap();
+
+    // Let the refund txs confirm.
+    thread::sleep(Duration::from_secs(10));
+
+    // The swap was aborted, without broadcasting any contract.
+    let history = taker.swap_history().unwrap();
+    assert_eq!(history.len(), 1);
+    let record = &history[0].record;
+    assert_eq!(record.outcome, SwapOutcome::Aborted);
+    assert!(!record.contracts_broadcasted);
+
+    // The Taker got its funds back, minus the mining fees of its funding and refund txs.
+    {
+        let wallet = taker.get_wallet();
+        let all_utxos = wallet.get_all_utxo().unwrap();
+        assert!(all_utxos.iter().all(|utxo| utxo.confirmations > 0));
+        assert_eq!(
+            wallet.balance_swap_coins(Some(&all_utxos)).unwrap(),
+            Amount::ZERO
+        );
+        assert_eq!(
+            wallet.balance_live_contract(Some(&all_utxos)).unwrap(),
+            Amount::ZERO
+        );
+
+        let seed_balance = wallet.balance_descriptor_utxo(Some(&all_utxos)).unwrap();
+        let refund_fees = org_taker_spend_balance
+            .checked_sub(seed_balance)
+            .unwrap()
+            .checked_sub(record.mining_fee)
+            .unwrap();
+        assert!(refund_fees > Amount::ZERO);
+        assert!(refund_fees < Amount::from_sat(10_000));
+    }
+
+    // After Swap is done,  wait for maker threads to conclude.
+    makers
+        .iter()
+        .for_each(|maker| maker.shutdown.store(true, Relaxed));
+
+    maker_threads
+        .into_iter()
+        .for_each(|thread| thread.join().unwrap());
+
+    // The Makers got their funds back, minus the mining fees of their funding and refund txs.
+    makers
+        .iter()
+        .zip(org_maker_spend_balances)
+        .for_each(|(maker, org_spend_balance)| {
+            let wallet = maker.get_wallet().read().unwrap();
+            let all_utxos = wallet.get_all_utxo().unwrap();
+            assert!(all_utxos.iter().all(|utxo| utxo.confirmations > 0));
+            assert_eq!(
+                wallet.balance_swap_coins(Some(&all_utxos)).unwrap(),
+                Amount::ZERO
+            );
+            assert_eq!(
+                wallet.balance_live_contract(Some(&all_utxos)).unwrap(),
+                Amount::ZERO
+            );
+
+            let seed_balance = wallet.balance_descriptor_utxo(Some(&all_utxos)).unwrap();
+            let fees = org_spend_balance.checked_sub(seed_balance).unwrap();
+            assert!(fees > Amount::ZERO);
+            assert!(fees < Amount::from_sat(20_000));
+        });
+
+    // Shutdown Directory Server
+    directory_server_instance.shutdown.store(true, Relaxed);
+
+    thread::sleep(Duration::from_secs(10));
+
+    info!("All checks successful. Terminating integration test case");
+
+    test_framework.stop();
+
+    block_generation_handle.join().unwrap();
+}
@@ -0,0 +1,142 @@
+#![cfg(feature = "integration-test")]
+use bitcoin::Amount;
+use coinswap::{
+    maker::{start_maker_server, MakerBehavior},
+    taker::{SwapOutcome, SwapParams, TakerBehavior},
+    utill::ConnectionType,
+};
+mod test_framework;
+use log::{info, warn};
+use std::{
+    sync::{atomic::Ordering::Relaxed, Arc},
+    thread,
+    time::Duration,
+};
+use test_framework::*;
+
+/// Cooperative Abort: Refunds Still In The Mempool.
+/// The Taker aborts the swap after the full setup, but asks the last Maker for the refund signatures of
+/// its incoming hop while the refunds of its outgoing hop are still in the mempool. The Maker refuses,
+/// as a refund in the mempool can still be replaced by the contract tx. The Taker then recovers via the
+/// contracts.
+#[test]
+fn test_cooperative_abort_with_unconfirmed_refunds() {
+    // ---- Setup ----
+
+    // 2 Makers with Normal behavior.
+    let makers_config_map = [
+        ((6102, None), MakerBehavior::Normal),
+        ((16102, None), MakerBehavior::Normal),
+    ];
+
+    // Initiate test framework, Makers.
+    // Taker has a special behavior AbortWithoutWaitingForRefunds.
+    let (test_framework, mut taker, makers, directory_server_instance, block_generation_handle) =
+        TestFramework::init(
+            makers_config_map.into(),
+            TakerBehavior::AbortWithoutWaitingForRefunds,
+            ConnectionType::CLEARNET,
+        );
+    taker.config.cooperative_abort = true;
+
+    warn!("Running Test: Maker refuses refund signatures for unconfirmed refunds.");
+
+    // Fund the Taker  with 3 utxos of 0.05 btc each and do basic checks on the balance
+    let org_taker_spend_balance = fund_and_verify_taker(
+        &mut taker,
+        &test_framework.bitcoind,
+        3,
+        Amount::from_btc(0.05).unwrap(),
+    );
+
+    // Fund the Maker with 4 utxos of 0.05 btc each and do basic checks on the balance.
+    let makers_ref = makers.iter().map(Arc::as_ref).collect::<Vec<_>>();
+    fund_and_verify_maker(
+        makers_ref,
+        &test_framework.bitcoind,
+        4,
+        Amount::from_btc(0.05).unwrap(),
+    );
+
+    //  Start the Maker Server threads
+    log::info!("Initiating Maker...");
+
+    let maker_threads = makers
+        .iter()
+        .map(|maker| {
+            let maker_clone = maker.clone();
+            thread::spawn(move || {
+                start_maker_server(maker_clone).unwrap();
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Makers take time to fully setup.
+    makers.iter().for_each(|maker| {
+        while !maker.is_setup_complete.load(Relaxed) {
+            log::info!("Waiting for maker setup completion");
+            // Introduce a delay of 10 seconds to prevent write lock starvation.
+            thread::sleep(Duration::from_secs(10));
+            continue;
+        }
+    });
+
+    // Initiate Coinswap
+    log::info!("Initiating coinswap protocol");
+
+    // Swap params for coinswap.
+    let swap_params = SwapParams {
+        send_amount: Amount::from_sat(500000),
+        maker_count: 2,
+        tx_count: 3,
+        required_confirms: 1,
+    };
+    taker.do_coinswap(swap_params).unwrap();
+
+    // The last hop was refunded, but the Maker refused to sign away its incoming hop. So the swap
+    // wasn't aborted, and the Taker recovered its outgoing hop via the contracts.
+    let history = taker.swap_history().unwrap();
+    assert_eq!(history.len(), 1);
+    let record = &history[0].record;
+    assert_eq!(record.outcome, SwapOutcome::Recovered);
+    assert!(record.contracts_broadcasted);
+
+    // The Taker got its funds back, minus the mining fees of its funding, refund and recovery txs.
+    {
+        let wallet = taker.get_wallet();
+        let all_utxos = wallet.get_all_utxo().unwrap();
+        assert_eq!(
+            wallet.balance_swap_coins(Some(&all_utxos)).unwrap(),
+            Amount::ZERO
+        );
+        assert_eq!(
+            wallet.balance_live_contract(Some(&all_utxos)).unwrap(),
+            Amount::ZERO
+        );
+
+        let seed_balance = wallet.balance_descriptor_utxo(Some(&all_utxos)).unwrap();
+        let fees = org_taker_spend_balance.checked_sub(seed_balance).unwrap();
+        assert!(fees > Amount::ZERO);
+        assert!(fees < Amount::from_sat(20_000));
+    }
+
+    // After Swap is done,  wait for maker threads to conclude.
+    makers
+        .iter()
+        .for_each(|maker| maker.shutdown.store(true, Relaxed));
+
+    maker_threads
+        .into_iter()
+        .for_each(|thread| thread.join().unwrap());
+
+    // Shutdown Directory Server
+    directory_server_instance.shutdown.store(true, Relaxed);
+
+    thread::sleep(Duration::from_secs(10));
+
+    info!("All checks successful. Terminating integration test case");
+
+    test_framework.stop();
+
+    block_generation_handle.join().unwrap();
+}
@@ -105,6 +105,7 @@ fn test_fidelity() {
                 Amount::from_sat(8000000),
                 LockTime::from_height((bitcoind.client.get_block_count().unwrap() as u32) + 950)
                     .unwrap(),
+                2.0,
             )
             .unwrap();
 
@@ -189,7 +190,7 @@ fn test_fidelity() {
         let seed_balance = wallet_read.balance_descriptor_utxo(None).unwrap();
 
         assert_eq!(fidelity_balance.to_sat(), 0);
-        assert_eq!(seed_balance.to_sat(), 103996000);
+        assert_eq!(seed_balance.to_sat(), 103997388);
     }
 
     // Stop the directory server.
@@ -1,7 +1,7 @@
 //! Integration test for Maker CLI functionality.
 #![cfg(feature = "integration-test")]
-use bitcoin::{Address, Amount};
-use bitcoind::BitcoinD;
+use bitcoin::{Address, Amount, OutPoint, Txid};
+use bitcoind::{bitcoincore_rpc::RpcApi, BitcoinD};
 use coinswap::utill::setup_logger;
 use std::{
     fs,
@@ -15,7 +15,10 @@ use std::{
 };
 
 mod test_framework;
-use test_framework::{await_message, generate_blocks, init_bitcoind, send_to_address, start_dns};
+use test_framework::{
+    await_message, core_wallet_client, generate_blocks, init_bitcoind, send_to_address, start_dns,
+    swap_change_address,
+};
 
 struct MakerCli {
     data_dir: PathBuf,
@@ -92,7 +95,7 @@ impl MakerCli {
 
         let (amount, addrs) = loop {
             let log_message = stdout_recv.recv().unwrap();
-            if log_message.contains("Send at least 0.05001000 BTC") {
+            if log_message.contains("Send at least 0.05000170 BTC") {
                 let parts: Vec<&str> = log_message.split_whitespace().collect();
                 let amount = Amount::from_str_in(parts[7], bitcoin::Denomination::Bitcoin).unwrap();
                 let addr = Address::from_str(parts[10]).unwrap().assume_checked();
@@ -175,7 +178,7 @@ fn test_maker_cli() {
     let swap_balance = maker_cli.execute_maker_cli(&["get-balance-swap"]);
     await_message(&rx, "RPC request received: SwapBalance");
 
-    assert_eq!(seed_balance, "1000000 sats");
+    assert_eq!(seed_balance, "999864 sats");
     assert_eq!(swap_balance, "0 sats");
     assert_eq!(fidelity_balance, "5000000 sats");
     assert_eq!(contract_balance, "0 sats");
@@ -195,7 +198,7 @@ fn test_maker_cli() {
 
     // Validate UTXOs
     assert_eq!(all_utxos.matches("ListUnspentResultEntry").count(), 2);
-    assert!(all_utxos.contains("amount: 1000000 SAT"));
+    assert!(all_utxos.contains("amount: 999864 SAT"));
     assert_eq!(fidelity_utxo.matches("ListUnspentResultEntry").count(), 1);
     assert!(fidelity_utxo.contains("amount: 5000000 SAT"));
     assert_eq!(swap_utxo.matches("ListUnspentResultEntry").count(), 0);
@@ -213,12 +216,12 @@ fn test_maker_cli() {
         "-a",
         "10000",
         "-f",
-        "1000",
+        "10",
     ]);
     generate_blocks(&maker_cli.bitcoind, 1);
 
-    // Check balances
-    assert_eq!(maker_cli.execute_maker_cli(&["get-balance"]), "999000 sats");
+    // Check balances. The 141 vB self-spend paid 10 sats/vB.
+    assert_eq!(maker_cli.execute_maker_cli(&["get-balance"]), "998454 sats");
     assert_eq!(
         maker_cli.execute_maker_cli(&["get-balance-contract"]),
         "0 sats"
@@ -233,6 +236,91 @@ fn test_maker_cli() {
     let seed_utxo = maker_cli.execute_maker_cli(&["list-utxo"]);
     assert_eq!(seed_utxo.matches("ListUnspentResultEntry").count(), 3);
 
+    // Spend an explicitly chosen utxo, the change of the previous spend.
+    let wallet_client = core_wallet_client(&maker_cli.bitcoind, "maker-wallet");
+    let find_utxo = |amount: Amount| {
+        let utxo = wallet_client
+            .list_unspent(None, None, None, None, None)
+            .unwrap()
+            .into_iter()
+            .find(|utxo| utxo.amount == amount)
+            .unwrap();
+        OutPoint::new(utxo.txid, utxo.vout)
+    };
+    let seed_outpoint = find_utxo(Amount::from_sat(988454));
+    let txid = maker_cli.execute_maker_cli(&[
+        "send-to-address",
+        "-t",
+        &address,
+        "-a",
+        "10000",
+        "-f",
+        "10",
+        "--utxo",
+        &seed_outpoint.to_string(),
+    ]);
+    await_message(&rx, "RPC request received: SendToAddress");
+    let tx = maker_cli
+        .bitcoind
+        .client
+        .get_raw_transaction(&Txid::from_str(&txid).unwrap(), None)
+        .unwrap();
+    assert_eq!(tx.input.len(), 1);
+    assert_eq!(tx.input[0].previous_output, seed_outpoint);
+    generate_blocks(&maker_cli.bitcoind, 1);
+
+    // A coin of the swap pool is not spent together with a seed coin.
+    let swap_coin_amount = Amount::from_sat(500000);
+    send_to_address(
+        &maker_cli.bitcoind,
+        &swap_change_address(&wallet_client),
+        swap_coin_amount,
+    );
+    generate_blocks(&maker_cli.bitcoind, 1);
+
+    let swap_outpoint = find_utxo(swap_coin_amount);
+    let seed_outpoint = find_utxo(Amount::from_sat(10000));
+    let error = maker_cli.execute_maker_cli(&[
+        "send-to-address",
+        "-t",
+        &address,
+        "-a",
+        "10000",
+        "-f",
+        "10",
+        "--utxo",
+        &seed_outpoint.to_string(),
+        "--utxo",
+        &swap_outpoint.to_string(),
+    ]);
+    await_message(&rx, "RPC request received: SendToAddress");
+    assert!(error.contains("CrossPoolSpend"));
+
+    // Fee policy check
+    let fee_policy = maker_cli.execute_maker_cli(&["get-fee-policy"]);
+    await_message(&rx, "RPC request received: GetFeePolicy");
+    assert!(fee_policy.contains("base_fee: 1000"));
+
+    let fee_policy = maker_cli.execute_maker_cli(&[
+        "set-fee-policy",
+        "--base-fee",
+        "2000",
+        "--amount-relative-fee-pct",
+        "1.5",
+    ]);
+    await_message(&rx, "RPC request received: SetFeePolicy");
+    assert_eq!(fee_policy.matches("base_fee: 2000").count(), 2);
+    assert_eq!(
+        fee_policy.matches("amount_relative_fee_pct: 1.5").count(),
+        2
+    );
+    assert_eq!(fee_policy.matches("time_relative_fee_pct: 0.1").count(), 2);
+
+    // The new policy is persisted in the config file.
+    let config = fs::read_to_string(maker_cli.data_dir.join("config.toml")).unwrap();
+    assert!(config.contains("base_fee = 2000"));
+    assert!(config.contains("amount_relative_fee_pct = 1.5"));
+
     // Shutdown check
     let stop = maker_cli.execute_maker_cli(&["stop"]);
     await_message(&rx, "RPC request received: Stop");
@@ -0,0 +1,125 @@
+#![cfg(feature = "integration-test")]
+use bitcoin::Amount;
+use coinswap::{
+    maker::{start_maker_server, MakerBehavior},
+    taker::{SwapParams, TakerBehavior},
+    utill::ConnectionType,
+};
+use std::sync::Arc;
+
+mod test_framework;
+use test_framework::*;
+
+use log::{info, warn};
+use std::{sync::atomic::Ordering::Relaxed, thread, time::Duration};
+
+/// This test does consecutive coinswap rounds between a Taker and 2 Makers, with the swap amount
+/// split into 2, 3 and 4 utxos. The Taker must receive back between 2 and `tx_count` swap coins,
+/// and at least the quoted amount.
+#[test]
+fn test_multi_split_coinswap() {
+    // ---- Setup ----
+
+    // 2 Makers with Normal behavior.
+    let makers_config_map = [
+        ((6102, None), MakerBehavior::Normal),
+        ((16102, None), MakerBehavior::Normal),
+    ];
+
+    // Initiate test framework, Makers and a Taker with default behavior.
+    let (test_framework, mut taker, makers, directory_server_instance, block_generation_handle) =
+        TestFramework::init(
+            makers_config_map.into(),
+            TakerBehavior::Normal,
+            ConnectionType::CLEARNET,
+        );
+
+    warn!("Running Test: Multi Split Coinswap Procedure");
+    let bitcoind = &test_framework.bitcoind;
+
+    // Fund the Taker  with 3 utxos of 0.05 btc each.
+    fund_and_verify_taker(&mut taker, bitcoind, 3, Amount::from_btc(0.05).unwrap());
+
+    // Fund the Maker with 4 utxos of 0.05 btc each.
+    let makers_ref = makers.iter().map(Arc::as_ref).collect::<Vec<_>>();
+    fund_and_verify_maker(makers_ref, bitcoind, 4, Amount::from_btc(0.05).unwrap());
+
+    //  Start the Maker Server threads
+    log::info!("Initiating Maker...");
+
+    let maker_threads = makers
+        .iter()
+        .map(|maker| {
+            let maker_clone = maker.clone();
+            thread::spawn(move || {
+                start_maker_server(maker_clone).unwrap();
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Makers take time to fully setup.
+    makers.iter().for_each(|maker| {
+        while !maker.is_setup_complete.load(Relaxed) {
+            log::info!("Waiting for maker setup completion");
+            // Introduce a delay of 10 seconds to prevent write lock starvation.
+            thread::sleep(Duration::from_secs(10));
+        }
+    });
+
+    for tx_count in 2..=4 {
+        info!("Initiating coinswap with {} splits", tx_count);
+
+        let swap_params = SwapParams {
+            send_amount: Amount::from_sat(500000),
+            maker_count: 2,
+            tx_count,
+            required_confirms: 1,
+        };
+
+        let quote = taker.quote_swap(swap_params).unwrap();
+        assert!(quote.hops.iter().all(|hop| hop.tx_count == tx_count));
+
+        let swap_coins_before = taker
+            .get_wallet()
+            .list_swap_coin_utxo_spend_info(None)
+            .unwrap();
+        let balance_before = taker.get_wallet().balance_swap_coins(None).unwrap();
+
+        taker.do_coinswap(swap_params).unwrap();
+
+        let wallet = taker.get_wallet();
+        let swap_coins_after = wallet.list_swap_coin_utxo_spend_info(None).unwrap();
+        let received_coins = swap_coins_after.len() - swap_coins_before.len();
+        let received_amount = wallet.balance_swap_coins(None).unwrap() - balance_before;
+
+        // The last maker splits its funding into 2 to `tx_count` txs.
+        assert!((2..=tx_count as usize).contains(&received_coins));
+        assert!(received_amount >= quote.receive_amount);
+        assert_eq!(wallet.balance_live_contract(None).unwrap(), Amount::ZERO);
+    }
+
+    // After Swaps are done, wait for maker threads to conclude.
+    makers
+        .iter()
+        .for_each(|maker| maker.shutdown.store(true, Relaxed));
+
+    maker_threads
+        .into_iter()
+        .for_each(|thread| thread.join().unwrap());
+
+    // No maker is left with live contracts.
+    makers.iter().for_each(|maker| {
+        let wallet = maker.wallet.read().unwrap();
+        assert_eq!(wallet.balance_live_contract(None).unwrap(), Amount::ZERO);
+    });
+
+    // Shutdown Directory Server
+    directory_server_instance.shutdown.store(true, Relaxed);
+
+    thread::sleep(Duration::from_secs(10));
+
+    info!("All checks successful. Terminating integration test case");
+
+    test_framework.stop();
+    block_generation_handle.join().unwrap();
+}
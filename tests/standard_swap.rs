@@ -85,7 +85,7 @@ fn test_standard_coinswap() {
 
             let live_contract_balance = wallet.balance_live_contract(Some(&all_utxos)).unwrap();
 
-            assert_eq!(seed_balance, Amount::from_btc(0.14999).unwrap());
+            assert_eq!(seed_balance, Amount::from_btc(0.14999558).unwrap());
             assert_eq!(fidelity_balance, Amount::from_btc(0.05).unwrap());
             assert_eq!(swapcoin_balance, Amount::ZERO);
             assert_eq!(live_contract_balance, Amount::ZERO);
@@ -104,8 +104,23 @@ fn test_standard_coinswap() {
         tx_count: 3,
         required_confirms: 1,
     };
+
+    // Quote the swap before doing it. Both makers are used, and all hops are quoted with the
+    // maximum split count.
+    let quote = taker.quote_swap(swap_params).unwrap();
+    assert_eq!(quote.hops.len(), 2);
+    assert_eq!(quote.hops[0].incoming_amount, swap_params.send_amount);
+    assert_eq!(
+        quote.total_cost,
+        swap_params.send_amount - quote.receive_amount + quote.taker_funding_fee
+    );
+    assert!(quote.recovery_cost > quote.taker_funding_fee);
+
     taker.do_coinswap(swap_params).unwrap();
 
+    // The taker received at least the quoted amount, as makers can split into fewer txs.
+    assert!(taker.get_wallet().balance_swap_coins(None).unwrap() >= quote.receive_amount);
+
     // After Swap is done,  wait for maker threads to conclude.
     makers
         .iter()
@@ -161,18 +176,16 @@ fn test_standard_coinswap() {
         .list_swap_coin_utxo_spend_info(None)
         .unwrap();
 
+    // The last maker splits its funding into 2 to 3 txs.
+    assert!((2..=3).contains(&swap_coins.len()));
+
     let tx = taker_wallet_mut
-        .spend_from_wallet(
-            Amount::from_sat(1000),
-            SendAmount::Max,
-            Destination::Wallet,
-            &swap_coins,
-        )
+        .spend_from_wallet(2.0, SendAmount::Max, Destination::Wallet, &swap_coins)
         .unwrap();
 
     assert_eq!(
         tx.input.len(),
-        3,
+        swap_coins.len(),
         "Not all swap coin utxos got included in the spend transaction"
     );
 
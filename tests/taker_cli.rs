@@ -1,10 +1,19 @@
 #![cfg(feature = "integration-test")]
-use bitcoin::{address::NetworkChecked, Address, Amount};
+use bitcoin::{
+    address::NetworkChecked, bip32::ChildNumber, Address, Amount, Network, OutPoint, Txid,
+};
 use bitcoind::{bitcoincore_rpc::RpcApi, tempfile::env::temp_dir, BitcoinD};
 
-use std::{fs, path::PathBuf, process::Command, str::FromStr};
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Output},
+    str::FromStr,
+};
 mod test_framework;
-use test_framework::{generate_blocks, init_bitcoind, send_to_address};
+use test_framework::{
+    core_wallet_client, generate_blocks, init_bitcoind, send_to_address, swap_change_address,
+};
 /// The taker-cli command struct
 struct TakerCli {
     data_dir: PathBuf,
@@ -31,6 +40,34 @@ impl TakerCli {
 
     // Execute a cli-command
     fn execute(&self, cmd: &[&str]) -> String {
+        let output = self.run(cmd);
+
+        // Capture the standard output and error from the command execution
+        let mut value = output.stdout;
+        let error = output.stderr;
+
+        // Panic if there is any error output
+        if !error.is_empty() {
+            panic!("Error: {:?}", String::from_utf8(error).unwrap());
+        }
+
+        // Remove the `\n` at the end of the output
+        value.pop();
+
+        // Convert the output bytes to a UTF-8 string
+        let output_string = std::str::from_utf8(&value).unwrap().to_string();
+
+        output_string
+    }
+
+    // Execute a cli-command expected to fail, and return its error output
+    fn execute_failing(&self, cmd: &[&str]) -> String {
+        let output = self.run(cmd);
+        assert!(!output.status.success());
+        String::from_utf8(output.stderr).unwrap()
+    }
+
+    fn run(&self, cmd: &[&str]) -> Output {
         let mut args = vec!["--data-directory", self.data_dir.to_str().unwrap()];
 
         // RPC authentication (user:password) from the cookie file
@@ -51,27 +88,10 @@ impl TakerCli {
             args.push(arg);
         }
 
-        let output = Command::new("./target/debug/taker")
+        Command::new("./target/debug/taker")
             .args(args)
             .output()
-            .unwrap();
-
-        // Capture the standard output and error from the command execution
-        let mut value = output.stdout;
-        let error = output.stderr;
-
-        // Panic if there is any error output
-        if !error.is_empty() {
-            panic!("Error: {:?}", String::from_utf8(error).unwrap());
-        }
-
-        // Remove the `\n` at the end of the output
-        value.pop();
-
-        // Convert the output bytes to a UTF-8 string
-        let output_string = std::str::from_utf8(&value).unwrap().to_string();
-
-        output_string
+            .unwrap()
     }
 }
 
@@ -104,7 +124,7 @@ fn test_taker_cli() {
     let no_of_seed_utxos = all_utxos.matches("ListUnspentResultEntry {").count();
     assert_eq!(3, no_of_seed_utxos);
 
-    // Send 100,000 sats to a new address within the wallet, with a fee of 1,000 sats.
+    // Send 100,000 sats to a new address within the wallet, at 10 sats/vB.
 
     // get new external address
     let new_address = taker_cli.execute(&["get-new-address"]);
@@ -116,7 +136,7 @@ fn test_taker_cli() {
         "-a",
         "100000",
         "-f",
-        "1000",
+        "10",
     ]);
 
     generate_blocks(bitcoind, 10);
@@ -124,8 +144,8 @@ fn test_taker_cli() {
     // Assert the total_amount & seed_amount must be initial (balance -fee)
     let spendable_balance = taker_cli.execute(&["get-balance"]);
 
-    // Since the amount is sent back to our wallet, the transaction fee is deducted from the balance.
-    assert_eq!("299999000 SAT", spendable_balance);
+    // Since the amount is sent back to our wallet, only the fee of the 141 vB tx is deducted from the balance.
+    assert_eq!("299998590 SAT", spendable_balance);
 
     // Assert that no of seed utxos are 2
     let all_utxos = taker_cli.execute(&["list-utxo"]);
@@ -133,6 +153,96 @@ fn test_taker_cli() {
     let no_of_seed_utxos = all_utxos.matches("ListUnspentResultEntry {").count();
     assert_eq!(4, no_of_seed_utxos);
 
+    // Spend an explicitly chosen utxo.
+    let wallet_client = core_wallet_client(bitcoind, "test_wallet");
+    let find_utxo = |amount: Amount| {
+        let utxo = wallet_client
+            .list_unspent(None, None, None, None, None)
+            .unwrap()
+            .into_iter()
+            .find(|utxo| utxo.amount == amount)
+            .unwrap();
+        OutPoint::new(utxo.txid, utxo.vout)
+    };
+    let seed_outpoint = find_utxo(Amount::ONE_BTC);
+    let txid = taker_cli.execute(&[
+        "send-to-address",
+        "-t",
+        &new_address,
+        "-a",
+        "100000",
+        "-f",
+        "10",
+        "--utxo",
+        &seed_outpoint.to_string(),
+    ]);
+    let tx = bitcoind
+        .client
+        .get_raw_transaction(&Txid::from_str(&txid).unwrap(), None)
+        .unwrap();
+    assert_eq!(tx.input.len(), 1);
+    assert_eq!(tx.input[0].previous_output, seed_outpoint);
+
+    generate_blocks(bitcoind, 1);
+
+    // A coin of the swap pool is not spent together with a seed coin.
+    let swap_coin_amount = Amount::from_btc(0.5).unwrap();
+    send_to_address(
+        bitcoind,
+        &swap_change_address(&wallet_client),
+        swap_coin_amount,
+    );
+    generate_blocks(bitcoind, 1);
+
+    let seed_outpoint = find_utxo(Amount::ONE_BTC);
+    let swap_outpoint = find_utxo(swap_coin_amount);
+    let error = taker_cli.execute_failing(&[
+        "send-to-address",
+        "-t",
+        &new_address,
+        "-a",
+        "100000",
+        "-f",
+        "10",
+        "--utxo",
+        &seed_outpoint.to_string(),
+        "--utxo",
+        &swap_outpoint.to_string(),
+    ]);
+    assert!(error.contains("CrossPoolSpend"));
+
+    // Spent alone, its change stays in the swap pool.
+    let txid = taker_cli.execute(&[
+        "send-to-address",
+        "-t",
+        &new_address,
+        "-a",
+        "100000",
+        "-f",
+        "10",
+        "--utxo",
+        &swap_outpoint.to_string(),
+    ]);
+    let tx = bitcoind
+        .client
+        .get_raw_transaction(&Txid::from_str(&txid).unwrap(), None)
+        .unwrap();
+    let change = tx
+        .output
+        .iter()
+        .find(|txout| txout.value != Amount::from_sat(100000))
+        .unwrap();
+    let change_address = Address::from_script(&change.script_pubkey, Network::Regtest).unwrap();
+    let change_path = wallet_client
+        .get_address_info(&change_address)
+        .unwrap()
+        .hd_key_path
+        .unwrap();
+    assert_eq!(
+        change_path[change_path.len() - 2],
+        ChildNumber::Normal { index: 2 }
+    );
+
     bitcoind.client.stop().unwrap();
 
     // Wait for some time for successfull shutdown of bitcoind.
@@ -32,7 +32,7 @@ use std::{
     sync::mpsc::{self, Receiver, Sender},
 };
 
-use bitcoind::bitcoincore_rpc::Auth;
+use bitcoind::bitcoincore_rpc::{Auth, Client};
 
 use coinswap::{
     maker::{Maker, MakerBehavior},
@@ -101,6 +101,35 @@ pub(crate) fn send_to_address(
         .unwrap()
 }
 
+/// Connect to the watch-only Core wallet backing a coinswap wallet.
+#[allow(dead_code)]
+pub(crate) fn core_wallet_client(bitcoind: &BitcoinD, wallet_name: &str) -> Client {
+    Client::new(
+        &bitcoind.rpc_url_with_wallet(wallet_name),
+        Auth::CookieFile(bitcoind.params.cookie_file.clone()),
+    )
+    .unwrap()
+}
+
+/// Derive the first address of a coinswap wallet's swap change keychain, from the descriptors imported
+/// in its Core wallet. Coins sent to it are in the swap pool of the wallet.
+#[allow(dead_code)]
+pub(crate) fn swap_change_address(wallet_client: &Client) -> bitcoin::Address {
+    let descriptors: serde_json::Value = wallet_client.call("listdescriptors", &[]).unwrap();
+    let descriptor = descriptors["descriptors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|descriptor| descriptor["desc"].as_str())
+        .find(|desc| desc.contains("/2/*)"))
+        .expect("swap change descriptor expected");
+    wallet_client
+        .derive_addresses(descriptor, Some([0, 0]))
+        .unwrap()[0]
+        .clone()
+        .assume_checked()
+}
+
 // Waits until the mpsc::Receiver<String> recieves the expected message.
 pub(crate) fn await_message(rx: &Receiver<String>, expected_message: &str) {
     loop {
@@ -330,10 +359,10 @@ pub fn verify_swap_results(
             let spendable_balance = seed_balance + swapcoin_balance;
 
             assert!(
-                seed_balance == Amount::from_btc(0.14557358).unwrap() // First maker on successful coinswap
-                    || seed_balance == Amount::from_btc(0.14532500).unwrap() // Second maker on successful coinswap
-                    || seed_balance == Amount::from_btc(0.14999).unwrap() // No spending
-                    || seed_balance == Amount::from_btc(0.14992232).unwrap(), // Recovery via timelock
+                seed_balance == Amount::from_btc(0.14557916).unwrap() // First maker on successful coinswap
+                    || seed_balance == Amount::from_btc(0.14533058).unwrap() // Second maker on successful coinswap
+                    || seed_balance == Amount::from_btc(0.14999558).unwrap() // No spending
+                    || seed_balance == Amount::from_btc(0.1499279).unwrap(), // Recovery via timelock
                 "Maker seed balance mismatch"
             );
 
@@ -446,6 +475,7 @@ impl TestFramework {
             Some(temp_dir.join("taker")),
             None,
             Some(taker_rpc_config),
+            None,
             taker_behavior,
             Some(connection_type),
         )
@@ -465,6 +495,7 @@ impl TestFramework {
                         Some(temp_dir.join(port.0.to_string())),
                         Some(maker_id),
                         Some(maker_rpc_config),
+                        None,
                         Some(port.0),
                         Some(base_rpc_port),
                         port.1,
@@ -0,0 +1,141 @@
+#![cfg(feature = "integration-test")]
+use bitcoin::{absolute::LockTime, Amount};
+use bitcoind::bitcoincore_rpc::{Auth, RpcApi};
+use coinswap::{
+    taker::{Taker, TakerBehavior},
+    utill::ConnectionType,
+    wallet::RPCConfig,
+};
+mod test_framework;
+use test_framework::*;
+
+use std::{
+    env, fs,
+    sync::atomic::{AtomicBool, Ordering::Relaxed},
+    thread,
+    time::Duration,
+};
+
+const MNEMONIC: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+/// Test Wallet Restore From Mnemonic
+///
+/// A wallet is funded on two receive addresses and creates a fidelity bond spending the coin of the
+/// second one. The wallet is then restored from its mnemonic into a new wallet file. The restored
+/// wallet must find the same balances and the bond, and must not hand out any of the used receive
+/// addresses again, even the one whose coin is spent.
+#[test]
+fn test_wallet_restore() {
+    // ---- Setup ----
+    let temp_dir = env::temp_dir().join("coinswap");
+    if temp_dir.exists() {
+        fs::remove_dir_all(&temp_dir).unwrap();
+    }
+    let bitcoind = init_bitcoind(&temp_dir);
+    let rpc_config = RPCConfig {
+        url: bitcoind.rpc_url().split_at(7).1.to_string(),
+        auth: Auth::CookieFile(bitcoind.params.cookie_file.clone()),
+        ..Default::default()
+    };
+    let restore_taker = |name: &str| {
+        let data_dir = temp_dir.join(name);
+        Taker::restore_wallet(
+            Some(data_dir.clone()),
+            Some(name.to_string()),
+            Some(rpc_config.clone()),
+            None,
+            MNEMONIC,
+            0,
+        )?;
+        Taker::init(
+            Some(data_dir),
+            Some(name.to_string()),
+            Some(rpc_config.clone()),
+            None,
+            TakerBehavior::Normal,
+            Some(ConnectionType::CLEARNET),
+        )
+    };
+
+    // The original wallet, created from the known mnemonic.
+    let mut taker = restore_taker("original").unwrap();
+    let wallet = taker.get_wallet_mut();
+
+    let first_addr = wallet.get_next_external_address().unwrap();
+    let second_addr = wallet.get_next_external_address().unwrap();
+    send_to_address(&bitcoind, &first_addr, Amount::from_btc(0.01).unwrap());
+    send_to_address(&bitcoind, &second_addr, Amount::ONE_BTC);
+    generate_blocks(&bitcoind, 1);
+    wallet.sync().unwrap();
+
+    // Only the coin of the second address covers the bond.
+    let shutdown = AtomicBool::new(false);
+    thread::scope(|s| {
+        s.spawn(|| {
+            while !shutdown.load(Relaxed) {
+                thread::sleep(Duration::from_secs(2));
+                generate_blocks(&bitcoind, 1);
+            }
+        });
+        let locktime =
+            LockTime::from_height(bitcoind.client.get_block_count().unwrap() as u32 + 150).unwrap();
+        wallet
+            .create_fidelity(Amount::from_btc(0.5).unwrap(), locktime, 2.0)
+            .unwrap();
+        shutdown.store(true, Relaxed);
+    });
+    wallet.sync().unwrap();
+
+    // ---- Test ----
+    let mut restored_taker = restore_taker("restored").unwrap();
+    let restored = restored_taker.get_wallet_mut();
+
+    // Only the final wallet file is left.
+    let wallets_dir = temp_dir.join("restored").join("wallets");
+    assert!(wallets_dir.join("restored").exists());
+    assert!(!wallets_dir.join("restored.restoring").exists());
+
+    // Same balances.
+    let utxos = wallet.get_all_utxo().unwrap();
+    let restored_utxos = restored.get_all_utxo().unwrap();
+    assert_eq!(
+        restored
+            .balance_descriptor_utxo(Some(&restored_utxos))
+            .unwrap(),
+        wallet.balance_descriptor_utxo(Some(&utxos)).unwrap()
+    );
+    assert_eq!(
+        restored
+            .balance_fidelity_bonds(Some(&restored_utxos))
+            .unwrap(),
+        Amount::from_btc(0.5).unwrap()
+    );
+
+    // Same bond.
+    let bonds = wallet.get_fidelity_bonds();
+    let restored_bonds = restored.get_fidelity_bonds();
+    assert_eq!(restored_bonds.len(), 1);
+    let (bond, bond_spk, _) = &bonds[&0];
+    let (restored_bond, restored_spk, is_spent) = &restored_bonds[&0];
+    assert_eq!(restored_bond.amount, bond.amount);
+    assert_eq!(restored_bond.lock_time, bond.lock_time);
+    assert_eq!(restored_spk, bond_spk);
+    assert!(!is_spent);
+
+    // The next receive address is past both used ones.
+    let restored_next_addr = restored.get_next_external_address().unwrap();
+    assert_ne!(restored_next_addr, first_addr);
+    assert_ne!(restored_next_addr, second_addr);
+    assert_eq!(
+        restored_next_addr,
+        wallet.get_next_external_address().unwrap()
+    );
+
+    // Restoring over an existing wallet file fails.
+    assert!(restore_taker("restored").is_err());
+
+    drop(taker);
+    drop(restored_taker);
+    bitcoind.client.stop().unwrap();
+}